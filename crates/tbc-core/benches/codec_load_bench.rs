@@ -1,18 +1,27 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use tbc_core::codec_tx::encode_message;
-use tbc_core::protocol::{TGPMessage, QueryMessage};
-use tbc_core::tgp::types::ZkProfile;
+use tbc_core::protocol::{
+    Intent, QueryMessage, RoutingMetadata, TGPMessage, TGPMODE, TGPParty, TGPVerb,
+};
 
 fn make_msg(i: usize) -> TGPMessage {
     let q = QueryMessage {
+        msg_type: "QUERY".into(),
+        tgp_version: "3.2".into(),
         id: format!("q-{i}"),
-        from: "buyer://alice".into(),
-        to: "seller://bob".into(),
-        asset: "USDC".into(),
+        session_token: None,
+        delegated_key: None,
+        scope: None,
+        routing: RoutingMetadata::default(),
+        intent: Intent {
+            verb: TGPVerb::PAY,
+            party: TGPParty::BUYER,
+            mode: TGPMODE::DIRECT,
+        },
+        payment_profile: "0x1111111111111111111111111111111111111111".into(),
         amount: 1000,
-        escrow_from_402: false,
-        escrow_contract_from_402: None,
-        zk_profile: ZkProfile::Optional,
+        chain_id: 369,
+        metadata: serde_json::Value::Null,
     };
     TGPMessage::Query(q)
 }
@@ -24,7 +24,7 @@ fn bench_replay_replay_hits(c: &mut Criterion) {
 
     c.bench_function("replay_hit_100k", |b| {
         b.iter(|| {
-            for i in 0..100_000 {
+            for _ in 0..100_000 {
                 black_box(cache.check_or_insert("msg-100")); // always replay
             }
         })
@@ -4,7 +4,10 @@
 //!   • Raw JSON parsing
 //!   • Message-type classification
 //!   • Structural validation
-//!   • Replay metadata extraction
+//!   • Message metadata extraction (replay key, canonical digest, see `tgp::canonical`)
+//!   • Canonical (RFC 8785) encoding
+//!   • Optional CBOR (RFC 8949) wire format over the same message types
+//!   • Bounded replay cache (`InMemoryReplayCache`)
 //!
 //! Supported types per TGP-00 v3.2:
 //!   • QUERY
@@ -13,9 +16,26 @@
 //!   • SETTLE
 //!
 //! Forbidden:
-//!   • OFFER (removed in v3.2)
+//!   • OFFER (removed in v3.2; 3.1 OFFERs are upgraded to ACK(status=offer))
+//!
+//! Legacy (pre-v3.2) payloads tagged on `phase` are upgraded into the
+//! canonical model by `tgp::legacy` before classification.
+//!
+//! Versioning:
+//!   • `VersionRegistry` maps each accepted wire version to an upgrade
+//!     shim that rewrites the raw JSON into the current (v3.2) shape
+//!   • Unknown versions fail with `UnsupportedVersion`, which names the
+//!     versions the gateway accepts
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-use serde_json::{Value, json};
+use serde_json::Value;
 use anyhow::{Result, anyhow};
 use uuid::Uuid;
 
@@ -23,42 +43,426 @@ use crate::protocol::{
     TGPMessage,
     QueryMessage,
     AckMessage, AckStatus,
-    ErrorMessage,
+    ErrorMessage, TgpErrorCode, make_protocol_error,
     SettleMessage,
+    TGP_VERSION,
 };
+use crate::tgp::canonical::{digest_hex, digest_value};
+use crate::tgp::legacy::{is_legacy_payload, upgrade_legacy_value};
+use crate::tgp::validation::validate_correlation_id;
 
 /// Metadata extracted during parse/classify stage.
 #[derive(Debug, Clone)]
 pub struct TGPMetadata {
     pub msg_id: String,
     pub msg_type: String,
+
+    /// QUERY this message belongs to: the message's own ID for a QUERY,
+    /// its `correlation_id` otherwise.
     pub correlation_id: Option<String>,
+
+    /// keccak256 of the canonical form of the payload as received (before
+    /// any legacy/version upgrade). Used for logging and signing.
+    pub digest: String,
+}
+
+impl TGPMetadata {
+    /// Replay cache key: (message type, message ID). A message re-sent
+    /// under the same ID is a replay even if its body was altered.
+    pub fn replay_key(&self) -> String {
+        format!("{}:{}", self.msg_type, self.msg_id)
+    }
+
+    /// `correlation_id` if it is a valid QUERY reference, safe to echo
+    /// back on a gateway-originated ERROR.
+    pub fn query_reference(&self) -> Option<&str> {
+        self.correlation_id
+            .as_deref()
+            .filter(|id| validate_correlation_id(id, Some("QUERY")).is_ok())
+    }
 }
 
 /// Replay protection trait.
 pub trait ReplayProtector {
     fn check_or_insert(&self, msg_id: &str) -> bool;
+
+    /// Counters for admin stats. Backends without counters report zeros.
+    fn stats(&self) -> ReplayStats {
+        ReplayStats::default()
+    }
+}
+
+// ===========================================================================
+// Bounded Replay Cache
+// ===========================================================================
+
+/// Default number of message IDs remembered by `InMemoryReplayCache`.
+pub const DEFAULT_REPLAY_CAPACITY: usize = 65_536;
+
+/// Default replay window. An ID seen longer ago than this is accepted again.
+pub const DEFAULT_REPLAY_TTL: Duration = Duration::from_secs(600);
+
+const REPLAY_SHARDS: usize = 16;
+
+/// Counters exposed for admin stats and benchmarks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    /// Duplicates rejected.
+    pub hits: u64,
+    /// Fresh IDs accepted.
+    pub misses: u64,
+    /// Entries dropped for capacity or TTL.
+    pub evictions: u64,
+    /// Entries currently held.
+    pub len: usize,
+}
+
+/// One shard: an ID → first-seen map plus an insertion-ordered queue.
+///
+/// Entries are never refreshed by a replay, so insertion order is also
+/// least-recently-used order and expired entries sit at the front.
+struct ReplayShard {
+    seen: HashMap<String, Instant>,
+    order: VecDeque<(String, Instant)>,
+}
+
+impl ReplayShard {
+    fn new() -> Self {
+        Self { seen: HashMap::new(), order: VecDeque::new() }
+    }
+
+    /// Drop expired entries, then the oldest until under `capacity`.
+    /// Returns the number of entries removed.
+    fn evict(&mut self, now: Instant, ttl: Duration, capacity: usize) -> u64 {
+        let mut evicted = 0;
+        while let Some((id, at)) = self.order.front() {
+            let expired = now.duration_since(*at) >= ttl;
+            if !expired && self.seen.len() < capacity {
+                break;
+            }
+            // Skip queue entries superseded by a re-insert after expiry
+            if self.seen.get(id) == Some(at) {
+                self.seen.remove(id);
+                evicted += 1;
+            }
+            self.order.pop_front();
+        }
+        evicted
+    }
+}
+
+/// Capacity- and TTL-bounded replay cache, sharded by message ID hash.
+///
+/// Memory is bounded by `capacity` regardless of traffic; an ID is rejected
+/// as a replay while it is both within `ttl` and not yet evicted.
+pub struct InMemoryReplayCache {
+    shards: Vec<Mutex<ReplayShard>>,
+    shard_capacity: usize,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl InMemoryReplayCache {
+    /// Cache holding up to `capacity` IDs for `DEFAULT_REPLAY_TTL`.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_ttl(capacity, DEFAULT_REPLAY_TTL)
+    }
+
+    pub fn with_ttl(capacity: usize, ttl: Duration) -> Self {
+        let shard_capacity = capacity.div_ceil(REPLAY_SHARDS).max(1);
+        Self {
+            shards: (0..REPLAY_SHARDS).map(|_| Mutex::new(ReplayShard::new())).collect(),
+            shard_capacity,
+            ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shard_capacity * REPLAY_SHARDS
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().seen.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> ReplayStats {
+        ReplayStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            len: self.len(),
+        }
+    }
+
+    fn shard_for(&self, msg_id: &str) -> &Mutex<ReplayShard> {
+        let mut hasher = DefaultHasher::new();
+        msg_id.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % REPLAY_SHARDS]
+    }
+
+    fn check_or_insert_at(&self, msg_id: &str, now: Instant) -> bool {
+        let mut shard = self.shard_for(msg_id).lock().unwrap();
+
+        let live = shard.seen
+            .get(msg_id)
+            .is_some_and(|at| now.duration_since(*at) < self.ttl);
+        if live {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        let evicted = shard.evict(now, self.ttl, self.shard_capacity);
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+
+        shard.seen.insert(msg_id.to_string(), now);
+        shard.order.push_back((msg_id.to_string(), now));
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        true
+    }
 }
 
-/// In-memory replay cache for dev/tests.
-#[derive(Default)]
-pub struct InMemoryReplayCache(std::sync::Mutex<std::collections::HashSet<String>>);
+impl Default for InMemoryReplayCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_CAPACITY)
+    }
+}
 
 impl ReplayProtector for InMemoryReplayCache {
     fn check_or_insert(&self, msg_id: &str) -> bool {
-        let mut guard = self.0.lock().unwrap();
-        guard.insert(msg_id.to_string())
+        self.check_or_insert_at(msg_id, Instant::now())
+    }
+
+    fn stats(&self) -> ReplayStats {
+        InMemoryReplayCache::stats(self)
     }
 }
 
+// ===========================================================================
+// Version Registry
+// ===========================================================================
+
+/// Rewrites a raw payload of one wire version into the current version.
+pub type UpgradeShim = fn(Value) -> std::result::Result<Value, String>;
+
+/// Decoder entry for a single accepted wire version.
+#[derive(Debug, Clone)]
+pub struct VersionCodec {
+    pub version: &'static str,
+    pub upgrade: UpgradeShim,
+}
+
+/// Raised when a payload declares a version the registry does not accept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsupportedVersion {
+    pub version: String,
+    pub supported: Vec<&'static str>,
+}
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unsupported TGP version {} (accepted: {})",
+            self.version,
+            self.supported.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+/// Accepted wire versions and their upgrade shims.
+///
+/// `VersionRegistry::default()` accepts 3.1 and 3.2. Future revisions are
+/// added with `with_version` without touching the canonical model.
+#[derive(Debug, Clone)]
+pub struct VersionRegistry {
+    codecs: Vec<VersionCodec>,
+
+    /// Chain assigned to upgraded legacy QUERYs, which carry none
+    legacy_chain_id: Option<u64>,
+}
+
+impl VersionRegistry {
+    /// Registry accepting only the current version.
+    pub fn new() -> Self {
+        Self {
+            codecs: vec![VersionCodec { version: TGP_VERSION, upgrade: Ok }],
+            legacy_chain_id: None,
+        }
+    }
+
+    /// Accept `version`, upgrading its payloads with `upgrade`.
+    /// Re-registering a version replaces its shim.
+    pub fn with_version(mut self, version: &'static str, upgrade: UpgradeShim) -> Self {
+        self.codecs.retain(|c| c.version != version);
+        self.codecs.push(VersionCodec { version, upgrade });
+        self.codecs.sort_by_key(|c| version_key(c.version));
+        self
+    }
+
+    /// Settle upgraded legacy QUERYs on `chain_id`. Without it legacy
+    /// QUERYs are rejected.
+    pub fn with_legacy_chain(mut self, chain_id: u64) -> Self {
+        self.legacy_chain_id = Some(chain_id);
+        self
+    }
+
+    /// Accepted versions, oldest first. Advertised on `/status`.
+    pub fn supported_versions(&self) -> Vec<&'static str> {
+        self.codecs.iter().map(|c| c.version).collect()
+    }
+
+    pub fn supports(&self, version: &str) -> bool {
+        self.codecs.iter().any(|c| c.version == version)
+    }
+
+    /// Wire version declared by a raw payload.
+    ///
+    /// Only QUERY is required to carry `tgp_version`. Other messages may
+    /// carry it; an OFFER (removed in 3.2) implies 3.1; anything else is
+    /// taken to be current.
+    pub fn detect_version(v: &Value) -> String {
+        if let Some(ver) = v.get("tgp_version").and_then(|x| x.as_str()) {
+            return ver.to_string();
+        }
+        match v.get("type").and_then(|x| x.as_str()) {
+            Some(t) if t.eq_ignore_ascii_case("OFFER") => "3.1".into(),
+            _ => TGP_VERSION.into(),
+        }
+    }
+
+    /// Upgrade a raw payload into the current wire version.
+    pub fn upgrade(&self, v: Value) -> Result<Value> {
+        let version = Self::detect_version(&v);
+
+        let codec = self.codecs
+            .iter()
+            .find(|c| c.version == version)
+            .ok_or_else(|| UnsupportedVersion {
+                version: version.clone(),
+                supported: self.supported_versions(),
+            })?;
+
+        (codec.upgrade)(v).map_err(|e| anyhow!("Invalid TGP {} message: {}", version, e))
+    }
+}
+
+/// Numeric (major, minor) ordering key, so "3.10" sorts after "3.2".
+/// Unparseable components sort first.
+fn version_key(version: &str) -> (u32, u32) {
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+impl Default for VersionRegistry {
+    fn default() -> Self {
+        Self::new().with_version("3.1", upgrade_v3_1)
+    }
+}
+
+/// 3.1 → 3.2 shim.
+///
+///   • `tgp_version` is re-stamped as 3.2
+///   • OFFER becomes ACK(status=offer); its `query_id` becomes `correlation_id`
+///   • QUERY.intent.mode (new in 3.2) defaults to `direct`
+pub fn upgrade_v3_1(mut v: Value) -> std::result::Result<Value, String> {
+    let obj = v.as_object_mut().ok_or("message must be a JSON object")?;
+
+    let typ = obj.get("type")
+        .and_then(|t| t.as_str())
+        .map(|t| t.to_uppercase())
+        .unwrap_or_default();
+
+    if obj.contains_key("tgp_version") {
+        obj.insert("tgp_version".into(), Value::from(TGP_VERSION));
+    }
+
+    match typ.as_str() {
+        "OFFER" => {
+            obj.insert("type".into(), Value::from("ACK"));
+            obj.insert("status".into(), Value::from("offer"));
+            if let Some(qid) = obj.remove("query_id") {
+                obj.insert("correlation_id".into(), qid);
+            }
+        }
+        "QUERY" => {
+            if let Some(intent) = obj.get_mut("intent").and_then(|i| i.as_object_mut()) {
+                intent.entry("mode").or_insert_with(|| Value::from("direct"));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(v)
+}
+
+fn default_registry() -> &'static VersionRegistry {
+    static REGISTRY: OnceLock<VersionRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(VersionRegistry::default)
+}
+
 /// ===========================================================================
 /// classify_message(raw_json)
 /// → (TGPMetadata, TGPMessage)
 /// ===========================================================================
 pub fn classify_message(raw: &str) -> Result<(TGPMetadata, TGPMessage)> {
+    classify_message_with(default_registry(), raw)
+}
+
+/// `classify_message` against an explicit version registry.
+pub fn classify_message_with(
+    versions: &VersionRegistry,
+    raw: &str,
+) -> Result<(TGPMetadata, TGPMessage)> {
     let v: Value = serde_json::from_str(raw)
         .map_err(|e| anyhow!("JSON parse error: {}", e))?;
 
+    classify_value_with(versions, v)
+}
+
+/// Classify an already-decoded payload (JSON or CBOR).
+///
+/// The digest is taken over the canonical JSON form, so a message has the
+/// same identity whichever wire format carried it.
+pub fn classify_value_with(
+    versions: &VersionRegistry,
+    v: Value,
+) -> Result<(TGPMetadata, TGPMessage)> {
+    let digest = digest_hex(&digest_value(&v));
+
+    // -----------------------------------------------------------------------
+    // 0. Upgrade legacy `phase`-tagged payloads
+    // -----------------------------------------------------------------------
+    if is_legacy_payload(&v) {
+        let msg = upgrade_legacy_value(v, versions.legacy_chain_id).map_err(|e| anyhow!(e))?;
+        let metadata = TGPMetadata {
+            msg_id: msg.id().to_string(),
+            msg_type: msg.msg_type().to_string(),
+            correlation_id: msg.query_id().map(str::to_string),
+            digest,
+        };
+        return Ok((metadata, msg));
+    }
+
+    // -----------------------------------------------------------------------
+    // 0b. Upgrade older wire versions to the current one
+    // -----------------------------------------------------------------------
+    let v = versions.upgrade(v)?;
+
     // -----------------------------------------------------------------------
     // 1. Extract "type"
     // -----------------------------------------------------------------------
@@ -75,16 +479,6 @@ pub fn classify_message(raw: &str) -> Result<(TGPMetadata, TGPMessage)> {
         None => Uuid::new_v4().to_string(),
     };
 
-    let correlation_id = v.get("correlation_id")
-        .and_then(|x| x.as_str())
-        .map(|s| s.to_string());
-
-    let metadata = TGPMetadata {
-        msg_id: msg_id.clone(),
-        msg_type: typ.clone(),
-        correlation_id,
-    };
-
     // -----------------------------------------------------------------------
     // 3. Dispatch by type
     // -----------------------------------------------------------------------
@@ -150,6 +544,13 @@ pub fn classify_message(raw: &str) -> Result<(TGPMetadata, TGPMessage)> {
         }
     };
 
+    let metadata = TGPMetadata {
+        msg_id,
+        msg_type: typ,
+        correlation_id: tgp_msg.query_id().map(str::to_string),
+        digest,
+    };
+
     Ok((metadata, tgp_msg))
 }
 
@@ -167,30 +568,193 @@ pub fn validate_and_classify_message(
     msg: &TGPMessage,
 ) -> TGPValidationResult {
 
-    let res = match msg {
-        TGPMessage::Query(m)  => m.validate(),
-        TGPMessage::Ack(m)    => m.validate(),
-        TGPMessage::Settle(m) => m.validate(),
-        TGPMessage::Error(m)  => m.validate(),
-    };
-
-    match res {
+    match msg.validate() {
         Ok(_) => TGPValidationResult::Accept,
         Err(e) => {
-            let err = ErrorMessage::new(
-                meta.msg_id.clone(),
-                "INVALID_MESSAGE",
-                format!("{}", e)
-            );
+            let mut err = make_protocol_error(TgpErrorCode::InvalidMessage, e);
+            // Only echo a correlation_id that is itself a valid QUERY reference
+            err.correlation_id = meta.query_reference().map(str::to_string);
             TGPValidationResult::Reject(err)
         }
     }
 }
 
+// ===========================================================================
+// Wire Formats
+// ===========================================================================
+
+/// Transport encoding, negotiated per WebSocket connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl WireFormat {
+    /// WebSocket subprotocol name for this format.
+    pub fn subprotocol(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "tgp.json",
+            WireFormat::Cbor => "tgp.cbor",
+        }
+    }
+
+    pub fn from_subprotocol(name: &str) -> Option<Self> {
+        match name.trim() {
+            "tgp.json" => Some(WireFormat::Json),
+            "tgp.cbor" => Some(WireFormat::Cbor),
+            _ => None,
+        }
+    }
+}
+
+/// Decode a CBOR frame into the JSON data model used by `classify_value_with`.
+pub fn decode_cbor(bytes: &[u8]) -> Result<Value> {
+    ciborium::de::from_reader(bytes)
+        .map_err(|e| anyhow!("CBOR decode error: {}", e))
+}
+
+/// Encode a message as CBOR (same field names and shapes as JSON).
+pub fn encode_cbor(msg: &TGPMessage) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    ciborium::ser::into_writer(msg, &mut out)
+        .map_err(|e| anyhow!("CBOR encode error: {}", e))?;
+    Ok(out)
+}
+
 /// ===========================================================================
 /// encode_message(msg)
+/// Canonical (RFC 8785) output: stable member order, no whitespace.
 /// ===========================================================================
 pub fn encode_message(msg: &TGPMessage) -> Result<String> {
-    serde_json::to_string(msg)
+    msg.canonical_json()
         .map_err(|e| anyhow!("encode error: {}", e))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "0x1111111111111111111111111111111111111111";
+
+    fn query_json(version: &str) -> String {
+        format!(
+            r#"{{"type":"QUERY","tgp_version":"{version}","id":"q-1",
+                "intent":{{"verb":"PAY","party":"BUYER"}},
+                "payment_profile":"{PROFILE}","amount":10,"chain_id":369}}"#
+        )
+    }
+
+    #[test]
+    fn test_default_registry_versions() {
+        let reg = VersionRegistry::default();
+        assert_eq!(reg.supported_versions(), vec!["3.1", "3.2"]);
+        assert!(reg.supports("3.1"));
+        assert!(!reg.supports("3.0"));
+    }
+
+    #[test]
+    fn test_v3_1_query_upgraded() {
+        let (_, msg) = classify_message(&query_json("3.1")).unwrap();
+        match msg {
+            TGPMessage::Query(q) => {
+                assert_eq!(q.tgp_version, TGP_VERSION);
+                assert!(q.validate().is_ok());
+            }
+            other => panic!("expected QUERY, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_v3_1_offer_becomes_ack_offer() {
+        let raw = r#"{"type":"OFFER","id":"offer-1","query_id":"q-1",
+            "intent":{"verb":"COMMIT","party":"BUYER","mode":"direct"}}"#;
+        let (meta, msg) = classify_message(raw).unwrap();
+        assert_eq!(meta.msg_type, "ACK");
+        match msg {
+            TGPMessage::Ack(a) => {
+                assert_eq!(a.status, AckStatus::Offer);
+                assert_eq!(a.id, "offer-1");
+                assert_eq!(a.correlation_id.as_deref(), Some("q-1"));
+            }
+            other => panic!("expected ACK, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unsupported_version_names_accepted() {
+        let err = classify_message(&query_json("2.0")).unwrap_err();
+        let unsupported = err.downcast_ref::<UnsupportedVersion>().unwrap();
+        assert_eq!(unsupported.version, "2.0");
+        assert!(err.to_string().contains("3.1, 3.2"));
+
+        // Future revisions register alongside the built-in ones
+        let reg = VersionRegistry::default().with_version("3.3", Ok);
+        assert!(reg.upgrade(serde_json::from_str(&query_json("3.3")).unwrap()).is_ok());
+
+        // Versions order numerically, not lexically
+        let reg = reg.with_version("3.10", Ok);
+        assert_eq!(reg.supported_versions(), vec!["3.1", "3.2", "3.3", "3.10"]);
+    }
+
+    #[test]
+    fn test_digest_ignores_formatting() {
+        let pretty = query_json("3.2").replace(r#""party":"BUYER""#, r#""party": "BUYER", "mode": "direct""#);
+        let compact = pretty.replace(['\n', ' '], "");
+        let (a, msg) = classify_message(&pretty).unwrap();
+        let (b, _) = classify_message(&compact).unwrap();
+        assert_eq!(a.digest, b.digest);
+
+        // Canonical re-encoding is stable and decodes to the same message
+        let encoded = encode_message(&msg).unwrap();
+        let (_, again) = classify_message(&encoded).unwrap();
+        assert_eq!(encode_message(&again).unwrap(), encoded);
+        assert_eq!(again.digest_hex().unwrap(), msg.digest_hex().unwrap());
+    }
+
+    #[test]
+    fn test_cbor_roundtrip_same_identity() {
+        let json = query_json("3.2").replace(r#""party":"BUYER""#, r#""party":"BUYER","mode":"direct""#);
+        let (_, msg) = classify_message(&json).unwrap();
+        let (json_meta, _) = classify_message(&encode_message(&msg).unwrap()).unwrap();
+
+        let bytes = encode_cbor(&msg).unwrap();
+        let (cbor_meta, decoded) =
+            classify_value_with(default_registry(), decode_cbor(&bytes).unwrap()).unwrap();
+
+        assert_eq!(decoded, msg);
+        assert_eq!(cbor_meta.digest, json_meta.digest);
+        assert!(decode_cbor(&[0xff, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_replay_cache_rejects_duplicates() {
+        let cache = InMemoryReplayCache::new(128);
+        assert!(cache.check_or_insert("m-1"));
+        assert!(!cache.check_or_insert("m-1"));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.len), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_replay_cache_bounded_by_capacity() {
+        let cache = InMemoryReplayCache::new(64);
+        for i in 0..10_000 {
+            cache.check_or_insert(&format!("m-{}", i));
+        }
+        assert!(cache.len() <= cache.capacity());
+        assert!(cache.stats().evictions >= 10_000 - cache.capacity() as u64);
+    }
+
+    #[test]
+    fn test_replay_cache_ttl_expiry() {
+        let cache = InMemoryReplayCache::with_ttl(128, Duration::from_secs(60));
+        let t0 = Instant::now();
+
+        assert!(cache.check_or_insert_at("m-1", t0));
+        assert!(!cache.check_or_insert_at("m-1", t0 + Duration::from_secs(59)));
+        assert!(cache.check_or_insert_at("m-1", t0 + Duration::from_secs(61)));
+        assert_eq!(cache.len(), 1);
+    }
+}
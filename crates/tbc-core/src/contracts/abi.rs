@@ -0,0 +1,144 @@
+//! Offline ABI Encoder
+//!
+//! Minimal Solidity ABI encoding for CoreProve contract calls. Covers only
+//! the argument types the settlement contract takes (`address`, `uint256`,
+//! `bytes32`, `bytes`), so calldata can be built without ethers, an ABI
+//! JSON file or a live provider.
+
+use sha3::{Digest, Keccak256};
+
+use super::types::{Address, Bytes32, U256};
+
+/// One ABI-encodable argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiToken {
+    Address(Address),
+    Uint(U256),
+    FixedBytes32(Bytes32),
+    Bytes(Vec<u8>),
+}
+
+impl AbiToken {
+    /// Head word for static tokens.
+    fn static_word(&self) -> [u8; 32] {
+        match self {
+            AbiToken::Address(addr) => {
+                let mut word = [0u8; 32];
+                word[12..].copy_from_slice(addr);
+                word
+            }
+            AbiToken::Uint(n) => n.to_be_bytes(),
+            AbiToken::FixedBytes32(b) => *b,
+            AbiToken::Bytes(_) => unreachable!("bytes is dynamic"),
+        }
+    }
+}
+
+/// First four bytes of `keccak256(signature)`.
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    let hash = Keccak256::digest(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Full `keccak256(signature)`, the `topics[0]` of an event log.
+pub fn event_topic(signature: &str) -> Bytes32 {
+    Keccak256::digest(signature.as_bytes()).into()
+}
+
+/// Encode arguments as a tuple (head/tail layout, ABI spec §"Formal Specification").
+pub fn encode_tokens(tokens: &[AbiToken]) -> Vec<u8> {
+    let head_len = tokens.len() * 32;
+    let mut head = Vec::with_capacity(head_len);
+    let mut tail = Vec::new();
+
+    for token in tokens {
+        match token {
+            AbiToken::Bytes(data) => {
+                let offset = U256::from_u64((head_len + tail.len()) as u64);
+                head.extend_from_slice(&offset.to_be_bytes());
+
+                tail.extend_from_slice(&U256::from_u64(data.len() as u64).to_be_bytes());
+                tail.extend_from_slice(data);
+                tail.resize(tail.len() + padding(data.len()), 0);
+            }
+            _ => head.extend_from_slice(&token.static_word()),
+        }
+    }
+
+    head.extend_from_slice(&tail);
+    head
+}
+
+/// Selector followed by the encoded arguments.
+pub fn encode_call(signature: &str, tokens: &[AbiToken]) -> Vec<u8> {
+    let mut calldata = function_selector(signature).to_vec();
+    calldata.extend_from_slice(&encode_tokens(tokens));
+    calldata
+}
+
+/// `uint256` word that must fit a `u64` (versions, chain IDs, timestamps).
+pub fn word_u64(word: &Bytes32) -> Option<u64> {
+    if word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(u64::from_be_bytes(word[24..].try_into().unwrap()))
+}
+
+/// `address` word (right-aligned in 32 bytes).
+pub fn word_address(word: &Bytes32) -> Address {
+    word[12..].try_into().unwrap()
+}
+
+/// Bytes needed to right-pad `len` to a 32-byte boundary.
+fn padding(len: usize) -> usize {
+    (32 - len % 32) % 32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_and_static_encoding() {
+        // ERC-20 transfer(address,uint256) -- the canonical reference vector
+        let mut to = [0u8; 20];
+        to[19] = 0x01;
+        let calldata = encode_call(
+            "transfer(address,uint256)",
+            &[AbiToken::Address(to), AbiToken::Uint(U256::from_u64(1000))],
+        );
+
+        assert_eq!(
+            hex::encode(calldata),
+            concat!(
+                "a9059cbb",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "00000000000000000000000000000000000000000000000000000000000003e8",
+            )
+        );
+    }
+
+    #[test]
+    fn test_event_topic() {
+        // ERC-20 Transfer(address,address,uint256)
+        assert_eq!(
+            hex::encode(event_topic("Transfer(address,address,uint256)")),
+            "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+    }
+
+    #[test]
+    fn test_dynamic_bytes_padding() {
+        let encoded = encode_tokens(&[AbiToken::Bytes(vec![0xab; 33])]);
+
+        // offset + length + two data words
+        assert_eq!(encoded.len(), 32 * 4);
+        assert_eq!(encoded[31], 0x20);
+        assert_eq!(encoded[63], 33);
+        assert_eq!(&encoded[64..97], &[0xab; 33][..]);
+        assert!(encoded[97..].iter().all(|b| *b == 0));
+
+        // Empty bytes: offset + zero length, no data words
+        assert_eq!(encode_tokens(&[AbiToken::Bytes(vec![])]).len(), 64);
+    }
+}
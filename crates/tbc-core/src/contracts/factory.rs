@@ -6,7 +6,8 @@
 //! with deterministic addresses and bytecode verification.
 
 use serde::{Deserialize, Serialize};
-use super::types::{Address, Bytes32, U256};
+use super::abi::{event_topic, word_address, word_u64};
+use super::types::{Address, Bytes32};
 
 // =============================================================================
 // ENUMS (from Solidity)
@@ -84,7 +85,7 @@ pub struct SetTemplateStabilityParams {
 }
 
 /// Parameters for deployMerchant() function call
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DeployMerchantParams {
     /// Template version to use
     pub version: u64,
@@ -153,9 +154,9 @@ impl DeployMerchantParams {
         use sha3::{Digest, Keccak256};
         
         let mut hasher = Keccak256::new();
-        hasher.update(&self.merchant_admin);
-        hasher.update(&self.version.to_be_bytes());
-        hasher.update(&self.salt);
+        hasher.update(self.merchant_admin);
+        hasher.update(self.version.to_be_bytes());
+        hasher.update(self.salt);
         
         let result = hasher.finalize();
         let mut hash = [0u8; 32];
@@ -174,6 +175,15 @@ pub struct RevokeMerchantParams {
 // EVENTS (from Solidity)
 // =============================================================================
 
+// Event signatures (`topics[0]` = `event_topic(SIG)`). Both merchant events
+// index every argument, so their logs carry no data.
+
+/// `MerchantDeployed` signature
+pub const MERCHANT_DEPLOYED_EVENT_SIG: &str = "MerchantDeployed(uint256,address,address)";
+
+/// `MerchantRevoked` signature
+pub const MERCHANT_REVOKED_EVENT_SIG: &str = "MerchantRevoked(address)";
+
 /// TemplateRegistered event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplateRegisteredEvent {
@@ -204,6 +214,32 @@ pub struct MerchantRevokedEvent {
     pub merchant_contract: Address,
 }
 
+impl MerchantDeployedEvent {
+    /// Decode from a log's `topics`; `None` if it is not this event.
+    pub fn from_log(topics: &[Bytes32], data: &[u8]) -> Option<Self> {
+        if topics.len() != 4 || topics[0] != event_topic(MERCHANT_DEPLOYED_EVENT_SIG) || !data.is_empty() {
+            return None;
+        }
+        Some(Self {
+            version: word_u64(&topics[1])?,
+            merchant_contract: word_address(&topics[2]),
+            merchant_admin: word_address(&topics[3]),
+        })
+    }
+}
+
+impl MerchantRevokedEvent {
+    /// Decode from a log's `topics`; `None` if it is not this event.
+    pub fn from_log(topics: &[Bytes32], data: &[u8]) -> Option<Self> {
+        if topics.len() != 2 || topics[0] != event_topic(MERCHANT_REVOKED_EVENT_SIG) || !data.is_empty() {
+            return None;
+        }
+        Some(Self {
+            merchant_contract: word_address(&topics[1]),
+        })
+    }
+}
+
 /// MerchantCodeHashMismatch event (indicates bytecode tampering)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerchantCodeHashMismatchEvent {
@@ -279,7 +315,7 @@ pub fn predict_create2_address(
     use sha3::{Digest, Keccak256};
     
     let mut hasher = Keccak256::new();
-    hasher.update(&[0xff]);
+    hasher.update([0xff]);
     hasher.update(factory);
     hasher.update(salt);
     hasher.update(init_code_hash);
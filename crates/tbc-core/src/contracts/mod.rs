@@ -10,11 +10,13 @@
 //! - Decoding events and return values
 //! - Type-safe contract binding in TBC
 
+pub mod abi;
 pub mod settlement;
 pub mod receipt_vault;
 pub mod factory;
 pub mod types;
 
+pub use abi::*;
 pub use settlement::*;
 pub use receipt_vault::*;
 pub use factory::*;
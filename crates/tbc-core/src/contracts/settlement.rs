@@ -8,6 +8,7 @@
 //! 3. Settlement distributes funds and mints receipt
 
 use serde::{Deserialize, Serialize};
+use super::abi::{encode_call, event_topic, word_address, word_u64, AbiToken};
 use super::types::{Address, Bytes32, U256};
 
 // =============================================================================
@@ -58,7 +59,7 @@ impl Default for BuyerCommit {
 /// Seller's fulfillment commitment
 ///
 /// Mirrors: struct SellerCommit in Solidity
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SellerCommit {
     /// ZK commitment of seller identity
     pub pk_hash: Bytes32,
@@ -73,17 +74,6 @@ pub struct SellerCommit {
     pub exists: bool,
 }
 
-impl Default for SellerCommit {
-    fn default() -> Self {
-        Self {
-            pk_hash: [0u8; 32],
-            nullifier: [0u8; 32],
-            timestamp: 0,
-            exists: false,
-        }
-    }
-}
-
 // =============================================================================
 // FUNCTION CALL PARAMETERS
 // =============================================================================
@@ -122,10 +112,92 @@ pub struct BuyerCancelParams {
     pub order_id: Bytes32,
 }
 
+// =============================================================================
+// CALLDATA
+// =============================================================================
+
+/// `buyerCommit` signature (selector `0x57ebfa73`)
+pub const BUYER_COMMIT_SIG: &str =
+    "buyerCommit(bytes32,address,uint256,bytes32,bytes32,uint256,bytes)";
+
+/// `sellerCommit` signature (selector `0x949fb292`)
+pub const SELLER_COMMIT_SIG: &str = "sellerCommit(bytes32,bytes32,bytes32,uint256,bytes)";
+
+/// `settle` signature (selector `0x987757dd`)
+pub const SETTLE_SIG: &str = "settle(bytes32)";
+
+/// `buyerCancelExpiredCommit` signature (selector `0xcd2eb301`)
+pub const BUYER_CANCEL_SIG: &str = "buyerCancelExpiredCommit(bytes32)";
+
+impl BuyerCommitParams {
+    /// ABI-encoded `buyerCommit` calldata.
+    pub fn calldata(&self) -> Vec<u8> {
+        encode_call(
+            BUYER_COMMIT_SIG,
+            &[
+                AbiToken::FixedBytes32(self.order_id),
+                AbiToken::Address(self.asset),
+                AbiToken::Uint(self.amount),
+                AbiToken::FixedBytes32(self.pk_hash),
+                AbiToken::FixedBytes32(self.nullifier),
+                AbiToken::Uint(U256::from_u64(self.timestamp)),
+                AbiToken::Bytes(self.zk_proof.clone()),
+            ],
+        )
+    }
+}
+
+impl SellerCommitParams {
+    /// ABI-encoded `sellerCommit` calldata.
+    pub fn calldata(&self) -> Vec<u8> {
+        encode_call(
+            SELLER_COMMIT_SIG,
+            &[
+                AbiToken::FixedBytes32(self.order_id),
+                AbiToken::FixedBytes32(self.pk_hash),
+                AbiToken::FixedBytes32(self.nullifier),
+                AbiToken::Uint(U256::from_u64(self.timestamp)),
+                AbiToken::Bytes(self.zk_proof.clone()),
+            ],
+        )
+    }
+}
+
+impl SettleParams {
+    /// ABI-encoded `settle` calldata.
+    pub fn calldata(&self) -> Vec<u8> {
+        encode_call(SETTLE_SIG, &[AbiToken::FixedBytes32(self.order_id)])
+    }
+}
+
+impl BuyerCancelParams {
+    /// ABI-encoded `buyerCancelExpiredCommit` calldata.
+    pub fn calldata(&self) -> Vec<u8> {
+        encode_call(BUYER_CANCEL_SIG, &[AbiToken::FixedBytes32(self.order_id)])
+    }
+}
+
 // =============================================================================
 // EVENTS (from Solidity)
 // =============================================================================
 
+// Event signatures (`topics[0]` = `event_topic(SIG)`); every settlement event
+// indexes `orderId` first, so `topics[1]` is the order ID.
+
+/// `BuyerCommitted` signature
+pub const BUYER_COMMITTED_EVENT_SIG: &str =
+    "BuyerCommitted(bytes32,bytes32,bytes32,uint256,address,uint256)";
+
+/// `SellerCommitted` signature
+pub const SELLER_COMMITTED_EVENT_SIG: &str = "SellerCommitted(bytes32,bytes32,bytes32,uint256)";
+
+/// `SettlementCompleted` signature
+pub const SETTLEMENT_COMPLETED_EVENT_SIG: &str =
+    "SettlementCompleted(bytes32,uint256,bytes32,bytes32,uint256,address,uint256,uint256)";
+
+/// `BuyerRefunded` signature
+pub const BUYER_REFUNDED_EVENT_SIG: &str = "BuyerRefunded(bytes32,bytes32,uint256,address,uint256)";
+
 /// BuyerCommitted event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuyerCommittedEvent {
@@ -169,6 +241,77 @@ pub struct BuyerRefundedEvent {
     pub timestamp: u64,
 }
 
+impl BuyerCommittedEvent {
+    /// Decode from a log's `topics` and `data`; `None` if it is not this event.
+    pub fn from_log(topics: &[Bytes32], data: &[u8]) -> Option<Self> {
+        let (order_id, w) = log_words(BUYER_COMMITTED_EVENT_SIG, topics, data, 5)?;
+        Some(Self {
+            order_id,
+            nullifier: w[0],
+            pk_hash: w[1],
+            amount: U256::from_be_bytes(w[2]),
+            asset: word_address(&w[3]),
+            timestamp: word_u64(&w[4])?,
+        })
+    }
+}
+
+impl SellerCommittedEvent {
+    /// Decode from a log's `topics` and `data`; `None` if it is not this event.
+    pub fn from_log(topics: &[Bytes32], data: &[u8]) -> Option<Self> {
+        let (order_id, w) = log_words(SELLER_COMMITTED_EVENT_SIG, topics, data, 3)?;
+        Some(Self {
+            order_id,
+            nullifier: w[0],
+            pk_hash: w[1],
+            timestamp: word_u64(&w[2])?,
+        })
+    }
+}
+
+impl SettlementCompletedEvent {
+    /// Decode from a log's `topics` and `data`; `None` if it is not this event.
+    pub fn from_log(topics: &[Bytes32], data: &[u8]) -> Option<Self> {
+        let (order_id, w) = log_words(SETTLEMENT_COMPLETED_EVENT_SIG, topics, data, 7)?;
+        Some(Self {
+            order_id,
+            chain_id: word_u64(&w[0])?,
+            buyer_pk_hash: w[1],
+            seller_pk_hash: w[2],
+            amount: U256::from_be_bytes(w[3]),
+            asset: word_address(&w[4]),
+            timestamp: word_u64(&w[5])?,
+            receipt_id: U256::from_be_bytes(w[6]),
+        })
+    }
+}
+
+impl BuyerRefundedEvent {
+    /// Decode from a log's `topics` and `data`; `None` if it is not this event.
+    pub fn from_log(topics: &[Bytes32], data: &[u8]) -> Option<Self> {
+        let (order_id, w) = log_words(BUYER_REFUNDED_EVENT_SIG, topics, data, 4)?;
+        Some(Self {
+            order_id,
+            buyer_pk_hash: w[0],
+            amount: U256::from_be_bytes(w[1]),
+            asset: word_address(&w[2]),
+            timestamp: word_u64(&w[3])?,
+        })
+    }
+}
+
+/// Indexed order ID and the first `n` data words of a log matching `signature`.
+fn log_words(signature: &str, topics: &[Bytes32], data: &[u8], n: usize) -> Option<(Bytes32, Vec<Bytes32>)> {
+    if topics.len() != 2 || topics[0] != event_topic(signature) || data.len() != n * 32 {
+        return None;
+    }
+    let words = data
+        .chunks_exact(32)
+        .map(|c| c.try_into().expect("32-byte chunk"))
+        .collect();
+    Some((topics[1], words))
+}
+
 /// MerchantActiveChanged event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerchantActiveChangedEvent {
@@ -286,7 +429,7 @@ impl SellerZKPublicSignals {
         
         let mut hasher = Keccak256::new();
         hasher.update(order_id);
-        hasher.update(&amount.to_be_bytes());
+        hasher.update(amount.to_be_bytes());
         hasher.update(asset);
         hasher.update(pk_hash);
         
@@ -38,12 +38,55 @@ impl U256 {
     /// Create from big-endian bytes
     pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
         let mut limbs = [0u64; 4];
-        for i in 0..4 {
+        for (i, limb) in limbs.iter_mut().enumerate() {
             let start = (3 - i) * 8;
-            limbs[i] = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
+            *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
         }
         U256(limbs)
     }
+
+    /// Parse a base-10 string (JSON wei amounts); `None` on overflow or
+    /// anything but ASCII digits.
+    pub fn from_dec_str(s: &str) -> Option<Self> {
+        if s.is_empty() {
+            return None;
+        }
+        let mut limbs = [0u64; 4];
+        for c in s.chars() {
+            let mut carry = c.to_digit(10)? as u128;
+            for limb in limbs.iter_mut() {
+                let cur = *limb as u128 * 10 + carry;
+                *limb = cur as u64;
+                carry = cur >> 64;
+            }
+            if carry != 0 {
+                return None;
+            }
+        }
+        Some(U256(limbs))
+    }
+}
+
+impl std::fmt::Display for U256 {
+    /// Base-10, as Solidity tooling and JSON-RPC decimal strings expect.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if *self == U256::ZERO {
+            return write!(f, "0");
+        }
+
+        let mut limbs = self.0;
+        let mut digits = Vec::new();
+        while limbs.iter().any(|l| *l != 0) {
+            let mut rem: u128 = 0;
+            for limb in limbs.iter_mut().rev() {
+                let cur = (rem << 64) | *limb as u128;
+                *limb = (cur / 10) as u64;
+                rem = cur % 10;
+            }
+            digits.push(char::from(b'0' + rem as u8));
+        }
+        write!(f, "{}", digits.iter().rev().collect::<String>())
+    }
 }
 
 impl From<u64> for U256 {
@@ -7,6 +7,7 @@
 //! - `protocol` - TGP message types and protocol logic
 //! - `tgp` - TGP session management and validation
 //! - `codec_tx` - Transaction encoding/decoding
+//! - `store` - Persistent replay / nullifier store
 //! - `contracts` - Solidity contract type mirrors
 //! - `zk` - Zero-knowledge proof types (TGP-EXT-ZK-00)
 
@@ -15,6 +16,7 @@
 pub mod protocol;
 pub mod tgp;
 pub mod codec_tx;
+pub mod store;
 pub mod contracts;
 pub mod zk;
 
@@ -6,21 +6,47 @@
 //!   • Routing normalization
 //!   • ACK construction helpers (offer / allow / deny / revise)
 //!   • Terminal SETTLE message construction
-//!   • Protocol-compliant error builder
+//!   • Typed error codes and protocol-compliant error builder
 //!
 //! Mirrors SIP separation of concerns but includes runtime validation
 //! because TBC must produce deterministic ACKs and SETTLE events.
+//!
+//! This is the ONLY message model in tbc-core. Pre-v3.2 `phase`-tagged
+//! payloads are upgraded into these types by `tgp::legacy`.
 
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
-use crate::tgp::types::{EconomicEnvelope};
+use crate::tgp::canonical;
+use crate::tgp::quote::QuotePreview;
+use crate::tgp::types::{EconomicEnvelope, SettleSource};
 use crate::tgp::validation::{
     validate_non_empty,
+    validate_query_id,
+    validate_correlation_id,
     validate_address,
     validate_positive_amount,
+    validate_rfc3339_format,
+    validate_transaction_hash,
 };
 
+/// Wire version produced and validated by this model. Older revisions are
+/// upgraded to it by the version registry in `codec_tx`.
+pub const TGP_VERSION: &str = "3.2";
+
+/// Fresh ID for a gateway-originated message, e.g. `ack-<uuid>`.
+fn new_message_id(prefix: &str) -> String {
+    format!("{}{}", prefix, Uuid::new_v4())
+}
+
+/// `correlation_id`, when present, MUST reference a QUERY.
+fn validate_query_reference(correlation_id: &Option<String>) -> Result<(), String> {
+    match correlation_id {
+        Some(ref id) => validate_correlation_id(id, Some("QUERY")),
+        None => Ok(()),
+    }
+}
+
 // -----------------------------------------------------------------------------
 // 0. Canonical Enumerations
 // -----------------------------------------------------------------------------
@@ -128,11 +154,14 @@ impl QueryMessage {
         if self.msg_type != "QUERY" {
             return Err("QUERY.type must equal \"QUERY\"".into());
         }
-        if self.tgp_version != "3.2" {
-            return Err("Unsupported TGP version (must be 3.2)".into());
+        if self.tgp_version != TGP_VERSION {
+            return Err(format!(
+                "Unsupported TGP version {} (must be {})",
+                self.tgp_version, TGP_VERSION
+            ));
         }
 
-        validate_non_empty(&self.id, "id")?;
+        validate_query_id(&self.id, "id")?;
         validate_address(&self.payment_profile, "payment_profile")?;
         validate_positive_amount(self.amount, "amount")?;
 
@@ -163,16 +192,94 @@ pub struct AckMessage {
     pub status: AckStatus,
     pub id: String,
 
+    /// QUERY this ACK answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+
     pub intent: Intent,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub routing: Option<RoutingMetadata>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tx: Option<EconomicEnvelope>,
+    pub tx: Option<Box<EconomicEnvelope>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<String>,
+
+    /// Why the QUERY was denied or must be revised
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<AckReason>,
+
+    /// Fee/gas preview answering a QUOTE (status=offer only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<Box<QuotePreview>>,
+}
+
+/// Structured payload for ACK(status=deny|revise).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AckReason {
+    /// Machine-readable reason code (e.g. `POLICY_AMOUNT_LIMIT`)
+    pub code: String,
+
+    /// Human-readable explanation
+    pub message: String,
+
+    /// What a revised QUERY must satisfy (ACK(status=revise) only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<FieldConstraint>,
+}
+
+impl AckReason {
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn with_constraint(mut self, constraint: FieldConstraint) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_non_empty(&self.code, "reason.code")?;
+        validate_non_empty(&self.message, "reason.message")?;
+        for c in &self.constraints {
+            validate_non_empty(&c.field, "reason.constraints.field")?;
+        }
+        Ok(())
+    }
+}
+
+/// One requirement on a QUERY field, e.g. `{"field":"amount","op":"max","value":1000}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldConstraint {
+    /// Dotted QUERY path (`amount`, `intent.mode`, ...)
+    pub field: String,
+    pub op: ConstraintOp,
+    pub value: serde_json::Value,
+}
+
+impl FieldConstraint {
+    pub fn new(field: impl Into<String>, op: ConstraintOp, value: impl Into<serde_json::Value>) -> Self {
+        Self {
+            field: field.into(),
+            op,
+            value: value.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintOp {
+    Min,
+    Max,
+    Eq,
+    OneOf,
 }
 
 // ----------------------------
@@ -184,11 +291,14 @@ impl AckMessage {
         AckMessage {
             msg_type: "ACK".into(),
             status: AckStatus::Offer,
-            id: query.id.clone(),
+            id: new_message_id("ack-"),
+            correlation_id: Some(query.id.clone()),
             intent: query.intent.clone(),
             routing: Some(query.routing.clone()),
             tx: None,
             expires_at: None,
+            reason: None,
+            quote: None,
         }
     }
 
@@ -200,35 +310,53 @@ impl AckMessage {
         AckMessage {
             msg_type: "ACK".into(),
             status: AckStatus::Allow,
-            id: query.id.clone(),
+            id: new_message_id("ack-"),
+            correlation_id: Some(query.id.clone()),
             intent: query.intent.clone(),
             routing: Some(query.routing.clone()),
-            tx: Some(envelope),
+            tx: Some(Box::new(envelope)),
             expires_at: Some(expires_at),
+            reason: None,
+            quote: None,
         }
     }
 
-    pub fn deny_for(query: &QueryMessage, reason: &str) -> Self {
+    /// ACK(status=offer) carrying a QUOTE preview. Never executable.
+    pub fn quote_for(query: &QueryMessage, quote: QuotePreview, expires_at: String) -> Self {
+        AckMessage {
+            expires_at: Some(expires_at),
+            quote: Some(Box::new(quote)),
+            ..Self::offer_for(query)
+        }
+    }
+
+    pub fn deny_for(query: &QueryMessage, reason: AckReason) -> Self {
         AckMessage {
             msg_type: "ACK".into(),
             status: AckStatus::Deny,
-            id: query.id.clone(),
+            id: new_message_id("ack-"),
+            correlation_id: Some(query.id.clone()),
             intent: query.intent.clone(),
             routing: Some(query.routing.clone()),
             tx: None,
             expires_at: None,
+            reason: Some(reason),
+            quote: None,
         }
     }
 
-    pub fn revise_for(query: &QueryMessage, reason: &str) -> Self {
+    pub fn revise_for(query: &QueryMessage, reason: AckReason) -> Self {
         AckMessage {
             msg_type: "ACK".into(),
             status: AckStatus::Revise,
-            id: query.id.clone(),
+            id: new_message_id("ack-"),
+            correlation_id: Some(query.id.clone()),
             intent: query.intent.clone(),
             routing: Some(query.routing.clone()),
             tx: None,
             expires_at: None,
+            reason: Some(reason),
+            quote: None,
         }
     }
 
@@ -236,6 +364,14 @@ impl AckMessage {
         if self.msg_type != "ACK" {
             return Err("ACK.type must equal \"ACK\"".into());
         }
+        validate_non_empty(&self.id, "id")?;
+        validate_query_reference(&self.correlation_id)?;
+        if let Some(ref expires_at) = self.expires_at {
+            validate_rfc3339_format(expires_at, "expires_at")?;
+        }
+        if let Some(ref tx) = self.tx {
+            tx.validate()?;
+        }
 
         if self.status == AckStatus::Allow && self.tx.is_none() {
             return Err("ACK.status=allow requires tx".into());
@@ -245,6 +381,32 @@ impl AckMessage {
             return Err("ACK.status=offer MUST NOT include tx".into());
         }
 
+        match (&self.status, &self.reason) {
+            (AckStatus::Deny | AckStatus::Revise, _) if self.tx.is_some() => {
+                return Err("ACK.status=deny|revise MUST NOT include tx".into());
+            }
+            (AckStatus::Revise, None) => {
+                return Err("ACK.status=revise requires reason".into());
+            }
+            (AckStatus::Revise, Some(r)) if r.constraints.is_empty() => {
+                return Err("ACK.status=revise requires reason.constraints".into());
+            }
+            (AckStatus::Offer | AckStatus::Allow, Some(_)) => {
+                return Err("ACK.reason is only valid for status=deny|revise".into());
+            }
+            _ => {}
+        }
+        if let Some(ref reason) = self.reason {
+            reason.validate()?;
+        }
+
+        if let Some(ref quote) = self.quote {
+            if self.status != AckStatus::Offer {
+                return Err("ACK.quote is only valid for status=offer".into());
+            }
+            quote.validate()?;
+        }
+
         Ok(())
     }
 }
@@ -253,6 +415,207 @@ impl AckMessage {
 // 5. ERROR -- Protocol Failure
 // -----------------------------------------------------------------------------
 
+/// Declares `TgpErrorCode` from one `Variant => "WIRE_CODE"` list, so the
+/// serde renames, `ALL` and `as_str` cannot drift apart.
+macro_rules! tgp_error_codes {
+    ($($(#[doc = $doc:literal])* $variant:ident => $wire:literal,)*) => {
+        /// Every `ERROR.code` the gateway can originate.
+        ///
+        /// The wire form stays a string; this enum is the single source of truth for
+        /// which layer a code belongs to, whether a client may retry, and how HTTP
+        /// transports report it. Codes received from peers that are not listed here
+        /// are passed through untouched (see [`ErrorMessage::error_code`]).
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        pub enum TgpErrorCode {
+            $($(#[doc = $doc])* #[serde(rename = $wire)] $variant,)*
+        }
+
+        impl TgpErrorCode {
+            /// All codes, in declaration order.
+            pub const ALL: &'static [TgpErrorCode] = &[$(Self::$variant),*];
+
+            /// Wire string carried in `ERROR.code`.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $wire,)*
+                }
+            }
+        }
+    };
+}
+
+tgp_error_codes! {
+    /// Payload is not valid JSON or not a TGP message
+    InvalidJson => "INVALID_JSON",
+
+    /// Binary frame is not valid CBOR
+    InvalidCbor => "INVALID_CBOR",
+
+    /// Message decoded but failed structural validation
+    InvalidMessage => "INVALID_MESSAGE",
+
+    /// `tgp_version` not accepted by this gateway
+    UnsupportedVersion => "UNSUPPORTED_VERSION",
+
+    /// Message ID already seen
+    ReplayDetected => "REPLAY_DETECTED",
+
+    /// Chain ID is zero or unknown
+    ChainInvalid => "TGP_CHAIN_INVALID",
+
+    /// payment_profile is not a valid contract address
+    ProfileInvalid => "TGP_PROFILE_INVALID",
+
+    /// QUERY amount is zero
+    AmountZero => "TGP_AMOUNT_ZERO",
+
+    /// L1 registry check failed
+    L1Failure => "TGP_L1_FAILURE",
+
+    /// L2 cryptographic validation failed
+    L2Failure => "TGP_L2_FAILURE",
+
+    /// L3 contract bytecode / RPC check failed
+    L3Failure => "TGP_L3_FAILURE",
+
+    /// L4 ZK / attestation check failed
+    L4Failure => "TGP_L4_FAILURE",
+
+    /// L5 policy evaluation failed
+    L5Failure => "TGP_L5_FAILURE",
+
+    /// L6 WITHDRAW eligibility failed
+    L6WithdrawFailure => "TGP_L6_WITHDRAW_FAILURE",
+
+    /// All layers passed but no Economic Envelope could be built
+    EnvelopeFailure => "TGP_ENVELOPE_FAILURE",
+
+    /// ACK or SETTLE arrived after the envelope's `expires_at`
+    EnvelopeExpired => "TGP_ENVELOPE_EXPIRED",
+
+    /// HTTP transport could not dispatch the message
+    HttpDispatchError => "TBC_HTTP_DISPATCH_ERROR",
+
+    /// WebSocket transport could not dispatch the message
+    WsDispatchError => "TBC_WS_DISPATCH_ERROR",
+
+    /// Binary WebSocket frame without the `tgp.cbor` subprotocol
+    WsBinaryRejected => "TBC_WS_BINARY_REJECTED",
+
+    /// QUERY already passed through this gateway (`routing.path`)
+    RoutingLoop => "TGP_ROUTING_LOOP",
+
+    /// `routing.path` reached the hop limit
+    HopLimitExceeded => "TGP_HOP_LIMIT_EXCEEDED",
+
+    /// No trusted peer serves the target transaction area
+    NoRoute => "TGP_NO_ROUTE",
+
+    /// Every candidate peer failed to answer a forwarded QUERY
+    ForwardFailed => "TBC_FORWARD_FAILED",
+
+    /// SETTLE claim contradicts its on-chain transaction receipt
+    SettleUnverified => "TGP_SETTLE_UNVERIFIED",
+
+    /// Receipt needed to verify a SETTLE could not be fetched
+    ReceiptUnavailable => "TBC_RECEIPT_UNAVAILABLE",
+
+    /// Unexpected gateway failure
+    InternalError => "INTERNAL_ERROR",
+}
+
+impl std::fmt::Display for TgpErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TgpErrorCode {
+    /// Look up a wire code. `None` for codes this gateway does not originate.
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.as_str() == code)
+    }
+
+    /// Value reported in `ERROR.layer_failed` (0 = transport / pre-layer).
+    pub fn layer(&self) -> u8 {
+        match self {
+            Self::ChainInvalid | Self::ProfileInvalid | Self::AmountZero | Self::L1Failure => 1,
+            Self::L2Failure => 2,
+            Self::L3Failure => 3,
+            Self::L4Failure => 4,
+            Self::L5Failure | Self::EnvelopeFailure => 5,
+            Self::L6WithdrawFailure => 6,
+            _ => 0,
+        }
+    }
+
+    /// May the client resend the same QUERY (with a fresh ID) and expect a
+    /// different outcome? False for anything the message itself caused.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::L3Failure
+                | Self::EnvelopeFailure
+                | Self::HttpDispatchError
+                | Self::WsDispatchError
+                | Self::ForwardFailed
+                | Self::ReceiptUnavailable
+                | Self::InternalError
+        )
+    }
+
+    /// HTTP status used when this ERROR is returned over HTTP.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::InvalidJson | Self::InvalidCbor | Self::InvalidMessage | Self::UnsupportedVersion => 400,
+            Self::ReplayDetected => 409,
+            Self::ChainInvalid | Self::ProfileInvalid | Self::AmountZero => 422,
+            Self::L2Failure => 401,
+            Self::L1Failure | Self::L4Failure | Self::L5Failure | Self::L6WithdrawFailure => 403,
+            Self::SettleUnverified => 403,
+            Self::EnvelopeExpired => 410,
+            Self::WsBinaryRejected => 415,
+            Self::NoRoute => 421,
+            Self::RoutingLoop | Self::HopLimitExceeded => 508,
+            Self::L3Failure | Self::ForwardFailed => 502,
+            Self::ReceiptUnavailable => 503,
+            Self::EnvelopeFailure | Self::HttpDispatchError | Self::WsDispatchError | Self::InternalError => 500,
+        }
+    }
+
+    /// Default human-readable message.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::InvalidJson => "Payload is not a valid TGP JSON message",
+            Self::InvalidCbor => "Payload is not a valid TGP CBOR message",
+            Self::InvalidMessage => "Message failed validation",
+            Self::UnsupportedVersion => "TGP version is not supported by this gateway",
+            Self::ReplayDetected => "Message has already been processed",
+            Self::ChainInvalid => "Chain ID is not valid",
+            Self::ProfileInvalid => "payment_profile is not a valid contract address",
+            Self::AmountZero => "Amount must be greater than zero",
+            Self::L1Failure => "Merchant registry check failed",
+            Self::L2Failure => "Cryptographic validation failed",
+            Self::L3Failure => "Contract bytecode or RPC verification failed",
+            Self::L4Failure => "ZK attestation check failed",
+            Self::L5Failure => "Rejected by gateway policy",
+            Self::L6WithdrawFailure => "WITHDRAW is not permitted for this party",
+            Self::EnvelopeFailure => "Economic Envelope could not be built",
+            Self::EnvelopeExpired => "Economic Envelope has expired",
+            Self::HttpDispatchError => "Gateway could not dispatch the HTTP request",
+            Self::WsDispatchError => "Gateway could not dispatch the WebSocket frame",
+            Self::WsBinaryRejected => "Binary frames require the tgp.cbor subprotocol",
+            Self::RoutingLoop => "QUERY has already passed through this gateway",
+            Self::HopLimitExceeded => "QUERY exceeded the gateway hop limit",
+            Self::NoRoute => "No trusted gateway serves the target transaction area",
+            Self::ForwardFailed => "No downstream gateway answered the forwarded QUERY",
+            Self::SettleUnverified => "SETTLE does not match the on-chain transaction receipt",
+            Self::ReceiptUnavailable => "Transaction receipt for the SETTLE is not available yet",
+            Self::InternalError => "Internal gateway error",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ErrorMessage {
     #[serde(rename = "type")]
@@ -260,8 +623,13 @@ pub struct ErrorMessage {
 
     pub id: String,
     pub code: String,
+    #[serde(default)]
     pub layer_failed: u8,
     pub message: String,
+
+    /// QUERY that caused this ERROR, when it can be identified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
 }
 
 impl ErrorMessage {
@@ -272,9 +640,21 @@ impl ErrorMessage {
             code: code.into(),
             layer_failed: 0,
             message: message.into(),
+            correlation_id: None,
         }
     }
 
+    /// Point this ERROR at the QUERY it answers.
+    pub fn with_correlation_id(mut self, query_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(query_id.into());
+        self
+    }
+
+    /// Typed view of `code`; `None` for codes from other implementations.
+    pub fn error_code(&self) -> Option<TgpErrorCode> {
+        TgpErrorCode::parse(&self.code)
+    }
+
     pub fn validate(&self) -> Result<(), String> {
         if self.msg_type != "ERROR" {
             return Err("ERROR.type must equal \"ERROR\"".into());
@@ -282,21 +662,20 @@ impl ErrorMessage {
         if self.id.is_empty() {
             return Err("ERROR.id is required".into());
         }
-        Ok(())
+        validate_query_reference(&self.correlation_id)
     }
 }
 
-pub fn make_protocol_error(
-    layer: u8,
-    code: impl Into<String>,
-    message: impl Into<String>,
-) -> ErrorMessage {
+/// Gateway-originated ERROR with a fresh `err-` ID. `layer_failed` comes
+/// from the code; attach the QUERY with [`ErrorMessage::with_correlation_id`].
+pub fn make_protocol_error(code: TgpErrorCode, message: impl Into<String>) -> ErrorMessage {
     ErrorMessage {
         msg_type: "ERROR".into(),
-        id: Uuid::new_v4().to_string(),
-        code: code.into(),
-        layer_failed: layer,
+        id: new_message_id("err-"),
+        code: code.as_str().into(),
+        layer_failed: code.layer(),
         message: message.into(),
+        correlation_id: None,
     }
 }
 
@@ -319,6 +698,18 @@ pub struct SettleMessage {
     pub result: SettleResult,
 
     pub timestamp: String,
+
+    /// QUERY this settlement terminates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+
+    /// Who reported the settlement (§5.4); absent = gateway watcher
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SettleSource>,
+
+    /// Layer-8 settlement transaction hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
 }
 
 impl SettleMessage {
@@ -336,6 +727,9 @@ impl SettleMessage {
                 escrow_id: escrow_id.into(),
             },
             timestamp: timestamp.into(),
+            correlation_id: None,
+            source: None,
+            tx_hash: None,
         }
     }
 
@@ -346,26 +740,204 @@ impl SettleMessage {
         if self.id.is_empty() {
             return Err("SETTLE.id is required".into());
         }
-        Ok(())
+        validate_rfc3339_format(&self.timestamp, "timestamp")?;
+        if let Some(ref tx) = self.tx_hash {
+            validate_transaction_hash(tx, "tx_hash")?;
+        }
+        validate_query_reference(&self.correlation_id)
     }
 }
 
 // -----------------------------------------------------------------------------
 // 7. TGP Message Envelope
 // -----------------------------------------------------------------------------
+//
+// Every message struct already carries its own `type` field, so the union
+// serializes untagged and deserializes by dispatching on `type`.
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(tag = "type")]
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(untagged)]
 pub enum TGPMessage {
-    #[serde(rename = "QUERY")]
     Query(QueryMessage),
-
-    #[serde(rename = "ACK")]
     Ack(AckMessage),
-
-    #[serde(rename = "SETTLE")]
     Settle(SettleMessage),
-
-    #[serde(rename = "ERROR")]
     Error(ErrorMessage),
-}
\ No newline at end of file
+}
+
+impl TGPMessage {
+    pub fn id(&self) -> &str {
+        match self {
+            TGPMessage::Query(m)  => &m.id,
+            TGPMessage::Ack(m)    => &m.id,
+            TGPMessage::Settle(m) => &m.id,
+            TGPMessage::Error(m)  => &m.id,
+        }
+    }
+
+    /// ID of the QUERY this message belongs to: its own ID for a QUERY,
+    /// `correlation_id` for everything else.
+    pub fn query_id(&self) -> Option<&str> {
+        match self {
+            TGPMessage::Query(m)  => Some(&m.id),
+            TGPMessage::Ack(m)    => m.correlation_id.as_deref(),
+            TGPMessage::Settle(m) => m.correlation_id.as_deref(),
+            TGPMessage::Error(m)  => m.correlation_id.as_deref(),
+        }
+    }
+
+    pub fn msg_type(&self) -> &'static str {
+        match self {
+            TGPMessage::Query(_)  => "QUERY",
+            TGPMessage::Ack(_)    => "ACK",
+            TGPMessage::Settle(_) => "SETTLE",
+            TGPMessage::Error(_)  => "ERROR",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            TGPMessage::Query(m)  => m.validate(),
+            TGPMessage::Ack(m)    => m.validate(),
+            TGPMessage::Settle(m) => m.validate(),
+            TGPMessage::Error(m)  => m.validate(),
+        }
+    }
+
+    /// RFC 8785 (JCS) canonical JSON. These are the bytes that are signed.
+    pub fn canonical_json(&self) -> Result<String, String> {
+        canonical::to_canonical_string(self)
+    }
+
+    /// keccak256 of `canonical_json()` -- byte-exact message identity.
+    pub fn digest(&self) -> Result<[u8; 32], String> {
+        Ok(canonical::keccak256(self.canonical_json()?.as_bytes()))
+    }
+
+    /// `digest()` as `0x`-prefixed hex.
+    pub fn digest_hex(&self) -> Result<String, String> {
+        Ok(canonical::digest_hex(&self.digest()?))
+    }
+}
+
+impl<'de> Deserialize<'de> for TGPMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let v = serde_json::Value::deserialize(deserializer)?;
+        let typ = v.get("type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| D::Error::missing_field("type"))?;
+
+        match typ {
+            "QUERY"  => serde_json::from_value(v).map(TGPMessage::Query),
+            "ACK"    => serde_json::from_value(v).map(TGPMessage::Ack),
+            "SETTLE" => serde_json::from_value(v).map(TGPMessage::Settle),
+            "ERROR"  => serde_json::from_value(v).map(TGPMessage::Error),
+            other => {
+                return Err(D::Error::unknown_variant(other, &["QUERY", "ACK", "SETTLE", "ERROR"]));
+            }
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_roundtrip_single_type_field() {
+        let msg = TGPMessage::Error(make_protocol_error(TgpErrorCode::L2Failure, "bad sig"));
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(json.matches("\"type\"").count(), 1);
+
+        let back: TGPMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, msg);
+    }
+
+    #[test]
+    fn test_unknown_type_rejected() {
+        let r: Result<TGPMessage, _> = serde_json::from_str(r#"{"type":"OFFER","id":"o-1"}"#);
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_error_code_wire_form_and_mapping() {
+        for &code in TgpErrorCode::ALL {
+            let json = serde_json::to_string(&code).unwrap();
+            assert_eq!(json, format!("\"{}\"", code));
+            assert_eq!(TgpErrorCode::parse(code.as_str()), Some(code));
+        }
+        assert_eq!(TgpErrorCode::parse("SOMETHING_ELSE"), None);
+
+        let err = make_protocol_error(TgpErrorCode::L6WithdrawFailure, "seller withdraw");
+        assert_eq!(err.code, "TGP_L6_WITHDRAW_FAILURE");
+        assert_eq!(err.layer_failed, 6);
+        assert_eq!(err.error_code(), Some(TgpErrorCode::L6WithdrawFailure));
+
+        assert_eq!(TgpErrorCode::ReplayDetected.http_status(), 409);
+        assert!(!TgpErrorCode::ReplayDetected.is_retryable());
+        assert!(TgpErrorCode::L3Failure.is_retryable());
+    }
+
+    #[test]
+    fn test_ack_and_error_correlate_to_query() {
+        let query: QueryMessage = serde_json::from_value(serde_json::json!({
+            "type": "QUERY", "tgp_version": "3.2", "id": "q-corr-1",
+            "intent": { "verb": "PAY", "party": "BUYER", "mode": "direct" },
+            "payment_profile": "0x1111111111111111111111111111111111111111",
+            "amount": 10, "chain_id": 369
+        })).unwrap();
+
+        let ack = AckMessage::offer_for(&query);
+        assert!(ack.id.starts_with("ack-"));
+        assert_eq!(ack.correlation_id.as_deref(), Some("q-corr-1"));
+        assert_eq!(TGPMessage::Ack(ack.clone()).query_id(), Some("q-corr-1"));
+        assert!(ack.validate().is_ok());
+
+        let err = make_protocol_error(TgpErrorCode::L5Failure, "limit")
+            .with_correlation_id(&query.id);
+        assert!(err.id.starts_with("err-"));
+        assert!(err.validate().is_ok());
+
+        // correlation_id must reference a QUERY
+        let bad = err.with_correlation_id("settle-1");
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_deny_and_revise_carry_reason() {
+        let query: QueryMessage = serde_json::from_value(serde_json::json!({
+            "type": "QUERY", "tgp_version": "3.2", "id": "q-reason-1",
+            "intent": { "verb": "PAY", "party": "BUYER", "mode": "direct" },
+            "payment_profile": "0x1111111111111111111111111111111111111111",
+            "amount": 10, "chain_id": 369
+        })).unwrap();
+
+        let deny = AckMessage::deny_for(&query, AckReason::new("MERCHANT_BLOCKED", "merchant suspended"));
+        assert!(deny.validate().is_ok());
+        let json = serde_json::to_value(&deny).unwrap();
+        assert_eq!(json["reason"]["code"], "MERCHANT_BLOCKED");
+        assert!(json["reason"].get("constraints").is_none());
+
+        let revise = AckMessage::revise_for(&query, AckReason::new("POLICY_MODE_REQUIRED", "use shielded")
+            .with_constraint(FieldConstraint::new("intent.mode", ConstraintOp::Eq, "shielded")));
+        let json = serde_json::to_value(&revise).unwrap();
+        assert_eq!(json["reason"]["constraints"][0],
+            serde_json::json!({ "field": "intent.mode", "op": "eq", "value": "shielded" }));
+
+        let back: AckMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(back, revise);
+
+        // revise without constraints is not actionable
+        let vague = AckMessage::revise_for(&query, AckReason::new("POLICY", "change something"));
+        assert!(vague.validate().is_err());
+    }
+}
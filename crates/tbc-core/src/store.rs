@@ -0,0 +1,287 @@
+//! # store.rs -- Persistent Replay / Nullifier Store
+//!
+//! Embedded (sled) store that survives restarts, so neither a used message
+//! ID nor a burned ZK nullifier becomes acceptable again after a reboot.
+//!
+//! Layout (one sled database under the configured data dir):
+//!   • tree `replay`     : msg_id    → first-seen unix seconds (u64 BE)
+//!   • tree `nullifiers` : nullifier → burn unix seconds      (u64 BE)
+//!
+//! Entries older than their TTL are treated as absent on lookup and removed
+//! by `compact()`, which the node runs periodically.
+//!
+//! Implements:
+//!   • `codec_tx::ReplayProtector`
+//!   • `zk::NullifierStore`
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::codec_tx::{ReplayProtector, ReplayStats, DEFAULT_REPLAY_TTL};
+use crate::zk::{NullifierStore, MAX_TIMESTAMP_DRIFT_SECONDS, PROOF_TTL_SECONDS};
+
+const REPLAY_TREE: &str = "replay";
+const NULLIFIER_TREE: &str = "nullifiers";
+
+/// Default nullifier retention. A proof older than `PROOF_TTL_SECONDS`
+/// (plus clock drift) is rejected on freshness alone, so its nullifier no
+/// longer needs to be remembered.
+pub const DEFAULT_NULLIFIER_TTL: Duration =
+    Duration::from_secs(PROOF_TTL_SECONDS + MAX_TIMESTAMP_DRIFT_SECONDS);
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn decode_ts(bytes: &[u8]) -> u64 {
+    bytes.try_into().map(u64::from_be_bytes).unwrap_or(0)
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// Durable replay and nullifier store.
+///
+/// Cheap to clone; clones share the same database handle and counters.
+#[derive(Clone)]
+pub struct PersistentStore {
+    db: sled::Db,
+    replay: sled::Tree,
+    nullifiers: sled::Tree,
+    replay_ttl: Duration,
+    nullifier_ttl: Duration,
+    counters: Arc<Counters>,
+}
+
+impl PersistentStore {
+    /// Open (or create) the store under `dir`.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(dir.as_ref())
+            .map_err(|e| anyhow!("open store {}: {}", dir.as_ref().display(), e))?;
+        Self::from_db(db)
+    }
+
+    /// Store that lives only as long as the process (tests, dev).
+    pub fn temporary() -> Result<Self> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .map_err(|e| anyhow!("open temporary store: {}", e))?;
+        Self::from_db(db)
+    }
+
+    fn from_db(db: sled::Db) -> Result<Self> {
+        let replay = db.open_tree(REPLAY_TREE)?;
+        let nullifiers = db.open_tree(NULLIFIER_TREE)?;
+        Ok(Self {
+            db,
+            replay,
+            nullifiers,
+            replay_ttl: DEFAULT_REPLAY_TTL,
+            nullifier_ttl: DEFAULT_NULLIFIER_TTL,
+            counters: Arc::new(Counters::default()),
+        })
+    }
+
+    pub fn with_replay_ttl(mut self, ttl: Duration) -> Self {
+        self.replay_ttl = ttl;
+        self
+    }
+
+    pub fn with_nullifier_ttl(mut self, ttl: Duration) -> Self {
+        self.nullifier_ttl = ttl;
+        self
+    }
+
+    pub fn replay_len(&self) -> usize {
+        self.replay.len()
+    }
+
+    pub fn nullifier_len(&self) -> usize {
+        self.nullifiers.len()
+    }
+
+    /// Flush pending writes to disk.
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Remove expired replay IDs and nullifiers. Returns entries removed.
+    pub fn compact(&self) -> Result<usize> {
+        self.compact_at(unix_now())
+    }
+
+    fn compact_at(&self, now: u64) -> Result<usize> {
+        let mut removed = 0;
+        for (tree, ttl) in [
+            (&self.replay, self.replay_ttl),
+            (&self.nullifiers, self.nullifier_ttl),
+        ] {
+            for entry in tree.iter() {
+                let (key, value) = entry?;
+                if Self::expired(decode_ts(&value), ttl, now) {
+                    // Only remove if untouched since we read it
+                    if tree.compare_and_swap(&key, Some(value), None::<&[u8]>)?.is_ok() {
+                        removed += 1;
+                    }
+                }
+            }
+        }
+        self.counters.evictions.fetch_add(removed as u64, Ordering::Relaxed);
+        Ok(removed)
+    }
+
+    fn expired(at: u64, ttl: Duration, now: u64) -> bool {
+        now.saturating_sub(at) >= ttl.as_secs()
+    }
+
+    /// Insert `key` unless a live entry exists. Returns true if inserted.
+    fn insert_fresh(tree: &sled::Tree, key: &str, at: u64, ttl: Duration, now: u64) -> Result<bool> {
+        let value = at.to_be_bytes();
+        let mut current = tree.get(key)?;
+        loop {
+            if let Some(ref existing) = current {
+                if !Self::expired(decode_ts(existing), ttl, now) {
+                    return Ok(false);
+                }
+            }
+            match tree.compare_and_swap(key, current, Some(&value[..]))? {
+                Ok(()) => return Ok(true),
+                Err(conflict) => current = conflict.current,
+            }
+        }
+    }
+
+    fn check_or_insert_at(&self, msg_id: &str, now: u64) -> bool {
+        match Self::insert_fresh(&self.replay, msg_id, now, self.replay_ttl, now) {
+            Ok(true) => {
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Ok(false) => {
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(e) => {
+                // Fail closed: an unreadable store must not admit replays
+                tracing::error!("replay store error: {}", e);
+                false
+            }
+        }
+    }
+}
+
+impl ReplayProtector for PersistentStore {
+    fn check_or_insert(&self, msg_id: &str) -> bool {
+        self.check_or_insert_at(msg_id, unix_now())
+    }
+
+    fn stats(&self) -> ReplayStats {
+        ReplayStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            len: self.replay_len(),
+        }
+    }
+}
+
+#[async_trait]
+impl NullifierStore for PersistentStore {
+    async fn exists(&self, nullifier: &str) -> bool {
+        match self.nullifiers.get(nullifier) {
+            Ok(Some(v)) => !Self::expired(decode_ts(&v), self.nullifier_ttl, unix_now()),
+            Ok(None) => false,
+            Err(e) => {
+                // Fail closed: treat as burned
+                tracing::error!("nullifier store error: {}", e);
+                true
+            }
+        }
+    }
+
+    async fn insert(&self, nullifier: &str, timestamp: u64) -> bool {
+        let now = unix_now();
+        // Retention counts from the later of proof time and arrival time
+        let at = timestamp.max(now);
+        Self::insert_fresh(&self.nullifiers, nullifier, at, self.nullifier_ttl, now)
+            .unwrap_or_else(|e| {
+                tracing::error!("nullifier store error: {}", e);
+                false
+            })
+    }
+
+    async fn remove(&self, nullifier: &str) {
+        if let Err(e) = self.nullifiers.remove(nullifier) {
+            tracing::error!("nullifier store error: {}", e);
+        }
+    }
+
+    async fn count(&self) -> usize {
+        self.nullifier_len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("tbc-store-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_replay_survives_restart() {
+        let dir = temp_dir();
+        {
+            let store = PersistentStore::open(&dir).unwrap();
+            assert!(store.check_or_insert("m-1"));
+            assert!(!store.check_or_insert("m-1"));
+            store.flush().unwrap();
+        }
+
+        let reopened = PersistentStore::open(&dir).unwrap();
+        assert!(!reopened.check_or_insert("m-1"));
+        drop(reopened);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_replay_ttl_and_compaction() {
+        let store = PersistentStore::temporary()
+            .unwrap()
+            .with_replay_ttl(Duration::from_secs(60));
+
+        assert!(store.check_or_insert_at("m-1", 1_000));
+        assert!(!store.check_or_insert_at("m-1", 1_059));
+        assert!(store.check_or_insert_at("m-1", 1_060));
+
+        assert!(store.check_or_insert_at("m-2", 1_000));
+        assert_eq!(store.compact_at(1_200).unwrap(), 2);
+        assert_eq!(store.replay_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_nullifier_burn() {
+        let store = PersistentStore::temporary().unwrap();
+
+        assert!(!store.exists("0xnull").await);
+        assert!(store.insert("0xnull", unix_now()).await);
+        assert!(!store.insert("0xnull", unix_now()).await);
+        assert!(store.exists("0xnull").await);
+        assert_eq!(store.count().await, 1);
+    }
+}
@@ -0,0 +1,394 @@
+//! TGP-00 v3.2 -- Anomaly Engine (anomaly.rs)
+//! --------------------------------------------------
+//! Scores every inbound message into an `AnomalySummary`:
+//!
+//!   • QUERY  -- domain mismatch (chain or transaction area not served here)
+//!   • ACK    -- unexpected ACK from a client, expired envelope
+//!   • SETTLE -- missing tx hash, low-trust `source`, expired envelope
+//!
+//! A QUERY score at or above the configured thresholds turns the ACK(allow)
+//! the pipeline produced into ACK(revise) or ACK(deny). ACK and SETTLE scores
+//! are reported (logs, admin stats); expiry stays fail-closed in the handlers.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::protocol::{
+    AckMessage, AckReason, AckStatus, ConstraintOp, FieldConstraint, QueryMessage, SettleMessage,
+};
+use crate::tgp::expiry::{check_ack_expiry, check_settle_expiry, IssuedEnvelopes};
+use crate::tgp::layers::PolicyVerdict;
+use crate::tgp::tai::{normalize_tai, TaiRegistry};
+use crate::tgp::types::{AnomalyKind, AnomalySummary};
+
+/// Reason code: ACK(allow) withheld because of the QUERY's anomaly score.
+pub const REASON_ANOMALY_SCORE: &str = "ANOMALY_SCORE";
+
+/// `SettleSource::trust_level` below which a SETTLE source is suspicious.
+pub const DEFAULT_MIN_SOURCE_TRUST: u8 = 60;
+
+/// Weight of each anomaly the engine raises (expiry weights live in `expiry.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnomalyWeights {
+    pub missing_tx_hash: u8,
+    pub suspicious_source: u8,
+    pub domain_mismatch: u8,
+    pub unexpected_ack: u8,
+}
+
+impl Default for AnomalyWeights {
+    fn default() -> Self {
+        Self {
+            missing_tx_hash: 20,
+            suspicious_source: 30,
+            domain_mismatch: 40,
+            unexpected_ack: 25,
+        }
+    }
+}
+
+/// Running counters, reported by the admin API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AnomalyStats {
+    /// Messages scored
+    pub scored: u64,
+    /// Messages with at least one anomaly
+    pub flagged: u64,
+    /// ACK(allow) turned into ACK(revise)
+    pub revised: u64,
+    /// ACK(allow) turned into ACK(deny)
+    pub denied: u64,
+    pub max_score: u16,
+    pub by_kind: BTreeMap<String, u64>,
+}
+
+/// Configurable anomaly scorer.
+#[derive(Debug, Default)]
+pub struct AnomalyEngine {
+    weights: AnomalyWeights,
+    min_source_trust: u8,
+    chains: Vec<u64>,
+    /// Normalized areas
+    areas: Vec<String>,
+    tai: Option<Arc<TaiRegistry>>,
+    revise_at: Option<u16>,
+    deny_at: Option<u16>,
+    stats: Mutex<AnomalyStats>,
+}
+
+impl AnomalyEngine {
+    /// Scores everything, changes nothing until a threshold is set.
+    pub fn new() -> Self {
+        Self {
+            min_source_trust: DEFAULT_MIN_SOURCE_TRUST,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_weights(mut self, weights: AnomalyWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn with_min_source_trust(mut self, trust: u8) -> Self {
+        self.min_source_trust = trust;
+        self
+    }
+
+    /// Chain served by this gateway; QUERYs for other chains are a domain
+    /// mismatch. No chains configured = no chain check.
+    pub fn with_chain(mut self, chain_id: u64) -> Self {
+        self.chains.push(chain_id);
+        self
+    }
+
+    /// Transaction area served by this gateway; a locally handled QUERY
+    /// naming another area is a domain mismatch. Compared after
+    /// `normalize_tai`.
+    pub fn with_area(mut self, area: impl Into<String>) -> Self {
+        let area = area.into();
+        self.areas.push(normalize_tai(&area).unwrap_or(area));
+        self
+    }
+
+    /// Serve every local area of `registry`, following runtime edits.
+    pub fn with_tai_registry(mut self, registry: Arc<TaiRegistry>) -> Self {
+        self.tai = Some(registry);
+        self
+    }
+
+    /// Normalized areas served here: configured ones plus the registry's.
+    fn served_areas(&self) -> Vec<String> {
+        let mut areas = self.areas.clone();
+        for area in self.tai.iter().flat_map(|t| t.served_areas()) {
+            if !areas.contains(&area) {
+                areas.push(area);
+            }
+        }
+        areas
+    }
+
+    /// QUERY score at which ACK(allow) becomes ACK(revise).
+    pub fn with_revise_threshold(mut self, score: u16) -> Self {
+        self.revise_at = Some(score);
+        self
+    }
+
+    /// QUERY score at which ACK(allow) becomes ACK(deny).
+    pub fn with_deny_threshold(mut self, score: u16) -> Self {
+        self.deny_at = Some(score);
+        self
+    }
+
+    pub fn stats(&self) -> AnomalyStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    // -------------------------------------------------------------------------
+    // Scoring
+    // -------------------------------------------------------------------------
+
+    pub fn score_query(&self, query: &QueryMessage) -> AnomalySummary {
+        let mut anomalies = AnomalySummary::new();
+        let weight = self.weights.domain_mismatch;
+
+        if !self.chains.is_empty() && !self.chains.contains(&query.chain_id) {
+            anomalies.add(
+                AnomalyKind::DomainMismatch,
+                weight,
+                format!("chain_id {} is not served by this gateway", query.chain_id),
+            );
+        }
+        if let Some(ref area) = query.routing.transaction_area_id {
+            let served = self.served_areas();
+            let normalized = normalize_tai(area).unwrap_or_else(|_| area.clone());
+            if !served.is_empty() && !served.contains(&normalized) {
+                anomalies.add(
+                    AnomalyKind::DomainMismatch,
+                    weight,
+                    format!("transaction area {} is not served by this gateway", area),
+                );
+            }
+        }
+
+        self.record(&anomalies);
+        anomalies
+    }
+
+    /// Clients never send ACKs to the gateway, except echoing an
+    /// ACK(allow) this gateway issued.
+    pub fn score_ack(&self, ack: &AckMessage, issued: &IssuedEnvelopes, now: DateTime<Utc>) -> AnomalySummary {
+        let mut anomalies = check_ack_expiry(ack, now);
+
+        let ours = ack.status == AckStatus::Allow
+            && ack.correlation_id.as_deref().is_some_and(|q| issued.expiry_of(q).is_some());
+        if !ours {
+            anomalies.add(
+                AnomalyKind::UnexpectedAck,
+                self.weights.unexpected_ack,
+                format!("ACK {} does not echo an envelope issued by this gateway", ack.id),
+            );
+        }
+
+        self.record(&anomalies);
+        anomalies
+    }
+
+    pub fn score_settle(&self, settle: &SettleMessage, issued: &IssuedEnvelopes, now: DateTime<Utc>) -> AnomalySummary {
+        let mut anomalies = check_settle_expiry(settle, issued, now);
+
+        if settle.tx_hash.is_none() {
+            anomalies.add(
+                AnomalyKind::MissingTxHash,
+                self.weights.missing_tx_hash,
+                format!("SETTLE {} carries no tx_hash", settle.id),
+            );
+        }
+        if let Some(source) = settle.source {
+            if source.trust_level() < self.min_source_trust {
+                anomalies.add(
+                    AnomalyKind::SuspiciousTxSource,
+                    self.weights.suspicious_source,
+                    format!("SETTLE source {:?} has trust {}", source, source.trust_level()),
+                );
+            }
+        }
+
+        self.record(&anomalies);
+        anomalies
+    }
+
+    // -------------------------------------------------------------------------
+    // Thresholds
+    // -------------------------------------------------------------------------
+
+    /// Verdict for a QUERY scoring `anomalies`, if a threshold is reached.
+    /// Revise needs at least one constraint the client can meet; otherwise
+    /// the QUERY is denied.
+    pub fn verdict(&self, anomalies: &AnomalySummary) -> Option<PolicyVerdict> {
+        let score = anomalies.total_score;
+        let reached = |t: Option<u16>| t.is_some_and(|t| score >= t);
+        if !reached(self.deny_at) && !reached(self.revise_at) {
+            return None;
+        }
+
+        let findings: Vec<&str> = anomalies.events.iter().map(|e| e.message.as_str()).collect();
+        let mut reason = AckReason::new(
+            REASON_ANOMALY_SCORE,
+            format!("anomaly score {}: {}", score, findings.join("; ")),
+        );
+        if reached(self.deny_at) {
+            return Some(PolicyVerdict::Deny(reason));
+        }
+
+        if anomalies.has(&AnomalyKind::DomainMismatch) {
+            if !self.chains.is_empty() {
+                reason = reason.with_constraint(FieldConstraint::new("chain_id", ConstraintOp::OneOf, self.chains.clone()));
+            }
+            let areas = self.served_areas();
+            if !areas.is_empty() {
+                reason = reason.with_constraint(FieldConstraint::new(
+                    "routing.transaction_area_id",
+                    ConstraintOp::OneOf,
+                    areas,
+                ));
+            }
+        }
+        Some(if reason.constraints.is_empty() {
+            PolicyVerdict::Deny(reason)
+        } else {
+            PolicyVerdict::Revise(reason)
+        })
+    }
+
+    /// Withhold an ACK(allow) whose QUERY scored past a threshold. Any other
+    /// ACK is returned unchanged.
+    pub fn apply(&self, query: &QueryMessage, ack: AckMessage, anomalies: &AnomalySummary) -> AckMessage {
+        if ack.status != AckStatus::Allow {
+            return ack;
+        }
+        let Some(verdict) = self.verdict(anomalies) else {
+            return ack;
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+        match verdict {
+            PolicyVerdict::Deny(reason) => {
+                stats.denied += 1;
+                AckMessage::deny_for(query, reason)
+            }
+            PolicyVerdict::Revise(reason) => {
+                stats.revised += 1;
+                AckMessage::revise_for(query, reason)
+            }
+        }
+    }
+
+    fn record(&self, anomalies: &AnomalySummary) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.scored += 1;
+        if anomalies.is_empty() {
+            return;
+        }
+        stats.flagged += 1;
+        stats.max_score = stats.max_score.max(anomalies.total_score);
+        for event in &anomalies.events {
+            *stats.by_kind.entry(format!("{:?}", event.kind)).or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+    use crate::tgp::types::tests::sample_envelope;
+    use crate::tgp::types::SettleSource;
+
+    fn allow(query: &QueryMessage) -> AckMessage {
+        AckMessage::allow_for(query, sample_envelope(), "2099-01-01T00:00:00Z".into())
+    }
+
+    #[test]
+    fn test_settle_scoring() {
+        let engine = AnomalyEngine::new();
+        let issued = IssuedEnvelopes::default();
+
+        let mut settle = SettleMessage::terminal("settle-1", "complete", "0x1", "2025-11-18T15:04:00Z");
+        settle.source = Some(SettleSource::BuyerNotify);
+        let anomalies = engine.score_settle(&settle, &issued, Utc::now());
+        assert!(anomalies.has(&AnomalyKind::MissingTxHash));
+        assert!(anomalies.has(&AnomalyKind::SuspiciousTxSource));
+        assert_eq!(anomalies.total_score, 50);
+
+        settle.source = Some(SettleSource::ControllerWatcher);
+        settle.tx_hash = Some(format!("0x{}", "ab".repeat(32)));
+        assert!(engine.score_settle(&settle, &issued, Utc::now()).is_empty());
+
+        let stats = engine.stats();
+        assert_eq!((stats.scored, stats.flagged), (2, 1));
+        assert_eq!(stats.by_kind["MissingTxHash"], 1);
+    }
+
+    #[test]
+    fn test_unexpected_ack() {
+        let engine = AnomalyEngine::new();
+        let issued = IssuedEnvelopes::default();
+        let query = sample_query();
+        let now = Utc::now();
+
+        let ack = allow(&query);
+        assert!(engine.score_ack(&ack, &issued, now).has(&AnomalyKind::UnexpectedAck));
+
+        issued.record(&ack);
+        assert!(engine.score_ack(&ack, &issued, now).is_empty());
+    }
+
+    #[test]
+    fn test_threshold_turns_allow_into_revise_or_deny() {
+        let mut query = sample_query();
+        query.chain_id = 1;
+
+        let engine = AnomalyEngine::new().with_chain(369).with_revise_threshold(40);
+        let anomalies = engine.score_query(&query);
+        let ack = engine.apply(&query, allow(&query), &anomalies);
+        assert_eq!(ack.status, AckStatus::Revise);
+        let reason = ack.reason.unwrap();
+        assert_eq!(reason.code, REASON_ANOMALY_SCORE);
+        assert_eq!(reason.constraints, vec![FieldConstraint::new("chain_id", ConstraintOp::OneOf, vec![369])]);
+
+        let engine = AnomalyEngine::new().with_chain(369).with_revise_threshold(10).with_deny_threshold(40);
+        let ack = engine.apply(&query, allow(&query), &engine.score_query(&query));
+        assert_eq!(ack.status, AckStatus::Deny);
+        assert_eq!(engine.stats().denied, 1);
+
+        // Below the threshold, or no threshold at all: unchanged
+        let lenient = AnomalyEngine::new().with_chain(369).with_deny_threshold(41);
+        let ack = lenient.apply(&query, allow(&query), &lenient.score_query(&query));
+        assert_eq!(ack.status, AckStatus::Allow);
+    }
+
+    #[test]
+    fn test_area_mismatch_uses_registry_and_normalized_ids() {
+        let home = format!("tai:keccak256:{}", "7a".repeat(32));
+        let branch = format!("tai:keccak256:{}", "8b".repeat(32));
+        let registry = TaiRegistry::from_toml_str(&format!(
+            "local_area = \"{}\"\n[[areas]]\ntai = \"{}\"\nlocal = true\n",
+            home, branch
+        ))
+        .unwrap();
+        let engine = AnomalyEngine::new().with_tai_registry(Arc::new(registry));
+
+        let mut query = sample_query();
+        for area in [home.clone(), branch.to_uppercase()] {
+            query.routing.transaction_area_id = Some(area);
+            assert!(!engine.score_query(&query).has(&AnomalyKind::DomainMismatch));
+        }
+
+        query.routing.transaction_area_id = Some("TAID-EU".into());
+        assert!(engine.score_query(&query).has(&AnomalyKind::DomainMismatch));
+    }
+}
@@ -0,0 +1,148 @@
+//! TGP-00 v3.2 -- Canonical JSON & Message Digest (canonical.rs)
+//! --------------------------------------------------
+//! RFC 8785 (JCS) style canonicalization so the extension and the gateway
+//! agree on byte-exact message identity:
+//!
+//!   • Object members sorted by UTF-16 code units of their names
+//!   • No insignificant whitespace
+//!   • Strings escaped minimally (`"`, `\`, control characters only)
+//!   • Integers printed without exponent or fraction
+//!
+//! The digest is keccak256 over the canonical UTF-8 bytes. It is logged
+//! on RX/TX and is the input to message signing.
+//!
+//! TGP messages carry no fractional numbers; floats are emitted in their
+//! shortest round-trip form, integral floats as integers.
+
+use serde::Serialize;
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+
+/// Canonical JSON text for `value`.
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// Canonical JSON text for any serializable value.
+pub fn to_canonical_string<T: Serialize>(value: &T) -> Result<String, String> {
+    let v = serde_json::to_value(value).map_err(|e| format!("canonical encode: {}", e))?;
+    Ok(canonicalize(&v))
+}
+
+/// keccak256 of the canonical form of `value`.
+pub fn digest_value(value: &Value) -> [u8; 32] {
+    keccak256(canonicalize(value).as_bytes())
+}
+
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(bytes);
+    hasher.finalize().into()
+}
+
+/// `0x`-prefixed lowercase hex, as digests appear in logs.
+pub fn digest_hex(digest: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(digest))
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(out, n),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut members: Vec<(&String, &Value)> = map.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (k, v)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, k);
+                out.push(':');
+                write_value(out, v);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_number(out: &mut String, n: &serde_json::Number) {
+    if let Some(u) = n.as_u64() {
+        out.push_str(&u.to_string());
+    } else if let Some(i) = n.as_i64() {
+        out.push_str(&i.to_string());
+    } else if let Some(f) = n.as_f64() {
+        if f.fract() == 0.0 && f.abs() < 1e21 {
+            out.push_str(&format!("{}", f as i128));
+        } else {
+            // ECMAScript writes positive exponents with an explicit sign
+            let s = n.to_string();
+            out.push_str(&s.replace("e", "e+").replace("e+-", "e-"));
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0C}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_member_order_and_whitespace_independent() {
+        let a: Value = serde_json::from_str(r#"{ "b": 1, "a": [true, null], "c": {"y": "x", "x": "y"} }"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{"c":{"x":"y","y":"x"},"a":[true,null],"b":1}"#).unwrap();
+
+        assert_eq!(canonicalize(&a), r#"{"a":[true,null],"b":1,"c":{"x":"y","y":"x"}}"#);
+        assert_eq!(digest_value(&a), digest_value(&b));
+    }
+
+    #[test]
+    fn test_rfc8785_strings_and_numbers() {
+        let v = json!({ "s": "€\u{0f}\"\\\n", "n": 1e30, "i": -5, "f": 4.0 });
+        assert_eq!(
+            canonicalize(&v),
+            "{\"f\":4,\"i\":-5,\"n\":1e+30,\"s\":\"€\\u000f\\\"\\\\\\n\"}"
+        );
+    }
+
+    #[test]
+    fn test_utf16_key_order() {
+        // U+1F600 (surrogates D83D DE00) sorts before U+FB33 in UTF-16,
+        // the reverse of code point order
+        let v = json!({ "\u{FB33}": 1, "\u{1F600}": 2 });
+        assert_eq!(canonicalize(&v), "{\"\u{1F600}\":2,\"\u{FB33}\":1}");
+    }
+}
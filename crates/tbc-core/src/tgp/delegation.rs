@@ -0,0 +1,538 @@
+//! TGP-00 v3.2 -- Delegated Keys (delegation.rs)
+//! --------------------------------------------------
+//! DKW-00 Delegated Key for Intent (DKI) checks for a QUERY's session
+//! context (§4.1):
+//!
+//!   • `delegated_key` -- the session key the wallet delegated to
+//!   • `scope`         -- the signed `DKIMessage` (DKW-00 §3.2, camelCase),
+//!                        optionally with the wallet's `signerAddress`, and
+//!                        the session key's `querySignature`
+//!   • `session_token` -- the wallet's EIP-712 signature over that message
+//!
+//! The signer is recovered from the EIP-712 digest (DKW-00 §3.1 domain).
+//! The delegation must be well-formed and live (§7.1, at most 24h), and
+//! its limits must cover the QUERY: chain, amount ≤ `maxValue`, merchant
+//! (the owner of the `payment_profile`, `0x0` = any) and settlement
+//! contract (the `payment_profile` itself).
+//!
+//! A signed DKI alone would be a bearer token, so the session key must
+//! also sign each QUERY (`DKIQuery`, same domain), binding the delegation
+//! to that QUERY's ID; the router's replay cache does the rest.
+//!
+//! `maxValue` bounds each QUERY on its own, not the session's total: with
+//! no session state, a delegation may be used for any number of QUERYs
+//! up to `maxValue` each until it expires or is revoked.
+//!
+//! Gateways keep no sessions (§3.2): the only state is an in-memory list
+//! of revoked nonces, session keys and signers edited by the admin API.
+
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::contracts::abi::{encode_tokens, AbiToken};
+use crate::contracts::types::{address_to_hex, hex_to_address, hex_to_bytes32, Address, Bytes32, U256};
+use crate::protocol::QueryMessage;
+use crate::tgp::canonical::keccak256;
+use crate::tgp::validation::validate_address;
+
+pub const DKI_DOMAIN_NAME: &str = "CoreProve DKI";
+pub const DKI_DOMAIN_VERSION: &str = "1";
+
+pub const EIP712_DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+pub const DKI_MESSAGE_TYPE: &str = "DKIMessage(bytes sessionKey,uint256 expiry,uint256 maxValue,\
+address merchant,address settlementContract,uint256 chainId,bytes32 nonce,string description)";
+pub const DKI_QUERY_TYPE: &str =
+    "DKIQuery(string id,address paymentProfile,uint256 amount,uint256 chainId,bytes32 nonce)";
+
+/// Longest delegation a wallet may grant (DKW-00 §6.2).
+pub const MAX_DELEGATION_SECS: u64 = 86_400;
+pub const MAX_DESCRIPTION_LEN: usize = 256;
+
+/// `merchant` value that delegates to any merchant.
+pub const ANY_MERCHANT: &str = "0x0000000000000000000000000000000000000000";
+
+// -----------------------------------------------------------------------------
+// 1. DKI Message
+// -----------------------------------------------------------------------------
+
+/// EIP-712 `DKIMessage` (DKW-00 §3.2).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DkiMessage {
+    /// Compressed secp256k1 public key (0x + 66 hex)
+    pub session_key: String,
+    /// Unix timestamp
+    pub expiry: u64,
+    /// Max spend in wei (base 10)
+    pub max_value: String,
+    pub merchant: String,
+    pub settlement_contract: String,
+    pub chain_id: u64,
+    /// 32-byte hex
+    pub nonce: String,
+    pub description: String,
+}
+
+impl DkiMessage {
+    /// DKW-00 §7.1 message rules at `now`.
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), String> {
+        let key = self.session_key.strip_prefix("0x").unwrap_or("");
+        if key.len() != 66 || !key.chars().all(|c| c.is_ascii_hexdigit()) || !matches!(&key[..2], "02" | "03") {
+            return Err("DKI sessionKey must be a compressed secp256k1 public key".into());
+        }
+
+        let now = now.timestamp().max(0) as u64;
+        if self.expiry <= now {
+            return Err(format!("DKI_EXPIRED: delegation expired at {}", self.expiry));
+        }
+        if self.expiry > now + MAX_DELEGATION_SECS {
+            return Err(format!("DKI lifetime exceeds {}s", MAX_DELEGATION_SECS));
+        }
+
+        match U256::from_dec_str(&self.max_value) {
+            Some(v) if v != U256::ZERO => {}
+            _ => return Err("DKI maxValue must be a positive base-10 amount".into()),
+        }
+
+        validate_address(&self.merchant, "DKI merchant")?;
+        validate_address(&self.settlement_contract, "DKI settlementContract")?;
+
+        if self.chain_id == 0 {
+            return Err("DKI chainId must be positive".into());
+        }
+        hex_to_bytes32(&self.nonce).map_err(|e| format!("DKI nonce: {}", e))?;
+
+        if self.description.chars().count() > MAX_DESCRIPTION_LEN {
+            return Err(format!("DKI description exceeds {} chars", MAX_DESCRIPTION_LEN));
+        }
+        Ok(())
+    }
+
+    /// `hashStruct(message)`. Fields must have passed `validate`.
+    pub fn struct_hash(&self) -> Result<Bytes32, String> {
+        let session_key = hex::decode(self.session_key.trim_start_matches("0x"))
+            .map_err(|e| format!("DKI sessionKey: {}", e))?;
+        let max_value = U256::from_dec_str(&self.max_value).ok_or("DKI maxValue is not a uint256")?;
+
+        Ok(keccak256(&encode_tokens(&[
+            AbiToken::FixedBytes32(keccak256(DKI_MESSAGE_TYPE.as_bytes())),
+            AbiToken::FixedBytes32(keccak256(&session_key)),
+            AbiToken::Uint(U256::from_u64(self.expiry)),
+            AbiToken::Uint(max_value),
+            AbiToken::Address(hex_to_address(&self.merchant)?),
+            AbiToken::Address(hex_to_address(&self.settlement_contract)?),
+            AbiToken::Uint(U256::from_u64(self.chain_id)),
+            AbiToken::FixedBytes32(hex_to_bytes32(&self.nonce)?),
+            AbiToken::FixedBytes32(keccak256(self.description.as_bytes())),
+        ])))
+    }
+
+    /// EIP-712 digest the wallet signed (`eth_signTypedData_v4`).
+    pub fn signing_hash(&self) -> Result<Bytes32, String> {
+        let mut preimage = vec![0x19, 0x01];
+        preimage.extend_from_slice(&domain_separator(self.chain_id));
+        preimage.extend_from_slice(&self.struct_hash()?);
+        Ok(keccak256(&preimage))
+    }
+}
+
+/// EIP-712 digest the session key signs for `query` under `dki`
+/// (`scope.querySignature`).
+pub fn query_signing_hash(query: &QueryMessage, dki: &DkiMessage) -> Result<Bytes32, String> {
+    let struct_hash = keccak256(&encode_tokens(&[
+        AbiToken::FixedBytes32(keccak256(DKI_QUERY_TYPE.as_bytes())),
+        AbiToken::FixedBytes32(keccak256(query.id.as_bytes())),
+        AbiToken::Address(hex_to_address(&query.payment_profile)?),
+        AbiToken::Uint(U256::from_u64(query.amount)),
+        AbiToken::Uint(U256::from_u64(query.chain_id)),
+        AbiToken::FixedBytes32(hex_to_bytes32(&dki.nonce)?),
+    ]));
+    let mut preimage = vec![0x19, 0x01];
+    preimage.extend_from_slice(&domain_separator(dki.chain_id));
+    preimage.extend_from_slice(&struct_hash);
+    Ok(keccak256(&preimage))
+}
+
+/// DKI domain separator for `chain_id` (verifyingContract is zero).
+pub fn domain_separator(chain_id: u64) -> Bytes32 {
+    keccak256(&encode_tokens(&[
+        AbiToken::FixedBytes32(keccak256(EIP712_DOMAIN_TYPE.as_bytes())),
+        AbiToken::FixedBytes32(keccak256(DKI_DOMAIN_NAME.as_bytes())),
+        AbiToken::FixedBytes32(keccak256(DKI_DOMAIN_VERSION.as_bytes())),
+        AbiToken::Uint(U256::from_u64(chain_id)),
+        AbiToken::Address([0u8; 20]),
+    ]))
+}
+
+/// Address that produced the 65-byte `r ‖ s ‖ v` `signature` over `digest`.
+/// High-s (malleable) signatures are rejected.
+pub fn recover_signer(digest: &Bytes32, signature: &str) -> Result<Address, String> {
+    let bytes = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+        .map_err(|e| format!("signature is not hex: {}", e))?;
+    if bytes.len() != 65 {
+        return Err(format!("signature must be 65 bytes, got {}", bytes.len()));
+    }
+
+    let sig = Signature::from_slice(&bytes[..64]).map_err(|e| format!("malformed signature: {}", e))?;
+    if sig.normalize_s().is_some() {
+        return Err("signature s value is not canonical".into());
+    }
+    let v = bytes[64];
+    let recovery_id = RecoveryId::from_byte(if v >= 27 { v - 27 } else { v })
+        .ok_or_else(|| format!("invalid signature v value {}", v))?;
+
+    let key = VerifyingKey::recover_from_prehash(digest, &sig, recovery_id)
+        .map_err(|e| format!("signature recovery failed: {}", e))?;
+    Ok(key_address(&key))
+}
+
+/// Address of a compressed (or uncompressed) secp256k1 public key.
+pub fn public_key_address(key: &str) -> Result<Address, String> {
+    let bytes = hex::decode(key.strip_prefix("0x").unwrap_or(key)).map_err(|e| format!("public key is not hex: {}", e))?;
+    let key = VerifyingKey::from_sec1_bytes(&bytes).map_err(|e| format!("malformed public key: {}", e))?;
+    Ok(key_address(&key))
+}
+
+fn key_address(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    keccak256(&point.as_bytes()[1..])[12..].try_into().unwrap()
+}
+
+// -----------------------------------------------------------------------------
+// 2. Revocations
+// -----------------------------------------------------------------------------
+
+/// Revoked delegations, by DKI nonce, session key or signer address.
+#[derive(Debug, Default)]
+pub struct DelegationRevocations {
+    revoked: RwLock<HashSet<String>>,
+}
+
+impl DelegationRevocations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Revoke every delegation matching `key`; false if already revoked.
+    pub fn revoke(&self, key: &str) -> Result<bool, String> {
+        let key = key.to_lowercase();
+        let hex_len = key.strip_prefix("0x").map(str::len);
+        if !matches!(hex_len, Some(40 | 64 | 66)) || !key[2..].chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("expected a DKI nonce, session key or signer address".into());
+        }
+        Ok(self.revoked.write().unwrap().insert(key))
+    }
+
+    /// False if `key` was not revoked.
+    pub fn restore(&self, key: &str) -> bool {
+        self.revoked.write().unwrap().remove(&key.to_lowercase())
+    }
+
+    pub fn is_revoked(&self, key: &str) -> bool {
+        self.revoked.read().unwrap().contains(&key.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.revoked.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.read().unwrap().is_empty()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 3. QUERY Check
+// -----------------------------------------------------------------------------
+
+/// Verify the delegation a QUERY's `delegated_key` acts under; returns the
+/// wallet that granted it. QUERYs without a `delegated_key` are not
+/// delegated and return `None`. `merchant_owner` is the admin of the
+/// `payment_profile` contract, when known; a DKI scoped to one merchant
+/// is refused without it.
+pub fn verify_delegation(
+    query: &QueryMessage,
+    now: DateTime<Utc>,
+    revocations: Option<&DelegationRevocations>,
+    merchant_owner: Option<&str>,
+) -> Result<Option<Address>, String> {
+    let Some(ref delegated_key) = query.delegated_key else {
+        return Ok(None);
+    };
+    let Some(ref signature) = query.session_token else {
+        return Err("delegated_key requires a session_token carrying the DKI signature".into());
+    };
+    let Some(ref scope) = query.scope else {
+        return Err("delegated_key requires a scope carrying the DKI message".into());
+    };
+    let dki: DkiMessage =
+        serde_json::from_value(scope.clone()).map_err(|e| format!("scope is not a DKI message: {}", e))?;
+
+    dki.validate(now)?;
+    if !delegated_key.eq_ignore_ascii_case(&dki.session_key) {
+        return Err("delegated_key is not the DKI sessionKey".into());
+    }
+
+    let signer = recover_signer(&dki.signing_hash()?, signature)
+        .map_err(|e| format!("DKI_INVALID_SIGNATURE: {}", e))?;
+    if let Some(claimed) = scope.get("signerAddress").and_then(|s| s.as_str()) {
+        if !claimed.eq_ignore_ascii_case(&address_to_hex(&signer)) {
+            return Err("DKI_INVALID_SIGNATURE: DKI was not signed by signerAddress".into());
+        }
+    }
+
+    // Limits of the delegation against the QUERY
+    if dki.chain_id != query.chain_id {
+        return Err(format!("DKI_WRONG_CHAIN: delegation is for chain {}", dki.chain_id));
+    }
+    let max_value = U256::from_dec_str(&dki.max_value).unwrap_or_default();
+    if U256::from_u64(query.amount).to_be_bytes() > max_value.to_be_bytes() {
+        return Err(format!("DKI_VALUE_EXCEEDED: amount {} exceeds maxValue {}", query.amount, dki.max_value));
+    }
+    if dki.merchant != ANY_MERCHANT {
+        match merchant_owner {
+            Some(owner) if dki.merchant.eq_ignore_ascii_case(owner) => {}
+            Some(_) => {
+                return Err("DKI_MERCHANT_UNAUTHORIZED: payment_profile is not owned by the delegated merchant".into())
+            }
+            None => {
+                return Err(format!(
+                    "DKI_MERCHANT_UNAUTHORIZED: owner of payment_profile {} is unknown",
+                    query.payment_profile.to_lowercase()
+                ))
+            }
+        }
+    }
+    if !dki.settlement_contract.eq_ignore_ascii_case(&query.payment_profile) {
+        return Err("DKI settlementContract is not the payment_profile".into());
+    }
+
+    if let Some(revoked) = revocations {
+        for key in [&dki.nonce, &dki.session_key, &address_to_hex(&signer)] {
+            if revoked.is_revoked(key) {
+                return Err(format!("delegation revoked ({})", key.to_lowercase()));
+            }
+        }
+    }
+
+    // The session key vouches for this very QUERY
+    let query_signature = scope
+        .get("querySignature")
+        .and_then(|s| s.as_str())
+        .ok_or("DKI_INVALID_SIGNATURE: scope carries no querySignature from the session key")?;
+    let session = recover_signer(&query_signing_hash(query, &dki)?, query_signature)
+        .map_err(|e| format!("DKI_INVALID_SIGNATURE: querySignature: {}", e))?;
+    if session != public_key_address(&dki.session_key)? {
+        return Err("DKI_INVALID_SIGNATURE: QUERY was not signed by the session key".into());
+    }
+
+    Ok(Some(signer))
+}
+
+/// Wallet that signed a delegated QUERY's DKI. Nothing else about the
+/// delegation is checked: callers rely on L2 (`verify_delegation`) having
+/// accepted the QUERY.
+pub fn delegating_wallet(query: &QueryMessage) -> Option<Address> {
+    query.delegated_key.as_ref()?;
+    let dki: DkiMessage = serde_json::from_value(query.scope.clone()?).ok()?;
+    recover_signer(&dki.signing_hash().ok()?, query.session_token.as_deref()?).ok()
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+    use k256::ecdsa::SigningKey;
+    use serde_json::json;
+
+    pub(crate) const WALLET_KEY: [u8; 32] = [0x11; 32];
+    const SESSION_KEY: [u8; 32] = [0x22; 32];
+    const NONCE: &str = "0x3333333333333333333333333333333333333333333333333333333333333333";
+
+    fn compressed(secret: &[u8; 32]) -> String {
+        let key = SigningKey::from_slice(secret).unwrap();
+        format!("0x{}", hex::encode(key.verifying_key().to_encoded_point(true).as_bytes()))
+    }
+
+    pub(crate) fn wallet_address() -> String {
+        let key = SigningKey::from_slice(&WALLET_KEY).unwrap();
+        let point = key.verifying_key().to_encoded_point(false);
+        format!("0x{}", hex::encode(&keccak256(&point.as_bytes()[1..])[12..]))
+    }
+
+    fn sign_digest(digest: &Bytes32, secret: &[u8; 32]) -> String {
+        let key = SigningKey::from_slice(secret).unwrap();
+        let (sig, recid) = key.sign_prehash_recoverable(digest).unwrap();
+        format!("0x{}{:02x}", hex::encode(sig.to_bytes()), recid.to_byte() + 27)
+    }
+
+    pub(crate) fn sign(dki: &DkiMessage, secret: &[u8; 32]) -> String {
+        sign_digest(&dki.signing_hash().unwrap(), secret)
+    }
+
+    /// Have the session key sign `q` as it now stands.
+    pub(crate) fn sign_query(q: &mut QueryMessage, dki: &DkiMessage) {
+        let signature = sign_digest(&query_signing_hash(q, dki).unwrap(), &SESSION_KEY);
+        q.scope.as_mut().unwrap()["querySignature"] = json!(signature);
+    }
+
+    pub(crate) fn sample_dki(profile: &str) -> DkiMessage {
+        DkiMessage {
+            session_key: compressed(&SESSION_KEY),
+            expiry: Utc::now().timestamp() as u64 + 3600,
+            max_value: "5000".into(),
+            merchant: ANY_MERCHANT.into(),
+            settlement_contract: profile.into(),
+            chain_id: 369,
+            nonce: NONCE.into(),
+            description: "Pizza Palace".into(),
+        }
+    }
+
+    /// QUERY acting under `dki`, signed by the test wallet.
+    pub(crate) fn delegated_query(dki: &DkiMessage) -> QueryMessage {
+        let mut q = sample_query();
+        q.delegated_key = Some(dki.session_key.clone());
+        q.session_token = Some(sign(dki, &WALLET_KEY));
+        let mut scope = serde_json::to_value(dki).unwrap();
+        scope["signerAddress"] = json!(wallet_address());
+        q.scope = Some(scope);
+        sign_query(&mut q, dki);
+        q
+    }
+
+    #[test]
+    fn test_domain_separator_matches_eip712() {
+        // keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)")
+        assert_eq!(
+            hex::encode(keccak256(EIP712_DOMAIN_TYPE.as_bytes())),
+            "8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f"
+        );
+        assert_ne!(domain_separator(369), domain_separator(1));
+
+        // Same digest as ethers-core `TypedData::encode_eip712` for this DKI
+        let dki = DkiMessage {
+            session_key: "0x02aabbccddeeff00112233445566778899aabbccddeeff00112233445566778899".into(),
+            expiry: 1_700_000_000,
+            max_value: "1000000000000000000".into(),
+            merchant: ANY_MERCHANT.into(),
+            settlement_contract: "0x1111111111111111111111111111111111111111".into(),
+            chain_id: 369,
+            nonce: NONCE.into(),
+            description: "Pizza Palace".into(),
+        };
+        assert_eq!(
+            hex::encode(dki.signing_hash().unwrap()),
+            "a9960d80e55590eb4b90d6638f29a1d41db15889fd3c3f35757ddabed583d745"
+        );
+    }
+
+    #[test]
+    fn test_verify_delegation_scope_and_signature() {
+        let now = Utc::now();
+        let q = sample_query();
+        let dki = sample_dki(&q.payment_profile);
+
+        let signer = verify_delegation(&delegated_query(&dki), now, None, None).unwrap();
+        assert_eq!(address_to_hex(&signer.unwrap()), wallet_address());
+        assert_eq!(verify_delegation(&q, now, None, None).unwrap(), None);
+
+        // Signed by another wallet than signerAddress
+        let mut forged = delegated_query(&dki);
+        forged.session_token = Some(sign(&dki, &[0x44; 32]));
+        assert!(verify_delegation(&forged, now, None, None).unwrap_err().starts_with("DKI_INVALID_SIGNATURE"));
+
+        // Scope edited after signing
+        let mut widened = delegated_query(&dki);
+        widened.scope.as_mut().unwrap()["maxValue"] = json!("9999999");
+        assert!(verify_delegation(&widened, now, None, None).is_err());
+
+        // Over the delegated amount, other merchant, other chain
+        let mut over = delegated_query(&dki);
+        over.amount = 5001;
+        assert!(verify_delegation(&over, now, None, None).unwrap_err().starts_with("DKI_VALUE_EXCEEDED"));
+
+        // A DKI for one merchant names its owner, not the contract
+        let owner = "0x2222222222222222222222222222222222222222";
+        let mut scoped = dki.clone();
+        scoped.merchant = owner.into();
+        let scoped_query = delegated_query(&scoped);
+        assert!(verify_delegation(&scoped_query, now, None, Some(owner)).is_ok());
+        assert!(verify_delegation(&scoped_query, now, None, None).unwrap_err().starts_with("DKI_MERCHANT_UNAUTHORIZED"));
+        let other_owner = Some("0x4444444444444444444444444444444444444444");
+        assert!(verify_delegation(&scoped_query, now, None, other_owner).is_err());
+        scoped.merchant = scoped.settlement_contract.clone();
+        assert!(verify_delegation(&delegated_query(&scoped), now, None, Some(owner)).is_err());
+
+        let mut other_chain = dki.clone();
+        other_chain.chain_id = 1;
+        assert!(verify_delegation(&delegated_query(&other_chain), now, None, None).is_err());
+
+        // Expired, and longer than 24h
+        let mut expired = dki.clone();
+        expired.expiry = now.timestamp() as u64 - 1;
+        assert!(verify_delegation(&delegated_query(&expired), now, None, None).unwrap_err().starts_with("DKI_EXPIRED"));
+
+        let mut too_long = dki.clone();
+        too_long.expiry = now.timestamp() as u64 + MAX_DELEGATION_SECS + 60;
+        assert!(verify_delegation(&delegated_query(&too_long), now, None, None).is_err());
+
+        // Missing signature
+        let mut unsigned = delegated_query(&dki);
+        unsigned.session_token = None;
+        assert!(verify_delegation(&unsigned, now, None, None).is_err());
+    }
+
+    #[test]
+    fn test_session_key_signs_each_query() {
+        let now = Utc::now();
+        let dki = sample_dki(&sample_query().payment_profile);
+        let q = delegated_query(&dki);
+
+        // The wallet's DKI signature, lifted onto another QUERY
+        let mut lifted = q.clone();
+        lifted.id = "q-lifted".into();
+        assert!(verify_delegation(&lifted, now, None, None).unwrap_err().contains("session key"));
+        sign_query(&mut lifted, &dki);
+        assert!(verify_delegation(&lifted, now, None, None).is_ok());
+
+        // Signed by some other key, or not at all
+        let mut other_key = q.clone();
+        let digest = query_signing_hash(&other_key, &dki).unwrap();
+        other_key.scope.as_mut().unwrap()["querySignature"] = json!(sign_digest(&digest, &[0x44; 32]));
+        assert!(verify_delegation(&other_key, now, None, None).is_err());
+
+        let mut bare = q;
+        bare.scope.as_mut().unwrap().as_object_mut().unwrap().remove("querySignature");
+        assert!(verify_delegation(&bare, now, None, None).unwrap_err().contains("querySignature"));
+    }
+
+    #[test]
+    fn test_revocations() {
+        let now = Utc::now();
+        let dki = sample_dki(&sample_query().payment_profile);
+        let q = delegated_query(&dki);
+        let revocations = DelegationRevocations::new();
+
+        assert!(revocations.revoke(NONCE).unwrap());
+        assert!(verify_delegation(&q, now, Some(&revocations), None).is_err());
+        assert!(revocations.restore(NONCE));
+
+        revocations.revoke(&wallet_address().to_uppercase().replace("0X", "0x")).unwrap();
+        assert!(verify_delegation(&q, now, Some(&revocations), None).is_err());
+        assert!(revocations.restore(&wallet_address()));
+        assert!(verify_delegation(&q, now, Some(&revocations), None).is_ok());
+
+        assert!(revocations.revoke("pizza").is_err());
+        assert!(revocations.is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+//! TGP-00 v3.2 -- Envelope Expiry (expiry.rs)
+//! --------------------------------------------------
+//! An ACK(status=allow) is only honoured until its `expires_at`. This module
+//! owns the three pieces of that rule:
+//!
+//!   • `EnvelopeLifetimes` -- how long an envelope lives, per payment profile
+//!   • `IssuedEnvelopes`   -- expiries, target contracts and orders of
+//!                            envelopes this gateway has issued, so a later
+//!                            SETTLE can be checked against them
+//!   • expiry checks that raise `AnomalyKind::ExpiredEnvelope`
+//!
+//! Inbound ACK is checked against its own `expires_at` / `tx.expiry`.
+//! Inbound SETTLE carries no expiry, so it is checked against the envelope
+//! issued for its `correlation_id`, when this gateway issued one. Both are
+//! evaluated at the gateway's receive time, never a client timestamp.
+//!
+//! `IssuedEnvelopes` lives in memory only: after a restart, SETTLEs for
+//! envelopes issued before it have no recorded expiry and are not checked.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::contracts::types::{hex_to_bytes32, Bytes32};
+use crate::protocol::{AckMessage, AckStatus, SettleMessage};
+use crate::tgp::types::{AnomalyKind, AnomalySummary};
+use crate::tgp::validation::parse_rfc3339;
+
+/// Envelope lifetime when no per-profile override applies.
+pub const DEFAULT_ENVELOPE_LIFETIME: Duration = Duration::from_secs(300);
+
+/// Anomaly weight of an expired envelope.
+pub const EXPIRED_ENVELOPE_WEIGHT: u8 = 40;
+
+/// Issued envelopes remembered for SETTLE checks.
+pub const DEFAULT_ISSUED_ENVELOPE_CAPACITY: usize = 65_536;
+
+// -----------------------------------------------------------------------------
+// 1. Lifetimes
+// -----------------------------------------------------------------------------
+
+/// Envelope lifetime, optionally overridden per payment profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvelopeLifetimes {
+    default: Duration,
+    /// Lower-cased payment_profile → lifetime
+    per_profile: HashMap<String, Duration>,
+}
+
+impl EnvelopeLifetimes {
+    pub fn new() -> Self {
+        Self::with_default(DEFAULT_ENVELOPE_LIFETIME)
+    }
+
+    pub fn with_default(default: Duration) -> Self {
+        Self {
+            default,
+            per_profile: HashMap::new(),
+        }
+    }
+
+    /// Override the lifetime for one payment profile.
+    pub fn with_profile(mut self, payment_profile: impl AsRef<str>, lifetime: Duration) -> Self {
+        self.per_profile
+            .insert(payment_profile.as_ref().to_lowercase(), lifetime);
+        self
+    }
+
+    pub fn lifetime_for(&self, payment_profile: &str) -> Duration {
+        self.per_profile
+            .get(&payment_profile.to_lowercase())
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Expiry of an envelope for `payment_profile` issued at `now`.
+    pub fn expires_at(&self, payment_profile: &str, now: DateTime<Utc>) -> DateTime<Utc> {
+        let lifetime = chrono::Duration::from_std(self.lifetime_for(payment_profile))
+            .unwrap_or(chrono::Duration::MAX);
+        now.checked_add_signed(lifetime).unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+impl Default for EnvelopeLifetimes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 2. Issued Envelopes
+// -----------------------------------------------------------------------------
+
+/// Bounded record of `query_id → expires_at` for issued ACK(allow).
+/// Oldest entries are dropped first once `capacity` is reached.
+///
+/// In memory only; envelopes issued before a restart are forgotten.
+/// Size `capacity` for the envelopes issued within one lifetime.
+#[derive(Debug)]
+pub struct IssuedEnvelopes {
+    capacity: usize,
+    inner: Mutex<IssuedInner>,
+}
+
+#[derive(Debug, Default)]
+struct IssuedInner {
+    envelopes: HashMap<String, IssuedEnvelope>,
+    order: VecDeque<String>,
+}
+
+#[derive(Debug, Clone)]
+struct IssuedEnvelope {
+    expires_at: DateTime<Utc>,
+    /// Lower-cased `tx.to` of the envelope (the settlement contract)
+    contract: Option<String>,
+    /// Order the envelope's settlement call acts on
+    order: Option<Bytes32>,
+}
+
+impl IssuedEnvelopes {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(IssuedInner::default()),
+        }
+    }
+
+    /// Remember the expiry, target contract and order of an issued ACK(allow). Other statuses (a QUOTE
+    /// offer also expires, but is never settled) and ACKs without a QUERY
+    /// reference or a parseable `expires_at` are ignored.
+    pub fn record(&self, ack: &AckMessage) {
+        if ack.status != AckStatus::Allow {
+            return;
+        }
+        let (Some(query_id), Some(expires_at)) = (&ack.correlation_id, &ack.expires_at) else {
+            return;
+        };
+        let Ok(expires_at) = parse_rfc3339(expires_at, "expires_at") else {
+            return;
+        };
+
+        let issued = IssuedEnvelope {
+            expires_at,
+            contract: ack.tx.as_ref().map(|tx| tx.to.to_lowercase()),
+            order: ack.tx.as_ref().and_then(|tx| calldata_order(&tx.data)),
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.envelopes.insert(query_id.clone(), issued).is_none() {
+            inner.order.push_back(query_id.clone());
+        }
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.envelopes.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn expiry_of(&self, query_id: &str) -> Option<DateTime<Utc>> {
+        self.inner.lock().unwrap().envelopes.get(query_id).map(|e| e.expires_at)
+    }
+
+    /// Settlement contract (lower-cased) the envelope for `query_id` targets.
+    pub fn contract_of(&self, query_id: &str) -> Option<String> {
+        self.inner.lock().unwrap().envelopes.get(query_id).and_then(|e| e.contract.clone())
+    }
+
+    /// Order the envelope for `query_id` acts on.
+    pub fn order_of(&self, query_id: &str) -> Option<Bytes32> {
+        self.inner.lock().unwrap().envelopes.get(query_id).and_then(|e| e.order)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().envelopes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for IssuedEnvelopes {
+    fn default() -> Self {
+        Self::new(DEFAULT_ISSUED_ENVELOPE_CAPACITY)
+    }
+}
+
+/// Every settlement contract call takes the order ID as its first argument.
+fn calldata_order(data: &str) -> Option<Bytes32> {
+    let data = data.strip_prefix("0x").unwrap_or(data);
+    hex_to_bytes32(data.get(8..72)?).ok()
+}
+
+// -----------------------------------------------------------------------------
+// 3. Expiry Checks
+// -----------------------------------------------------------------------------
+
+fn flag_if_expired(
+    anomalies: &mut AnomalySummary,
+    what: &str,
+    expires_at: DateTime<Utc>,
+    at: DateTime<Utc>,
+) {
+    if at >= expires_at {
+        anomalies.add(
+            AnomalyKind::ExpiredEnvelope,
+            EXPIRED_ENVELOPE_WEIGHT,
+            format!("{} expired at {} (checked at {})", what, expires_at.to_rfc3339(), at.to_rfc3339()),
+        );
+    }
+}
+
+/// Anomalies for an inbound ACK evaluated at `now`.
+///
+/// Both `expires_at` and `tx.expiry` are checked; timestamps that do not
+/// parse are left to `AckMessage::validate`.
+pub fn check_ack_expiry(ack: &AckMessage, now: DateTime<Utc>) -> AnomalySummary {
+    let mut anomalies = AnomalySummary::new();
+
+    if let Some(Ok(t)) = ack.expires_at.as_deref().map(|t| parse_rfc3339(t, "expires_at")) {
+        flag_if_expired(&mut anomalies, "ACK.expires_at", t, now);
+    }
+    let envelope_expiry = ack.tx.as_ref().and_then(|tx| tx.expiry.as_deref());
+    if let Some(Ok(t)) = envelope_expiry.map(|t| parse_rfc3339(t, "tx.expiry")) {
+        flag_if_expired(&mut anomalies, "tx.expiry", t, now);
+    }
+
+    anomalies
+}
+
+/// Anomalies for an inbound SETTLE received at `now`: arriving after the
+/// expiry of the envelope issued for its QUERY. The SETTLE's own
+/// `timestamp` is client-supplied and is not trusted here.
+pub fn check_settle_expiry(settle: &SettleMessage, issued: &IssuedEnvelopes, now: DateTime<Utc>) -> AnomalySummary {
+    let mut anomalies = AnomalySummary::new();
+
+    if let Some(expires_at) = settle.correlation_id.as_deref().and_then(|q| issued.expiry_of(q)) {
+        flag_if_expired(&mut anomalies, "Envelope", expires_at, now);
+    }
+
+    anomalies
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+    use crate::tgp::types::tests::sample_envelope;
+    use crate::tgp::types::EconomicEnvelope;
+
+    fn at(ts: &str) -> DateTime<Utc> {
+        parse_rfc3339(ts, "ts").unwrap()
+    }
+
+    fn allow_ack(expires_at: &str) -> AckMessage {
+        let envelope = EconomicEnvelope {
+            expiry: Some(expires_at.into()),
+            ..sample_envelope()
+        };
+        AckMessage::allow_for(&sample_query(), envelope, expires_at.into())
+    }
+
+    #[test]
+    fn test_lifetime_per_profile() {
+        let profile = "0xabcdef0000000000000000000000000000000001";
+        let lifetimes = EnvelopeLifetimes::new()
+            .with_profile("0xABCDEF0000000000000000000000000000000001", Duration::from_secs(30));
+
+        assert_eq!(lifetimes.lifetime_for(profile), Duration::from_secs(30));
+        assert_eq!(
+            lifetimes.lifetime_for("0x2222222222222222222222222222222222222222"),
+            DEFAULT_ENVELOPE_LIFETIME
+        );
+        assert_eq!(
+            lifetimes.expires_at(profile, at("2025-11-18T15:00:00Z")),
+            at("2025-11-18T15:00:30Z")
+        );
+    }
+
+    #[test]
+    fn test_expired_ack_flagged() {
+        let ack = allow_ack("2025-11-18T15:05:00Z");
+
+        assert!(check_ack_expiry(&ack, at("2025-11-18T15:04:59Z")).events.is_empty());
+
+        let late = check_ack_expiry(&ack, at("2025-11-18T15:05:00Z"));
+        assert_eq!(late.events.len(), 2);
+        assert!(late.events.iter().all(|e| e.kind == AnomalyKind::ExpiredEnvelope));
+    }
+
+    #[test]
+    fn test_settle_checked_against_issued_envelope() {
+        let issued = IssuedEnvelopes::new(1);
+        let ack = allow_ack("2025-11-18T15:05:00Z");
+        issued.record(&ack);
+
+        let query_id = ack.correlation_id.clone().unwrap();
+        let mut settle = SettleMessage::terminal("settle-1", "complete", "0x1", "2025-11-18T15:04:00Z");
+        settle.correlation_id = Some(query_id);
+        assert!(check_settle_expiry(&settle, &issued, at("2025-11-18T15:04:00Z")).events.is_empty());
+
+        let anomalies = check_settle_expiry(&settle, &issued, at("2025-11-18T15:06:00Z"));
+        assert_eq!(anomalies.events[0].kind, AnomalyKind::ExpiredEnvelope);
+
+        // A back-dated client timestamp does not help a late SETTLE
+        settle.timestamp = "2025-11-18T15:00:00Z".into();
+        assert!(!check_settle_expiry(&settle, &issued, at("2025-11-18T15:06:00Z")).is_empty());
+
+        // Unknown QUERY: nothing to check against
+        settle.correlation_id = Some("q-unknown".into());
+        assert!(check_settle_expiry(&settle, &issued, at("2025-11-18T15:06:00Z")).events.is_empty());
+    }
+
+    #[test]
+    fn test_issued_order_read_from_calldata() {
+        let issued = IssuedEnvelopes::default();
+        let mut ack = allow_ack("2025-11-18T15:05:00Z");
+        ack.tx.as_mut().unwrap().data = format!("0x987757dd{}", "22".repeat(32));
+        issued.record(&ack);
+
+        let query_id = ack.correlation_id.unwrap();
+        assert_eq!(issued.order_of(&query_id), Some([0x22; 32]));
+        assert_eq!(issued.contract_of(&query_id).as_deref(), Some("0x1111111111111111111111111111111111111111"));
+    }
+}
@@ -0,0 +1,119 @@
+//! L1 -- Registry Check
+//!
+//! Confirms the QUERY's `payment_profile` refers to a known merchant
+//! settlement contract: one in the configured merchant set and/or the
+//! factory-backed `MerchantRegistry`. Without either, L1 only rejects
+//! profiles that can never be a deployed contract (the zero address).
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::protocol::{QueryMessage, TgpErrorCode};
+use crate::tgp::merchants::MerchantRegistry;
+use crate::tgp::validation::validate_payment_profile;
+
+use super::VerificationLayer;
+
+/// L1 registry layer.
+#[derive(Debug, Clone, Default)]
+pub struct RegistryLayer {
+    /// Lower-cased merchant contract addresses. `None` = open registry.
+    merchants: Option<HashSet<String>>,
+
+    /// Factory mirror, shared with whoever refreshes and edits it
+    registry: Option<Arc<MerchantRegistry>>,
+}
+
+impl RegistryLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict L1 to the given merchant contract addresses.
+    pub fn with_merchants<I, S>(mut self, merchants: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.merchants = Some(
+            merchants
+                .into_iter()
+                .map(|m| m.as_ref().to_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Require merchants deployed by a known factory (or whitelisted).
+    pub fn with_registry(mut self, registry: Arc<MerchantRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+}
+
+#[async_trait]
+impl VerificationLayer for RegistryLayer {
+    fn layer(&self) -> u8 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "Registry"
+    }
+
+    fn error_code(&self) -> TgpErrorCode {
+        TgpErrorCode::L1Failure
+    }
+
+    async fn verify(&self, query: &QueryMessage) -> Result<(), String> {
+        validate_payment_profile(&query.payment_profile)?;
+
+        if let Some(ref merchants) = self.merchants {
+            if !merchants.contains(&query.payment_profile.to_lowercase()) {
+                return Err(format!(
+                    "payment_profile {} is not a registered merchant contract",
+                    query.payment_profile
+                ));
+            }
+        }
+        if let Some(ref registry) = self.registry {
+            registry.check(&query.payment_profile)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Stateless L1 entry point using the default (open) registry.
+pub async fn layer1_registry_check(query: &QueryMessage) -> Result<(), String> {
+    RegistryLayer::new().verify(query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+
+    #[tokio::test]
+    async fn test_registry_allowlist() {
+        let q = sample_query();
+
+        let open = RegistryLayer::new();
+        assert!(open.verify(&q).await.is_ok());
+
+        let listed = RegistryLayer::new().with_merchants([q.payment_profile.clone()]);
+        assert!(listed.verify(&q).await.is_ok());
+
+        let other = RegistryLayer::new().with_merchants(["0x2222222222222222222222222222222222222222"]);
+        assert!(other.verify(&q).await.is_err());
+
+        // Registry edits (admin whitelist) apply to the shared layer
+        let registry = Arc::new(MerchantRegistry::new());
+        let mirrored = RegistryLayer::new().with_registry(registry.clone());
+        assert!(mirrored.verify(&q).await.is_err());
+        registry.whitelist(&q.payment_profile).unwrap();
+        assert!(mirrored.verify(&q).await.is_ok());
+    }
+}
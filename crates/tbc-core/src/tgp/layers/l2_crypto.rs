@@ -0,0 +1,158 @@
+//! L2 -- Cryptographic Validation
+//!
+//! Checks the session context carried in the QUERY (§4.1):
+//!   • `session_token` / `delegated_key` must not be blank when present
+//!   • `scope` must be a JSON object when present
+//!   • a `delegated_key` must act under a DKW-00 delegation: the DKI in
+//!     `scope`, signed by the wallet (EIP-712, `session_token`), live,
+//!     not revoked, covering the QUERY, which the session key signed in
+//!     turn (see `tgp::delegation`)
+//!
+//! A DKI scoped to one merchant names the merchant's owner: the admin the
+//! `MerchantRegistry` recorded for the `payment_profile`, or the one
+//! configured for it.
+//!
+//! Gateways MUST NOT persist delegated keys or session tokens (§3.2),
+//! so everything here is derived from the QUERY alone, apart from the
+//! revocation list and merchant owners.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::protocol::{QueryMessage, TgpErrorCode};
+use crate::tgp::delegation::{verify_delegation, DelegationRevocations};
+use crate::tgp::merchants::MerchantRegistry;
+use crate::tgp::validation::validate_non_empty;
+
+use super::VerificationLayer;
+
+/// L2 cryptographic layer.
+#[derive(Debug, Clone, Default)]
+pub struct CryptographicLayer {
+    /// Revoked delegations, shared with whoever edits them
+    revocations: Option<Arc<DelegationRevocations>>,
+
+    /// Resolves a merchant's admin
+    registry: Option<Arc<MerchantRegistry>>,
+
+    /// Lower-cased merchant → lower-cased admin, for merchants the
+    /// registry does not know
+    owners: HashMap<String, String>,
+}
+
+impl CryptographicLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject delegations revoked in `revocations`.
+    pub fn with_revocations(mut self, revocations: Arc<DelegationRevocations>) -> Self {
+        self.revocations = Some(revocations);
+        self
+    }
+
+    /// Look up merchants' admins in `registry`.
+    pub fn with_registry(mut self, registry: Arc<MerchantRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Merchant `contract` is administered by `admin`.
+    pub fn with_merchant_admin(mut self, contract: impl AsRef<str>, admin: impl AsRef<str>) -> Self {
+        self.owners.insert(contract.as_ref().to_lowercase(), admin.as_ref().to_lowercase());
+        self
+    }
+
+    fn merchant_owner(&self, merchant: &str) -> Option<String> {
+        let merchant = merchant.to_lowercase();
+        self.registry
+            .as_ref()
+            .and_then(|r| r.get(&merchant))
+            .map(|record| record.admin)
+            .or_else(|| self.owners.get(&merchant).cloned())
+    }
+}
+
+#[async_trait]
+impl VerificationLayer for CryptographicLayer {
+    fn layer(&self) -> u8 {
+        2
+    }
+
+    fn name(&self) -> &'static str {
+        "Signature"
+    }
+
+    fn error_code(&self) -> TgpErrorCode {
+        TgpErrorCode::L2Failure
+    }
+
+    async fn verify(&self, query: &QueryMessage) -> Result<(), String> {
+        if let Some(ref token) = query.session_token {
+            validate_non_empty(token, "session_token")?;
+        }
+
+        if let Some(ref key) = query.delegated_key {
+            validate_non_empty(key, "delegated_key")?;
+        }
+
+        if let Some(ref scope) = query.scope {
+            if !scope.is_object() {
+                return Err("scope must be a JSON object".into());
+            }
+        }
+
+        let owner = self.merchant_owner(&query.payment_profile);
+        verify_delegation(query, Utc::now(), self.revocations.as_deref(), owner.as_deref())?;
+        Ok(())
+    }
+}
+
+/// Stateless L2 entry point.
+pub async fn layer2_cryptographic_check(query: &QueryMessage) -> Result<(), String> {
+    CryptographicLayer::new().verify(query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::delegation::tests::{delegated_query, sample_dki};
+    use crate::tgp::layers::tests::sample_query;
+
+    #[tokio::test]
+    async fn test_delegated_key_requires_signed_dki() {
+        let mut q = sample_query();
+        q.delegated_key = Some("0xabc".into());
+        assert!(layer2_cryptographic_check(&q).await.is_err());
+
+        // A session token alone no longer vouches for the key
+        q.session_token = Some("tok".into());
+        assert!(layer2_cryptographic_check(&q).await.is_err());
+
+        q.scope = Some(serde_json::json!(["not", "an", "object"]));
+        assert!(layer2_cryptographic_check(&q).await.is_err());
+
+        let dki = sample_dki(&q.payment_profile);
+        assert!(layer2_cryptographic_check(&delegated_query(&dki)).await.is_ok());
+
+        let revocations = Arc::new(DelegationRevocations::new());
+        revocations.revoke(&dki.session_key).unwrap();
+        let layer = CryptographicLayer::new().with_revocations(revocations);
+        assert!(layer.verify(&delegated_query(&dki)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_merchant_scoped_dki_checked_against_owner() {
+        let admin = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let mut dki = sample_dki(&sample_query().payment_profile);
+        dki.merchant = admin.into();
+        let q = delegated_query(&dki);
+
+        assert!(CryptographicLayer::new().verify(&q).await.is_err());
+        let layer = CryptographicLayer::new().with_merchant_admin(&q.payment_profile, admin);
+        assert!(layer.verify(&q).await.is_ok());
+    }
+}
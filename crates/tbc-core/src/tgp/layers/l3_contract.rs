@@ -0,0 +1,482 @@
+//! L3 -- Contract Bytecode & RPC Integrity
+//!
+//! Confirms the QUERY targets a chain this gateway can verify against.
+//! With code sources plugged in by the node, L3 also checks the merchant
+//! contract's deployed bytecode:
+//!
+//!   • every configured RPC endpoint is asked for `eth_getCode` of the
+//!     `payment_profile`, and a quorum of them must agree on keccak256(code)
+//!     -- a single hijacked or lagging RPC cannot vouch for a contract
+//!   • the agreed hash must be the `TemplateInfo.code_hash` of the
+//!     merchant's template version, from the `MerchantRegistry` or the
+//!     configured merchant versions; a merchant of unknown version is
+//!     refused
+//!
+//! Fetches stop as soon as a quorum agrees: slow endpoints are not waited on.
+//!
+//! Disagreements are recorded as `MerchantCodeHashMismatchEvent` alerts.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::task::JoinSet;
+
+use crate::contracts::factory::{MerchantCodeHashMismatchEvent, TemplateInfo};
+use crate::contracts::types::{address_to_hex, bytes32_to_hex, hex_to_address, Bytes32};
+use crate::protocol::{QueryMessage, TgpErrorCode};
+use crate::tgp::canonical::keccak256;
+use crate::tgp::merchants::MerchantRegistry;
+use crate::tgp::validation::validate_chain_id;
+
+use super::VerificationLayer;
+
+/// Alerts kept for the admin API; older ones are dropped.
+pub const MAX_RECENT_ALERTS: usize = 100;
+
+// -----------------------------------------------------------------------------
+// Code Sources & Alerts
+// -----------------------------------------------------------------------------
+
+/// One RPC endpoint L3 reads deployed bytecode from.
+#[async_trait]
+pub trait CodeSource: Send + Sync {
+    /// Label used in alerts and errors (never the full URL).
+    fn label(&self) -> String;
+
+    /// `eth_getCode(address, "latest")`; empty when no contract is deployed.
+    async fn code_at(&self, address: &str) -> Result<Vec<u8>, String>;
+}
+
+/// A bytecode disagreement seen by L3.
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeIntegrityAlert {
+    pub chain_id: u64,
+    /// RPC endpoint that disagreed with the quorum; `None` when the agreed
+    /// code does not match the template
+    pub source: Option<String>,
+    pub event: MerchantCodeHashMismatchEvent,
+}
+
+#[derive(Debug, Default)]
+struct AlertLog {
+    recent: VecDeque<CodeIntegrityAlert>,
+    total: u64,
+}
+
+/// Alert log shared by L3 and the admin API.
+#[derive(Debug, Default)]
+pub struct CodeIntegrityAlerts {
+    inner: Mutex<AlertLog>,
+}
+
+impl CodeIntegrityAlerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, alert: CodeIntegrityAlert) {
+        tracing::warn!(
+            "L3 code hash mismatch for {}: expected {}, got {} (source: {})",
+            address_to_hex(&alert.event.deployed_contract),
+            bytes32_to_hex(&alert.event.expected_hash),
+            bytes32_to_hex(&alert.event.actual_hash),
+            alert.source.as_deref().unwrap_or("quorum"),
+        );
+        let mut log = self.inner.lock().unwrap();
+        if log.recent.len() == MAX_RECENT_ALERTS {
+            log.recent.pop_front();
+        }
+        log.recent.push_back(alert);
+        log.total += 1;
+    }
+
+    /// Most recent alerts, oldest first.
+    pub fn recent(&self) -> Vec<CodeIntegrityAlert> {
+        self.inner.lock().unwrap().recent.iter().cloned().collect()
+    }
+
+    /// Alerts recorded since startup.
+    pub fn total(&self) -> u64 {
+        self.inner.lock().unwrap().total
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Layer
+// -----------------------------------------------------------------------------
+
+/// L3 contract/RPC layer.
+#[derive(Clone, Default)]
+pub struct ContractRpcLayer {
+    /// Chains with a configured RPC. `None` = accept any valid chain ID.
+    supported_chains: Option<HashSet<u64>>,
+
+    /// Endpoints queried for bytecode. Empty = no bytecode check.
+    sources: Vec<Arc<dyn CodeSource>>,
+
+    /// Endpoints that must agree; `None` = simple majority
+    quorum: Option<usize>,
+
+    /// Template version → keccak256(runtime bytecode)
+    templates: HashMap<u64, Bytes32>,
+
+    /// Resolves a merchant's template version
+    registry: Option<Arc<MerchantRegistry>>,
+
+    /// Lower-cased merchant → template version, for merchants the
+    /// registry does not know
+    versions: HashMap<String, u64>,
+
+    alerts: Arc<CodeIntegrityAlerts>,
+}
+
+impl ContractRpcLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict L3 to the given chain IDs.
+    pub fn with_supported_chains(mut self, chains: impl IntoIterator<Item = u64>) -> Self {
+        self.supported_chains = Some(chains.into_iter().collect());
+        self
+    }
+
+    /// Check merchant bytecode against these endpoints.
+    pub fn with_code_sources(mut self, sources: Vec<Arc<dyn CodeSource>>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Number of endpoints that must return the same code (at least 1).
+    pub fn with_quorum(mut self, quorum: usize) -> Self {
+        self.quorum = Some(quorum.max(1));
+        self
+    }
+
+    /// Expect merchants of template `version` to run `info.code_hash`.
+    /// Templates that do not exist are ignored.
+    pub fn with_template(mut self, version: u64, info: &TemplateInfo) -> Self {
+        if info.exists {
+            self.templates.insert(version, info.code_hash);
+        }
+        self
+    }
+
+    /// Look up merchants' template versions in `registry`.
+    pub fn with_registry(mut self, registry: Arc<MerchantRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Merchant `contract` runs template `version`.
+    pub fn with_merchant_version(mut self, contract: impl AsRef<str>, version: u64) -> Self {
+        self.versions.insert(contract.as_ref().to_lowercase(), version);
+        self
+    }
+
+    /// Record alerts into a log shared with the caller.
+    pub fn with_alerts(mut self, alerts: Arc<CodeIntegrityAlerts>) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    pub fn alerts(&self) -> &Arc<CodeIntegrityAlerts> {
+        &self.alerts
+    }
+
+    fn required_agreement(&self) -> usize {
+        self.quorum.unwrap_or(self.sources.len() / 2 + 1)
+    }
+
+    /// keccak256 of the code a quorum of sources agrees on.
+    async fn quorum_code_hash(&self, query: &QueryMessage) -> Result<Bytes32, String> {
+        let address = query.payment_profile.to_lowercase();
+        let deployed_contract = hex_to_address(&address).map_err(str::to_string)?;
+
+        let mut fetches = JoinSet::new();
+        for (index, source) in self.sources.iter().enumerate() {
+            let source = Arc::clone(source);
+            let address = address.clone();
+            fetches.spawn(async move { (index, source.code_at(&address).await) });
+        }
+
+        let required = self.required_agreement();
+        let mut results: Vec<(usize, Result<Bytes32, String>)> = Vec::with_capacity(self.sources.len());
+        while let Some(joined) = fetches.join_next().await {
+            let (index, code) = joined.map_err(|e| format!("code fetch task failed: {}", e))?;
+            let hash = code.map(|code| keccak256(&code));
+            let agreeing = match hash {
+                Ok(ref h) => 1 + results.iter().filter(|(_, r)| r.as_ref() == Ok(h)).count(),
+                Err(_) => 0,
+            };
+            results.push((index, hash));
+            // Quorum reached: the remaining fetches are dropped (and aborted)
+            if agreeing >= required {
+                break;
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+
+        // Code hash → endpoints that returned it
+        let mut votes: Vec<(Bytes32, Vec<String>)> = Vec::new();
+        let mut failures = Vec::new();
+        for (index, result) in results {
+            let label = self.sources[index].label();
+            match result {
+                Ok(hash) => match votes.iter_mut().find(|(h, _)| *h == hash) {
+                    Some((_, labels)) => labels.push(label),
+                    None => votes.push((hash, vec![label])),
+                },
+                Err(e) => failures.push(format!("{}: {}", label, e)),
+            }
+        }
+
+        // Ties go to the first endpoint's answer
+        let Some((agreed, agreeing)) = votes
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, (_, labels))| (labels.len(), std::cmp::Reverse(*i)))
+            .map(|(_, (hash, labels))| (*hash, labels.len()))
+        else {
+            return Err(format!("no RPC endpoint returned code for {}: {}", address, failures.join("; ")));
+        };
+
+        for (hash, labels) in votes.iter().filter(|(h, _)| *h != agreed) {
+            for label in labels {
+                self.alerts.record(CodeIntegrityAlert {
+                    chain_id: query.chain_id,
+                    source: Some(label.clone()),
+                    event: MerchantCodeHashMismatchEvent {
+                        deployed_contract,
+                        expected_hash: agreed,
+                        actual_hash: *hash,
+                    },
+                });
+            }
+        }
+
+        if agreeing < required {
+            return Err(format!(
+                "RPC quorum not reached for {}: {} of {} endpoints agree on its code (need {})",
+                address,
+                agreeing,
+                self.sources.len(),
+                required
+            ));
+        }
+        if agreed == keccak256(&[]) {
+            return Err(format!("no contract is deployed at {}", address));
+        }
+        Ok(agreed)
+    }
+
+    /// Compare the agreed hash with the merchant's template.
+    fn check_template(&self, query: &QueryMessage, actual: Bytes32) -> Result<(), String> {
+        if self.templates.is_empty() {
+            return Ok(());
+        }
+
+        let merchant = query.payment_profile.to_lowercase();
+        let version = self
+            .registry
+            .as_ref()
+            .and_then(|r| r.get(&merchant))
+            .map(|record| record.version)
+            .or_else(|| self.versions.get(&merchant).copied())
+            .ok_or_else(|| format!("template version of merchant {} is unknown", merchant))?;
+
+        let expected = *self
+            .templates
+            .get(&version)
+            .ok_or_else(|| format!("no code hash is known for template v{}", version))?;
+        if expected == actual {
+            return Ok(());
+        }
+
+        self.alerts.record(CodeIntegrityAlert {
+            chain_id: query.chain_id,
+            source: None,
+            event: MerchantCodeHashMismatchEvent {
+                deployed_contract: hex_to_address(&query.payment_profile).map_err(str::to_string)?,
+                expected_hash: expected,
+                actual_hash: actual,
+            },
+        });
+        Err(format!(
+            "code hash {} of {} does not match its template",
+            bytes32_to_hex(&actual),
+            query.payment_profile.to_lowercase()
+        ))
+    }
+}
+
+impl std::fmt::Debug for ContractRpcLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContractRpcLayer")
+            .field("supported_chains", &self.supported_chains)
+            .field("sources", &self.sources.iter().map(|s| s.label()).collect::<Vec<_>>())
+            .field("quorum", &self.required_agreement())
+            .field("templates", &self.templates.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl VerificationLayer for ContractRpcLayer {
+    fn layer(&self) -> u8 {
+        3
+    }
+
+    fn name(&self) -> &'static str {
+        "Bytecode"
+    }
+
+    fn error_code(&self) -> TgpErrorCode {
+        TgpErrorCode::L3Failure
+    }
+
+    async fn verify(&self, query: &QueryMessage) -> Result<(), String> {
+        validate_chain_id(query.chain_id)?;
+
+        if let Some(ref chains) = self.supported_chains {
+            if !chains.contains(&query.chain_id) {
+                return Err(format!("chain_id {} is not served by this gateway", query.chain_id));
+            }
+        }
+
+        if self.sources.is_empty() {
+            return Ok(());
+        }
+        let actual = self.quorum_code_hash(query).await?;
+        self.check_template(query, actual)
+    }
+}
+
+/// Stateless L3 entry point.
+pub async fn layer3_contract_rpc_check(query: &QueryMessage) -> Result<(), String> {
+    ContractRpcLayer::new().verify(query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+
+    struct FixedCode(&'static str, Result<Vec<u8>, String>);
+
+    #[async_trait]
+    impl CodeSource for FixedCode {
+        fn label(&self) -> String {
+            self.0.to_string()
+        }
+
+        async fn code_at(&self, _address: &str) -> Result<Vec<u8>, String> {
+            self.1.clone()
+        }
+    }
+
+    fn source(label: &'static str, code: &[u8]) -> Arc<dyn CodeSource> {
+        Arc::new(FixedCode(label, Ok(code.to_vec())))
+    }
+
+    /// Answers as the inner source after the delay; without one, never.
+    struct Slow(Option<std::time::Duration>, Arc<dyn CodeSource>);
+
+    #[async_trait]
+    impl CodeSource for Slow {
+        fn label(&self) -> String {
+            self.1.label()
+        }
+
+        async fn code_at(&self, address: &str) -> Result<Vec<u8>, String> {
+            match self.0 {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => std::future::pending().await,
+            }
+            self.1.code_at(address).await
+        }
+    }
+
+    fn template(code: &[u8]) -> TemplateInfo {
+        TemplateInfo { code_hash: keccak256(code), exists: true, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_supported_chains() {
+        let q = sample_query();
+        assert!(ContractRpcLayer::new().with_supported_chains([369]).verify(&q).await.is_ok());
+        assert!(ContractRpcLayer::new().with_supported_chains([1]).verify(&q).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_code_quorum_and_template() {
+        let q = sample_query();
+        let genuine: &[u8] = &[0x60, 0x80, 0x60, 0x40];
+        let swapped: &[u8] = &[0x60, 0x00];
+        let down: Arc<dyn CodeSource> = Arc::new(FixedCode("c", Err("timeout".into())));
+
+        // Two of three agree on the template's code; the hijacked RPC,
+        // answering before the quorum is reached, is reported
+        let late: Arc<dyn CodeSource> = Arc::new(Slow(Some(std::time::Duration::from_millis(50)), source("c", genuine)));
+        let layer = ContractRpcLayer::new()
+            .with_code_sources(vec![source("a", genuine), source("b", swapped), late])
+            .with_template(1, &template(genuine))
+            .with_merchant_version(&q.payment_profile, 1);
+        assert!(layer.verify(&q).await.is_ok());
+        let alerts = layer.alerts().recent();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].source.as_deref(), Some("b"));
+        assert_eq!(alerts[0].event.expected_hash, keccak256(genuine));
+
+        // No majority when one endpoint is down and the others disagree
+        let layer = ContractRpcLayer::new()
+            .with_code_sources(vec![source("a", genuine), source("b", swapped), down.clone()]);
+        assert!(layer.verify(&q).await.unwrap_err().contains("quorum"));
+
+        // A quorum of one tolerates the outage
+        let layer = ContractRpcLayer::new()
+            .with_code_sources(vec![source("a", genuine), down])
+            .with_quorum(1);
+        assert!(layer.verify(&q).await.is_ok());
+
+        // Once a quorum agrees, a hanging endpoint is not waited for
+        let hung: Arc<dyn CodeSource> = Arc::new(Slow(None, source("c", genuine)));
+        let layer = ContractRpcLayer::new().with_code_sources(vec![source("a", genuine), hung, source("b", genuine)]);
+        let verified = tokio::time::timeout(std::time::Duration::from_secs(5), layer.verify(&q)).await;
+        assert!(verified.expect("quorum reached without the hung endpoint").is_ok());
+
+        // All endpoints agree on a contract that is not the template
+        let layer = ContractRpcLayer::new()
+            .with_code_sources(vec![source("a", swapped), source("b", swapped)])
+            .with_template(1, &template(genuine))
+            .with_merchant_version(&q.payment_profile, 1);
+        assert!(layer.verify(&q).await.is_err());
+        assert_eq!(layer.alerts().recent()[0].source, None);
+        assert_eq!(layer.alerts().total(), 1);
+
+        // Nothing deployed
+        let layer = ContractRpcLayer::new().with_code_sources(vec![source("a", &[])]);
+        assert!(layer.verify(&q).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_template_bound_to_merchant_version() {
+        let q = sample_query();
+        let v1: &[u8] = &[0x60, 0x80, 0x60, 0x40];
+        let v2: &[u8] = &[0x60, 0x80, 0x60, 0x60];
+        let layer = || {
+            ContractRpcLayer::new()
+                .with_code_sources(vec![source("a", v2)])
+                .with_template(1, &template(v1))
+                .with_template(2, &template(v2))
+        };
+
+        // Running some template is not enough: it must be the merchant's
+        assert!(layer().verify(&q).await.unwrap_err().contains("unknown"));
+        assert!(layer().with_merchant_version(&q.payment_profile, 1).verify(&q).await.is_err());
+        assert!(layer().with_merchant_version(&q.payment_profile, 2).verify(&q).await.is_ok());
+        assert!(layer().with_merchant_version(&q.payment_profile, 3).verify(&q).await.is_err());
+    }
+}
@@ -0,0 +1,491 @@
+//! L4 -- ZK / Attestation (optional)
+//!
+//! L4 is optional in TGP-00. A QUERY may carry a TGP-EXT-ZK-00 proof
+//! envelope (`{"type": "TGP_ZK_PROOF", "payload": ...}`) in
+//! `metadata.zk_proof_envelope`; one is required when `intent.mode` is
+//! SHIELDED or `metadata.zk_profile` is REQUIRED, and must then attest the
+//! QUERY's party (ZKB01 for a buyer, ZKS01 for a seller). An attached
+//! envelope is:
+//!
+//!   • checked for shape, circuit version and freshness
+//!   • bound to the QUERY: `chain_id`, `order_id` (the order the settlement
+//!     call acts on), `profile_hash` = keccak256(payment_profile address),
+//!     `session_id` = keccak256(session_token), and `session_pubkey` =
+//!     `delegated_key` when the QUERY is delegated
+//!   • verified by the configured `ZkVerifier` (none configured = reject)
+//!   • rejected if its nullifier was already burned
+//!
+//! The nullifier is only burned in the commit phase, after every layer has
+//! passed -- never for QUOTE or any other dry run -- and is un-burned by
+//! `rollback` if the QUERY is not answered with ACK(allow) after all.
+//! ZKB01/ZKS01 proofs are then rewritten into a `ContractSafeProof` for
+//! the envelope builder; ZKM01 stays with the gateway.
+//!
+//! A gateway that only routes shielded settlement enables `require_shielded`.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::contracts::types::{bytes32_to_hex, hex_to_address, hex_to_bytes32};
+use crate::protocol::{QueryMessage, TGPMODE, TGPParty, TGPVerb, TgpErrorCode};
+use crate::tgp::canonical::keccak256;
+use crate::tgp::tx_builder::settlement_call_for;
+use crate::tgp::types::ZkProfile;
+use crate::zk::{
+    ContractBuyerOutput, ContractSafeProof, ContractSellerOutput, MemoryNullifierStore,
+    NullifierStore, ZkBuyerInputs, ZkErrorCode, ZkProofEnvelope, ZkProofPayload, ZkProofType,
+    ZkSellerInputs, ZkVerifier, CURRENT_PROOF_VERSION, MAX_TIMESTAMP_DRIFT_SECONDS,
+    PROOF_TTL_SECONDS,
+};
+
+use super::{LayerOutputs, VerificationLayer};
+
+/// QUERY `metadata` key carrying the TGP_ZK_PROOF envelope.
+pub const ZK_ENVELOPE_METADATA_KEY: &str = "zk_proof_envelope";
+
+/// L4 ZK/attestation layer.
+#[derive(Clone)]
+pub struct ZkAttestationLayer {
+    require_shielded: bool,
+
+    /// Proof backend; shielded QUERYs fail closed without one
+    verifier: Option<Arc<dyn ZkVerifier>>,
+
+    /// Burned nullifiers, shared with whoever reports or persists them
+    nullifiers: Arc<dyn NullifierStore>,
+
+    proof_ttl_secs: u64,
+}
+
+impl ZkAttestationLayer {
+    pub fn new() -> Self {
+        Self {
+            require_shielded: false,
+            verifier: None,
+            nullifiers: Arc::new(MemoryNullifierStore::default()),
+            proof_ttl_secs: PROOF_TTL_SECONDS,
+        }
+    }
+
+    /// Reject every QUERY whose `intent.mode` is not SHIELDED.
+    pub fn require_shielded(mut self, required: bool) -> Self {
+        self.require_shielded = required;
+        self
+    }
+
+    /// Verify attached proofs with `verifier`.
+    pub fn with_verifier(mut self, verifier: Arc<dyn ZkVerifier>) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Burn nullifiers into `store`.
+    pub fn with_nullifier_store(mut self, store: Arc<dyn NullifierStore>) -> Self {
+        self.nullifiers = store;
+        self
+    }
+
+    /// Oldest proof accepted, in seconds.
+    pub fn with_proof_ttl(mut self, secs: u64) -> Self {
+        self.proof_ttl_secs = secs;
+        self
+    }
+}
+
+impl Default for ZkAttestationLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for ZkAttestationLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZkAttestationLayer")
+            .field("require_shielded", &self.require_shielded)
+            .field("verifier", &self.verifier.as_ref().map(|v| v.supported_types()))
+            .field("proof_ttl_secs", &self.proof_ttl_secs)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl VerificationLayer for ZkAttestationLayer {
+    fn layer(&self) -> u8 {
+        4
+    }
+
+    fn name(&self) -> &'static str {
+        "ZK"
+    }
+
+    fn error_code(&self) -> TgpErrorCode {
+        TgpErrorCode::L4Failure
+    }
+
+    async fn verify(&self, query: &QueryMessage) -> Result<(), String> {
+        if self.require_shielded && query.intent.mode != TGPMODE::SHIELDED {
+            return Err("gateway requires intent.mode = SHIELDED".into());
+        }
+
+        let Some(envelope) = attached_envelope(query)? else {
+            if proof_required(query) {
+                return Err(zk_err(ZkErrorCode::InvalidInputs, "shielded QUERY carries no TGP_ZK_PROOF envelope"));
+            }
+            return Ok(());
+        };
+        let payload = &envelope.payload;
+
+        if proof_required(query) {
+            let party_proof = match query.intent.party {
+                TGPParty::BUYER => ZkProofType::ZKB01,
+                TGPParty::SELLER => ZkProofType::ZKS01,
+            };
+            if payload.zk_type != party_proof {
+                return Err(zk_err(
+                    ZkErrorCode::UnknownType,
+                    format!("shielded {:?} QUERY needs a {} proof, not {}", query.intent.party, party_proof, payload.zk_type),
+                ));
+            }
+        }
+
+        if payload.proof_version != CURRENT_PROOF_VERSION {
+            return Err(zk_err(
+                ZkErrorCode::UnsupportedVersion,
+                format!("proof_version {} (supported: {})", payload.proof_version, CURRENT_PROOF_VERSION),
+            ));
+        }
+        let now = Utc::now().timestamp().max(0) as u64;
+        if payload.zk_timestamp > now + MAX_TIMESTAMP_DRIFT_SECONDS
+            || !payload.is_timestamp_valid(now.max(payload.zk_timestamp), self.proof_ttl_secs)
+        {
+            return Err(zk_err(ZkErrorCode::ExpiredProof, "zk_timestamp outside the proof window"));
+        }
+
+        contract_safe_proof(query, payload)?;
+
+        let Some(ref verifier) = self.verifier else {
+            return Err(zk_err(ZkErrorCode::InternalError, "no ZK verifier configured"));
+        };
+        if !verifier.supported_types().contains(&payload.zk_type) {
+            return Err(zk_err(ZkErrorCode::UnknownType, format!("{} is not verified here", payload.zk_type)));
+        }
+        let result = verifier.verify(payload).await;
+        if !result.valid {
+            return Err(zk_err(ZkErrorCode::InvalidProof, result.error.unwrap_or_default()));
+        }
+        if !result.nullifier.eq_ignore_ascii_case(&payload.zk_nullifier) {
+            return Err(zk_err(ZkErrorCode::InvalidProof, "verifier reported another nullifier"));
+        }
+
+        if self.nullifiers.exists(&payload.zk_nullifier.to_lowercase()).await {
+            return Err(zk_err(ZkErrorCode::Replay, "nullifier already used"));
+        }
+        Ok(())
+    }
+
+    async fn commit(&self, query: &QueryMessage, outputs: &mut LayerOutputs) -> Result<(), String> {
+        let Some(envelope) = attached_envelope(query)? else {
+            return Ok(());
+        };
+        let payload = &envelope.payload;
+        let proof = contract_safe_proof(query, payload)?;
+
+        // Atomic: of two QUERYs racing with one proof, only one burns it
+        if !self.nullifiers.insert(&payload.zk_nullifier.to_lowercase(), payload.zk_timestamp).await {
+            return Err(zk_err(ZkErrorCode::Replay, "nullifier already used"));
+        }
+        outputs.zk = proof;
+        Ok(())
+    }
+
+    async fn rollback(&self, query: &QueryMessage) {
+        if let Ok(Some(envelope)) = attached_envelope(query) {
+            self.nullifiers.remove(&envelope.payload.zk_nullifier.to_lowercase()).await;
+        }
+    }
+}
+
+/// Stateless L4 entry point.
+pub async fn layer4_zk_attestation_check(query: &QueryMessage) -> Result<(), String> {
+    ZkAttestationLayer::new().verify(query).await
+}
+
+fn zk_err(code: ZkErrorCode, detail: impl std::fmt::Display) -> String {
+    format!("{}: {}", code, detail)
+}
+
+/// Does the QUERY ask for shielded settlement?
+fn proof_required(query: &QueryMessage) -> bool {
+    query.intent.mode == TGPMODE::SHIELDED
+        || query
+            .metadata
+            .get("zk_profile")
+            .and_then(|p| serde_json::from_value::<ZkProfile>(p.clone()).ok())
+            == Some(ZkProfile::Required)
+}
+
+/// The TGP_ZK_PROOF envelope attached to the QUERY, if any.
+fn attached_envelope(query: &QueryMessage) -> Result<Option<ZkProofEnvelope>, String> {
+    let Some(raw) = query.metadata.get(ZK_ENVELOPE_METADATA_KEY) else {
+        return Ok(None);
+    };
+    let envelope: ZkProofEnvelope = serde_json::from_value(raw.clone())
+        .map_err(|e| zk_err(ZkErrorCode::InvalidInputs, format!("malformed TGP_ZK_PROOF envelope: {}", e)))?;
+    envelope.validate().map_err(|e| zk_err(ZkErrorCode::InvalidInputs, e))?;
+    Ok(Some(envelope))
+}
+
+/// Bind the proof to the QUERY and rewrite it into contract outputs
+/// (`None` for ZKM01, which never reaches the contract).
+fn contract_safe_proof(query: &QueryMessage, payload: &ZkProofPayload) -> Result<Option<ContractSafeProof>, String> {
+    if payload.chain_id != query.chain_id {
+        return Err(zk_err(ZkErrorCode::ChainMismatch, format!("proof is for chain {}", payload.chain_id)));
+    }
+
+    let order_id = settlement_call_for(query)?.order_id();
+    if !payload.order_id.eq_ignore_ascii_case(&bytes32_to_hex(&order_id)) {
+        return Err(zk_err(ZkErrorCode::OrderMismatch, "order_id does not match the QUERY's order"));
+    }
+
+    let profile = hex_to_address(&query.payment_profile).map_err(str::to_string)?;
+    if hex_to_bytes32(&payload.profile_hash).ok() != Some(keccak256(&profile)) {
+        return Err(zk_err(ZkErrorCode::InvalidInputs, "profile_hash does not match payment_profile"));
+    }
+
+    let Some(ref token) = query.session_token else {
+        return Err(zk_err(ZkErrorCode::InvalidInputs, "proof requires a session_token to bind session_id"));
+    };
+    if hex_to_bytes32(&payload.session_id).ok() != Some(keccak256(token.as_bytes())) {
+        return Err(zk_err(ZkErrorCode::InvalidInputs, "session_id does not match session_token"));
+    }
+    if let Some(ref key) = query.delegated_key {
+        if !key.eq_ignore_ascii_case(&payload.session_pubkey) {
+            return Err(zk_err(ZkErrorCode::PkMismatch, "session_pubkey is not the delegated_key"));
+        }
+    }
+
+    let invalid_inputs = |e: serde_json::Error| zk_err(ZkErrorCode::InvalidInputs, format!("zk_inputs: {}", e));
+    let same_proof = |nullifier: &str, timestamp: &str, session_pubkey: &str, chain_id: u64| {
+        if !nullifier.eq_ignore_ascii_case(&payload.zk_nullifier)
+            || timestamp != payload.zk_timestamp.to_string()
+            || !session_pubkey.eq_ignore_ascii_case(&payload.session_pubkey)
+            || chain_id != payload.chain_id
+        {
+            return Err(zk_err(ZkErrorCode::InvalidInputs, "zk_inputs disagree with the envelope"));
+        }
+        Ok(())
+    };
+
+    match (payload.zk_type, &query.intent.verb, &query.intent.party) {
+        (ZkProofType::ZKB01, TGPVerb::COMMIT | TGPVerb::PAY, TGPParty::BUYER) => {
+            let inputs: ZkBuyerInputs = serde_json::from_value(payload.zk_inputs.clone()).map_err(invalid_inputs)?;
+            same_proof(&inputs.nullifier, &inputs.timestamp, &inputs.session_pubkey, inputs.chain_id)?;
+            if inputs.amount != query.amount.to_string() {
+                return Err(zk_err(ZkErrorCode::InvalidInputs, "proven amount is not the QUERY amount"));
+            }
+            if !inputs.escrow_address.eq_ignore_ascii_case(&query.payment_profile) {
+                return Err(zk_err(ZkErrorCode::InvalidInputs, "escrow_address is not the payment_profile"));
+            }
+            Ok(Some(ContractSafeProof {
+                buyer: Some(ContractBuyerOutput {
+                    pk_hash: inputs.pk_hash,
+                    nullifier: inputs.nullifier,
+                    timestamp: inputs.timestamp,
+                    amount: inputs.amount,
+                }),
+                seller: None,
+            }))
+        }
+        (ZkProofType::ZKS01, TGPVerb::COMMIT, TGPParty::SELLER) => {
+            let inputs: ZkSellerInputs = serde_json::from_value(payload.zk_inputs.clone()).map_err(invalid_inputs)?;
+            same_proof(&inputs.nullifier, &inputs.timestamp, &inputs.session_pubkey, inputs.chain_id)?;
+            Ok(Some(ContractSafeProof {
+                buyer: None,
+                seller: Some(ContractSellerOutput {
+                    pk_hash: inputs.pk_hash,
+                    nullifier: inputs.nullifier,
+                    timestamp: inputs.timestamp,
+                    order_hash: inputs.order_hash,
+                }),
+            }))
+        }
+        (ZkProofType::ZKM01, _, _) => Ok(None),
+        (zk_type, verb, party) => Err(zk_err(
+            ZkErrorCode::UnknownType,
+            format!("{} does not attest {:?} by {:?}", zk_type, verb, party),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+    use crate::tgp::layers::LayerPipeline;
+    use crate::zk::MockZkVerifier;
+    use serde_json::json;
+
+    const NULLIFIER: &str = "0x4444444444444444444444444444444444444444444444444444444444444444";
+    const SESSION_PUBKEY: &str = "0x02aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+
+    /// Shielded buyer QUERY carrying a ZKB01 proof bound to it.
+    fn shielded_query() -> QueryMessage {
+        let mut q = sample_query();
+        q.intent.mode = TGPMODE::SHIELDED;
+        q.session_token = Some("session-1".into());
+
+        let now = Utc::now().timestamp() as u64;
+        let order_id = bytes32_to_hex(&keccak256(q.id.as_bytes()));
+        let profile_hash = bytes32_to_hex(&keccak256(&hex_to_address(&q.payment_profile).unwrap()));
+        let payload = json!({
+            "zk_type": "ZKB01",
+            "zk_proof": "cHJvb2Y",
+            "zk_inputs": {
+                "escrow_address": q.payment_profile,
+                "amount": q.amount.to_string(),
+                "pk_hash": "0x5555555555555555555555555555555555555555555555555555555555555555",
+                "nullifier": NULLIFIER,
+                "timestamp": now.to_string(),
+                "session_pubkey": SESSION_PUBKEY,
+                "deposit_tx_hash": "0x6666666666666666666666666666666666666666666666666666666666666666",
+                "chain_id": q.chain_id,
+            },
+            "zk_nullifier": NULLIFIER,
+            "zk_timestamp": now,
+            "session_pubkey": SESSION_PUBKEY,
+            "device_commitment": "0x77",
+            "proof_version": CURRENT_PROOF_VERSION,
+            "session_id": bytes32_to_hex(&keccak256(b"session-1")),
+            "order_id": order_id,
+            "profile_hash": profile_hash,
+            "chain_id": q.chain_id,
+        });
+        q.metadata = json!({ ZK_ENVELOPE_METADATA_KEY: { "type": "TGP_ZK_PROOF", "payload": payload } });
+        q
+    }
+
+    fn zk_layer(store: Arc<MemoryNullifierStore>) -> ZkAttestationLayer {
+        ZkAttestationLayer::new()
+            .with_verifier(Arc::new(MockZkVerifier::default()))
+            .with_nullifier_store(store)
+    }
+
+    #[tokio::test]
+    async fn test_require_shielded() {
+        let mut q = sample_query();
+        let layer = ZkAttestationLayer::new().require_shielded(true);
+        assert!(layer.verify(&q).await.is_err());
+
+        // Shielded, but nothing to attest it
+        q.intent.mode = TGPMODE::SHIELDED;
+        assert!(layer.verify(&q).await.unwrap_err().starts_with("ZK_INVALID_INPUTS"));
+
+        let q = shielded_query();
+        assert!(layer.verify(&q).await.unwrap_err().contains("no ZK verifier"));
+        assert!(zk_layer(Arc::default()).require_shielded(true).verify(&q).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_proof_bound_to_query() {
+        let layer = zk_layer(Arc::default());
+
+        let mut other_order = shielded_query();
+        other_order.id = "q-other".into();
+        assert!(layer.verify(&other_order).await.unwrap_err().starts_with("ZK_ORDER_MISMATCH"));
+
+        let mut other_chain = shielded_query();
+        other_chain.chain_id = 1;
+        assert!(layer.verify(&other_chain).await.unwrap_err().starts_with("ZK_CHAIN_MISMATCH"));
+
+        let mut other_session = shielded_query();
+        other_session.session_token = Some("session-2".into());
+        assert!(layer.verify(&other_session).await.is_err());
+
+        let mut other_amount = shielded_query();
+        other_amount.amount += 1;
+        assert!(layer.verify(&other_amount).await.is_err());
+
+        let mut delegated = shielded_query();
+        delegated.delegated_key = Some("0x03bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".into());
+        assert!(layer.verify(&delegated).await.unwrap_err().starts_with("ZK_PK_MISMATCH"));
+
+        let mut invalid = shielded_query();
+        invalid.metadata[ZK_ENVELOPE_METADATA_KEY]["payload"]["zk_proof"] = json!("INVALID");
+        assert!(layer.verify(&invalid).await.unwrap_err().starts_with("ZK_INVALID_PROOF"));
+    }
+
+    #[tokio::test]
+    async fn test_nullifier_burned_on_commit_only() {
+        let store = Arc::new(MemoryNullifierStore::default());
+        let pipeline = LayerPipeline::default().with_layer(zk_layer(store.clone()));
+        let q = shielded_query();
+
+        // Dry runs (QUOTE) verify the proof but never burn it
+        assert!(pipeline.dry_run(&q).await.is_ok());
+        assert_eq!(store.count().await, 0);
+
+        let outputs = pipeline.run(&q).await.unwrap();
+        let buyer = outputs.zk.unwrap().buyer.unwrap();
+        assert_eq!(buyer.nullifier, NULLIFIER);
+        assert_eq!(buyer.amount, q.amount.to_string());
+        assert!(store.exists(NULLIFIER).await);
+
+        let replay = pipeline.run(&q).await.unwrap_err();
+        assert_eq!(replay.code.layer(), 4);
+        assert!(replay.reason.starts_with("ZK_REPLAY"));
+
+        // Refused after the commit after all: the proof is usable again
+        pipeline.rollback(&q).await;
+        assert_eq!(store.count().await, 0);
+        assert!(pipeline.run(&q).await.is_ok());
+    }
+
+    /// L5 stand-in that passes every check but refuses at commit, like
+    /// a velocity slot taken by a concurrent QUERY.
+    struct RefuseCommit;
+
+    #[async_trait]
+    impl VerificationLayer for RefuseCommit {
+        fn layer(&self) -> u8 { 5 }
+        fn name(&self) -> &'static str { "RefuseCommit" }
+        fn error_code(&self) -> TgpErrorCode { TgpErrorCode::L5Failure }
+        async fn verify(&self, _query: &QueryMessage) -> Result<(), String> {
+            Ok(())
+        }
+        async fn commit(&self, _query: &QueryMessage, _outputs: &mut LayerOutputs) -> Result<(), String> {
+            Err("velocity limit reached".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nullifier_returned_when_a_later_commit_refuses() {
+        let store = Arc::new(MemoryNullifierStore::default());
+        let pipeline = LayerPipeline::default()
+            .with_layer(zk_layer(store.clone()))
+            .with_layer(RefuseCommit);
+
+        let failure = pipeline.run(&shielded_query()).await.unwrap_err();
+        assert_eq!(failure.code.layer(), 5);
+        assert_eq!(store.count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shielded_query_needs_its_party_proof() {
+        let layer = zk_layer(Arc::default());
+
+        // A merchant-policy proof does not attest a shielded buyer
+        let mut merchant_proof = shielded_query();
+        merchant_proof.metadata[ZK_ENVELOPE_METADATA_KEY]["payload"]["zk_type"] = json!("ZKM01");
+        assert!(layer.verify(&merchant_proof).await.unwrap_err().starts_with("ZK_UNKNOWN_TYPE"));
+
+        let mut seller = shielded_query();
+        seller.intent.party = TGPParty::SELLER;
+        assert!(layer.verify(&seller).await.unwrap_err().contains("needs a ZKS01 proof"));
+
+        // Outside shielded mode a ZKM01 proof may ride along
+        merchant_proof.intent.mode = TGPMODE::DIRECT;
+        assert!(layer.verify(&merchant_proof).await.is_ok());
+    }
+}
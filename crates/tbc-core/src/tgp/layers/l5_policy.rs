@@ -0,0 +1,241 @@
+//! L5 -- Policy Evaluation
+//!
+//! Merchant rules, fees and limits. The layer enforces its own optional
+//! amount ceiling and settlement mode plus, when given a `PolicyEngine`,
+//! the global and per-merchant policies declared there (`tgp::policy`).
+//!
+//! Broken rules fail softly: the client gets ACK(status=revise) listing
+//! every constraint to meet, or ACK(status=deny) when any broken rule
+//! cannot be met by revising the QUERY. Velocity is counted in the commit
+//! phase, so QUOTEs and rejected QUERYs never use up a buyer's allowance.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::protocol::{AckReason, ConstraintOp, FieldConstraint, QueryMessage, TGPMODE, TgpErrorCode};
+use crate::tgp::policy::{Policy, PolicyEngine, RuleAction, RuleViolation};
+use crate::tgp::tx_builder::EnvelopeParams;
+use crate::tgp::validation::validate_amount_nonzero;
+
+use super::{LayerOutputs, PolicyVerdict, VerificationLayer};
+
+pub use crate::tgp::policy::REASON_AMOUNT_LIMIT;
+
+/// Reason code: `intent.mode` not the one policy requires.
+pub const REASON_MODE_REQUIRED: &str = "POLICY_MODE_REQUIRED";
+
+/// L5 policy layer.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyLayer {
+    /// Rules set through the builder
+    rules: Policy,
+    required_mode: Option<TGPMODE>,
+
+    /// Declarative policies, shared with the admin API
+    engine: Option<Arc<PolicyEngine>>,
+
+    /// Fees the envelope would carry, for `max_fee_bps`
+    fees: EnvelopeParams,
+}
+
+impl PolicyLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject QUERYs whose `amount` exceeds `max`.
+    pub fn with_max_amount(mut self, max: u64) -> Self {
+        self.rules.max_amount = Some(max);
+        self
+    }
+
+    /// Require `intent.mode` (e.g. shielded settlement).
+    pub fn with_required_mode(mut self, mode: TGPMODE) -> Self {
+        self.required_mode = Some(mode);
+        self
+    }
+
+    /// Enforce the policies held by `engine`.
+    pub fn with_engine(mut self, engine: Arc<PolicyEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// Price fee rules with the gateway's envelope parameters.
+    pub fn with_fee_schedule(mut self, params: &EnvelopeParams) -> Self {
+        self.fees = params.clone();
+        self
+    }
+
+    /// Routing fee plus the merchant contract's fees, in basis points.
+    fn fee_bps(&self, query: &QueryMessage) -> u32 {
+        let merchant = self
+            .fees
+            .merchant_fees_for(&query.payment_profile)
+            .map_or(0, |m| m.tbc_fee_bps.saturating_add(m.zk_fee_bps));
+        u32::try_from(merchant).unwrap_or(u32::MAX).saturating_add(self.fees.fees_bps)
+    }
+
+    /// Rules the QUERY breaks, in evaluation order.
+    fn violations(&self, query: &QueryMessage) -> Vec<RuleViolation> {
+        let fee_bps = self.fee_bps(query);
+        let mut out = self.rules.check(query, fee_bps);
+
+        if let Some(ref mode) = self.required_mode {
+            if query.intent.mode != *mode {
+                let wire = serde_json::to_value(mode).unwrap_or_default();
+                out.push(RuleViolation::revise(
+                    REASON_MODE_REQUIRED,
+                    format!("intent.mode must be {}", wire.as_str().unwrap_or_default()),
+                    FieldConstraint::new("intent.mode", ConstraintOp::Eq, wire),
+                ));
+            }
+        }
+
+        if let Some(ref engine) = self.engine {
+            out.extend(engine.evaluate(query, fee_bps, now()));
+        }
+        out
+    }
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+#[async_trait]
+impl VerificationLayer for PolicyLayer {
+    fn layer(&self) -> u8 {
+        5
+    }
+
+    fn name(&self) -> &'static str {
+        "Policy"
+    }
+
+    fn error_code(&self) -> TgpErrorCode {
+        TgpErrorCode::L5Failure
+    }
+
+    async fn review(&self, query: &QueryMessage) -> Option<PolicyVerdict> {
+        let violations = self.violations(query);
+
+        // A rule no revision can meet wins
+        if let Some(deny) = violations.iter().find(|v| v.action == RuleAction::Deny) {
+            let mut reason = AckReason::new(deny.code, deny.message.clone());
+            if let Some(ref constraint) = deny.constraint {
+                reason = reason.with_constraint(constraint.clone());
+            }
+            return Some(PolicyVerdict::Deny(reason));
+        }
+
+        // One reason code/message (the first violation), every constraint
+        let first = violations.first()?;
+        let mut reason = AckReason::new(first.code, first.message.clone());
+        for constraint in violations.into_iter().filter_map(|v| v.constraint) {
+            reason = reason.with_constraint(constraint);
+        }
+        Some(PolicyVerdict::Revise(reason))
+    }
+
+    async fn verify(&self, query: &QueryMessage) -> Result<(), String> {
+        validate_amount_nonzero(query.amount)?;
+
+        match self.violations(query).into_iter().next() {
+            Some(violation) => Err(violation.message),
+            None => Ok(()),
+        }
+    }
+
+    async fn commit(&self, query: &QueryMessage, _outputs: &mut LayerOutputs) -> Result<(), String> {
+        match self.engine {
+            Some(ref engine) => engine.admit(query, now()).map_err(|v| v.message),
+            None => Ok(()),
+        }
+    }
+
+    async fn rollback(&self, query: &QueryMessage) {
+        if let Some(ref engine) = self.engine {
+            engine.release(query);
+        }
+    }
+}
+
+/// Stateless L5 entry point.
+pub async fn layer5_policy_check(query: &QueryMessage) -> Result<(), String> {
+    PolicyLayer::new().verify(query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::delegation::tests::{delegated_query, sample_dki};
+    use crate::tgp::layers::tests::sample_query;
+    use crate::tgp::layers::LayerPipeline;
+    use crate::tgp::policy::{VelocityLimit, REASON_FEE_LIMIT, REASON_VELOCITY_LIMIT};
+
+    #[tokio::test]
+    async fn test_max_amount() {
+        let q = sample_query();
+        assert!(PolicyLayer::new().with_max_amount(1_000).verify(&q).await.is_ok());
+        assert!(PolicyLayer::new().with_max_amount(999).verify(&q).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_review_lists_constraints() {
+        let q = sample_query();
+        let layer = PolicyLayer::new()
+            .with_max_amount(999)
+            .with_required_mode(TGPMODE::SHIELDED);
+
+        let Some(PolicyVerdict::Revise(reason)) = layer.review(&q).await else {
+            panic!("expected revise verdict");
+        };
+        assert_eq!(reason.code, REASON_AMOUNT_LIMIT);
+        assert_eq!(reason.constraints, vec![
+            FieldConstraint::new("amount", ConstraintOp::Max, 999),
+            FieldConstraint::new("intent.mode", ConstraintOp::Eq, "shielded"),
+        ]);
+
+        assert!(PolicyLayer::new().with_max_amount(1_000).review(&q).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_engine_policies_hot_swap() {
+        let engine = Arc::new(PolicyEngine::new());
+        let q = delegated_query(&sample_dki(&sample_query().payment_profile));
+        let pipeline = LayerPipeline::default().with_layer(
+            PolicyLayer::new()
+                .with_engine(engine.clone())
+                .with_fee_schedule(&EnvelopeParams::new().with_fees_bps(50)),
+        );
+        assert!(pipeline.run(&q).await.is_ok());
+
+        // Replaced at runtime: the fee ceiling denies, whatever else is revisable
+        engine
+            .set_global(Policy { max_fee_bps: Some(40), max_amount: Some(10), ..Policy::default() })
+            .unwrap();
+        let failure = pipeline.run(&q).await.unwrap_err();
+        let Some(PolicyVerdict::Deny(reason)) = failure.verdict else {
+            panic!("expected deny verdict");
+        };
+        assert_eq!(reason.code, REASON_FEE_LIMIT);
+
+        // Velocity is only counted for committed QUERYs, never dry runs
+        let velocity = VelocityLimit { window_secs: 3600, max_queries: Some(1), max_amount: None };
+        engine
+            .upsert_merchant(&q.payment_profile, Policy { velocity: Some(velocity), ..Policy::default() })
+            .unwrap();
+        engine.set_global(Policy::default()).unwrap();
+        assert!(pipeline.dry_run(&q).await.is_ok());
+        assert!(pipeline.run(&q).await.is_ok());
+        let failure = pipeline.run(&q).await.unwrap_err();
+        assert!(matches!(failure.verdict, Some(PolicyVerdict::Deny(ref r)) if r.code == REASON_VELOCITY_LIMIT));
+
+        // ... and a QUERY refused after its commit gives its slot back
+        pipeline.rollback(&q).await;
+        assert!(pipeline.run(&q).await.is_ok());
+    }
+}
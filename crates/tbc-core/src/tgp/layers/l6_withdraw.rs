@@ -0,0 +1,72 @@
+//! L6 -- Escrow / WITHDRAW Eligibility
+//!
+//! Only evaluated for `intent.verb == WITHDRAW` (§9: "WITHDRAW MUST be
+//! validated at L6"). A WITHDRAW maps to `buyerCancelExpiredCommit()`,
+//! which only the buyer may call.
+
+use async_trait::async_trait;
+
+use crate::protocol::{QueryMessage, TgpErrorCode, TGPParty, TGPVerb};
+
+use super::VerificationLayer;
+
+/// L6 WITHDRAW eligibility layer.
+#[derive(Debug, Clone, Default)]
+pub struct WithdrawEligibilityLayer;
+
+impl WithdrawEligibilityLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl VerificationLayer for WithdrawEligibilityLayer {
+    fn layer(&self) -> u8 {
+        6
+    }
+
+    fn name(&self) -> &'static str {
+        "Withdraw"
+    }
+
+    fn error_code(&self) -> TgpErrorCode {
+        TgpErrorCode::L6WithdrawFailure
+    }
+
+    fn applies_to(&self, query: &QueryMessage) -> bool {
+        query.intent.verb == TGPVerb::WITHDRAW
+    }
+
+    async fn verify(&self, query: &QueryMessage) -> Result<(), String> {
+        if query.intent.party != TGPParty::BUYER {
+            return Err("WITHDRAW is only available to the BUYER party".into());
+        }
+        Ok(())
+    }
+}
+
+/// Stateless L6 entry point.
+pub async fn layer6_withdraw_eligibility(query: &QueryMessage) -> Result<(), String> {
+    WithdrawEligibilityLayer::new().verify(query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+
+    #[tokio::test]
+    async fn test_withdraw_party() {
+        let mut q = sample_query();
+        let layer = WithdrawEligibilityLayer::new();
+        assert!(!layer.applies_to(&q));
+
+        q.intent.verb = TGPVerb::WITHDRAW;
+        assert!(layer.applies_to(&q));
+        assert!(layer.verify(&q).await.is_ok());
+
+        q.intent.party = TGPParty::SELLER;
+        assert!(layer.verify(&q).await.is_err());
+    }
+}
@@ -0,0 +1,329 @@
+//! TGP-00 v3.2 -- Layered Verification Model (§6)
+//! --------------------------------------------------
+//! Gateways MUST evaluate layers sequentially:
+//!   • L1 — Registry Check                  (`l1_registry.rs`)
+//!   • L2 — Cryptographic Validation        (`l2_crypto.rs`)
+//!   • L3 — Contract Bytecode & RPC         (`l3_contract.rs`)
+//!   • L4 — ZK / Attestation (optional)     (`l4_zk.rs`)
+//!   • L5 — Policy Evaluation               (`l5_policy.rs`)
+//!   • L6 — Escrow / WITHDRAW Eligibility   (`l6_withdraw.rs`)
+//!
+//! Each layer implements [`VerificationLayer`] so it can be configured,
+//! replaced and tested in isolation. [`LayerPipeline`] holds the ordered
+//! set of layers and is what the state engine (`state.rs`) evaluates.
+//!
+//! Failure at any layer MUST produce ERROR (fail-closed). The one exception
+//! is a soft policy verdict ([`VerificationLayer::review`]): the QUERY is
+//! answered with ACK(deny|revise) instead, still without an envelope.
+//!
+//! Once every layer has passed, [`LayerPipeline::run`] lets each layer
+//! commit what accepting the QUERY consumes ([`VerificationLayer::commit`],
+//! e.g. L4 burning a ZK nullifier) and collects [`LayerOutputs`] for the
+//! envelope builder. A dry run (QUOTE) never commits. Whatever refuses the
+//! QUERY after its commit -- a later layer's commit, the envelope builder,
+//! the anomaly engine -- hands it back with [`LayerPipeline::rollback`], so
+//! only an ACK(allow) consumes anything.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::protocol::{AckReason, QueryMessage, TgpErrorCode};
+use crate::zk::ContractSafeProof;
+
+pub mod l1_registry;
+pub mod l2_crypto;
+pub mod l3_contract;
+pub mod l4_zk;
+pub mod l5_policy;
+pub mod l6_withdraw;
+
+pub use l1_registry::{RegistryLayer, layer1_registry_check};
+pub use l2_crypto::{CryptographicLayer, layer2_cryptographic_check};
+pub use l3_contract::{
+    CodeIntegrityAlert, CodeIntegrityAlerts, CodeSource, ContractRpcLayer, layer3_contract_rpc_check,
+};
+pub use l4_zk::{ZkAttestationLayer, layer4_zk_attestation_check, ZK_ENVELOPE_METADATA_KEY};
+pub use l5_policy::{PolicyLayer, layer5_policy_check};
+pub use l6_withdraw::{WithdrawEligibilityLayer, layer6_withdraw_eligibility};
+
+// -----------------------------------------------------------------------------
+// 0. Layer Trait
+// -----------------------------------------------------------------------------
+
+/// A single verification layer (L1–L6).
+///
+/// Implementations MUST be deterministic for a given QUERY and chain state,
+/// and MUST NOT mutate the QUERY.
+#[async_trait]
+pub trait VerificationLayer: Send + Sync {
+    /// Layer number reported in `ERROR.layer_failed` (1–6).
+    fn layer(&self) -> u8;
+
+    /// Short layer name for logs and admin status ("Registry", "Policy", ...).
+    fn name(&self) -> &'static str;
+
+    /// `ERROR.code` emitted when this layer rejects a QUERY.
+    fn error_code(&self) -> TgpErrorCode;
+
+    /// Whether this layer is evaluated for the given QUERY.
+    /// Layers that only apply to certain verbs (e.g. L6) override this.
+    fn applies_to(&self, _query: &QueryMessage) -> bool {
+        true
+    }
+
+    /// Soft check evaluated before [`verify`](Self::verify). `Some(verdict)`
+    /// answers the QUERY with ACK(deny|revise) rather than ERROR. Only
+    /// policy layers override this.
+    async fn review(&self, _query: &QueryMessage) -> Option<PolicyVerdict> {
+        None
+    }
+
+    /// Evaluate the layer. `Err(reason)` rejects the QUERY.
+    async fn verify(&self, query: &QueryMessage) -> Result<(), String>;
+
+    /// Consume what accepting the QUERY uses up, once every layer has
+    /// verified it; never called for a dry run. `Err(reason)` still
+    /// rejects the QUERY. Layers hand results to the envelope builder
+    /// through `outputs`.
+    async fn commit(&self, _query: &QueryMessage, _outputs: &mut LayerOutputs) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Undo a successful [`commit`](Self::commit) for a QUERY that ended
+    /// up without an ACK(allow).
+    async fn rollback(&self, _query: &QueryMessage) {}
+}
+
+/// Results of an accepted QUERY that shape its Economic Envelope.
+#[derive(Debug, Clone, Default)]
+pub struct LayerOutputs {
+    /// Verified ZK outputs (L4) replacing the proof in the settlement call
+    pub zk: Option<ContractSafeProof>,
+}
+
+/// Soft rejection returned by [`VerificationLayer::review`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyVerdict {
+    /// QUERY is not acceptable in any form → ACK(status=deny)
+    Deny(AckReason),
+    /// QUERY is acceptable once it meets `reason.constraints` → ACK(status=revise)
+    Revise(AckReason),
+}
+
+// -----------------------------------------------------------------------------
+// 1. Layer Failure
+// -----------------------------------------------------------------------------
+
+/// Describes which layer rejected a QUERY and why (`code.layer()`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerFailure {
+    pub code: TgpErrorCode,
+    pub reason: String,
+
+    /// Set when the layer rejected softly (ACK instead of ERROR)
+    pub verdict: Option<PolicyVerdict>,
+}
+
+// -----------------------------------------------------------------------------
+// 2. Pipeline
+// -----------------------------------------------------------------------------
+
+/// Ordered L1–L6 pipeline.
+///
+/// `LayerPipeline::default()` installs the stock layers. Individual layers
+/// are swapped with [`LayerPipeline::with_layer`], which replaces any layer
+/// registered under the same number:
+///
+/// ```rust
+/// use tbc_core::tgp::layers::{LayerPipeline, PolicyLayer};
+///
+/// let pipeline = LayerPipeline::default()
+///     .with_layer(PolicyLayer::new().with_max_amount(1_000_000));
+/// assert_eq!(pipeline.len(), 6);
+/// ```
+#[derive(Clone)]
+pub struct LayerPipeline {
+    layers: Vec<Arc<dyn VerificationLayer>>,
+}
+
+impl LayerPipeline {
+    /// Empty pipeline (no layers). Mostly useful for tests.
+    pub fn empty() -> Self {
+        Self { layers: Vec::new() }
+    }
+
+    /// Insert or replace a layer, keeping layers sorted by number.
+    pub fn with_layer(self, layer: impl VerificationLayer + 'static) -> Self {
+        self.with_shared_layer(Arc::new(layer))
+    }
+
+    /// Same as [`with_layer`](Self::with_layer) for an already-shared layer.
+    pub fn with_shared_layer(mut self, layer: Arc<dyn VerificationLayer>) -> Self {
+        self.layers.retain(|l| l.layer() != layer.layer());
+        self.layers.push(layer);
+        self.layers.sort_by_key(|l| l.layer());
+        self
+    }
+
+    /// Layers in evaluation order.
+    pub fn layers(&self) -> impl Iterator<Item = &Arc<dyn VerificationLayer>> {
+        self.layers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Evaluate all applicable layers in order, stopping at the first
+    /// failure, then commit them.
+    pub async fn run(&self, query: &QueryMessage) -> Result<LayerOutputs, LayerFailure> {
+        self.run_through(query, u8::MAX).await?;
+
+        let mut outputs = LayerOutputs::default();
+        let committing: Vec<_> = self.layers.iter().filter(|l| l.applies_to(query)).collect();
+        for (i, layer) in committing.iter().enumerate() {
+            if let Err(reason) = layer.commit(query, &mut outputs).await {
+                for committed in committing[..i].iter().rev() {
+                    committed.rollback(query).await;
+                }
+                return Err(LayerFailure {
+                    code: layer.error_code(),
+                    reason,
+                    verdict: None,
+                });
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Undo every layer's commit for a QUERY [`run`](Self::run) accepted
+    /// but that is answered with something other than ACK(allow).
+    pub async fn rollback(&self, query: &QueryMessage) {
+        for layer in self.layers.iter().rev().filter(|l| l.applies_to(query)) {
+            layer.rollback(query).await;
+        }
+    }
+
+    /// Dry run for QUOTE: L1–L5 only. Escrow eligibility (L6) is never
+    /// evaluated because nothing will be executed.
+    pub async fn dry_run(&self, query: &QueryMessage) -> Result<(), LayerFailure> {
+        self.run_through(query, 5).await
+    }
+
+    async fn run_through(&self, query: &QueryMessage, last_layer: u8) -> Result<(), LayerFailure> {
+        for layer in &self.layers {
+            if layer.layer() > last_layer || !layer.applies_to(query) {
+                continue;
+            }
+
+            if let Some(verdict) = layer.review(query).await {
+                let reason = match &verdict {
+                    PolicyVerdict::Deny(r) | PolicyVerdict::Revise(r) => r.message.clone(),
+                };
+                return Err(LayerFailure {
+                    code: layer.error_code(),
+                    reason,
+                    verdict: Some(verdict),
+                });
+            }
+
+            if let Err(reason) = layer.verify(query).await {
+                return Err(LayerFailure {
+                    code: layer.error_code(),
+                    reason,
+                    verdict: None,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for LayerPipeline {
+    fn default() -> Self {
+        Self::empty()
+            .with_layer(RegistryLayer::new())
+            .with_layer(CryptographicLayer::new())
+            .with_layer(ContractRpcLayer::new())
+            .with_layer(ZkAttestationLayer::new())
+            .with_layer(PolicyLayer::new())
+            .with_layer(WithdrawEligibilityLayer::new())
+    }
+}
+
+impl std::fmt::Debug for LayerPipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.layers.iter().map(|l| format!("L{} {}", l.layer(), l.name())))
+            .finish()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::protocol::{Intent, RoutingMetadata, TGPMODE, TGPParty, TGPVerb};
+
+    pub(crate) fn sample_query() -> QueryMessage {
+        QueryMessage {
+            msg_type: "QUERY".into(),
+            tgp_version: "3.2".into(),
+            id: "q-layers-1".into(),
+            session_token: None,
+            delegated_key: None,
+            scope: None,
+            routing: RoutingMetadata::default(),
+            intent: Intent {
+                verb: TGPVerb::COMMIT,
+                party: TGPParty::BUYER,
+                mode: TGPMODE::DIRECT,
+            },
+            payment_profile: "0x1111111111111111111111111111111111111111".into(),
+            amount: 1_000,
+            chain_id: 369,
+            metadata: serde_json::Value::Null,
+        }
+    }
+
+    struct RejectAll;
+
+    #[async_trait]
+    impl VerificationLayer for RejectAll {
+        fn layer(&self) -> u8 { 3 }
+        fn name(&self) -> &'static str { "RejectAll" }
+        fn error_code(&self) -> TgpErrorCode { TgpErrorCode::L3Failure }
+        async fn verify(&self, _query: &QueryMessage) -> Result<(), String> {
+            Err("rejected".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_pipeline_accepts_valid_query() {
+        let pipeline = LayerPipeline::default();
+        assert_eq!(pipeline.len(), 6);
+        assert!(pipeline.run(&sample_query()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_with_layer_replaces_same_number() {
+        let pipeline = LayerPipeline::default().with_layer(RejectAll);
+        assert_eq!(pipeline.len(), 6);
+
+        let order: Vec<u8> = pipeline.layers().map(|l| l.layer()).collect();
+        assert_eq!(order, vec![1, 2, 3, 4, 5, 6]);
+
+        let failure = pipeline.run(&sample_query()).await.unwrap_err();
+        assert_eq!(failure.code.layer(), 3);
+        assert_eq!(failure.code, TgpErrorCode::L3Failure);
+        assert_eq!(failure.reason, "rejected");
+    }
+}
@@ -0,0 +1,356 @@
+// ============================================================================
+// Legacy TGP Message Upgrade -- pre-v3.2 `phase`-tagged payloads
+// crates/tbc-core/src/tgp/legacy.rs
+//
+// Early clients tagged messages on `phase` and described the transfer with
+// from/to/asset instead of intent/payment_profile/chain_id. These types
+// exist ONLY to decode such payloads and upgrade them into the canonical
+// TGP-00 v3.2 model in `crate::protocol`. Nothing in the gateway handles
+// legacy types directly.
+//
+// Upgrade rules:
+//   • QUERY  → intent.verb = COMMIT if escrow_from_402, else PAY
+//              intent.mode = SHIELDED if zk_profile = REQUIRED, else DIRECT
+//              payment_profile = escrow_contract_from_402, else `to`
+//              chain_id = the gateway's legacy chain (legacy QUERYs carry
+//              none); rejected when the gateway has not configured one
+//              from/to/asset/zk_profile preserved under metadata.legacy
+//   • ACK    → allow=true → status=offer (legacy ACKs carry no envelope)
+//              allow=false → status=deny; query_id → correlation_id
+//   • SETTLE → success → final_status complete/reverted
+//              query_id (or `query_or_offer_id`) → correlation_id
+//   • ERROR  → layer_failed = 0, correlation_id preserved
+// ============================================================================
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::types::{SettleSource, ZkProfile};
+use crate::protocol::{
+    AckMessage, AckReason, AckStatus, ErrorMessage, Intent, QueryMessage, RoutingMetadata,
+    SettleMessage, TGPMessage, TGPMODE, TGPParty, TGPVerb, TGP_VERSION,
+};
+
+/// Version stamped on upgraded messages.
+pub const UPGRADED_TGP_VERSION: &str = TGP_VERSION;
+
+// ============================================================================
+// Detection
+// ============================================================================
+
+/// A payload is legacy when it is tagged on `phase` and carries no `type`.
+pub fn is_legacy_payload(v: &Value) -> bool {
+    v.get("type").is_none() && v.get("phase").is_some()
+}
+
+/// Decode a legacy payload and upgrade it into the canonical model.
+/// `chain_id` is the chain legacy QUERYs settle on at this gateway.
+pub fn upgrade_legacy_value(v: Value, chain_id: Option<u64>) -> Result<TGPMessage, String> {
+    let legacy: LegacyMessage = serde_json::from_value(v)
+        .map_err(|e| format!("Invalid legacy TGP message: {}", e))?;
+    legacy.upgrade(chain_id)
+}
+
+// ============================================================================
+// Legacy Message Union
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "phase")]
+pub enum LegacyMessage {
+    #[serde(rename = "QUERY")]
+    Query(LegacyQuery),
+
+    #[serde(rename = "ACK")]
+    Ack(LegacyAck),
+
+    #[serde(rename = "SETTLE")]
+    Settle(LegacySettle),
+
+    #[serde(rename = "ERROR")]
+    Error(LegacyError),
+}
+
+impl LegacyMessage {
+    pub fn upgrade(self, chain_id: Option<u64>) -> Result<TGPMessage, String> {
+        Ok(match self {
+            LegacyMessage::Query(m)  => TGPMessage::Query(m.upgrade(chain_id)?),
+            LegacyMessage::Ack(m)    => TGPMessage::Ack(m.upgrade()),
+            LegacyMessage::Settle(m) => TGPMessage::Settle(m.upgrade()),
+            LegacyMessage::Error(m)  => TGPMessage::Error(m.upgrade()),
+        })
+    }
+}
+
+// ============================================================================
+// Legacy QUERY
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LegacyQuery {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+
+    pub asset: String,
+    pub amount: u64,
+
+    #[serde(default)]
+    pub escrow_from_402: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escrow_contract_from_402: Option<String>,
+
+    #[serde(default)]
+    pub zk_profile: ZkProfile,
+
+    /// Not part of the legacy schema; honoured when a client sends it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+}
+
+impl LegacyQuery {
+    /// Upgrade onto `chain_id` unless the QUERY names its own chain.
+    pub fn upgrade(self, chain_id: Option<u64>) -> Result<QueryMessage, String> {
+        let chain_id = self.chain_id.or(chain_id).ok_or_else(|| {
+            "Legacy QUERY carries no chain_id and this gateway has no legacy chain configured".to_string()
+        })?;
+
+        let verb = if self.escrow_from_402 { TGPVerb::COMMIT } else { TGPVerb::PAY };
+        let mode = if self.zk_profile == ZkProfile::Required {
+            TGPMODE::SHIELDED
+        } else {
+            TGPMODE::DIRECT
+        };
+
+        let payment_profile = self.escrow_contract_from_402
+            .clone()
+            .unwrap_or_else(|| self.to.clone());
+
+        Ok(QueryMessage {
+            msg_type: "QUERY".into(),
+            tgp_version: UPGRADED_TGP_VERSION.into(),
+            id: self.id,
+            session_token: None,
+            delegated_key: None,
+            scope: None,
+            routing: RoutingMetadata::default(),
+            intent: Intent {
+                verb,
+                party: TGPParty::BUYER,
+                mode,
+            },
+            payment_profile,
+            amount: self.amount,
+            chain_id,
+            metadata: json!({
+                "legacy": {
+                    "from": self.from,
+                    "to": self.to,
+                    "asset": self.asset,
+                    "zk_profile": self.zk_profile,
+                }
+            }),
+        })
+    }
+}
+
+// ============================================================================
+// Legacy ACK
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LegacyAck {
+    pub id: String,
+    pub query_id: String,
+    pub allow: bool,
+
+    #[serde(default)]
+    pub escrow_required: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coreprover_contract: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zk_profile: Option<ZkProfile>,
+}
+
+impl LegacyAck {
+    pub fn upgrade(self) -> AckMessage {
+        let mode = if self.zk_profile == Some(ZkProfile::Required) {
+            TGPMODE::SHIELDED
+        } else {
+            TGPMODE::DIRECT
+        };
+
+        AckMessage {
+            msg_type: "ACK".into(),
+            status: if self.allow { AckStatus::Offer } else { AckStatus::Deny },
+            id: self.id,
+            correlation_id: Some(self.query_id),
+            intent: Intent {
+                verb: if self.escrow_required { TGPVerb::COMMIT } else { TGPVerb::PAY },
+                party: TGPParty::BUYER,
+                mode,
+            },
+            routing: None,
+            tx: None,
+            expires_at: None,
+            reason: (!self.allow).then(|| {
+                AckReason::new("LEGACY_DENY", "QUERY denied by a pre-v3.2 gateway")
+            }),
+            quote: None,
+        }
+    }
+}
+
+// ============================================================================
+// Legacy SETTLE
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LegacySettle {
+    pub id: String,
+
+    /// Pre-v3.2 clients sent `query_or_offer_id` (OFFER has since been removed).
+    #[serde(alias = "query_or_offer_id")]
+    pub query_id: String,
+
+    pub success: bool,
+    pub source: SettleSource,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer8_tx: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+impl LegacySettle {
+    pub fn upgrade(self) -> SettleMessage {
+        let final_status = if self.success { "complete" } else { "reverted" };
+        let escrow_id = self.session_id.clone().unwrap_or_else(|| self.query_id.clone());
+
+        let mut settle = SettleMessage::terminal(
+            self.id,
+            final_status,
+            escrow_id,
+            Utc::now().to_rfc3339(),
+        );
+        settle.correlation_id = Some(self.query_id);
+        settle.source = Some(self.source);
+        settle.tx_hash = self.layer8_tx;
+        settle
+    }
+}
+
+// ============================================================================
+// Legacy ERROR
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LegacyError {
+    pub id: String,
+    pub code: String,
+    pub message: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl LegacyError {
+    pub fn upgrade(self) -> ErrorMessage {
+        let mut err = ErrorMessage::new(self.id, self.code, self.message);
+        err.correlation_id = self.correlation_id;
+        err
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_query_upgrade() {
+        let v = json!({
+            "phase": "QUERY",
+            "id": "q-1",
+            "from": "buyer://alice",
+            "to": "0x1111111111111111111111111111111111111111",
+            "asset": "USDC",
+            "amount": 1000,
+            "escrow_from_402": false,
+            "zk_profile": "REQUIRED"
+        });
+        assert!(is_legacy_payload(&v));
+
+        // Legacy QUERYs name no chain: the gateway must supply one
+        let err = upgrade_legacy_value(v.clone(), None).unwrap_err();
+        assert!(err.contains("no chain_id"));
+
+        match upgrade_legacy_value(v, Some(369)).unwrap() {
+            TGPMessage::Query(q) => {
+                assert_eq!(q.tgp_version, "3.2");
+                assert_eq!(q.chain_id, 369);
+                assert_eq!(q.intent.verb, TGPVerb::PAY);
+                assert_eq!(q.intent.mode, TGPMODE::SHIELDED);
+                assert_eq!(q.payment_profile, "0x1111111111111111111111111111111111111111");
+                assert_eq!(q.metadata["legacy"]["asset"], "USDC");
+                assert!(q.validate().is_ok());
+            }
+            other => panic!("expected QUERY, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_legacy_settle_query_or_offer_id() {
+        let v = json!({
+            "phase": "SETTLE",
+            "id": "settle-1",
+            "query_or_offer_id": "q-1",
+            "success": false,
+            "source": "ControllerWatcher",
+            "layer8_tx": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef"
+        });
+
+        match upgrade_legacy_value(v, None).unwrap() {
+            TGPMessage::Settle(s) => {
+                assert_eq!(s.result.final_status, "reverted");
+                assert_eq!(s.correlation_id.as_deref(), Some("q-1"));
+                assert_eq!(s.source, Some(SettleSource::ControllerWatcher));
+                assert!(s.validate().is_ok());
+            }
+            other => panic!("expected SETTLE, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_legacy_ack_and_error_upgrade() {
+        let ack = upgrade_legacy_value(json!({
+            "phase": "ACK", "id": "ack-1", "query_id": "q-1", "allow": false
+        }), None).unwrap();
+        match ack {
+            TGPMessage::Ack(a) => {
+                assert_eq!(a.status, AckStatus::Deny);
+                assert_eq!(a.id, "ack-1");
+                assert_eq!(a.correlation_id.as_deref(), Some("q-1"));
+            }
+            other => panic!("expected ACK, got {:?}", other),
+        }
+
+        let err = upgrade_legacy_value(json!({
+            "phase": "ERROR", "id": "err-1", "code": "TIMEOUT",
+            "message": "timed out", "correlation_id": "q-1"
+        }), None).unwrap();
+        assert_eq!(err.msg_type(), "ERROR");
+        assert!(err.validate().is_ok());
+    }
+}
@@ -0,0 +1,521 @@
+//! TGP-00 v3.2 -- Merchant Registry (merchants.rs)
+//! --------------------------------------------------
+//! Local mirror of the merchant contracts deployed by the known
+//! `MerchantContractFactory` instances, consulted by L1 (`RegistryLayer`):
+//!
+//!   • `MerchantDeployed` / `MerchantRevoked` logs from a known factory add
+//!     and revoke merchants (the node replays the factory history, then
+//!     follows new blocks)
+//!   • deployment parameters, when known, pin a merchant to its CREATE2
+//!     address (`predict_create2_address` over `compute_final_salt`); the
+//!     observed deployment must then match their version and admin
+//!   • admin whitelist entries admit merchants the mirror has not seen
+//!
+//! A revocation by the factory always wins, whitelist or not.
+//!
+//! The deployment parameters of the merchants a gateway serves are read
+//! from a TOML merchant file. Their fee schedules price QUOTEs and the L5
+//! fee ceiling; an entry naming its `factory` and that factory's CREATE2
+//! `init_code_hash` is also pinned (`MerchantRegistry::pin`):
+//!
+//! ```toml
+//! [[merchants]]
+//! contract = "0x1111…"
+//! version = 1
+//! merchant_admin = "0xaaaa…"
+//! tbc_relay_address = "0xbbbb…"
+//! zk_verifier = "0xcccc…"
+//! tbc_fee_recipient = "0x0101…"
+//! zk_fee_recipient = "0x0202…"
+//! merchant_fee_recipient = "0x0303…"
+//! tbc_fee_bps = 100
+//! zk_fee_bps = 50
+//! ttl_seconds = 3600
+//! salt = "0x0000…"
+//! factory = "0xffff…"          # optional, with init_code_hash
+//! init_code_hash = "0x5a5a…"
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::contracts::factory::{
+    predict_create2_address, DeployMerchantParams, MerchantDeployedEvent, MerchantRevokedEvent,
+};
+use crate::contracts::types::{address_to_hex, hex_to_address, hex_to_bytes32, Bytes32};
+use crate::tgp::settle_watch::log_parts;
+use crate::tgp::validation::validate_address;
+
+/// A merchant contract observed being deployed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MerchantRecord {
+    pub factory: String,
+    pub version: u64,
+    pub admin: String,
+}
+
+/// Counters for the admin API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MerchantRegistryStats {
+    pub factories: usize,
+    pub deployed: usize,
+    pub revoked: usize,
+    pub whitelisted: usize,
+    pub pinned: usize,
+    /// Next block the chain mirror will scan
+    pub next_block: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    /// Lower-cased merchant address → deployment
+    deployed: HashMap<String, MerchantRecord>,
+    revoked: HashSet<String>,
+    whitelist: HashSet<String>,
+    /// Predicted CREATE2 address → parameters it was derived from
+    pinned: HashMap<String, DeployMerchantParams>,
+    next_block: Option<u64>,
+}
+
+/// Merchant registry shared by L1 and the admin API.
+#[derive(Debug, Default)]
+pub struct MerchantRegistry {
+    /// Lower-cased factory addresses
+    factories: Vec<String>,
+    inner: RwLock<RegistryState>,
+}
+
+impl MerchantRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust deployments made by the factory at `address`.
+    pub fn with_factory(mut self, address: impl Into<String>) -> Result<Self, String> {
+        let address = address.into().to_lowercase();
+        validate_address(&address, "factory")?;
+        if !self.factories.contains(&address) {
+            self.factories.push(address);
+        }
+        Ok(self)
+    }
+
+    pub fn factories(&self) -> &[String] {
+        &self.factories
+    }
+
+    // ---- chain mirror ----
+
+    /// Apply a `MerchantDeployed` / `MerchantRevoked` log. Logs from other
+    /// contracts, or other events, are ignored (returns false).
+    pub fn apply_log(&self, log: &Value) -> bool {
+        let Some(emitter) = log.get("address").and_then(Value::as_str).map(str::to_lowercase) else {
+            return false;
+        };
+        if !self.factories.contains(&emitter) {
+            return false;
+        }
+        let Some((topics, data)) = log_parts(log) else {
+            return false;
+        };
+
+        let mut state = self.inner.write().unwrap();
+        if let Some(e) = MerchantDeployedEvent::from_log(&topics, &data) {
+            let merchant = address_to_hex(&e.merchant_contract);
+            state.revoked.remove(&merchant);
+            state.deployed.insert(merchant, MerchantRecord {
+                factory: emitter,
+                version: e.version,
+                admin: address_to_hex(&e.merchant_admin),
+            });
+            true
+        } else if let Some(e) = MerchantRevokedEvent::from_log(&topics, &data) {
+            state.revoked.insert(address_to_hex(&e.merchant_contract));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// First block the mirror has not scanned yet.
+    pub fn next_block(&self) -> Option<u64> {
+        self.inner.read().unwrap().next_block
+    }
+
+    pub fn set_next_block(&self, block: u64) {
+        self.inner.write().unwrap().next_block = Some(block);
+    }
+
+    /// Pin the merchant that `factory` deploys from `params`; returns its
+    /// predicted address. `init_code_hash` is keccak256 of the factory's
+    /// CREATE2 init code for `params.version`.
+    pub fn pin_deployment(
+        &self,
+        factory: &str,
+        params: &DeployMerchantParams,
+        init_code_hash: &Bytes32,
+    ) -> Result<String, String> {
+        let factory = factory.to_lowercase();
+        if !self.factories.contains(&factory) {
+            return Err(format!("{} is not a known MerchantContractFactory", factory));
+        }
+        params.validate().map_err(|e| e.to_string())?;
+
+        let factory_addr = hex_to_address(&factory).map_err(str::to_string)?;
+        let predicted = address_to_hex(&predict_create2_address(
+            &factory_addr,
+            &params.compute_final_salt(),
+            init_code_hash,
+        ));
+        self.inner.write().unwrap().pinned.insert(predicted.clone(), params.clone());
+        Ok(predicted)
+    }
+
+    /// Pin a merchant file entry that names its factory; its parameters
+    /// must predict the listed contract. False for entries without one.
+    pub fn pin(&self, deployment: &MerchantDeployment) -> Result<bool, String> {
+        let (Some(factory), Some(init_code_hash)) = (&deployment.factory, &deployment.init_code_hash) else {
+            return Ok(false);
+        };
+        let predicted = self
+            .pin_deployment(factory, &deployment.params, init_code_hash)
+            .map_err(|e| format!("merchant {}: {}", deployment.contract, e))?;
+        if predicted != deployment.contract {
+            return Err(format!(
+                "merchant {}: its deployment parameters predict {}",
+                deployment.contract, predicted
+            ));
+        }
+        Ok(true)
+    }
+
+    // ---- admin whitelist ----
+
+    /// Admit `address` without a factory deployment; false if already listed.
+    pub fn whitelist(&self, address: &str) -> Result<bool, String> {
+        validate_address(address, "merchant")?;
+        Ok(self.inner.write().unwrap().whitelist.insert(address.to_lowercase()))
+    }
+
+    /// False if `address` was not whitelisted.
+    pub fn unwhitelist(&self, address: &str) -> bool {
+        self.inner.write().unwrap().whitelist.remove(&address.to_lowercase())
+    }
+
+    // ---- L1 ----
+
+    /// Is `merchant` a live contract from a known factory (or whitelisted)?
+    pub fn check(&self, merchant: &str) -> Result<(), String> {
+        let merchant = merchant.to_lowercase();
+        let state = self.inner.read().unwrap();
+
+        if state.revoked.contains(&merchant) {
+            return Err(format!("merchant {} was revoked by its factory", merchant));
+        }
+        if state.whitelist.contains(&merchant) {
+            return Ok(());
+        }
+        let Some(record) = state.deployed.get(&merchant) else {
+            return Err(format!("{} was not deployed by a known MerchantContractFactory", merchant));
+        };
+        if let Some(params) = state.pinned.get(&merchant) {
+            if params.version != record.version || address_to_hex(&params.merchant_admin) != record.admin {
+                return Err(format!("merchant {} does not match its CREATE2 deployment parameters", merchant));
+            }
+        }
+        Ok(())
+    }
+
+    /// Merchants `check` admits: live factory deployments and the
+    /// whitelist, ordered by address. The settlement watcher polls these.
+    pub fn merchants(&self) -> Vec<String> {
+        let state = self.inner.read().unwrap();
+        let mut merchants: Vec<String> = state
+            .deployed
+            .keys()
+            .chain(state.whitelist.iter())
+            .filter(|m| !state.revoked.contains(*m))
+            .cloned()
+            .collect();
+        merchants.sort();
+        merchants.dedup();
+        merchants
+    }
+
+    pub fn get(&self, merchant: &str) -> Option<MerchantRecord> {
+        self.inner.read().unwrap().deployed.get(&merchant.to_lowercase()).cloned()
+    }
+
+    pub fn stats(&self) -> MerchantRegistryStats {
+        let state = self.inner.read().unwrap();
+        MerchantRegistryStats {
+            factories: self.factories.len(),
+            deployed: state.deployed.len(),
+            revoked: state.revoked.len(),
+            whitelisted: state.whitelist.len(),
+            pinned: state.pinned.len(),
+            next_block: state.next_block,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Merchant File
+// -----------------------------------------------------------------------------
+
+/// A merchant contract served by this gateway and the parameters it was
+/// deployed with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerchantDeployment {
+    /// Lower-cased merchant contract address (the QUERY's payment_profile)
+    pub contract: String,
+    pub params: DeployMerchantParams,
+    /// Lower-cased factory that deployed the contract
+    pub factory: Option<String>,
+    /// keccak256 of the factory's CREATE2 init code for `params.version`
+    pub init_code_hash: Option<Bytes32>,
+}
+
+/// `[[merchants]]` entry as written in the merchant file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MerchantEntry {
+    contract: String,
+    version: u64,
+    merchant_admin: String,
+    tbc_relay_address: String,
+    zk_verifier: String,
+    tbc_fee_recipient: String,
+    zk_fee_recipient: String,
+    merchant_fee_recipient: String,
+    tbc_fee_bps: u64,
+    zk_fee_bps: u64,
+    ttl_seconds: u64,
+    #[serde(default)]
+    initial_supported_assets: Vec<String>,
+    salt: String,
+    factory: Option<String>,
+    init_code_hash: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MerchantFile {
+    #[serde(default)]
+    merchants: Vec<MerchantEntry>,
+}
+
+impl MerchantEntry {
+    fn into_deployment(self) -> Result<MerchantDeployment, String> {
+        let contract = self.contract.to_lowercase();
+        validate_address(&contract, "contract")?;
+
+        let address = |field: &str, hex: &str| {
+            hex_to_address(hex).map_err(|e| format!("merchant {} {}: {}", contract, field, e))
+        };
+        let params = DeployMerchantParams {
+            version: self.version,
+            merchant_admin: address("merchant_admin", &self.merchant_admin)?,
+            tbc_relay_address: address("tbc_relay_address", &self.tbc_relay_address)?,
+            zk_verifier: address("zk_verifier", &self.zk_verifier)?,
+            tbc_fee_recipient: address("tbc_fee_recipient", &self.tbc_fee_recipient)?,
+            zk_fee_recipient: address("zk_fee_recipient", &self.zk_fee_recipient)?,
+            merchant_fee_recipient: address("merchant_fee_recipient", &self.merchant_fee_recipient)?,
+            tbc_fee_bps: self.tbc_fee_bps,
+            zk_fee_bps: self.zk_fee_bps,
+            ttl_seconds: self.ttl_seconds,
+            initial_supported_assets: self
+                .initial_supported_assets
+                .iter()
+                .map(|a| address("initial_supported_assets", a))
+                .collect::<Result<_, _>>()?,
+            salt: hex_to_bytes32(&self.salt).map_err(|e| format!("merchant {} salt: {}", contract, e))?,
+        };
+        params.validate().map_err(|e| format!("merchant {}: {}", contract, e))?;
+
+        let (factory, init_code_hash) = match (self.factory, self.init_code_hash) {
+            (Some(factory), Some(hash)) => {
+                let factory = factory.to_lowercase();
+                validate_address(&factory, "factory").map_err(|e| format!("merchant {}: {}", contract, e))?;
+                let hash = hex_to_bytes32(&hash)
+                    .map_err(|e| format!("merchant {} init_code_hash: {}", contract, e))?;
+                (Some(factory), Some(hash))
+            }
+            (None, None) => (None, None),
+            _ => return Err(format!("merchant {}: factory and init_code_hash go together", contract)),
+        };
+
+        Ok(MerchantDeployment { contract, params, factory, init_code_hash })
+    }
+}
+
+/// Parse a merchant file. Any malformed or duplicate entry fails the whole file.
+pub fn merchant_deployments_from_toml(raw: &str) -> Result<Vec<MerchantDeployment>, String> {
+    let file: MerchantFile = toml::from_str(raw).map_err(|e| format!("merchant file: {}", e))?;
+
+    let mut out: Vec<MerchantDeployment> = Vec::with_capacity(file.merchants.len());
+    for entry in file.merchants {
+        let deployment = entry.into_deployment()?;
+        if out.iter().any(|d| d.contract == deployment.contract) {
+            return Err(format!("merchant file: duplicate merchant {}", deployment.contract));
+        }
+        out.push(deployment);
+    }
+    Ok(out)
+}
+
+pub fn load_merchant_deployments(path: impl AsRef<Path>) -> Result<Vec<MerchantDeployment>, String> {
+    let path = path.as_ref();
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("merchant file {}: {}", path.display(), e))?;
+    merchant_deployments_from_toml(&raw)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::abi::event_topic;
+    use crate::contracts::factory::{MERCHANT_DEPLOYED_EVENT_SIG, MERCHANT_REVOKED_EVENT_SIG};
+    use crate::contracts::types::bytes32_to_hex;
+    use crate::tgp::quote::tests::sample_merchant as params;
+    use serde_json::json;
+
+    const FACTORY: &str = "0x00000000000000000000000000000000000000fa";
+
+    fn topic_address(addr: &str) -> String {
+        format!("0x{:0>64}", addr.trim_start_matches("0x"))
+    }
+
+    fn deployed_log(emitter: &str, version: u64, merchant: &str, admin: &str) -> Value {
+        json!({
+            "address": emitter,
+            "topics": [
+                bytes32_to_hex(&event_topic(MERCHANT_DEPLOYED_EVENT_SIG)),
+                format!("0x{:064x}", version),
+                topic_address(merchant),
+                topic_address(admin),
+            ],
+            "data": "0x",
+        })
+    }
+
+    fn revoked_log(merchant: &str) -> Value {
+        json!({
+            "address": FACTORY,
+            "topics": [bytes32_to_hex(&event_topic(MERCHANT_REVOKED_EVENT_SIG)), topic_address(merchant)],
+            "data": "0x",
+        })
+    }
+
+    #[test]
+    fn test_mirror_deploy_and_revoke() {
+        let merchant = "0x1111111111111111111111111111111111111111";
+        let admin = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let registry = MerchantRegistry::new().with_factory(FACTORY).unwrap();
+        assert!(registry.check(merchant).is_err());
+
+        // Deployments by an unknown factory are not mirrored
+        assert!(!registry.apply_log(&deployed_log("0x00000000000000000000000000000000000000ee", 1, merchant, admin)));
+        assert!(registry.check(merchant).is_err());
+
+        assert!(registry.apply_log(&deployed_log(FACTORY, 1, merchant, admin)));
+        assert!(registry.check(&merchant.to_uppercase().replace("0X", "0x")).is_ok());
+        assert_eq!(registry.get(merchant).unwrap().admin, admin);
+        assert_eq!(registry.merchants(), vec![merchant.to_string()]);
+
+        // Revocation wins over the whitelist
+        registry.whitelist(merchant).unwrap();
+        assert!(registry.apply_log(&revoked_log(merchant)));
+        assert!(registry.check(merchant).is_err());
+        assert!(registry.merchants().is_empty());
+
+        let listed = "0x2222222222222222222222222222222222222222";
+        assert!(registry.whitelist(listed).unwrap());
+        assert!(registry.check(listed).is_ok());
+        assert!(registry.unwhitelist(listed));
+        assert!(registry.check(listed).is_err());
+        assert!(registry.whitelist("not-an-address").is_err());
+    }
+
+    #[test]
+    fn test_pinned_deployment_must_match_params() {
+        let registry = MerchantRegistry::new().with_factory(FACTORY).unwrap();
+        let init_code_hash = [0x42; 32];
+        let predicted = registry.pin_deployment(FACTORY, &params(), &init_code_hash).unwrap();
+        assert_eq!(predicted.len(), 42);
+        assert!(registry.pin_deployment("0x00000000000000000000000000000000000000ee", &params(), &init_code_hash).is_err());
+
+        // Observed with another admin than the parameters name
+        let impostor = "0xdddddddddddddddddddddddddddddddddddddddd";
+        registry.apply_log(&deployed_log(FACTORY, 1, &predicted, impostor));
+        assert!(registry.check(&predicted).is_err());
+
+        registry.apply_log(&deployed_log(FACTORY, 1, &predicted, &address_to_hex(&[0xaa; 20])));
+        assert!(registry.check(&predicted).is_ok());
+        assert_eq!(registry.stats().pinned, 1);
+    }
+
+    #[test]
+    fn test_merchant_file() {
+        let file = format!(
+            r#"
+            [[merchants]]
+            contract = "0x1111111111111111111111111111111111111111"
+            version = 1
+            merchant_admin = "0x{aa}"
+            tbc_relay_address = "0x{bb}"
+            zk_verifier = "0x{cc}"
+            tbc_fee_recipient = "0x{r1}"
+            zk_fee_recipient = "0x{r2}"
+            merchant_fee_recipient = "0x{r3}"
+            tbc_fee_bps = 100
+            zk_fee_bps = 50
+            ttl_seconds = 3600
+            salt = "0x{salt}"
+            "#,
+            aa = "aa".repeat(20), bb = "bb".repeat(20), cc = "cc".repeat(20),
+            r1 = "01".repeat(20), r2 = "02".repeat(20), r3 = "03".repeat(20),
+            salt = "00".repeat(32),
+        );
+        let merchants = merchant_deployments_from_toml(&file).unwrap();
+        assert_eq!(merchants.len(), 1);
+        assert_eq!(merchants[0].contract, "0x1111111111111111111111111111111111111111");
+        assert_eq!(merchants[0].params, params());
+        assert_eq!(merchants[0].factory, None);
+
+        // Malformed entries fail the file rather than being skipped
+        assert!(merchant_deployments_from_toml(&file.replace(&"cc".repeat(20), "cc")).is_err());
+        assert!(merchant_deployments_from_toml(&format!("{}{}", file, file)).is_err());
+        let half_pinned = format!("{}factory = \"{}\"\n", file.trim_end(), FACTORY);
+        assert!(merchant_deployments_from_toml(&half_pinned).is_err());
+    }
+
+    #[test]
+    fn test_merchant_file_entry_pinned() {
+        let registry = MerchantRegistry::new().with_factory(FACTORY).unwrap();
+        let mut deployment = MerchantDeployment {
+            contract: "0x1111111111111111111111111111111111111111".into(),
+            params: params(),
+            factory: None,
+            init_code_hash: None,
+        };
+        assert_eq!(registry.pin(&deployment), Ok(false));
+
+        // Parameters that do not predict the listed contract fail
+        deployment.factory = Some(FACTORY.into());
+        deployment.init_code_hash = Some([0x42; 32]);
+        assert!(registry.pin(&deployment).is_err());
+
+        deployment.contract = registry.pin_deployment(FACTORY, &params(), &[0x42; 32]).unwrap();
+        assert_eq!(registry.pin(&deployment), Ok(true));
+        assert_eq!(registry.stats().pinned, 1);
+    }
+}
@@ -1,4 +1,17 @@
-// pub mod state;  // TODO: Depends on layers, tx_builder modules not yet implemented
+pub mod state;
 pub mod types;
-pub mod messages;
+pub mod legacy;
 pub mod validation;
+pub mod layers;
+pub mod tx_builder;
+pub mod canonical;
+pub mod expiry;
+pub mod quote;
+pub mod routing;
+pub mod tai;
+pub mod anomaly;
+pub mod settle_verify;
+pub mod settle_watch;
+pub mod merchants;
+pub mod delegation;
+pub mod policy;
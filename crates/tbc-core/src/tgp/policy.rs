@@ -0,0 +1,788 @@
+//! TGP-00 v3.2 -- L5 Policy Engine (policy.rs)
+//! --------------------------------------------------
+//! Declarative merchant rules evaluated by L5 (`PolicyLayer`). A policy is
+//! a set of optional rules; the global policy applies to every QUERY and a
+//! merchant's policy (keyed by payment profile) is added on top of it.
+//!
+//!   rule                                  violated →
+//!   max_amount                            revise (amount ≤ max)
+//!   allowed_chains                        revise (chain_id one of)
+//!   allowed_assets                        revise (metadata.asset one of)
+//!   zk_profile  REQUIRED / NONE           revise (intent.mode shielded / direct)
+//!   max_fee_bps                           deny   (gateway + merchant fees)
+//!   allowed_areas / blocked_areas         deny   (routing.transaction_area_id)
+//!   allowed_jurisdictions                 revise when untagged, else deny
+//!   blocked_jurisdictions                 deny   (metadata.jurisdiction, untagged too)
+//!   velocity                              deny   (accepted QUERYs per buyer)
+//!
+//! Assets are contract addresses, the native coin being address(0).
+//! Jurisdiction tags are explicit (`metadata.jurisdiction`, e.g. `US-CA`,
+//! Appendix A.10); a rule for `US` covers `US-CA`. Velocity counts the
+//! QUERYs L5 has committed within `window_secs`, per buyer: the wallet
+//! that signed the QUERY's DKI, as L2 verified it. Session keys and tokens
+//! are the client's to rotate, so an undelegated QUERY is refused wherever
+//! a velocity limit applies. A global limit counts across merchants.
+//!
+//! Policies are loaded from TOML (or JSON, by file extension) at startup,
+//! replaced at runtime by admin commands, and reloaded from their file
+//! without a restart. Velocity history survives reloads.
+//!
+//! ```toml
+//! [global]
+//! allowed_chains = [369]
+//! max_fee_bps = 200
+//! blocked_jurisdictions = ["KP", "IR"]
+//!
+//! [merchants."0x1111…"]
+//! max_amount = 5_000_000
+//! zk_profile = "REQUIRED"
+//! allowed_areas = ["tai:keccak256:7abf…"]
+//! velocity = { window_secs = 3600, max_queries = 10, max_amount = 20_000_000 }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::contracts::types::{address_to_hex, NATIVE_ETH};
+use crate::protocol::{ConstraintOp, FieldConstraint, QueryMessage, TGPMODE};
+use crate::tgp::delegation::delegating_wallet;
+use crate::tgp::tai::normalize_tai;
+use crate::tgp::types::ZkProfile;
+use crate::tgp::validation::validate_address;
+
+/// Reason code: `amount` above the policy ceiling.
+pub const REASON_AMOUNT_LIMIT: &str = "POLICY_AMOUNT_LIMIT";
+
+/// Reason code: `chain_id` not allowed.
+pub const REASON_CHAIN_NOT_ALLOWED: &str = "POLICY_CHAIN_NOT_ALLOWED";
+
+/// Reason code: `metadata.asset` not allowed.
+pub const REASON_ASSET_NOT_ALLOWED: &str = "POLICY_ASSET_NOT_ALLOWED";
+
+/// Reason code: fees above the policy ceiling.
+pub const REASON_FEE_LIMIT: &str = "POLICY_FEE_LIMIT";
+
+/// Reason code: `intent.mode` does not meet the required ZK profile.
+pub const REASON_ZK_PROFILE: &str = "POLICY_ZK_PROFILE";
+
+/// Reason code: transaction area not allowed.
+pub const REASON_AREA_NOT_ALLOWED: &str = "POLICY_AREA_NOT_ALLOWED";
+
+/// Reason code: jurisdiction tag missing where one is required.
+pub const REASON_JURISDICTION_REQUIRED: &str = "POLICY_JURISDICTION_REQUIRED";
+
+/// Reason code: jurisdiction not allowed.
+pub const REASON_JURISDICTION_NOT_ALLOWED: &str = "POLICY_JURISDICTION_NOT_ALLOWED";
+
+/// Reason code: buyer over a velocity limit.
+pub const REASON_VELOCITY_LIMIT: &str = "POLICY_VELOCITY_LIMIT";
+
+/// QUERY `metadata` key carrying the jurisdiction tag.
+pub const JURISDICTION_METADATA_KEY: &str = "jurisdiction";
+
+// -----------------------------------------------------------------------------
+// 1. Rules
+// -----------------------------------------------------------------------------
+
+/// How a QUERY breaking a rule is answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    /// ACK(status=deny)
+    Deny,
+    /// ACK(status=revise) with the constraint to meet
+    Revise,
+}
+
+/// One broken rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleViolation {
+    pub action: RuleAction,
+    pub code: &'static str,
+    pub message: String,
+    pub constraint: Option<FieldConstraint>,
+}
+
+impl RuleViolation {
+    pub fn deny(code: &'static str, message: impl Into<String>) -> Self {
+        Self { action: RuleAction::Deny, code, message: message.into(), constraint: None }
+    }
+
+    pub fn revise(code: &'static str, message: impl Into<String>, constraint: FieldConstraint) -> Self {
+        Self { action: RuleAction::Revise, code, message: message.into(), constraint: Some(constraint) }
+    }
+}
+
+/// Accepted QUERYs a buyer may make within a window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VelocityLimit {
+    pub window_secs: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_queries: Option<u32>,
+
+    /// Summed `amount` of the QUERYs in the window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<u64>,
+}
+
+/// A declarative rule set. Every rule is optional; empty lists mean "any".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<u64>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_chains: Vec<u64>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_assets: Vec<String>,
+
+    /// Ceiling on gateway routing + merchant contract fees
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_bps: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zk_profile: Option<ZkProfile>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_areas: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked_areas: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_jurisdictions: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked_jurisdictions: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<VelocityLimit>,
+}
+
+impl Policy {
+    /// Canonicalize addresses, areas and jurisdiction tags.
+    pub fn normalize(&mut self) -> Result<(), String> {
+        for asset in &mut self.allowed_assets {
+            validate_address(asset, "allowed_assets")?;
+            *asset = asset.to_lowercase();
+        }
+        for area in self.allowed_areas.iter_mut().chain(self.blocked_areas.iter_mut()) {
+            *area = normalize_tai(area)?;
+        }
+        for tag in self.allowed_jurisdictions.iter_mut().chain(self.blocked_jurisdictions.iter_mut()) {
+            *tag = tag.trim().to_uppercase();
+            if tag.is_empty() {
+                return Err("jurisdiction tags must not be empty".into());
+            }
+        }
+        if self.velocity.as_ref().is_some_and(|v| v.window_secs == 0) {
+            return Err("velocity.window_secs must be positive".into());
+        }
+        Ok(())
+    }
+
+    /// Rules `query` breaks, velocity aside (see [`PolicyEngine`]).
+    /// `fee_bps` is what the QUERY would pay in fees.
+    pub fn check(&self, query: &QueryMessage, fee_bps: u32) -> Vec<RuleViolation> {
+        let mut out = Vec::new();
+
+        if let Some(max) = self.max_amount {
+            if query.amount > max {
+                out.push(RuleViolation::revise(
+                    REASON_AMOUNT_LIMIT,
+                    format!("amount {} exceeds policy limit {}", query.amount, max),
+                    FieldConstraint::new("amount", ConstraintOp::Max, max),
+                ));
+            }
+        }
+
+        if !self.allowed_chains.is_empty() && !self.allowed_chains.contains(&query.chain_id) {
+            out.push(RuleViolation::revise(
+                REASON_CHAIN_NOT_ALLOWED,
+                format!("chain {} is not allowed by policy", query.chain_id),
+                FieldConstraint::new("chain_id", ConstraintOp::OneOf, self.allowed_chains.clone()),
+            ));
+        }
+
+        if !self.allowed_assets.is_empty() {
+            let asset = query_asset(query);
+            if !self.allowed_assets.contains(&asset) {
+                out.push(RuleViolation::revise(
+                    REASON_ASSET_NOT_ALLOWED,
+                    format!("asset {} is not allowed by policy", asset),
+                    FieldConstraint::new("metadata.asset", ConstraintOp::OneOf, self.allowed_assets.clone()),
+                ));
+            }
+        }
+
+        let required_mode = match self.zk_profile {
+            Some(ZkProfile::Required) => Some(TGPMODE::SHIELDED),
+            Some(ZkProfile::None) => Some(TGPMODE::DIRECT),
+            Some(ZkProfile::Optional) | None => None,
+        };
+        if let Some(mode) = required_mode.filter(|m| *m != query.intent.mode) {
+            let wire = serde_json::to_value(&mode).unwrap_or_default();
+            out.push(RuleViolation::revise(
+                REASON_ZK_PROFILE,
+                format!("policy requires intent.mode {}", wire.as_str().unwrap_or_default()),
+                FieldConstraint::new("intent.mode", ConstraintOp::Eq, wire),
+            ));
+        }
+
+        if let Some(max) = self.max_fee_bps {
+            if fee_bps > max {
+                out.push(RuleViolation::deny(
+                    REASON_FEE_LIMIT,
+                    format!("fees of {} bps exceed the policy limit of {} bps", fee_bps, max),
+                ));
+            }
+        }
+
+        let area = query.routing.transaction_area_id.as_deref().and_then(|a| normalize_tai(a).ok());
+        let area_blocked = area.as_ref().is_some_and(|a| self.blocked_areas.contains(a));
+        let area_unlisted = !self.allowed_areas.is_empty()
+            && !area.as_ref().is_some_and(|a| self.allowed_areas.contains(a));
+        if area_blocked || area_unlisted {
+            out.push(RuleViolation::deny(
+                REASON_AREA_NOT_ALLOWED,
+                format!("transaction area {} is not allowed by policy", area.as_deref().unwrap_or("(none)")),
+            ));
+        }
+
+        let jurisdiction = query
+            .metadata
+            .get(JURISDICTION_METADATA_KEY)
+            .and_then(Value::as_str)
+            .map(|j| j.trim().to_uppercase());
+        match jurisdiction {
+            Some(ref tag) if covers(&self.blocked_jurisdictions, tag)
+                || (!self.allowed_jurisdictions.is_empty() && !covers(&self.allowed_jurisdictions, tag)) =>
+            {
+                out.push(RuleViolation::deny(
+                    REASON_JURISDICTION_NOT_ALLOWED,
+                    format!("jurisdiction {} is not allowed by policy", tag),
+                ));
+            }
+            // Without a tag a blocklist could be dodged by leaving it out
+            None if !self.blocked_jurisdictions.is_empty() => {
+                out.push(RuleViolation::deny(
+                    REASON_JURISDICTION_REQUIRED,
+                    "policy requires a metadata.jurisdiction tag",
+                ));
+            }
+            None if !self.allowed_jurisdictions.is_empty() => {
+                out.push(RuleViolation::revise(
+                    REASON_JURISDICTION_REQUIRED,
+                    "policy requires a metadata.jurisdiction tag",
+                    FieldConstraint::new(
+                        format!("metadata.{}", JURISDICTION_METADATA_KEY),
+                        ConstraintOp::OneOf,
+                        self.allowed_jurisdictions.clone(),
+                    ),
+                ));
+            }
+            _ => {}
+        }
+
+        out
+    }
+}
+
+/// `metadata.asset`, lowercased; native coin when absent.
+fn query_asset(query: &QueryMessage) -> String {
+    query
+        .metadata
+        .get("asset")
+        .and_then(Value::as_str)
+        .map(str::to_lowercase)
+        .unwrap_or_else(|| address_to_hex(&NATIVE_ETH))
+}
+
+/// Does any of `rules` name `tag` or a region containing it (`US` ⊇ `US-CA`)?
+fn covers(rules: &[String], tag: &str) -> bool {
+    rules
+        .iter()
+        .any(|r| tag == r || tag.strip_prefix(r.as_str()).is_some_and(|rest| rest.starts_with('-')))
+}
+
+/// Who velocity limits are counted against: the delegating wallet.
+fn buyer_key(query: &QueryMessage) -> Option<String> {
+    delegating_wallet(query).map(|wallet| address_to_hex(&wallet))
+}
+
+// -----------------------------------------------------------------------------
+// 2. Engine
+// -----------------------------------------------------------------------------
+
+/// On-disk (TOML/JSON) form of the policies.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyFile {
+    pub global: Policy,
+
+    /// Policies by payment profile
+    pub merchants: HashMap<String, Policy>,
+}
+
+/// (timestamp, amount, QUERY id) of each accepted QUERY, by scope and buyer.
+type VelocityHistory = HashMap<(String, String), VecDeque<(u64, u64, String)>>;
+
+/// Scope of a global velocity limit.
+const GLOBAL_SCOPE: &str = "*";
+
+/// How often `admit` drops buyers whose window has passed.
+const VELOCITY_SWEEP_SECS: u64 = 60;
+
+/// Live, shareable policy set consulted by L5.
+#[derive(Debug, Default)]
+pub struct PolicyEngine {
+    inner: RwLock<PolicyFile>,
+    velocity: Mutex<VelocityHistory>,
+
+    /// When the velocity history was last swept
+    swept_at: AtomicU64,
+}
+
+impl PolicyEngine {
+    /// No policies: every QUERY is allowed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_file(mut file: PolicyFile) -> Result<Self, String> {
+        file.global.normalize().map_err(|e| format!("policy global: {}", e))?;
+        let mut merchants = HashMap::new();
+        for (profile, mut policy) in file.merchants {
+            let profile = normalize_profile(&profile)?;
+            policy.normalize().map_err(|e| format!("policy {}: {}", profile, e))?;
+            if merchants.insert(profile.clone(), policy).is_some() {
+                return Err(format!("policy: duplicate merchant {}", profile));
+            }
+        }
+        file.merchants = merchants;
+
+        Ok(Self { inner: RwLock::new(file), ..Self::default() })
+    }
+
+    pub fn from_toml_str(raw: &str) -> Result<Self, String> {
+        Self::from_file(toml::from_str(raw).map_err(|e| format!("policy: {}", e))?)
+    }
+
+    pub fn from_json_str(raw: &str) -> Result<Self, String> {
+        Self::from_file(serde_json::from_str(raw).map_err(|e| format!("policy: {}", e))?)
+    }
+
+    /// Load a `.json` file as JSON, anything else as TOML.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path).map_err(|e| format!("policy {}: {}", path.display(), e))?;
+        if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+            Self::from_json_str(&raw)
+        } else {
+            Self::from_toml_str(&raw)
+        }
+    }
+
+    /// Replace every policy with the contents of `path`; returns the number
+    /// of merchant policies. On error the current policies are kept.
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let fresh = Self::load(path)?.inner.into_inner().unwrap();
+        let count = fresh.merchants.len();
+        *self.inner.write().unwrap() = fresh;
+        Ok(count)
+    }
+
+    /// Current policies.
+    pub fn policies(&self) -> PolicyFile {
+        self.inner.read().unwrap().clone()
+    }
+
+    pub fn set_global(&self, mut policy: Policy) -> Result<(), String> {
+        policy.normalize()?;
+        self.inner.write().unwrap().global = policy;
+        Ok(())
+    }
+
+    /// Add or replace a merchant's policy; returns the previous one.
+    pub fn upsert_merchant(&self, profile: &str, mut policy: Policy) -> Result<Option<Policy>, String> {
+        let profile = normalize_profile(profile)?;
+        policy.normalize()?;
+        Ok(self.inner.write().unwrap().merchants.insert(profile, policy))
+    }
+
+    pub fn remove_merchant(&self, profile: &str) -> Option<Policy> {
+        self.inner.write().unwrap().merchants.remove(&profile.to_lowercase())
+    }
+
+    /// Rules `query` breaks under the global and its merchant's policy,
+    /// velocity included. `now` is in Unix seconds.
+    pub fn evaluate(&self, query: &QueryMessage, fee_bps: u32, now: u64) -> Vec<RuleViolation> {
+        let (mut out, limits) = {
+            let policies = self.inner.read().unwrap();
+            let mut out = policies.global.check(query, fee_bps);
+            if let Some(policy) = policies.merchants.get(&query.payment_profile.to_lowercase()) {
+                out.extend(policy.check(query, fee_bps));
+            }
+            (out, velocity_limits(&policies, query))
+        };
+
+        let mut history = self.velocity.lock().unwrap();
+        out.extend(velocity_violation(&mut history, query, &limits, now));
+        out
+    }
+
+    /// Count an accepted QUERY against its buyer's velocity limits. Checks
+    /// and records atomically, so concurrent QUERYs cannot both take the
+    /// last slot.
+    pub fn admit(&self, query: &QueryMessage, now: u64) -> Result<(), RuleViolation> {
+        let limits = velocity_limits(&self.inner.read().unwrap(), query);
+        if limits.is_empty() {
+            return Ok(());
+        }
+
+        let mut history = self.velocity.lock().unwrap();
+        if now >= self.swept_at.load(Ordering::Relaxed) + VELOCITY_SWEEP_SECS {
+            sweep_velocity(&mut history, &self.inner.read().unwrap(), now);
+            self.swept_at.store(now, Ordering::Relaxed);
+        }
+        if let Some(violation) = velocity_violation(&mut history, query, &limits, now) {
+            return Err(violation);
+        }
+        if let Some(buyer) = buyer_key(query) {
+            for (scope, _) in limits {
+                history.entry((scope, buyer.clone())).or_default().push_back((now, query.amount, query.id.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Give back the slots `admit` took for a QUERY that was refused after
+    /// all: its own entries, under whatever scopes it was admitted.
+    pub fn release(&self, query: &QueryMessage) {
+        let Some(buyer) = buyer_key(query) else {
+            return;
+        };
+
+        let mut history = self.velocity.lock().unwrap();
+        history.retain(|(_, owner), events| {
+            if *owner == buyer {
+                events.retain(|(_, _, id)| *id != query.id);
+            }
+            !events.is_empty()
+        });
+    }
+}
+
+fn normalize_profile(profile: &str) -> Result<String, String> {
+    validate_address(profile, "policy merchant")?;
+    Ok(profile.to_lowercase())
+}
+
+/// Velocity limits that apply to `query`, by scope.
+fn velocity_limits(policies: &PolicyFile, query: &QueryMessage) -> Vec<(String, VelocityLimit)> {
+    let profile = query.payment_profile.to_lowercase();
+    let merchant = policies.merchants.get(&profile).and_then(|p| p.velocity.clone());
+
+    policies
+        .global
+        .velocity
+        .clone()
+        .map(|v| (GLOBAL_SCOPE.to_string(), v))
+        .into_iter()
+        .chain(merchant.map(|v| (profile, v)))
+        .collect()
+}
+
+/// Drop expired history across all buyers, and scopes that no longer
+/// have a limit.
+fn sweep_velocity(history: &mut VelocityHistory, policies: &PolicyFile, now: u64) {
+    history.retain(|(scope, _), events| {
+        let policy = if scope == GLOBAL_SCOPE { Some(&policies.global) } else { policies.merchants.get(scope) };
+        let Some(limit) = policy.and_then(|p| p.velocity.as_ref()) else {
+            return false;
+        };
+        while events.front().is_some_and(|(ts, _, _)| ts + limit.window_secs <= now) {
+            events.pop_front();
+        }
+        !events.is_empty()
+    });
+}
+
+/// First limit one more QUERY would exceed, dropping expired history.
+fn velocity_violation(
+    history: &mut VelocityHistory,
+    query: &QueryMessage,
+    limits: &[(String, VelocityLimit)],
+    now: u64,
+) -> Option<RuleViolation> {
+    if limits.is_empty() {
+        return None;
+    }
+    let Some(buyer) = buyer_key(query) else {
+        return Some(RuleViolation::deny(
+            REASON_VELOCITY_LIMIT,
+            "velocity limits require a QUERY delegated by the buyer's wallet",
+        ));
+    };
+
+    for (scope, limit) in limits {
+        let key = (scope.clone(), buyer.clone());
+        let Some(events) = history.get_mut(&key) else {
+            continue;
+        };
+        while events.front().is_some_and(|(ts, _, _)| ts + limit.window_secs <= now) {
+            events.pop_front();
+        }
+        if events.is_empty() {
+            history.remove(&key);
+            continue;
+        }
+
+        let over_count = limit.max_queries.is_some_and(|max| events.len() as u64 >= max as u64);
+        let spent: u128 = events.iter().map(|(_, amount, _)| *amount as u128).sum();
+        let over_amount = limit
+            .max_amount
+            .is_some_and(|max| spent + query.amount as u128 > max as u128);
+        if over_count || over_amount {
+            return Some(RuleViolation::deny(
+                REASON_VELOCITY_LIMIT,
+                format!("buyer is over the velocity limit for {}s", limit.window_secs),
+            ));
+        }
+    }
+    None
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::delegation::tests::{sample_dki, sign, WALLET_KEY};
+    use crate::tgp::layers::tests::sample_query;
+    use serde_json::json;
+
+    const AREA: &str = "tai:keccak256:7abf92c600000000000000000000000000000000000000000000000000000001";
+
+    fn engine() -> PolicyEngine {
+        let profile = sample_query().payment_profile;
+        PolicyEngine::from_toml_str(&format!(
+            r#"
+            [global]
+            allowed_chains = [369]
+            max_fee_bps = 200
+            blocked_jurisdictions = ["KP"]
+
+            [merchants."{profile}"]
+            max_amount = 5000
+            zk_profile = "REQUIRED"
+            allowed_areas = ["{AREA}"]
+            velocity = {{ window_secs = 60, max_queries = 2 }}
+            "#
+        ))
+        .unwrap()
+    }
+
+    fn codes(violations: &[RuleViolation]) -> Vec<&'static str> {
+        violations.iter().map(|v| v.code).collect()
+    }
+
+    /// Have the wallet `wallet` delegate `session_key` for `q`.
+    fn delegate(q: &mut QueryMessage, wallet: &[u8; 32], session_key: &str) {
+        let mut dki = sample_dki(&q.payment_profile);
+        dki.session_key = session_key.into();
+        q.delegated_key = Some(dki.session_key.clone());
+        q.session_token = Some(sign(&dki, wallet));
+        q.scope = Some(serde_json::to_value(&dki).unwrap());
+    }
+
+    const SESSION_KEY: &str = "0x02aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+
+    #[test]
+    fn test_rules_deny_or_revise() {
+        let engine = engine();
+        let mut q = sample_query();
+        q.metadata = json!({ "jurisdiction": "US" });
+
+        let violations = engine.evaluate(&q, 0, 0);
+        assert_eq!(codes(&violations), vec![REASON_ZK_PROFILE, REASON_AREA_NOT_ALLOWED, REASON_VELOCITY_LIMIT]);
+        assert_eq!(violations[0].action, RuleAction::Revise);
+        assert_eq!(violations[0].constraint, Some(FieldConstraint::new("intent.mode", ConstraintOp::Eq, "shielded")));
+        assert_eq!(violations[1].action, RuleAction::Deny);
+
+        q.intent.mode = TGPMODE::SHIELDED;
+        q.routing.transaction_area_id = Some(AREA.to_uppercase());
+        delegate(&mut q, &WALLET_KEY, SESSION_KEY);
+        assert!(engine.evaluate(&q, 0, 0).is_empty());
+
+        q.chain_id = 1;
+        q.amount = 6000;
+        q.metadata = json!({ "jurisdiction": "kp-01" });
+        assert_eq!(
+            codes(&engine.evaluate(&q, 201, 0)),
+            vec![REASON_CHAIN_NOT_ALLOWED, REASON_FEE_LIMIT, REASON_JURISDICTION_NOT_ALLOWED, REASON_AMOUNT_LIMIT],
+        );
+
+        // Other merchants only see the global policy
+        let mut other = sample_query();
+        other.payment_profile = "0x2222222222222222222222222222222222222222".into();
+        other.metadata = json!({ "jurisdiction": "US" });
+        assert!(engine.evaluate(&other, 0, 0).is_empty());
+
+        assert!(PolicyEngine::from_toml_str("[global]\nmax_amont = 1").is_err());
+        assert!(PolicyEngine::from_json_str(r#"{"merchants": {"not-an-address": {}}}"#).is_err());
+    }
+
+    #[test]
+    fn test_jurisdiction_allow_list() {
+        let mut policy = Policy {
+            allowed_jurisdictions: vec!["us".into()],
+            ..Policy::default()
+        };
+        policy.normalize().unwrap();
+
+        let mut q = sample_query();
+        let missing = policy.check(&q, 0);
+        assert_eq!(missing[0].code, REASON_JURISDICTION_REQUIRED);
+        assert_eq!(missing[0].action, RuleAction::Revise);
+
+        q.metadata = json!({ "jurisdiction": "US-CA" });
+        assert!(policy.check(&q, 0).is_empty());
+        q.metadata = json!({ "jurisdiction": "USA" });
+        assert_eq!(policy.check(&q, 0)[0].code, REASON_JURISDICTION_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn test_jurisdiction_block_list_needs_a_tag() {
+        let policy = Policy { blocked_jurisdictions: vec!["KP".into()], ..Policy::default() };
+
+        // Leaving the tag out does not slip past the blocklist
+        let mut q = sample_query();
+        let missing = policy.check(&q, 0);
+        assert_eq!(missing[0].code, REASON_JURISDICTION_REQUIRED);
+        assert_eq!(missing[0].action, RuleAction::Deny);
+
+        q.metadata = json!({ "jurisdiction": "KP" });
+        assert_eq!(policy.check(&q, 0)[0].code, REASON_JURISDICTION_NOT_ALLOWED);
+        q.metadata = json!({ "jurisdiction": "US" });
+        assert!(policy.check(&q, 0).is_empty());
+    }
+
+    #[test]
+    fn test_velocity_counts_admitted_queries() {
+        let engine = engine();
+        let mut q = sample_query();
+        q.intent.mode = TGPMODE::SHIELDED;
+        q.routing.transaction_area_id = Some(AREA.into());
+        q.metadata = json!({ "jurisdiction": "US" });
+
+        // No verified buyer to count against: a bare session token is
+        // the client's to pick
+        assert!(engine.admit(&q, 0).is_err());
+        q.session_token = Some("buyer-1".into());
+        assert!(engine.admit(&q, 0).is_err());
+
+        delegate(&mut q, &WALLET_KEY, SESSION_KEY);
+        assert!(engine.admit(&q, 0).is_ok());
+        assert!(engine.admit(&q, 10).is_ok());
+        assert_eq!(engine.admit(&q, 20).unwrap_err().code, REASON_VELOCITY_LIMIT);
+        assert_eq!(codes(&engine.evaluate(&q, 0, 20)), vec![REASON_VELOCITY_LIMIT]);
+
+        // A fresh session key under the same wallet is the same buyer
+        let mut rotated = q.clone();
+        delegate(&mut rotated, &WALLET_KEY, &SESSION_KEY.replace("0x02", "0x03"));
+        assert_eq!(engine.admit(&rotated, 20).unwrap_err().code, REASON_VELOCITY_LIMIT);
+
+        // Another wallet, and the same buyer once the window has passed
+        let mut other = q.clone();
+        delegate(&mut other, &[0x44; 32], SESSION_KEY);
+        assert!(engine.evaluate(&other, 0, 20).is_empty());
+        assert!(engine.admit(&q, 60).is_ok());
+    }
+
+    #[test]
+    fn test_release_returns_only_its_own_slots() {
+        let engine = engine();
+        let mut q = sample_query();
+        q.intent.mode = TGPMODE::SHIELDED;
+        q.routing.transaction_area_id = Some(AREA.into());
+        delegate(&mut q, &WALLET_KEY, SESSION_KEY);
+
+        // Two QUERYs of one buyer for the same amount; only the first is
+        // refused after its commit
+        let mut first = q.clone();
+        first.id = "q-first".into();
+        let mut second = q.clone();
+        second.id = "q-second".into();
+        assert!(engine.admit(&first, 0).is_ok());
+        assert!(engine.admit(&second, 10).is_ok());
+
+        // Policies reloaded in between: the merchant limit is gone
+        engine.remove_merchant(&q.payment_profile);
+        engine.release(&first);
+
+        let history = engine.velocity.lock().unwrap();
+        let ids: Vec<_> = history.values().flatten().map(|(ts, _, id)| (*ts, id.as_str())).collect();
+        assert_eq!(ids, vec![(10, "q-second")]);
+    }
+
+    #[test]
+    fn test_velocity_history_swept() {
+        let engine = engine();
+        let mut q = sample_query();
+        q.intent.mode = TGPMODE::SHIELDED;
+        q.routing.transaction_area_id = Some(AREA.into());
+
+        for wallet in 1..=3u8 {
+            delegate(&mut q, &[wallet; 32], SESSION_KEY);
+            assert!(engine.admit(&q, 0).is_ok());
+        }
+        assert_eq!(engine.velocity.lock().unwrap().len(), 3);
+
+        // Once their window has passed, buyers that never return are dropped
+        delegate(&mut q, &[4; 32], SESSION_KEY);
+        assert!(engine.admit(&q, 3600).is_ok());
+        assert_eq!(engine.velocity.lock().unwrap().len(), 1);
+
+        // ... as are buyers of a limit that was lifted
+        engine.remove_merchant(&q.payment_profile);
+        engine.set_global(Policy {
+            velocity: Some(VelocityLimit { window_secs: 60, max_queries: Some(5), max_amount: None }),
+            ..Policy::default()
+        }).unwrap();
+        assert!(engine.admit(&q, 3660).is_ok());
+        let history = engine.velocity.lock().unwrap();
+        assert_eq!(history.keys().map(|(scope, _)| scope.as_str()).collect::<Vec<_>>(), vec![GLOBAL_SCOPE]);
+    }
+
+    #[test]
+    fn test_reload_keeps_policies_on_error() {
+        let dir = std::env::temp_dir().join(format!("tbc-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy.json");
+        std::fs::write(&path, r#"{"global": {"max_amount": 10}}"#).unwrap();
+
+        let engine = PolicyEngine::load(&path).unwrap();
+        assert_eq!(engine.policies().global.max_amount, Some(10));
+
+        std::fs::write(&path, r#"{"global": {"max_amount": 20}, "merchants": {"0x1111111111111111111111111111111111111111": {}}}"#).unwrap();
+        assert_eq!(engine.reload(&path).unwrap(), 1);
+        assert_eq!(engine.policies().global.max_amount, Some(20));
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(engine.reload(&path).is_err());
+        assert_eq!(engine.policies().global.max_amount, Some(20));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
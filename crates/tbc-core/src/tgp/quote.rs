@@ -0,0 +1,151 @@
+//! TGP-00 v3.2 -- QUOTE Preview (quote.rs)
+//! --------------------------------------------------
+//! QUERY(verb=QUOTE) → dry-run L1–L5 → ACK(status=offer) + `quote`
+//!
+//! A quote prices what the buyer would pay for the same QUERY issued as
+//! COMMIT/PAY, using the merchant contract's deployment parameters
+//! (`DeployMerchantParams`). It never carries an executable `tx`.
+//!
+//! Fee split on `amount` (all lines in the price asset, base units):
+//!   • tbc_fee       -- `tbc_fee_bps`, to `tbc_fee_recipient`
+//!   • zk_relay_fee  -- `zk_fee_bps`, to `zk_fee_recipient`
+//!   • merchant_fee  -- the remainder, to `merchant_fee_recipient`
+//!   • routing_fee   -- this gateway's fee, charged on top (optional)
+
+use serde::{Deserialize, Serialize};
+
+use crate::contracts::address_to_hex;
+use crate::protocol::{AckReason, QueryMessage};
+use crate::tgp::tx_builder::{bps_of, native_asset, routing_fee_line, EnvelopeParams};
+use crate::tgp::types::FeeLine;
+
+/// Reason code: no merchant fee schedule is known for the payment profile.
+pub const REASON_NO_FEE_SCHEDULE: &str = "QUOTE_NO_FEE_SCHEDULE";
+
+/// Reason code: the merchant's fee schedule is invalid.
+pub const REASON_INVALID_FEE_SCHEDULE: &str = "QUOTE_INVALID_FEE_SCHEDULE";
+
+/// Fee and gas preview attached to ACK(status=offer) for a QUOTE.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotePreview {
+    pub price: FeeLine,
+    pub tbc_fee: FeeLine,
+    pub zk_relay_fee: FeeLine,
+    pub merchant_fee: FeeLine,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_fee: Option<FeeLine>,
+
+    /// What the buyer pays in total (price + routing fee)
+    pub total: String,
+
+    /// Gas limit the executable envelope would carry
+    pub estimated_gas: u64,
+}
+
+impl QuotePreview {
+    pub fn validate(&self) -> Result<(), String> {
+        self.price.validate("quote.price")?;
+        self.tbc_fee.validate("quote.tbc_fee")?;
+        self.zk_relay_fee.validate("quote.zk_relay_fee")?;
+        self.merchant_fee.validate("quote.merchant_fee")?;
+        if let Some(ref line) = self.routing_fee {
+            line.validate("quote.routing_fee")?;
+        }
+        Ok(())
+    }
+}
+
+/// Price a QUOTE against the merchant's fee schedule.
+///
+/// Failures are returned as an [`AckReason`] so the client receives
+/// ACK(status=deny) with a reason rather than an ERROR.
+pub fn build_quote(params: &EnvelopeParams, query: &QueryMessage) -> Result<QuotePreview, AckReason> {
+    let merchant = params.merchant_fees_for(&query.payment_profile).ok_or_else(|| {
+        AckReason::new(
+            REASON_NO_FEE_SCHEDULE,
+            format!("no fee schedule for payment profile {}", query.payment_profile),
+        )
+    })?;
+    merchant.validate().map_err(|e| {
+        AckReason::new(REASON_INVALID_FEE_SCHEDULE, format!("merchant fee schedule invalid: {:?}", e))
+    })?;
+
+    let tbc_fee = bps_of(query.amount, merchant.tbc_fee_bps);
+    let zk_relay_fee = bps_of(query.amount, merchant.zk_fee_bps);
+    let merchant_fee = query.amount as u128 - tbc_fee - zk_relay_fee;
+
+    let routing_fee = routing_fee_line(params, query);
+    let total = query.amount as u128 + bps_of(query.amount, params.fees_bps as u64);
+
+    let line = |amount: u128, payer: &str, payee: String| FeeLine {
+        amount: amount.to_string(),
+        asset: native_asset(query.chain_id),
+        payer: payer.into(),
+        payee,
+        protocol_fee: None,
+        policy_ref: None,
+    };
+
+    Ok(QuotePreview {
+        price: line(query.amount as u128, "buyer", "seller".into()),
+        tbc_fee: line(tbc_fee, "escrow", address_to_hex(&merchant.tbc_fee_recipient)),
+        zk_relay_fee: line(zk_relay_fee, "escrow", address_to_hex(&merchant.zk_fee_recipient)),
+        merchant_fee: line(merchant_fee, "escrow", address_to_hex(&merchant.merchant_fee_recipient)),
+        routing_fee,
+        total: total.to_string(),
+        estimated_gas: params.gas_limit,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::contracts::DeployMerchantParams;
+    use crate::protocol::TGPVerb;
+    use crate::tgp::layers::tests::sample_query;
+
+    pub(crate) fn sample_merchant() -> DeployMerchantParams {
+        DeployMerchantParams {
+            version: 1,
+            merchant_admin: [0xaa; 20],
+            tbc_relay_address: [0xbb; 20],
+            zk_verifier: [0xcc; 20],
+            tbc_fee_recipient: [0x01; 20],
+            zk_fee_recipient: [0x02; 20],
+            merchant_fee_recipient: [0x03; 20],
+            tbc_fee_bps: 100,
+            zk_fee_bps: 50,
+            ttl_seconds: 3600,
+            initial_supported_assets: vec![],
+            salt: [0u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_quote_fee_split() {
+        let mut q = sample_query();
+        q.intent.verb = TGPVerb::QUOTE;
+        q.amount = 10_000;
+
+        let params = EnvelopeParams::new()
+            .with_fees_bps(20)
+            .with_merchant_fees(&q.payment_profile, sample_merchant());
+
+        let quote = build_quote(&params, &q).unwrap();
+        assert_eq!(quote.tbc_fee.amount, "100");
+        assert_eq!(quote.zk_relay_fee.amount, "50");
+        assert_eq!(quote.merchant_fee.amount, "9850");
+        assert_eq!(quote.merchant_fee.payee, format!("0x{}", "03".repeat(20)));
+        assert_eq!(quote.routing_fee.as_ref().unwrap().amount, "20");
+        assert_eq!(quote.total, "10020");
+        assert_eq!(quote.estimated_gas, params.gas_limit);
+        assert!(quote.validate().is_ok());
+    }
+
+    #[test]
+    fn test_quote_requires_fee_schedule() {
+        let reason = build_quote(&EnvelopeParams::new(), &sample_query()).unwrap_err();
+        assert_eq!(reason.code, REASON_NO_FEE_SCHEDULE);
+    }
+}
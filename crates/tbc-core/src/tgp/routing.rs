@@ -0,0 +1,284 @@
+//! TGP-00 v3.2 -- Multi-hop Routing (routing.rs)
+//! --------------------------------------------------
+//! Decides whether a QUERY is handled here or forwarded to a peer gateway
+//! (§2.2 "Gateways MAY forward QUERY messages using routing metadata").
+//!
+//!   • `routing.transaction_area_id` names the target area. Absent, or one
+//!     of ours → handled locally.
+//!   • Otherwise the QUERY is forwarded with this gateway's area appended to
+//!     `routing.path` (prior hops are never removed, §5.1).
+//!   • Our own area already in `path` → loop; `path` at the hop limit → stop.
+//!   • Peers are ranked by `DomainTrust` weight (then URL, so the choice is
+//!     deterministic); a client `next_gateway` is honoured only when it names
+//!     one of those peers.
+//!
+//! Pure and transport-free: the gateway owns the actual HTTP/WS forwarding.
+//! A static `RoutingTable` or the live TAI registry (`tai.rs`) is plugged
+//! into the router through [`RouteResolver`].
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{QueryMessage, TgpErrorCode};
+use crate::tgp::types::DomainTrust;
+
+/// Maximum `routing.path` length before a QUERY is no longer forwarded.
+pub const DEFAULT_MAX_HOPS: usize = 8;
+
+/// A neighbouring gateway this gateway may forward to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerGateway {
+    /// The peer's own transaction area ID
+    pub area: String,
+
+    /// TGP endpoint (`http(s)://…` or `ws(s)://…`)
+    pub url: String,
+
+    pub trust: DomainTrust,
+
+    /// Further areas reachable through this peer
+    #[serde(default)]
+    pub routes: Vec<String>,
+}
+
+impl PeerGateway {
+    pub fn new(area: impl Into<String>, url: impl Into<String>, trust: DomainTrust) -> Self {
+        Self {
+            area: area.into(),
+            url: url.into(),
+            trust,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Also reach `area` through this peer.
+    pub fn with_route(mut self, area: impl Into<String>) -> Self {
+        self.routes.push(area.into());
+        self
+    }
+
+    pub fn reaches(&self, area: &str) -> bool {
+        self.area == area || self.routes.iter().any(|r| r == area)
+    }
+}
+
+/// Outcome of [`RoutingTable::route`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteDecision {
+    /// Handle the QUERY on this gateway.
+    Local,
+
+    /// Forward `query` (path already extended) to the first peer that
+    /// answers, in order.
+    Forward {
+        query: Box<QueryMessage>,
+        peers: Vec<PeerGateway>,
+    },
+}
+
+/// Why a QUERY could not be routed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoutingError {
+    Loop { area: String },
+    HopLimit { hops: usize, max: usize },
+    NoRoute { area: String },
+    /// Routing metadata is malformed or contradicts the area's registration
+    Invalid { area: String, reason: String },
+}
+
+impl RoutingError {
+    pub fn code(&self) -> TgpErrorCode {
+        match self {
+            RoutingError::Loop { .. } => TgpErrorCode::RoutingLoop,
+            RoutingError::HopLimit { .. } => TgpErrorCode::HopLimitExceeded,
+            RoutingError::NoRoute { .. } => TgpErrorCode::NoRoute,
+            RoutingError::Invalid { .. } => TgpErrorCode::InvalidMessage,
+        }
+    }
+}
+
+impl std::fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoutingError::Loop { area } => write!(f, "routing loop: {} already in path", area),
+            RoutingError::HopLimit { hops, max } => write!(f, "path has {} hops (limit {})", hops, max),
+            RoutingError::NoRoute { area } => write!(f, "no trusted peer serves area {}", area),
+            RoutingError::Invalid { area, reason } => write!(f, "invalid routing for area {}: {}", area, reason),
+        }
+    }
+}
+
+/// Decides where a QUERY is handled.
+pub trait RouteResolver: Send + Sync {
+    fn route(&self, query: &QueryMessage) -> Result<RouteDecision, RoutingError>;
+}
+
+/// This gateway's areas and its forwarding peers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingTable {
+    local_area: String,
+    served: HashSet<String>,
+    peers: Vec<PeerGateway>,
+    max_hops: usize,
+    min_trust: DomainTrust,
+}
+
+impl RoutingTable {
+    pub fn new(local_area: impl Into<String>) -> Self {
+        let local_area = local_area.into();
+        Self {
+            served: HashSet::from([local_area.clone()]),
+            local_area,
+            peers: Vec::new(),
+            max_hops: DEFAULT_MAX_HOPS,
+            min_trust: DomainTrust::Low,
+        }
+    }
+
+    /// Also handle QUERYs targeting `area` locally.
+    pub fn with_served_area(mut self, area: impl Into<String>) -> Self {
+        self.served.insert(area.into());
+        self
+    }
+
+    pub fn with_peer(mut self, peer: PeerGateway) -> Self {
+        self.peers.retain(|p| p.url != peer.url);
+        self.peers.push(peer);
+        self
+    }
+
+    pub fn with_max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    /// Never forward to peers trusted less than `trust`.
+    pub fn with_min_trust(mut self, trust: DomainTrust) -> Self {
+        self.min_trust = trust;
+        self
+    }
+
+    pub fn local_area(&self) -> &str {
+        &self.local_area
+    }
+
+    pub fn peers(&self) -> &[PeerGateway] {
+        &self.peers
+    }
+
+    pub fn route(&self, query: &QueryMessage) -> Result<RouteDecision, RoutingError> {
+        let Some(target) = query.routing.transaction_area_id.as_deref() else {
+            return Ok(RouteDecision::Local);
+        };
+        if self.served.contains(target) {
+            return Ok(RouteDecision::Local);
+        }
+
+        let path = &query.routing.path;
+        if path.iter().any(|hop| hop == &self.local_area) {
+            return Err(RoutingError::Loop { area: self.local_area.clone() });
+        }
+        if path.len() >= self.max_hops {
+            return Err(RoutingError::HopLimit { hops: path.len(), max: self.max_hops });
+        }
+
+        let mut peers: Vec<PeerGateway> = self
+            .peers
+            .iter()
+            .filter(|p| p.reaches(target))
+            .filter(|p| p.trust.weight() >= self.min_trust.weight())
+            // Never hand the QUERY back to an area it has already crossed
+            .filter(|p| !path.contains(&p.area))
+            .cloned()
+            .collect();
+        peers.sort_by(|a, b| b.trust.weight().cmp(&a.trust.weight()).then_with(|| a.url.cmp(&b.url)));
+
+        if let Some(ref requested) = query.routing.next_gateway {
+            let pos = peers
+                .iter()
+                .position(|p| &p.url == requested)
+                .ok_or_else(|| RoutingError::NoRoute { area: target.to_string() })?;
+            let preferred = peers.remove(pos);
+            peers.insert(0, preferred);
+        }
+
+        if peers.is_empty() {
+            return Err(RoutingError::NoRoute { area: target.to_string() });
+        }
+
+        let mut forwarded = query.clone();
+        forwarded.routing.path.push(self.local_area.clone());
+        forwarded.routing.next_gateway = None;
+
+        Ok(RouteDecision::Forward { query: Box::new(forwarded), peers })
+    }
+}
+
+impl RouteResolver for RoutingTable {
+    fn route(&self, query: &QueryMessage) -> Result<RouteDecision, RoutingError> {
+        RoutingTable::route(self, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgp::layers::tests::sample_query;
+
+    fn query_to(area: &str, path: &[&str]) -> QueryMessage {
+        let mut q = sample_query();
+        q.routing.transaction_area_id = Some(area.into());
+        q.routing.path = path.iter().map(|s| s.to_string()).collect();
+        q
+    }
+
+    fn table() -> RoutingTable {
+        RoutingTable::new("TAID-EU")
+            .with_peer(PeerGateway::new("TAID-US", "https://us-b.example/tgp", DomainTrust::Medium))
+            .with_peer(PeerGateway::new("TAID-US", "https://us-a.example/tgp", DomainTrust::High))
+            .with_peer(PeerGateway::new("TAID-APAC", "wss://apac.example/tgp/ws", DomainTrust::Unknown))
+    }
+
+    #[test]
+    fn test_local_and_forward() {
+        assert_eq!(table().route(&sample_query()).unwrap(), RouteDecision::Local);
+        assert_eq!(table().route(&query_to("TAID-EU", &[])).unwrap(), RouteDecision::Local);
+
+        let RouteDecision::Forward { query, peers } = table().route(&query_to("TAID-US", &["TAID-X"])).unwrap() else {
+            panic!("expected forward");
+        };
+        assert_eq!(query.routing.path, vec!["TAID-X", "TAID-EU"]);
+        let urls: Vec<&str> = peers.iter().map(|p| p.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://us-a.example/tgp", "https://us-b.example/tgp"]);
+    }
+
+    #[test]
+    fn test_next_gateway_must_be_a_peer() {
+        let mut q = query_to("TAID-US", &[]);
+        q.routing.next_gateway = Some("https://us-b.example/tgp".into());
+        let RouteDecision::Forward { query, peers } = table().route(&q).unwrap() else {
+            panic!("expected forward");
+        };
+        assert_eq!(peers[0].url, "https://us-b.example/tgp");
+        assert_eq!(query.routing.next_gateway, None);
+
+        q.routing.next_gateway = Some("https://attacker.example".into());
+        assert_eq!(table().route(&q).unwrap_err().code(), TgpErrorCode::NoRoute);
+    }
+
+    #[test]
+    fn test_loop_hop_limit_and_trust() {
+        let looped = table().route(&query_to("TAID-US", &["TAID-EU"])).unwrap_err();
+        assert_eq!(looped.code(), TgpErrorCode::RoutingLoop);
+
+        let far = table().with_max_hops(2).route(&query_to("TAID-US", &["A", "B"])).unwrap_err();
+        assert_eq!(far.code(), TgpErrorCode::HopLimitExceeded);
+
+        // Below the default minimum trust
+        let untrusted = table().route(&query_to("TAID-APAC", &[])).unwrap_err();
+        assert_eq!(untrusted.code(), TgpErrorCode::NoRoute);
+        let allowed = table().with_min_trust(DomainTrust::Unknown).route(&query_to("TAID-APAC", &[]));
+        assert!(matches!(allowed, Ok(RouteDecision::Forward { .. })));
+    }
+}
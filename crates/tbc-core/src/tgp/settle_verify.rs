@@ -0,0 +1,361 @@
+//! TGP-00 v3.2 -- SETTLE Verification (settle_verify.rs)
+//! --------------------------------------------------
+//! Every SETTLE routed to the gateway is only a claim, whatever `source` it
+//! names: the gateway's own watcher publishes its SETTLEs on the internal
+//! SETTLE feed, never through the router. Before it is passed on, the claim
+//! is checked against the settlement transaction's receipt:
+//!
+//!   • chain     -- the receipt comes from the gateway's chain, and a
+//!                  `SettlementCompleted` event names that chain
+//!   • status    -- `complete`/`refunded` need a successful tx,
+//!                  `reverted` a failed one
+//!   • contract  -- the tx targets the settlement contract of the envelope
+//!                  issued for the QUERY (or a configured contract)
+//!   • event     -- `SettlementCompleted` / `BuyerRefunded` emitted by that
+//!                  contract, for the order named in `escrow_id`, which
+//!                  must be the order of the envelope issued for the QUERY
+//!
+//! Receipts come from a `ReceiptProvider` (the node's RPC adapter).
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::contracts::settlement::{BuyerRefundedEvent, SettlementCompletedEvent};
+use crate::contracts::types::{hex_to_bytes32, Bytes32};
+use crate::protocol::{SettleMessage, TgpErrorCode};
+use crate::tgp::expiry::IssuedEnvelopes;
+use crate::tgp::settle_watch::log_parts;
+
+// -----------------------------------------------------------------------------
+// 1. Receipt Source
+// -----------------------------------------------------------------------------
+
+#[async_trait]
+pub trait ReceiptProvider: Send + Sync {
+    /// Chain the provider reads from.
+    async fn chain_id(&self) -> Result<u64, String>;
+
+    /// `eth_getTransactionReceipt` result; `None` while the tx is unknown or pending.
+    async fn tx_receipt(&self, tx_hash: &str) -> Result<Option<Value>, String>;
+}
+
+// -----------------------------------------------------------------------------
+// 2. Errors
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettleVerifyError {
+    /// The receipt contradicts the SETTLE (or there is nothing to check)
+    Forged(String),
+    /// The receipt could not be fetched; the SETTLE may be retried
+    Unavailable(String),
+}
+
+impl SettleVerifyError {
+    pub fn code(&self) -> TgpErrorCode {
+        match self {
+            Self::Forged(_) => TgpErrorCode::SettleUnverified,
+            Self::Unavailable(_) => TgpErrorCode::ReceiptUnavailable,
+        }
+    }
+}
+
+impl std::fmt::Display for SettleVerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Forged(msg) | Self::Unavailable(msg) => f.write_str(msg),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 3. Verifier
+// -----------------------------------------------------------------------------
+
+pub struct SettlementVerifier {
+    provider: Arc<dyn ReceiptProvider>,
+    chain_id: u64,
+    /// Lower-cased settlement contracts accepted when no issued envelope names one
+    contracts: Vec<String>,
+}
+
+impl SettlementVerifier {
+    pub fn new(provider: Arc<dyn ReceiptProvider>, chain_id: u64) -> Self {
+        Self {
+            provider,
+            chain_id,
+            contracts: Vec::new(),
+        }
+    }
+
+    /// Accept settlements through `address` for QUERYs this gateway did not issue.
+    pub fn with_contract(mut self, address: impl Into<String>) -> Self {
+        let address = address.into().to_lowercase();
+        if !self.contracts.contains(&address) {
+            self.contracts.push(address);
+        }
+        self
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Check a SETTLE against its receipt, whatever its claimed `source`.
+    pub async fn verify(
+        &self,
+        settle: &SettleMessage,
+        issued: &IssuedEnvelopes,
+    ) -> Result<(), SettleVerifyError> {
+        let tx_hash = settle
+            .tx_hash
+            .as_deref()
+            .ok_or_else(|| SettleVerifyError::Forged("SETTLE carries no tx_hash".into()))?;
+        let order = settle.correlation_id.as_deref().and_then(|q| issued.order_of(q));
+
+        let contracts = match settle.correlation_id.as_deref().and_then(|q| issued.contract_of(q)) {
+            Some(contract) => vec![contract],
+            None if !self.contracts.is_empty() => self.contracts.clone(),
+            None => {
+                return Err(SettleVerifyError::Forged(
+                    "no settlement contract known for this SETTLE".into(),
+                ))
+            }
+        };
+
+        let chain_id = self.provider.chain_id().await.map_err(SettleVerifyError::Unavailable)?;
+        if chain_id != self.chain_id {
+            return Err(SettleVerifyError::Unavailable(format!(
+                "receipt provider serves chain {}, expected {}",
+                chain_id, self.chain_id
+            )));
+        }
+
+        let receipt = self
+            .provider
+            .tx_receipt(tx_hash)
+            .await
+            .map_err(SettleVerifyError::Unavailable)?
+            .ok_or_else(|| SettleVerifyError::Unavailable(format!("no receipt for {}", tx_hash)))?;
+
+        check_receipt(settle, &receipt, &contracts, order.as_ref(), self.chain_id)
+            .map_err(SettleVerifyError::Forged)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 4. Receipt Checks (pure)
+// -----------------------------------------------------------------------------
+
+/// Does `receipt` prove `settle`? `contracts` are the acceptable (lower-cased)
+/// settlement contracts, `order` the order of the envelope issued for the
+/// QUERY when this gateway issued one.
+pub fn check_receipt(
+    settle: &SettleMessage,
+    receipt: &Value,
+    contracts: &[String],
+    order: Option<&Bytes32>,
+    chain_id: u64,
+) -> Result<(), String> {
+    if let (Some(claimed), Some(actual)) = (settle.tx_hash.as_deref(), hex_field(receipt, "transactionHash")) {
+        if !claimed.eq_ignore_ascii_case(&actual) {
+            return Err(format!("receipt is for {}, not {}", actual, claimed));
+        }
+    }
+
+    let to = hex_field(receipt, "to").ok_or("receipt has no target contract")?;
+    if !contracts.contains(&to) {
+        return Err(format!("tx targets {}, not the settlement contract", to));
+    }
+
+    let succeeded = match hex_field(receipt, "status").as_deref() {
+        Some("0x1") => true,
+        Some("0x0") => false,
+        other => return Err(format!("receipt status {:?} is not 0x0/0x1", other)),
+    };
+
+    let status = settle.result.final_status.to_lowercase();
+    match (status.as_str(), succeeded) {
+        ("complete", true) => {
+            let event = contract_logs(receipt, &to)
+                .find_map(|(topics, data)| SettlementCompletedEvent::from_log(&topics, &data))
+                .ok_or("no SettlementCompleted event in receipt")?;
+            if event.chain_id != chain_id {
+                return Err(format!("settlement on chain {}, expected {}", event.chain_id, chain_id));
+            }
+            check_order(&settle.result.escrow_id, order, &event.order_id)
+        }
+        ("refunded", true) => {
+            let event = contract_logs(receipt, &to)
+                .find_map(|(topics, data)| BuyerRefundedEvent::from_log(&topics, &data))
+                .ok_or("no BuyerRefunded event in receipt")?;
+            check_order(&settle.result.escrow_id, order, &event.order_id)
+        }
+        ("reverted", false) => Ok(()),
+        ("complete" | "refunded" | "reverted", _) => Err(format!(
+            "final_status {} contradicts receipt status {}",
+            status,
+            if succeeded { "0x1" } else { "0x0" }
+        )),
+        _ => Err(format!("final_status {} cannot be verified", status)),
+    }
+}
+
+/// `escrow_id` must be the bytes32 order ID of the event, and the event
+/// must be for the order of the issued envelope (`issued`), if any.
+fn check_order(escrow_id: &str, issued: Option<&Bytes32>, order_id: &Bytes32) -> Result<(), String> {
+    let claimed = hex_to_bytes32(escrow_id)
+        .map_err(|_| format!("escrow_id {} is not a bytes32 order ID", escrow_id))?;
+    if claimed != *order_id {
+        return Err(format!("event is for order 0x{}, not {}", hex::encode(order_id), escrow_id));
+    }
+    match issued {
+        Some(issued) if issued != order_id => Err(format!(
+            "event is for order 0x{}, the envelope for order 0x{}",
+            hex::encode(order_id),
+            hex::encode(issued)
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn hex_field(v: &Value, key: &str) -> Option<String> {
+    v.get(key)?.as_str().map(str::to_lowercase)
+}
+
+/// `(topics, data)` of every well-formed log emitted by `contract`.
+fn contract_logs<'a>(receipt: &'a Value, contract: &'a str) -> impl Iterator<Item = (Vec<Bytes32>, Vec<u8>)> + 'a {
+    receipt
+        .get("logs")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(move |log| hex_field(log, "address").as_deref() == Some(contract))
+        .filter_map(log_parts)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::abi::event_topic;
+    use crate::contracts::settlement::SETTLEMENT_COMPLETED_EVENT_SIG;
+    use crate::contracts::types::U256;
+    use crate::tgp::types::SettleSource;
+    use serde_json::json;
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000000cc";
+    const TX: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+    const ORDER: &str = "0x2222222222222222222222222222222222222222222222222222222222222222";
+
+    fn word(n: u64) -> String {
+        hex::encode(U256::from_u64(n).to_be_bytes())
+    }
+
+    /// Receipt of a successful `settle(ORDER)` on `chain_id`.
+    fn completed_receipt(chain_id: u64) -> Value {
+        let data: String = [chain_id, 0, 0, 1000, 0, 1_700_000_000, 7].iter().map(|n| word(*n)).collect();
+        json!({
+            "transactionHash": TX,
+            "status": "0x1",
+            "to": CONTRACT,
+            "logs": [{
+                "address": CONTRACT,
+                "topics": [
+                    format!("0x{}", hex::encode(event_topic(SETTLEMENT_COMPLETED_EVENT_SIG))),
+                    ORDER,
+                ],
+                "data": format!("0x{}", data),
+            }],
+        })
+    }
+
+    fn claim(final_status: &str) -> SettleMessage {
+        let mut settle = SettleMessage::terminal("settle-1", final_status, ORDER, "2025-11-18T15:04:00Z");
+        settle.source = Some(SettleSource::BuyerNotify);
+        settle.tx_hash = Some(TX.into());
+        settle
+    }
+
+    struct FixedReceipt(Option<Value>);
+
+    #[async_trait]
+    impl ReceiptProvider for FixedReceipt {
+        async fn chain_id(&self) -> Result<u64, String> {
+            Ok(1)
+        }
+        async fn tx_receipt(&self, _tx_hash: &str) -> Result<Option<Value>, String> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_receipt_checks() {
+        let contracts = vec![CONTRACT.to_string()];
+        let receipt = completed_receipt(1);
+        assert_eq!(check_receipt(&claim("complete"), &receipt, &contracts, None, 1), Ok(()));
+
+        // Wrong chain, wrong status claim, wrong order, wrong contract
+        assert!(check_receipt(&claim("complete"), &completed_receipt(10), &contracts, None, 1).is_err());
+        assert!(check_receipt(&claim("reverted"), &receipt, &contracts, None, 1).is_err());
+        assert!(check_receipt(&claim("refunded"), &receipt, &contracts, None, 1).is_err());
+        let mut other_order = claim("complete");
+        other_order.result.escrow_id = format!("0x{}", "33".repeat(32));
+        assert!(check_receipt(&other_order, &receipt, &contracts, None, 1).is_err());
+        let mut not_an_order = claim("complete");
+        not_an_order.result.escrow_id = "escrow-1".into();
+        assert!(check_receipt(&not_an_order, &receipt, &contracts, None, 1).is_err());
+        let other = vec!["0x00000000000000000000000000000000000000dd".to_string()];
+        assert!(check_receipt(&claim("complete"), &receipt, &other, None, 1).is_err());
+
+        // The event must come from the settlement contract itself
+        let mut spoofed = receipt.clone();
+        spoofed["logs"][0]["address"] = json!("0x00000000000000000000000000000000000000dd");
+        assert!(check_receipt(&claim("complete"), &spoofed, &contracts, None, 1).is_err());
+
+        // The event must be for the order the envelope was issued for
+        let issued: Bytes32 = hex_to_bytes32(ORDER).unwrap();
+        assert_eq!(check_receipt(&claim("complete"), &receipt, &contracts, Some(&issued), 1), Ok(()));
+        assert!(check_receipt(&claim("complete"), &receipt, &contracts, Some(&[0x33; 32]), 1).is_err());
+
+        let mut reverted = receipt;
+        reverted["status"] = json!("0x0");
+        assert_eq!(check_receipt(&claim("reverted"), &reverted, &contracts, None, 1), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_verify_every_source() {
+        let issued = IssuedEnvelopes::default();
+        let verifier = SettlementVerifier::new(Arc::new(FixedReceipt(Some(completed_receipt(1)))), 1)
+            .with_contract(CONTRACT);
+        assert_eq!(verifier.verify(&claim("complete"), &issued).await, Ok(()));
+
+        // A claimed watcher source earns no trust
+        let mut watcher = claim("bogus");
+        watcher.source = Some(SettleSource::ControllerWatcher);
+        assert!(verifier.verify(&watcher, &issued).await.is_err());
+
+        let mut no_hash = claim("complete");
+        no_hash.tx_hash = None;
+        let err = verifier.verify(&no_hash, &issued).await.unwrap_err();
+        assert_eq!(err.code(), TgpErrorCode::SettleUnverified);
+
+        let pending = SettlementVerifier::new(Arc::new(FixedReceipt(None)), 1).with_contract(CONTRACT);
+        let err = pending.verify(&claim("complete"), &issued).await.unwrap_err();
+        assert_eq!(err.code(), TgpErrorCode::ReceiptUnavailable);
+
+        let wrong_chain = SettlementVerifier::new(Arc::new(FixedReceipt(Some(completed_receipt(1)))), 10)
+            .with_contract(CONTRACT);
+        assert!(wrong_chain.verify(&claim("complete"), &issued).await.is_err());
+
+        // Nothing to check the target contract against
+        let unscoped = SettlementVerifier::new(Arc::new(FixedReceipt(Some(completed_receipt(1)))), 1);
+        let err = unscoped.verify(&claim("complete"), &issued).await.unwrap_err();
+        assert_eq!(err.code(), TgpErrorCode::SettleUnverified);
+    }
+}
@@ -0,0 +1,133 @@
+//! TGP-00 v3.2 -- Settlement Watch (settle_watch.rs)
+//! --------------------------------------------------
+//! Turns settlement contract logs into the SETTLE messages the gateway's
+//! own watcher reports (`SettleSource::ControllerWatcher`):
+//!
+//!   • `SettlementCompleted` → final_status `complete`
+//!   • `BuyerRefunded`       → final_status `refunded`
+//!   • `BuyerCommitted`      → final_status `committed`
+//!
+//! `escrow_id` is the order ID (bytes32 hex), `tx_hash` the emitting tx.
+//! The node polls for these logs; pairing a SETTLE with the QUERY that
+//! opened the order is left to whoever delivers it.
+
+use serde_json::Value;
+
+use crate::contracts::abi::event_topic;
+use crate::contracts::settlement::{
+    BuyerCommittedEvent, BuyerRefundedEvent, SettlementCompletedEvent,
+    BUYER_COMMITTED_EVENT_SIG, BUYER_REFUNDED_EVENT_SIG, SETTLEMENT_COMPLETED_EVENT_SIG,
+};
+use crate::contracts::types::{bytes32_to_hex, hex_to_bytes32, Bytes32};
+use crate::protocol::SettleMessage;
+use crate::tgp::state::make_settle_message;
+use crate::tgp::types::SettleSource;
+
+pub const STATUS_COMPLETE: &str = "complete";
+pub const STATUS_REFUNDED: &str = "refunded";
+pub const STATUS_COMMITTED: &str = "committed";
+
+/// Event signatures the watcher subscribes to.
+pub const WATCHED_EVENT_SIGS: [&str; 3] = [
+    SETTLEMENT_COMPLETED_EVENT_SIG,
+    BUYER_REFUNDED_EVENT_SIG,
+    BUYER_COMMITTED_EVENT_SIG,
+];
+
+/// `topics[0]` filter for `eth_getLogs` (any of the watched events).
+pub fn watched_topics() -> Vec<String> {
+    WATCHED_EVENT_SIGS.iter().map(|sig| bytes32_to_hex(&event_topic(sig))).collect()
+}
+
+/// `(topics, data)` of a JSON-RPC log object; `None` if malformed.
+pub fn log_parts(log: &Value) -> Option<(Vec<Bytes32>, Vec<u8>)> {
+    let topics = log
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().and_then(|t| hex_to_bytes32(t).ok()))
+        .collect::<Option<Vec<_>>>()?;
+    let data = log.get("data")?.as_str()?;
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(data)).ok()?;
+    Some((topics, data))
+}
+
+/// Watcher SETTLE for a settlement contract log, `None` for other logs.
+pub fn settle_from_log(log: &Value) -> Option<SettleMessage> {
+    let (topics, data) = log_parts(log)?;
+
+    let (final_status, order_id) = if let Some(e) = SettlementCompletedEvent::from_log(&topics, &data) {
+        (STATUS_COMPLETE, e.order_id)
+    } else if let Some(e) = BuyerRefundedEvent::from_log(&topics, &data) {
+        (STATUS_REFUNDED, e.order_id)
+    } else if let Some(e) = BuyerCommittedEvent::from_log(&topics, &data) {
+        (STATUS_COMMITTED, e.order_id)
+    } else {
+        return None;
+    };
+
+    let tx_hash = log.get("transactionHash").and_then(Value::as_str).map(str::to_lowercase);
+    let log_index = log.get("logIndex").and_then(Value::as_str).unwrap_or("0x0");
+
+    // Deterministic ID: the same log always yields the same SETTLE
+    let id = format!("settle-{}-{}", tx_hash.as_deref().unwrap_or("unknown"), log_index);
+
+    let mut settle = make_settle_message(id, final_status, bytes32_to_hex(&order_id));
+    settle.source = Some(SettleSource::ControllerWatcher);
+    settle.tx_hash = tx_hash;
+    Some(settle)
+}
+
+/// True once no further SETTLE is expected for the order.
+pub fn is_final(settle: &SettleMessage) -> bool {
+    settle.result.final_status != STATUS_COMMITTED
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::types::U256;
+    use serde_json::json;
+
+    const ORDER: &str = "0x2222222222222222222222222222222222222222222222222222222222222222";
+    const TX: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+    fn log(sig: &str, words: &[u64]) -> Value {
+        let data: String = words.iter().map(|n| hex::encode(U256::from_u64(*n).to_be_bytes())).collect();
+        json!({
+            "address": "0x00000000000000000000000000000000000000cc",
+            "topics": [bytes32_to_hex(&event_topic(sig)), ORDER],
+            "data": format!("0x{}", data),
+            "transactionHash": TX,
+            "logIndex": "0x3",
+        })
+    }
+
+    #[test]
+    fn test_settle_from_logs() {
+        let done = settle_from_log(&log(SETTLEMENT_COMPLETED_EVENT_SIG, &[369, 0, 0, 1000, 0, 1, 7])).unwrap();
+        assert_eq!(done.result.final_status, STATUS_COMPLETE);
+        assert_eq!(done.result.escrow_id, ORDER);
+        assert_eq!(done.source, Some(SettleSource::ControllerWatcher));
+        assert_eq!(done.tx_hash.as_deref(), Some(TX));
+        assert_eq!(done.id, format!("settle-{}-0x3", TX));
+        assert!(done.validate().is_ok());
+        assert!(is_final(&done));
+
+        let refunded = settle_from_log(&log(BUYER_REFUNDED_EVENT_SIG, &[0, 1000, 0, 1])).unwrap();
+        assert_eq!(refunded.result.final_status, STATUS_REFUNDED);
+
+        let committed = settle_from_log(&log(BUYER_COMMITTED_EVENT_SIG, &[0, 0, 1000, 0, 1])).unwrap();
+        assert_eq!(committed.result.final_status, STATUS_COMMITTED);
+        assert!(!is_final(&committed));
+
+        // Wrong data length, unrelated event
+        assert!(settle_from_log(&log(BUYER_REFUNDED_EVENT_SIG, &[0, 1000])).is_none());
+        assert!(settle_from_log(&log("Transfer(address,address,uint256)", &[1])).is_none());
+        assert_eq!(watched_topics().len(), 3);
+    }
+}
@@ -8,35 +8,32 @@
 //!   • Deterministic terminal SETTLE message generation
 //!   • Layered Verification Model (L1–L6) dispatcher
 //!   • Economic Envelope generation (delegates to tx_builder)
+//!   • QUOTE previews: dry-run L1–L5 → ACK(offer), never a tx (quote.rs)
 //!   • WITHDRAW eligibility determination
 //!   • Fail-closed behavior
 //!
 //! Stateless by design -- all state derived from QUERY payload and blockchain.
 
-use chrono::{Utc};
-use crate::tgp::protocol::{
+use chrono::Utc;
+
+use crate::protocol::{
     QueryMessage,
-    AckMessage, AckStatus,
-    ErrorMessage, make_protocol_error,
+    TGPVerb,
+    AckMessage,
+    ErrorMessage, make_protocol_error, TgpErrorCode,
     SettleMessage,
 };
-use crate::tgp::types::{EconomicEnvelope};
+use crate::tgp::types::EconomicEnvelope;
 use crate::tgp::validation::{
     validate_payment_profile,
     validate_chain_id,
     validate_amount_nonzero,
 };
 
-use crate::tgp::layers::{
-    layer1_registry_check,
-    layer2_cryptographic_check,
-    layer3_contract_rpc_check,
-    layer4_zk_attestation_check,
-    layer5_policy_check,
-    layer6_withdraw_eligibility,
-};
+use crate::tgp::layers::{LayerFailure, LayerPipeline, PolicyVerdict};
 
-use crate::tgp::tx_builder::build_envelope_for;
+use crate::tgp::quote::build_quote;
+use crate::tgp::tx_builder::{build_envelope_with_proof, EnvelopeParams};
 
 // -----------------------------------------------------------------------------
 // 0. Result Type
@@ -53,9 +50,17 @@ pub enum TGPStateResult {
 // 1. QUERY Entry Point
 // -----------------------------------------------------------------------------
 
-/// Main entry point for all inbound QUERY messages.
-/// Equivalent to "Server Transaction Processing" in SIP.
-pub async fn handle_query(
+/// Main entry point for all inbound QUERY messages, using the stock L1–L6
+/// pipeline. Equivalent to "Server Transaction Processing" in SIP.
+pub async fn handle_query(query: QueryMessage) -> TGPStateResult {
+    handle_query_with(&LayerPipeline::default(), &EnvelopeParams::default(), query).await
+}
+
+/// Same as [`handle_query`] with a caller-supplied layer pipeline and
+/// gateway envelope parameters.
+pub async fn handle_query_with(
+    layers: &LayerPipeline,
+    params: &EnvelopeParams,
     mut query: QueryMessage,
 ) -> TGPStateResult {
 
@@ -69,78 +74,80 @@ pub async fn handle_query(
     // ---------------------------------------------------------
     if let Err(e) = validate_chain_id(query.chain_id) {
         return TGPStateResult::Error(make_protocol_error(
-            1, "TGP_CHAIN_INVALID", e,
-        ));
+            TgpErrorCode::ChainInvalid, e,
+        ).with_correlation_id(&query.id));
     }
 
     if let Err(e) = validate_payment_profile(&query.payment_profile) {
         return TGPStateResult::Error(make_protocol_error(
-            1, "TGP_PROFILE_INVALID", e,
-        ));
+            TgpErrorCode::ProfileInvalid, e,
+        ).with_correlation_id(&query.id));
     }
 
     if let Err(e) = validate_amount_nonzero(query.amount) {
         return TGPStateResult::Error(make_protocol_error(
-            1, "TGP_AMOUNT_ZERO", e,
-        ));
+            TgpErrorCode::AmountZero, e,
+        ).with_correlation_id(&query.id));
     }
 
     // ---------------------------------------------------------
-    // Begin Layered Verification Model (L1–L6)
+    // QUOTE: dry-run preview, never an executable envelope
     // ---------------------------------------------------------
-
-    // L1 -- Registry / Merchant Profile
-    if let Err(reason) = layer1_registry_check(&query).await {
-        return TGPStateResult::Error(make_protocol_error(
-            1, "TGP_L1_FAILURE", reason
-        ));
-    }
-
-    // L2 -- Key/Signature/Delegated-key checks
-    if let Err(reason) = layer2_cryptographic_check(&query).await {
-        return TGPStateResult::Error(make_protocol_error(
-            2, "TGP_L2_FAILURE", reason
-        ));
+    if query.intent.verb == TGPVerb::QUOTE {
+        return handle_quote(layers, params, query).await;
     }
 
-    // L3 -- Contract bytecode & chain RPC validation
-    if let Err(reason) = layer3_contract_rpc_check(&query).await {
-        return TGPStateResult::Error(make_protocol_error(
-            3, "TGP_L3_FAILURE", reason
-        ));
-    }
+    // ---------------------------------------------------------
+    // Layered Verification Model (L1–L6)
+    //   L1 -- Registry / Merchant Profile
+    //   L2 -- Key/Signature/Delegated-key checks
+    //   L3 -- Contract bytecode & chain RPC validation
+    //   L4 -- Optional ZK / Attestation
+    //   L5 -- Policy evaluation (merchant rules, fees, limits)
+    //   L6 -- WITHDRAW eligibility (only if requested)
+    //   Soft L5 verdicts answer with ACK(deny|revise), never ERROR.
+    //   Layers then commit (L4 burns the ZK nullifier) and are
+    //   rolled back if no envelope can be built.
+    // ---------------------------------------------------------
+    let outputs = match layers.run(&query).await {
+        Ok(outputs) => outputs,
+        Err(failure) => return layer_rejection(&query, failure),
+    };
 
-    // L4 -- Optional ZK / Attestation
-    if let Err(reason) = layer4_zk_attestation_check(&query).await {
-        return TGPStateResult::Error(make_protocol_error(
-            4, "TGP_L4_FAILURE", reason
-        ));
+    // ---------------------------------------------------------
+    // All Layers Passed → Build Envelope
+    // ---------------------------------------------------------
+    match build_envelope_with_proof(params, &query, outputs.zk.as_ref()).await {
+        Ok(envelope) => finalize_ack_allow(query, envelope, params),
+        Err(reason) => {
+            layers.rollback(&query).await;
+            TGPStateResult::Error(make_protocol_error(
+                TgpErrorCode::EnvelopeFailure, reason
+            ).with_correlation_id(&query.id))
+        }
     }
+}
 
-    // L5 -- Policy evaluation (merchant rules, fees, limits)
-    if let Err(reason) = layer5_policy_check(&query).await {
-        return TGPStateResult::Error(make_protocol_error(
-            5, "TGP_L5_FAILURE", reason
-        ));
+/// QUOTE → dry-run L1–L5 → ACK(offer) with fee breakdown, or ACK(deny)
+/// when the merchant's fees cannot be priced.
+async fn handle_quote(
+    layers: &LayerPipeline,
+    params: &EnvelopeParams,
+    query: QueryMessage,
+) -> TGPStateResult {
+    if let Err(failure) = layers.dry_run(&query).await {
+        return layer_rejection(&query, failure);
     }
 
-    // L6 -- WITHDRAW eligibility (only if requested)
-    if matches!(query.intent.verb, crate::tgp::protocol::TGPVerb::WITHDRAW) {
-        if let Err(reason) = layer6_withdraw_eligibility(&query).await {
-            return TGPStateResult::Error(make_protocol_error(
-                6, "TGP_L6_WITHDRAW_FAILURE", reason
-            ));
+    match build_quote(params, &query) {
+        Ok(quote) => {
+            let expires = params
+                .lifetimes
+                .expires_at(&query.payment_profile, Utc::now())
+                .to_rfc3339();
+            TGPStateResult::Ack(AckMessage::quote_for(&query, quote, expires))
         }
-    }
-
-    // ---------------------------------------------------------
-    // All Layers Passed → Build Envelope
-    // ---------------------------------------------------------
-    match build_envelope_for(&query).await {
-        Ok(envelope) => finalize_ack_allow(query, envelope),
-        Err(reason) => TGPStateResult::Error(make_protocol_error(
-            5, "TGP_ENVELOPE_FAILURE", reason
-        )),
+        Err(reason) => TGPStateResult::Ack(AckMessage::deny_for(&query, reason)),
     }
 }
 
@@ -148,15 +155,33 @@ pub async fn handle_query(
 // 2. ACK Construction Helpers
 // -----------------------------------------------------------------------------
 
+/// Soft verdicts become ACK(deny|revise); anything else fails closed.
+fn layer_rejection(query: &QueryMessage, failure: LayerFailure) -> TGPStateResult {
+    match failure.verdict {
+        Some(PolicyVerdict::Deny(reason)) => {
+            TGPStateResult::Ack(AckMessage::deny_for(query, reason))
+        }
+        Some(PolicyVerdict::Revise(reason)) => {
+            TGPStateResult::Ack(AckMessage::revise_for(query, reason))
+        }
+        None => TGPStateResult::Error(make_protocol_error(
+            failure.code, failure.reason,
+        ).with_correlation_id(&query.id)),
+    }
+}
+
 fn finalize_ack_allow(
     query: QueryMessage,
-    envelope: EconomicEnvelope,
+    mut envelope: EconomicEnvelope,
+    params: &EnvelopeParams,
 ) -> TGPStateResult {
 
-    let expires = Utc::now()
-        .checked_add_signed(chrono::Duration::minutes(5))
-        .unwrap()
+    // ACK and envelope expire together
+    let expires = params
+        .lifetimes
+        .expires_at(&query.payment_profile, Utc::now())
         .to_rfc3339();
+    envelope.expiry = Some(expires.clone());
 
     TGPStateResult::Ack(
         AckMessage::allow_for(&query, envelope, expires)
@@ -174,11 +199,11 @@ pub fn ack_offer(query: &QueryMessage) -> AckMessage {
 // -----------------------------------------------------------------------------
 
 /// The state engine produces SETTLE messages when:
-//!   • Settlement contract reached final status
-//!   • Timeout fired
-//!   • Refund triggered
-//!   • Withdraw processed
-//!   • RPC indicates revert
+///   • Settlement contract reached final status
+///   • Timeout fired
+///   • Refund triggered
+///   • Withdraw processed
+///   • RPC indicates revert
 pub fn make_settle_message(
     id: impl Into<String>,
     final_status: impl Into<String>,
@@ -191,4 +216,94 @@ pub fn make_settle_message(
         escrow_id.into(),
         Utc::now().to_rfc3339(),
     )
-}
\ No newline at end of file
+}
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{AckStatus, ConstraintOp, TGPParty};
+    use crate::tgp::layers::tests::sample_query;
+    use crate::tgp::layers::PolicyLayer;
+    use crate::tgp::quote::tests::sample_merchant;
+
+    #[tokio::test]
+    async fn test_valid_query_yields_allow() {
+        match handle_query(sample_query()).await {
+            TGPStateResult::Ack(ack) => {
+                assert_eq!(ack.status, AckStatus::Allow);
+                assert!(ack.tx.is_some());
+                assert!(ack.expires_at.is_some());
+                assert_eq!(ack.tx.as_ref().unwrap().expiry, ack.expires_at);
+                assert!(ack.validate().is_ok());
+            }
+            other => panic!("expected ACK, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seller_withdraw_fails_at_l6() {
+        let mut q = sample_query();
+        q.intent.verb = TGPVerb::WITHDRAW;
+        q.intent.party = TGPParty::SELLER;
+
+        match handle_query(q).await {
+            TGPStateResult::Error(err) => {
+                assert_eq!(err.layer_failed, 6);
+                assert_eq!(err.code, "TGP_L6_WITHDRAW_FAILURE");
+            }
+            other => panic!("expected ERROR, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_soft_policy_failure_yields_revise() {
+        let layers = LayerPipeline::default().with_layer(PolicyLayer::new().with_max_amount(500));
+        let q = sample_query();
+
+        match handle_query_with(&layers, &EnvelopeParams::default(), q.clone()).await {
+            TGPStateResult::Ack(ack) => {
+                assert_eq!(ack.status, AckStatus::Revise);
+                assert_eq!(ack.correlation_id.as_deref(), Some(q.id.as_str()));
+                assert!(ack.tx.is_none());
+
+                let reason = ack.reason.as_ref().unwrap();
+                assert_eq!(reason.code, "POLICY_AMOUNT_LIMIT");
+                assert_eq!(reason.constraints[0].field, "amount");
+                assert_eq!(reason.constraints[0].op, ConstraintOp::Max);
+                assert_eq!(reason.constraints[0].value, 500);
+                assert!(ack.validate().is_ok());
+            }
+            other => panic!("expected ACK(revise), got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_yields_offer_without_tx() {
+        let mut q = sample_query();
+        q.intent.verb = TGPVerb::QUOTE;
+        let params = EnvelopeParams::new().with_merchant_fees(&q.payment_profile, sample_merchant());
+
+        match handle_query_with(&LayerPipeline::default(), &params, q.clone()).await {
+            TGPStateResult::Ack(ack) => {
+                assert_eq!(ack.status, AckStatus::Offer);
+                assert!(ack.tx.is_none());
+                assert!(ack.expires_at.is_some());
+                assert_eq!(ack.quote.as_ref().unwrap().tbc_fee.amount, "10");
+                assert!(ack.validate().is_ok());
+            }
+            other => panic!("expected ACK(offer), got {:?}", other),
+        }
+
+        // Unknown merchant: denied with a reason, still no tx
+        match handle_query_with(&LayerPipeline::default(), &EnvelopeParams::default(), q).await {
+            TGPStateResult::Ack(ack) => {
+                assert_eq!(ack.status, AckStatus::Deny);
+                assert_eq!(ack.reason.unwrap().code, "QUOTE_NO_FEE_SCHEDULE");
+            }
+            other => panic!("expected ACK(deny), got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,478 @@
+//! TGP-00 v3.2 -- Transaction Area Registry (tai.rs)
+//! --------------------------------------------------
+//! Transaction Areas (Appendix A) known to this gateway:
+//!
+//!   • which areas are served here (`local = true`)
+//!   • the chains each area settles on and, optionally, its merchant set
+//!   • the peer gateways through which a remote area is reached
+//!
+//! The registry is loaded from TOML at startup, edited at runtime by admin
+//! commands and consulted by the router for every QUERY that names a
+//! `routing.transaction_area_id`: area IDs are normalized, checked against
+//! the registration and the QUERY is then routed as by `RoutingTable`.
+//!
+//! ```toml
+//! local_area = "tai:keccak256:7abf…"
+//! max_hops = 8
+//! min_trust = "low"
+//!
+//! [[areas]]
+//! tai = "tai:keccak256:7abf…"
+//! name = "Reflexive-Insurance-CA"
+//! local = true
+//! chain_ids = [369]
+//! merchants = ["0x1111…"]
+//!
+//! [[areas]]
+//! tai = "tai:keccak256:8bde…"
+//! chain_ids = [1]
+//! peers = [{ url = "https://us.example/tgp", trust = "high" }]
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::QueryMessage;
+use crate::tgp::routing::{
+    PeerGateway, RouteDecision, RouteResolver, RoutingError, RoutingTable, DEFAULT_MAX_HOPS,
+};
+use crate::tgp::types::DomainTrust;
+use crate::tgp::validation::validate_address;
+
+/// Prefix of a canonical TAI (Appendix A.3).
+pub const CANONICAL_TAI_PREFIX: &str = "tai:keccak256:";
+
+/// Longest accepted area identifier.
+pub const MAX_TAI_LEN: usize = 128;
+
+// -----------------------------------------------------------------------------
+// 1. Normalization
+// -----------------------------------------------------------------------------
+
+/// Normalize an area identifier.
+///
+/// `tai:` URIs are case-insensitive and lowercased; a canonical
+/// `tai:keccak256:` ID must carry a 32-byte hex hash. Other operator-assigned
+/// IDs (e.g. `TAID-EU`) are kept as given but limited to `[A-Za-z0-9:._-]`.
+pub fn normalize_tai(raw: &str) -> Result<String, String> {
+    let tai = raw.trim();
+    if tai.is_empty() {
+        return Err("transaction area ID must not be empty".into());
+    }
+    if tai.len() > MAX_TAI_LEN {
+        return Err(format!("transaction area ID longer than {} chars", MAX_TAI_LEN));
+    }
+    if !tai.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '.' | '_' | '-')) {
+        return Err(format!("transaction area ID has invalid characters: {}", tai));
+    }
+
+    if !tai.to_ascii_lowercase().starts_with("tai:") {
+        return Ok(tai.to_string());
+    }
+    let tai = tai.to_ascii_lowercase();
+    if let Some(hash) = tai.strip_prefix(CANONICAL_TAI_PREFIX) {
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("canonical TAI must carry 64 hex chars: {}", tai));
+        }
+    }
+    Ok(tai)
+}
+
+// -----------------------------------------------------------------------------
+// 2. Transaction Areas
+// -----------------------------------------------------------------------------
+
+/// A peer gateway serving one area.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AreaPeer {
+    pub url: String,
+
+    #[serde(default = "default_peer_trust")]
+    pub trust: DomainTrust,
+}
+
+fn default_peer_trust() -> DomainTrust {
+    DomainTrust::Low
+}
+
+/// One registered transaction area.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionArea {
+    pub tai: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Served by this gateway
+    #[serde(default)]
+    pub local: bool,
+
+    /// Chains the area settles on (empty = any)
+    #[serde(default)]
+    pub chain_ids: Vec<u64>,
+
+    /// Payment profiles registered in the area (empty = any)
+    #[serde(default)]
+    pub merchants: Vec<String>,
+
+    /// Gateways reaching a remote area
+    #[serde(default)]
+    pub peers: Vec<AreaPeer>,
+}
+
+impl TransactionArea {
+    pub fn new(tai: impl Into<String>) -> Self {
+        Self {
+            tai: tai.into(),
+            name: None,
+            local: false,
+            chain_ids: Vec::new(),
+            merchants: Vec::new(),
+            peers: Vec::new(),
+        }
+    }
+
+    /// Mark the area as served by this gateway.
+    pub fn local(mut self) -> Self {
+        self.local = true;
+        self
+    }
+
+    pub fn with_chain(mut self, chain_id: u64) -> Self {
+        self.chain_ids.push(chain_id);
+        self
+    }
+
+    pub fn with_merchant(mut self, payment_profile: impl Into<String>) -> Self {
+        self.merchants.push(payment_profile.into());
+        self
+    }
+
+    pub fn with_peer(mut self, url: impl Into<String>, trust: DomainTrust) -> Self {
+        self.peers.push(AreaPeer { url: url.into(), trust });
+        self
+    }
+
+    /// Validate and normalize in place (TAI, lower-cased merchants).
+    pub fn normalize(&mut self) -> Result<(), String> {
+        self.tai = normalize_tai(&self.tai)?;
+
+        for merchant in self.merchants.iter_mut() {
+            validate_address(merchant, "merchants[]")?;
+            *merchant = merchant.to_lowercase();
+        }
+        for peer in &self.peers {
+            let scheme = peer.url.split_once("://").map(|(s, _)| s);
+            if !matches!(scheme, Some("http" | "https" | "ws" | "wss")) {
+                return Err(format!("peer URL must be http(s) or ws(s): {}", peer.url));
+            }
+        }
+        if self.local && !self.peers.is_empty() {
+            return Err(format!("local area {} must not list peers", self.tai));
+        }
+        Ok(())
+    }
+
+    fn check_query(&self, query: &QueryMessage) -> Result<(), String> {
+        if !self.chain_ids.is_empty() && !self.chain_ids.contains(&query.chain_id) {
+            return Err(format!("chain_id {} is not settled in this area", query.chain_id));
+        }
+        let profile = query.payment_profile.to_lowercase();
+        if !self.merchants.is_empty() && !self.merchants.contains(&profile) {
+            return Err(format!("payment_profile {} is not registered in this area", profile));
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 3. Registry
+// -----------------------------------------------------------------------------
+
+/// On-disk (TOML) form of the registry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaiRegistryFile {
+    pub local_area: String,
+
+    #[serde(default = "default_max_hops")]
+    pub max_hops: usize,
+
+    #[serde(default = "default_peer_trust")]
+    pub min_trust: DomainTrust,
+
+    #[serde(default)]
+    pub areas: Vec<TransactionArea>,
+}
+
+fn default_max_hops() -> usize {
+    DEFAULT_MAX_HOPS
+}
+
+#[derive(Debug)]
+struct RegistryState {
+    local_area: String,
+    max_hops: usize,
+    min_trust: DomainTrust,
+    areas: HashMap<String, TransactionArea>,
+}
+
+/// Live, shareable TAI registry.
+#[derive(Debug)]
+pub struct TaiRegistry {
+    inner: RwLock<RegistryState>,
+}
+
+impl TaiRegistry {
+    /// Empty registry for this gateway's own area.
+    pub fn new(local_area: &str) -> Result<Self, String> {
+        Self::from_file(TaiRegistryFile {
+            local_area: local_area.to_string(),
+            max_hops: DEFAULT_MAX_HOPS,
+            min_trust: DomainTrust::Low,
+            areas: Vec::new(),
+        })
+    }
+
+    pub fn from_toml_str(raw: &str) -> Result<Self, String> {
+        let file: TaiRegistryFile = toml::from_str(raw).map_err(|e| format!("TAI registry: {}", e))?;
+        Self::from_file(file)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::from_toml_str(&read_file(path.as_ref())?)
+    }
+
+    /// Replace the whole registry with the contents of `path`.
+    /// On error the current registry is kept.
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let fresh = Self::load(path)?.inner.into_inner().unwrap();
+        let count = fresh.areas.len();
+        *self.inner.write().unwrap() = fresh;
+        Ok(count)
+    }
+
+    fn from_file(file: TaiRegistryFile) -> Result<Self, String> {
+        let mut areas = HashMap::new();
+        for mut area in file.areas {
+            area.normalize()?;
+            if areas.contains_key(&area.tai) {
+                return Err(format!("TAI registry: duplicate area {}", area.tai));
+            }
+            areas.insert(area.tai.clone(), area);
+        }
+
+        Ok(Self {
+            inner: RwLock::new(RegistryState {
+                local_area: normalize_tai(&file.local_area)?,
+                max_hops: file.max_hops,
+                min_trust: file.min_trust,
+                areas,
+            }),
+        })
+    }
+
+    pub fn with_max_hops(mut self, max_hops: usize) -> Self {
+        self.inner.get_mut().unwrap().max_hops = max_hops;
+        self
+    }
+
+    /// Never forward to peers trusted less than `trust`.
+    pub fn with_min_trust(mut self, trust: DomainTrust) -> Self {
+        self.inner.get_mut().unwrap().min_trust = trust;
+        self
+    }
+
+    pub fn local_area(&self) -> String {
+        self.inner.read().unwrap().local_area.clone()
+    }
+
+    /// Areas handled on this gateway: `local_area` and every area
+    /// registered as `local`, normalized and ordered by TAI.
+    pub fn served_areas(&self) -> Vec<String> {
+        let state = self.inner.read().unwrap();
+        let mut served: Vec<String> = state.areas.values().filter(|a| a.local).map(|a| a.tai.clone()).collect();
+        if !served.contains(&state.local_area) {
+            served.push(state.local_area.clone());
+        }
+        served.sort();
+        served
+    }
+
+    /// Add or replace an area; returns the previous registration.
+    pub fn upsert(&self, mut area: TransactionArea) -> Result<Option<TransactionArea>, String> {
+        area.normalize()?;
+        Ok(self.inner.write().unwrap().areas.insert(area.tai.clone(), area))
+    }
+
+    pub fn remove(&self, tai: &str) -> Option<TransactionArea> {
+        let tai = normalize_tai(tai).ok()?;
+        self.inner.write().unwrap().areas.remove(&tai)
+    }
+
+    pub fn get(&self, tai: &str) -> Option<TransactionArea> {
+        let tai = normalize_tai(tai).ok()?;
+        self.inner.read().unwrap().areas.get(&tai).cloned()
+    }
+
+    /// All areas, ordered by TAI.
+    pub fn areas(&self) -> Vec<TransactionArea> {
+        let mut areas: Vec<_> = self.inner.read().unwrap().areas.values().cloned().collect();
+        areas.sort_by(|a, b| a.tai.cmp(&b.tai));
+        areas
+    }
+
+    /// Routing table for the current registrations. A peer URL listed under
+    /// several remote areas becomes one peer reaching all of them.
+    pub fn routing_table(&self) -> RoutingTable {
+        let state = self.inner.read().unwrap();
+
+        let mut table = RoutingTable::new(&state.local_area)
+            .with_max_hops(state.max_hops)
+            .with_min_trust(state.min_trust);
+        let mut peers: Vec<PeerGateway> = Vec::new();
+
+        let mut areas: Vec<_> = state.areas.values().collect();
+        areas.sort_by(|a, b| a.tai.cmp(&b.tai));
+        for area in areas {
+            if area.local {
+                table = table.with_served_area(&area.tai);
+                continue;
+            }
+            for peer in &area.peers {
+                match peers.iter_mut().find(|p| p.url == peer.url) {
+                    Some(existing) => existing.routes.push(area.tai.clone()),
+                    None => peers.push(PeerGateway::new(&area.tai, &peer.url, peer.trust)),
+                }
+            }
+        }
+
+        peers.into_iter().fold(table, RoutingTable::with_peer)
+    }
+
+    /// Normalize `routing` and check it against the target area's
+    /// registration (chains, merchants).
+    fn normalized(&self, query: &QueryMessage) -> Result<QueryMessage, RoutingError> {
+        let invalid = |area: &str, reason: String| RoutingError::Invalid { area: area.to_string(), reason };
+        let mut q = query.clone();
+
+        if let Some(ref raw) = query.routing.transaction_area_id {
+            let tai = normalize_tai(raw).map_err(|e| invalid(raw, e))?;
+            if let Some(area) = self.inner.read().unwrap().areas.get(&tai) {
+                area.check_query(query).map_err(|e| invalid(&tai, e))?;
+            }
+            q.routing.transaction_area_id = Some(tai);
+        }
+        q.routing.path = query
+            .routing
+            .path
+            .iter()
+            .map(|hop| normalize_tai(hop).map_err(|e| invalid(hop, format!("routing.path: {}", e))))
+            .collect::<Result<_, _>>()?;
+
+        Ok(q)
+    }
+}
+
+impl RouteResolver for TaiRegistry {
+    fn route(&self, query: &QueryMessage) -> Result<RouteDecision, RoutingError> {
+        let normalized = self.normalized(query)?;
+        self.routing_table().route(&normalized)
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("TAI registry {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TgpErrorCode;
+    use crate::tgp::layers::tests::sample_query;
+
+    const EU: &str = "tai:keccak256:7abf92c600000000000000000000000000000000000000000000000000000001";
+    const US: &str = "tai:keccak256:8bde441100000000000000000000000000000000000000000000000000000002";
+
+    fn registry_toml() -> String {
+        let profile = sample_query().payment_profile;
+        format!(
+            r#"
+            local_area = "{EU}"
+            max_hops = 4
+
+            [[areas]]
+            tai = "{EU}"
+            local = true
+            chain_ids = [369]
+            merchants = ["{profile}"]
+
+            [[areas]]
+            tai = "{US}"
+            chain_ids = [1, 369]
+            peers = [{{ url = "https://us.example/tgp", trust = "high" }}]
+            "#
+        )
+    }
+
+    fn query_to(area: &str) -> QueryMessage {
+        let mut q = sample_query();
+        q.routing.transaction_area_id = Some(area.into());
+        q
+    }
+
+    #[test]
+    fn test_normalize_tai() {
+        assert_eq!(normalize_tai(&format!("  {}", EU.to_uppercase())).unwrap(), EU);
+        assert_eq!(normalize_tai("TAID-EU").unwrap(), "TAID-EU");
+        assert!(normalize_tai("tai:keccak256:abc").is_err());
+        assert!(normalize_tai("area with spaces").is_err());
+        assert!(normalize_tai("").is_err());
+    }
+
+    #[test]
+    fn test_registry_routes_from_toml() {
+        let registry = TaiRegistry::from_toml_str(&registry_toml()).unwrap();
+        assert_eq!(registry.local_area(), EU);
+
+        assert_eq!(registry.route(&query_to(&EU.to_uppercase())).unwrap(), RouteDecision::Local);
+
+        let RouteDecision::Forward { query, peers } = registry.route(&query_to(US)).unwrap() else {
+            panic!("expected forward");
+        };
+        assert_eq!(peers[0].url, "https://us.example/tgp");
+        assert_eq!(query.routing.path, vec![EU.to_string()]);
+    }
+
+    #[test]
+    fn test_registration_checked() {
+        let registry = TaiRegistry::from_toml_str(&registry_toml()).unwrap();
+
+        let mut wrong_chain = query_to(EU);
+        wrong_chain.chain_id = 1;
+        assert_eq!(registry.route(&wrong_chain).unwrap_err().code(), TgpErrorCode::InvalidMessage);
+
+        let mut stranger = query_to(EU);
+        stranger.payment_profile = "0x9999999999999999999999999999999999999999".into();
+        assert!(matches!(registry.route(&stranger), Err(RoutingError::Invalid { .. })));
+
+        assert!(matches!(registry.route(&query_to("bad area")), Err(RoutingError::Invalid { .. })));
+    }
+
+    #[test]
+    fn test_upsert_and_remove() {
+        let registry = TaiRegistry::new(EU).unwrap();
+        assert_eq!(registry.route(&query_to(US)).unwrap_err().code(), TgpErrorCode::NoRoute);
+
+        registry
+            .upsert(TransactionArea::new(US).with_peer("wss://us.example/tgp/ws", DomainTrust::Medium))
+            .unwrap();
+        assert!(matches!(registry.route(&query_to(US)), Ok(RouteDecision::Forward { .. })));
+
+        assert!(registry.upsert(TransactionArea::new(US).with_peer("ftp://x", DomainTrust::High)).is_err());
+        assert!(registry.remove(US).is_some());
+        assert_eq!(registry.route(&query_to(US)).unwrap_err().code(), TgpErrorCode::NoRoute);
+    }
+}
@@ -0,0 +1,505 @@
+//! TGP-00 v3.2 -- Economic Envelope Builder (tx_builder.rs)
+//! --------------------------------------------------
+//! Builds the Economic Envelope (§7, TGP-01 §4) attached to ACK(status=allow).
+//!
+//! Invoked by the state engine only after L1–L6 have passed.
+//! Pure and deterministic: the same QUERY and parameters always yield the
+//! same envelope. Expiry is stamped separately by the state engine.
+//!
+//! Calldata is ABI-encoded offline from the QUERY intent:
+//!
+//!   COMMIT/PAY (BUYER) → buyerCommit
+//!   COMMIT (SELLER)    → sellerCommit
+//!   CLAIM              → settle
+//!   WITHDRAW (BUYER)   → buyerCancelExpiredCommit
+//!
+//! Call arguments come from QUERY `metadata` (`order_id`, `asset`,
+//! `pk_hash`, `nullifier`, `timestamp`, `zk_proof`). Only a buyerCommit may
+//! omit `order_id`; it then defaults to `keccak256(query.id)`. A proof
+//! verified by L4 replaces `pk_hash`, `nullifier`, `timestamp` and
+//! `zk_proof` with its contract-safe outputs (TGP-EXT-ZK-00 §6).
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::contracts::{
+    hex_to_address, hex_to_bytes32, is_native_eth, Address, Bytes32, BuyerCancelParams,
+    BuyerCommitParams, DeployMerchantParams, SellerCommitParams, SettleParams, NATIVE_ETH, U256,
+};
+use crate::protocol::{QueryMessage, TGPParty, TGPVerb};
+use crate::tgp::canonical::keccak256;
+use crate::tgp::expiry::EnvelopeLifetimes;
+use crate::tgp::types::{EconomicEnvelope, FeeLine, EE_VERSION};
+use crate::zk::ContractSafeProof;
+
+/// Gateway fee ceiling advertised in every envelope (basis points).
+pub const DEFAULT_MAX_FEES_BPS: u32 = 100;
+
+/// Gas limit for escrow contract calls.
+pub const DEFAULT_GAS_LIMIT: u64 = 250_000;
+
+/// Gateway-side inputs to the envelope that do not come from the QUERY.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvelopeParams {
+    pub gas_limit: u64,
+
+    /// Routing fee charged by this gateway (basis points of the amount)
+    pub fees_bps: u32,
+    pub max_fees_bps: u32,
+
+    pub rpc_url: Option<String>,
+    pub tbc_endpoint: Option<String>,
+
+    pub lifetimes: EnvelopeLifetimes,
+
+    /// Merchant contract fee schedules by payment profile (lowercased),
+    /// used to price QUOTE previews
+    pub merchant_fees: HashMap<String, DeployMerchantParams>,
+}
+
+impl EnvelopeParams {
+    pub fn new() -> Self {
+        Self {
+            gas_limit: DEFAULT_GAS_LIMIT,
+            fees_bps: 0,
+            max_fees_bps: DEFAULT_MAX_FEES_BPS,
+            rpc_url: None,
+            tbc_endpoint: None,
+            lifetimes: EnvelopeLifetimes::default(),
+            merchant_fees: HashMap::new(),
+        }
+    }
+
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn with_fees_bps(mut self, fees_bps: u32) -> Self {
+        self.fees_bps = fees_bps;
+        self
+    }
+
+    pub fn with_max_fees_bps(mut self, max_fees_bps: u32) -> Self {
+        self.max_fees_bps = max_fees_bps;
+        self
+    }
+
+    pub fn with_rpc_url(mut self, url: impl Into<String>) -> Self {
+        self.rpc_url = Some(url.into());
+        self
+    }
+
+    pub fn with_tbc_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.tbc_endpoint = Some(url.into());
+        self
+    }
+
+    pub fn with_lifetimes(mut self, lifetimes: EnvelopeLifetimes) -> Self {
+        self.lifetimes = lifetimes;
+        self
+    }
+
+    /// Register the deployment parameters of the merchant contract at `profile`.
+    pub fn with_merchant_fees(mut self, profile: &str, params: DeployMerchantParams) -> Self {
+        self.merchant_fees.insert(profile.to_lowercase(), params);
+        self
+    }
+
+    pub fn merchant_fees_for(&self, profile: &str) -> Option<&DeployMerchantParams> {
+        self.merchant_fees.get(&profile.to_lowercase())
+    }
+}
+
+impl Default for EnvelopeParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Settlement contract call carried by an envelope.
+#[derive(Debug, Clone)]
+pub enum SettlementCall {
+    BuyerCommit(BuyerCommitParams),
+    SellerCommit(SellerCommitParams),
+    Settle(SettleParams),
+    BuyerCancel(BuyerCancelParams),
+}
+
+impl SettlementCall {
+    /// ABI-encoded calldata for the call.
+    pub fn calldata(&self) -> Vec<u8> {
+        match self {
+            SettlementCall::BuyerCommit(p) => p.calldata(),
+            SettlementCall::SellerCommit(p) => p.calldata(),
+            SettlementCall::Settle(p) => p.calldata(),
+            SettlementCall::BuyerCancel(p) => p.calldata(),
+        }
+    }
+
+    /// Order the call acts on.
+    pub fn order_id(&self) -> Bytes32 {
+        match self {
+            SettlementCall::BuyerCommit(p) => p.order_id,
+            SettlementCall::SellerCommit(p) => p.order_id,
+            SettlementCall::Settle(p) => p.order_id,
+            SettlementCall::BuyerCancel(p) => p.order_id,
+        }
+    }
+
+    /// Native coin attached to the transaction (wei). Only a buyerCommit in
+    /// the native asset carries value; every other call sends zero.
+    pub fn value(&self) -> U256 {
+        match self {
+            SettlementCall::BuyerCommit(p) if is_native_eth(&p.asset) => p.amount,
+            _ => U256::ZERO,
+        }
+    }
+}
+
+/// Map a verified QUERY to the settlement contract call it executes.
+pub fn settlement_call_for(query: &QueryMessage) -> Result<SettlementCall, String> {
+    let meta = &query.metadata;
+    let intent = &query.intent;
+
+    match (&intent.verb, &intent.party) {
+        (TGPVerb::COMMIT | TGPVerb::PAY, TGPParty::BUYER) => {
+            let order_id = match meta_bytes32(meta, "order_id")? {
+                Some(id) => id,
+                None => keccak256(query.id.as_bytes()),
+            };
+            Ok(SettlementCall::BuyerCommit(BuyerCommitParams {
+                order_id,
+                asset: meta_address(meta, "asset")?.unwrap_or(NATIVE_ETH),
+                amount: U256::from_u64(query.amount),
+                pk_hash: meta_bytes32(meta, "pk_hash")?.unwrap_or_default(),
+                nullifier: meta_bytes32(meta, "nullifier")?.unwrap_or_default(),
+                timestamp: meta_u64(meta, "timestamp")?.unwrap_or(0),
+                zk_proof: meta_bytes(meta, "zk_proof")?.unwrap_or_default(),
+            }))
+        }
+        (TGPVerb::COMMIT, TGPParty::SELLER) => {
+            Ok(SettlementCall::SellerCommit(SellerCommitParams {
+                order_id: required_order_id(meta)?,
+                pk_hash: meta_bytes32(meta, "pk_hash")?.unwrap_or_default(),
+                nullifier: meta_bytes32(meta, "nullifier")?.unwrap_or_default(),
+                timestamp: meta_u64(meta, "timestamp")?.unwrap_or(0),
+                zk_proof: meta_bytes(meta, "zk_proof")?.unwrap_or_default(),
+            }))
+        }
+        (TGPVerb::CLAIM, _) => Ok(SettlementCall::Settle(SettleParams {
+            order_id: required_order_id(meta)?,
+        })),
+        (TGPVerb::WITHDRAW, TGPParty::BUYER) => Ok(SettlementCall::BuyerCancel(BuyerCancelParams {
+            order_id: required_order_id(meta)?,
+        })),
+        (TGPVerb::QUOTE, _) => Err("QUOTE never produces an executable envelope".into()),
+        (verb, party) => Err(format!("No settlement call for {:?} by {:?}", verb, party)),
+    }
+}
+
+/// Rewrite a commit call with the outputs of its verified ZK proof. Proof
+/// bytes never reach the contract: TBC attests to them instead.
+pub fn apply_contract_safe_proof(call: &mut SettlementCall, proof: &ContractSafeProof) -> Result<(), String> {
+    match (call, &proof.buyer, &proof.seller) {
+        (SettlementCall::BuyerCommit(p), Some(out), None) => {
+            if U256::from_dec_str(&out.amount) != Some(p.amount) {
+                return Err("ZK buyer output amount does not match the commit".into());
+            }
+            p.pk_hash = proof_bytes32(&out.pk_hash, "pk_hash")?;
+            p.nullifier = proof_bytes32(&out.nullifier, "nullifier")?;
+            p.timestamp = proof_u64(&out.timestamp)?;
+            p.zk_proof = Vec::new();
+            Ok(())
+        }
+        (SettlementCall::SellerCommit(p), None, Some(out)) => {
+            p.pk_hash = proof_bytes32(&out.pk_hash, "pk_hash")?;
+            p.nullifier = proof_bytes32(&out.nullifier, "nullifier")?;
+            p.timestamp = proof_u64(&out.timestamp)?;
+            p.zk_proof = Vec::new();
+            Ok(())
+        }
+        _ => Err("ZK outputs do not match the settlement call".into()),
+    }
+}
+
+fn proof_bytes32(value: &str, field: &str) -> Result<Bytes32, String> {
+    hex_to_bytes32(value).map_err(|e| format!("zk output {}: {}", field, e))
+}
+
+fn proof_u64(value: &str) -> Result<u64, String> {
+    value.parse().map_err(|_| "zk output timestamp: must be a u64".to_string())
+}
+
+// ---- metadata accessors ----
+
+fn meta_str<'a>(meta: &'a Value, key: &str) -> Option<&'a str> {
+    meta.get(key).and_then(Value::as_str)
+}
+
+fn required_order_id(meta: &Value) -> Result<Bytes32, String> {
+    meta_bytes32(meta, "order_id")?.ok_or_else(|| "metadata.order_id is required".to_string())
+}
+
+fn meta_bytes32(meta: &Value, key: &str) -> Result<Option<Bytes32>, String> {
+    meta_str(meta, key)
+        .map(|s| hex_to_bytes32(s).map_err(|e| format!("metadata.{}: {}", key, e)))
+        .transpose()
+}
+
+fn meta_address(meta: &Value, key: &str) -> Result<Option<Address>, String> {
+    meta_str(meta, key)
+        .map(|s| hex_to_address(s).map_err(|e| format!("metadata.{}: {}", key, e)))
+        .transpose()
+}
+
+fn meta_bytes(meta: &Value, key: &str) -> Result<Option<Vec<u8>>, String> {
+    meta_str(meta, key)
+        .map(|s| {
+            hex::decode(s.strip_prefix("0x").unwrap_or(s))
+                .map_err(|_| format!("metadata.{}: invalid hex", key))
+        })
+        .transpose()
+}
+
+fn meta_u64(meta: &Value, key: &str) -> Result<Option<u64>, String> {
+    match meta.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => n
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| format!("metadata.{}: must be a u64", key)),
+        Some(Value::String(s)) => s
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("metadata.{}: must be a u64", key)),
+        Some(_) => Err(format!("metadata.{}: must be a u64", key)),
+    }
+}
+
+/// Asset identifier for the chain's native coin (`evm:native:<chain_id>`).
+pub fn native_asset(chain_id: u64) -> String {
+    format!("evm:native:{}", chain_id)
+}
+
+/// This gateway's routing fee on `query`, if it charges one.
+pub(crate) fn routing_fee_line(params: &EnvelopeParams, query: &QueryMessage) -> Option<FeeLine> {
+    (params.fees_bps > 0).then(|| FeeLine {
+        amount: bps_of(query.amount, params.fees_bps as u64).to_string(),
+        asset: native_asset(query.chain_id),
+        payer: "buyer".into(),
+        payee: "tbc_operator".into(),
+        protocol_fee: None,
+        policy_ref: None,
+    })
+}
+
+/// `amount * bps / 10000`, rounded down.
+pub(crate) fn bps_of(amount: u64, bps: u64) -> u128 {
+    amount as u128 * bps as u128 / 10_000
+}
+
+/// Build the Economic Envelope for an already-verified QUERY with default
+/// gateway parameters.
+pub async fn build_envelope_for(query: &QueryMessage) -> Result<EconomicEnvelope, String> {
+    build_envelope_with(&EnvelopeParams::default(), query).await
+}
+
+/// Build the Economic Envelope for an already-verified QUERY.
+pub async fn build_envelope_with(
+    params: &EnvelopeParams,
+    query: &QueryMessage,
+) -> Result<EconomicEnvelope, String> {
+    build_envelope_with_proof(params, query, None).await
+}
+
+/// Build the Economic Envelope for an already-verified QUERY, carrying the
+/// outputs of the ZK proof L4 verified for it.
+pub async fn build_envelope_with_proof(
+    params: &EnvelopeParams,
+    query: &QueryMessage,
+    zk: Option<&ContractSafeProof>,
+) -> Result<EconomicEnvelope, String> {
+    let mut call = settlement_call_for(query)?;
+    if let Some(proof) = zk {
+        apply_contract_safe_proof(&mut call, proof)?;
+    }
+
+    let asset = native_asset(query.chain_id);
+    let routing_fee = routing_fee_line(params, query);
+
+    let envelope = EconomicEnvelope {
+        ee_version: EE_VERSION.into(),
+        to: query.payment_profile.clone(),
+        value: call.value().to_string(),
+        data: format!("0x{}", hex::encode(call.calldata())),
+        chain_id: query.chain_id,
+        gas_limit: params.gas_limit,
+        rpc_url: params.rpc_url.clone(),
+        tbc_endpoint: params.tbc_endpoint.clone(),
+        fees_bps: params.fees_bps,
+        max_fees_bps: params.max_fees_bps,
+        price: FeeLine {
+            amount: query.amount.to_string(),
+            asset,
+            payer: "buyer".into(),
+            payee: "seller".into(),
+            protocol_fee: None,
+            policy_ref: None,
+        },
+        buyer_fee: None,
+        routing_fee,
+        expiry: None,
+    };
+
+    envelope.validate()?;
+    Ok(envelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TGPParty;
+    use crate::tgp::layers::tests::sample_query;
+
+    const ORDER_ID: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+    fn query_for(verb: TGPVerb, party: TGPParty, metadata: Value) -> QueryMessage {
+        let mut q = sample_query();
+        q.intent.verb = verb;
+        q.intent.party = party;
+        q.metadata = metadata;
+        q
+    }
+
+    fn calldata_hex(q: &QueryMessage) -> String {
+        hex::encode(settlement_call_for(q).unwrap().calldata())
+    }
+
+    // Golden vectors: independently encoded with a reference keccak256 /
+    // ABI implementation.
+
+    #[test]
+    fn test_golden_buyer_commit() {
+        let q = query_for(TGPVerb::COMMIT, TGPParty::BUYER, serde_json::json!({
+            "order_id": ORDER_ID,
+            "pk_hash": format!("0x{}", "22".repeat(32)),
+            "nullifier": format!("0x{}", "33".repeat(32)),
+            "timestamp": 1_700_000_000u64,
+            "zk_proof": "0xdeadbeef",
+        }));
+
+        assert_eq!(calldata_hex(&q), concat!(
+            "57ebfa73",
+            "1111111111111111111111111111111111111111111111111111111111111111",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "00000000000000000000000000000000000000000000000000000000000003e8",
+            "2222222222222222222222222222222222222222222222222222222222222222",
+            "3333333333333333333333333333333333333333333333333333333333333333",
+            "000000000000000000000000000000000000000000000000000000006553f100",
+            "00000000000000000000000000000000000000000000000000000000000000e0",
+            "0000000000000000000000000000000000000000000000000000000000000004",
+            "deadbeef00000000000000000000000000000000000000000000000000000000",
+        ));
+    }
+
+    #[test]
+    fn test_golden_seller_commit() {
+        let q = query_for(TGPVerb::COMMIT, TGPParty::SELLER, serde_json::json!({
+            "order_id": ORDER_ID,
+            "pk_hash": format!("0x{}", "44".repeat(32)),
+            "nullifier": format!("0x{}", "55".repeat(32)),
+            "timestamp": "1700000100",
+        }));
+
+        assert_eq!(calldata_hex(&q), concat!(
+            "949fb292",
+            "1111111111111111111111111111111111111111111111111111111111111111",
+            "4444444444444444444444444444444444444444444444444444444444444444",
+            "5555555555555555555555555555555555555555555555555555555555555555",
+            "000000000000000000000000000000000000000000000000000000006553f164",
+            "00000000000000000000000000000000000000000000000000000000000000a0",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ));
+    }
+
+    #[test]
+    fn test_golden_settle_and_cancel() {
+        let meta = serde_json::json!({ "order_id": ORDER_ID });
+
+        let claim = query_for(TGPVerb::CLAIM, TGPParty::SELLER, meta.clone());
+        assert_eq!(calldata_hex(&claim), concat!(
+            "987757dd",
+            "1111111111111111111111111111111111111111111111111111111111111111",
+        ));
+
+        let withdraw = query_for(TGPVerb::WITHDRAW, TGPParty::BUYER, meta);
+        assert_eq!(calldata_hex(&withdraw), concat!(
+            "cd2eb301",
+            "1111111111111111111111111111111111111111111111111111111111111111",
+        ));
+    }
+
+    #[test]
+    fn test_intent_mapping_rejections() {
+        // Only a buyerCommit may derive its order id
+        let claim = query_for(TGPVerb::CLAIM, TGPParty::BUYER, Value::Null);
+        assert!(settlement_call_for(&claim).is_err());
+
+        let seller_withdraw = query_for(TGPVerb::WITHDRAW, TGPParty::SELLER,
+            serde_json::json!({ "order_id": ORDER_ID }));
+        assert!(settlement_call_for(&seller_withdraw).is_err());
+
+        let bad_hex = query_for(TGPVerb::PAY, TGPParty::BUYER,
+            serde_json::json!({ "nullifier": "0x1234" }));
+        assert!(settlement_call_for(&bad_hex).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_envelope_from_query() {
+        let q = sample_query();
+        let params = EnvelopeParams::new()
+            .with_fees_bps(50)
+            .with_rpc_url("https://rpc.pulsechain.com");
+
+        let e = build_envelope_with(&params, &q).await.unwrap();
+        assert_eq!(e.to, q.payment_profile);
+        assert_eq!(e.value, "1000");
+
+        // Default order id is keccak256(query.id); wallet executes data verbatim
+        let order_id = hex::encode(keccak256(q.id.as_bytes()));
+        assert!(e.data.starts_with(&format!("0x57ebfa73{}", order_id)));
+        assert_eq!(e.chain_id, 369);
+        assert_eq!(e.gas_limit, DEFAULT_GAS_LIMIT);
+        assert_eq!(e.routing_fee.unwrap().amount, "5");
+
+        // Fee above the advertised ceiling never leaves the builder
+        let greedy = params.with_fees_bps(DEFAULT_MAX_FEES_BPS + 1);
+        assert!(build_envelope_with(&greedy, &q).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_envelope_carries_contract_safe_proof() {
+        let q = query_for(TGPVerb::COMMIT, TGPParty::BUYER,
+            serde_json::json!({ "zk_proof": "0xdeadbeef" }));
+        let mut proof = ContractSafeProof {
+            buyer: Some(crate::zk::ContractBuyerOutput {
+                pk_hash: format!("0x{}", "55".repeat(32)),
+                nullifier: format!("0x{}", "44".repeat(32)),
+                timestamp: "1700000000".into(),
+                amount: q.amount.to_string(),
+            }),
+            seller: None,
+        };
+
+        let e = build_envelope_with_proof(&EnvelopeParams::new(), &q, Some(&proof)).await.unwrap();
+        assert!(e.data.contains(&"55".repeat(32)));
+        assert!(e.data.contains(&"44".repeat(32)));
+        assert!(!e.data.contains("deadbeef"));
+
+        // Outputs proven for another amount never reach the contract
+        proof.buyer.as_mut().unwrap().amount = "1".into();
+        assert!(build_envelope_with_proof(&EnvelopeParams::new(), &q, Some(&proof)).await.is_err());
+    }
+}
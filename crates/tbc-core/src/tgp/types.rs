@@ -8,24 +8,36 @@
 //
 // Aligned with TGP-00 v3.2:
 //   • OFFER removed
-//   • EconomicEnvelope attached to ACK(status=allow)
+//   • EconomicEnvelope attached to ACK(status=allow), TGP-01 schema
 //   • ZkProfile retained as pure client preference
 //   • DomainTrust optional for multi-gateway routing
-//   • Anomaly scoring remains pure
+//   • Anomaly data remains pure (scored by tgp/anomaly.rs)
 //   • SettleSource remains pure
 // ============================================================================
 
 use serde::{Deserialize, Serialize};
 
+use crate::tgp::validation::{
+    parse_decimal_scaled,
+    validate_address,
+    validate_decimal_amount,
+    validate_hex_data,
+    validate_non_empty,
+    validate_rfc3339_format,
+    validate_uint256_decimal,
+    validate_url_format,
+};
+
 // ============================================================================
 // ZkProfile (§4.1 intent.mode)
 // ============================================================================
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, Default)]
 pub enum ZkProfile {
     #[serde(rename = "NONE")]
     None,
 
     #[serde(rename = "OPTIONAL")]
+    #[default]
     Optional,
 
     #[serde(rename = "REQUIRED")]
@@ -42,12 +54,6 @@ impl ZkProfile {
     }
 }
 
-impl Default for ZkProfile {
-    fn default() -> Self {
-        ZkProfile::Optional
-    }
-}
-
 impl std::fmt::Display for ZkProfile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -59,35 +65,199 @@ impl std::fmt::Display for ZkProfile {
 }
 
 // ============================================================================
-// EconomicEnvelope (§7) -- For ACK(status=allow)
+// EconomicEnvelope (§7, TGP-01 §4) -- For ACK(status=allow)
 // ============================================================================
 //
 // In TGP v3.2, the envelope appears *only* on ACK(status=allow),
 // never on QUERY, never on OFFER (removed).
 //
+// Two halves:
+//   • the executable transaction (to/value/data/chain_id/gas_limit), which
+//     the wallet submits verbatim
+//   • the TGP-01 economics (price, buyer/routing fees, protocol fee split)
+//     the transaction is priced under
+//
+// Monetary amounts are canonical string decimals (TGP-01 §4.2); `value` is
+// a base-10 wei integer so it round-trips uint256 exactly.
+//
+
+/// Envelope schema version (TGP-01 `ee_version`).
+pub const EE_VERSION: &str = "1.0";
+
+/// Highest `gas_limit` an envelope may ask the wallet to sign for.
+pub const MAX_ENVELOPE_GAS_LIMIT: u64 = 15_000_000;
+
+/// Plain value transfer cost; no contract call can use less.
+pub const MIN_ENVELOPE_GAS_LIMIT: u64 = 21_000;
+
+/// Decimal places used when comparing fee amounts against the price.
+const FEE_COMPARE_SCALE: u32 = 18;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EconomicEnvelope {
+    pub ee_version: String,
+
+    // ---- executable transaction ----
+    pub to: String,
+    pub value: String,
+    pub data: String,
+    pub chain_id: u64,
+    pub gas_limit: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tbc_endpoint: Option<String>,
+
+    // ---- economics ----
+    /// Gateway fee charged on this transaction (basis points)
+    pub fees_bps: u32,
+
+    /// Ceiling the gateway will never exceed (basis points)
     pub max_fees_bps: u32,
 
+    pub price: FeeLine,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buyer_fee: Option<FeeLine>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_fee: Option<FeeLine>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expiry: Option<String>,
 }
 
+/// One priced line of the envelope (TGP-01 `price` / `*_fee`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeeLine {
+    pub amount: String,
+    pub asset: String,
+    pub payer: String,
+    pub payee: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_fee: Option<ProtocolFee>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_ref: Option<String>,
+}
+
+/// Share of a fee routed to a Prove Router (TGP-01 §6).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProtocolFee {
+    pub ratio: f64,
+    pub dest: String,
+    pub mode: String,
+    pub asset: String,
+}
+
+impl FeeLine {
+    pub fn validate(&self, field_name: &str) -> Result<(), String> {
+        validate_decimal_amount(&self.amount, &format!("{}.amount", field_name))?;
+        validate_non_empty(&self.asset, &format!("{}.asset", field_name))?;
+        validate_non_empty(&self.payer, &format!("{}.payer", field_name))?;
+        validate_non_empty(&self.payee, &format!("{}.payee", field_name))?;
+
+        if let Some(ref pf) = self.protocol_fee {
+            if !(0.0..=1.0).contains(&pf.ratio) {
+                return Err(format!("{}.protocol_fee.ratio must be within [0, 1], got {}", field_name, pf.ratio));
+            }
+            validate_non_empty(&pf.dest, &format!("{}.protocol_fee.dest", field_name))?;
+            validate_non_empty(&pf.mode, &format!("{}.protocol_fee.mode", field_name))?;
+            validate_non_empty(&pf.asset, &format!("{}.protocol_fee.asset", field_name))?;
+        }
+        Ok(())
+    }
+}
+
 impl EconomicEnvelope {
     pub fn validate(&self) -> Result<(), String> {
+        if self.ee_version != EE_VERSION {
+            return Err(format!("Unsupported ee_version {} (must be {})", self.ee_version, EE_VERSION));
+        }
+
+        // ---- executable transaction ----
+        validate_address(&self.to, "to")?;
+        validate_uint256_decimal(&self.value, "value")?;
+        validate_hex_data(&self.data, "data")?;
+        if self.chain_id == 0 {
+            return Err("chain_id must be non-zero".into());
+        }
+        if !(MIN_ENVELOPE_GAS_LIMIT..=MAX_ENVELOPE_GAS_LIMIT).contains(&self.gas_limit) {
+            return Err(format!(
+                "gas_limit must be within {}..={}, got {}",
+                MIN_ENVELOPE_GAS_LIMIT, MAX_ENVELOPE_GAS_LIMIT, self.gas_limit
+            ));
+        }
+        if let Some(ref url) = self.rpc_url {
+            validate_url_format(url, "rpc_url")?;
+        }
+        if let Some(ref url) = self.tbc_endpoint {
+            validate_url_format(url, "tbc_endpoint")?;
+        }
+
+        // ---- economics ----
         if self.max_fees_bps > 10000 {
             return Err(format!("max_fees_bps cannot exceed 10000, got {}", self.max_fees_bps));
         }
+        if self.fees_bps > self.max_fees_bps {
+            return Err(format!(
+                "fees_bps ({}) exceeds max_fees_bps ({})",
+                self.fees_bps, self.max_fees_bps
+            ));
+        }
+
+        self.price.validate("price")?;
+        self.validate_fee_split()?;
 
         if let Some(ref expiry) = self.expiry {
-            if !expiry.contains('T') {
-                return Err("expiry must be RFC3339 (missing 'T')".into());
-            }
-            if !(expiry.ends_with('Z') || expiry.contains('+') || expiry.contains('-')) {
-                return Err("expiry must be RFC3339 (timezone missing)".into());
+            validate_rfc3339_format(expiry, "expiry")?;
+        }
+
+        Ok(())
+    }
+
+    /// Fees are quoted in the price asset and together stay within
+    /// `max_fees_bps` of the price.
+    fn validate_fee_split(&self) -> Result<(), String> {
+        let mut total_fees: u128 = 0;
+
+        for (name, line) in [("buyer_fee", &self.buyer_fee), ("routing_fee", &self.routing_fee)] {
+            let Some(line) = line else { continue };
+            line.validate(name)?;
+
+            if line.asset != self.price.asset {
+                return Err(format!(
+                    "{}.asset ({}) must match price.asset ({})",
+                    name, line.asset, self.price.asset
+                ));
             }
+            let amount = parse_decimal_scaled(&line.amount, FEE_COMPARE_SCALE, name)?;
+            total_fees = total_fees
+                .checked_add(amount)
+                .ok_or_else(|| "total fees out of range".to_string())?;
+        }
+
+        if total_fees == 0 {
+            return Ok(());
         }
 
+        let price = parse_decimal_scaled(&self.price.amount, FEE_COMPARE_SCALE, "price")?;
+        let within = match (total_fees.checked_mul(10_000), price.checked_mul(self.max_fees_bps as u128)) {
+            (Some(fees), Some(cap)) => fees <= cap,
+            _ => false,
+        };
+        if !within {
+            return Err(format!(
+                "fees ({} + {}) exceed max_fees_bps ({}) of price {}",
+                self.buyer_fee.as_ref().map_or("0", |f| f.amount.as_str()),
+                self.routing_fee.as_ref().map_or("0", |f| f.amount.as_str()),
+                self.max_fees_bps,
+                self.price.amount
+            ));
+        }
         Ok(())
     }
 }
@@ -98,8 +268,12 @@ impl EconomicEnvelope {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum SettleSource {
+    // PascalCase aliases accept pre-v3.2 (`phase`-tagged) SETTLE payloads
+    #[serde(alias = "BuyerNotify")]
     BuyerNotify,
+    #[serde(alias = "ControllerWatcher")]
     ControllerWatcher,
+    #[serde(alias = "CoreproverIndexer")]
     CoreproverIndexer,
 }
 
@@ -137,7 +311,7 @@ pub struct AnomalyEvent {
     pub message: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct AnomalySummary {
     pub total_score: u16,
     pub events: Vec<AnomalyEvent>,
@@ -149,19 +323,35 @@ impl AnomalySummary {
     }
 
     pub fn add(&mut self, kind: AnomalyKind, weight: u8, msg: impl Into<String>) {
-        self.total_score += weight as u16;
+        self.total_score = self.total_score.saturating_add(weight as u16);
         self.events.push(AnomalyEvent {
             kind,
             weight,
             message: msg.into(),
         });
     }
+
+    /// Fold another summary's events into this one.
+    pub fn merge(&mut self, other: AnomalySummary) {
+        for e in other.events {
+            self.add(e.kind, e.weight, e.message);
+        }
+    }
+
+    pub fn has(&self, kind: &AnomalyKind) -> bool {
+        self.events.iter().any(|e| &e.kind == kind)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
 }
 
 // ============================================================================
 // Optional Domain Trust (routing metadata support)
 // ============================================================================
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DomainTrust {
     Unknown,
     Low,
@@ -180,13 +370,105 @@ impl DomainTrust {
     }
 }
 
+impl std::str::FromStr for DomainTrust {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "unknown" => Ok(DomainTrust::Unknown),
+            "low" => Ok(DomainTrust::Low),
+            "medium" => Ok(DomainTrust::Medium),
+            "high" => Ok(DomainTrust::High),
+            other => Err(format!("unknown domain trust level: {}", other)),
+        }
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
+    pub(crate) fn sample_envelope() -> EconomicEnvelope {
+        let line = |amount: &str, payee: &str| FeeLine {
+            amount: amount.into(),
+            asset: "evm:native:369".into(),
+            payer: "buyer".into(),
+            payee: payee.into(),
+            protocol_fee: None,
+            policy_ref: None,
+        };
+
+        EconomicEnvelope {
+            ee_version: EE_VERSION.into(),
+            to: "0x1111111111111111111111111111111111111111".into(),
+            value: "1000".into(),
+            data: "0x".into(),
+            chain_id: 369,
+            gas_limit: 250_000,
+            rpc_url: Some("https://rpc.pulsechain.com".into()),
+            tbc_endpoint: None,
+            fees_bps: 100,
+            max_fees_bps: 100,
+            price: line("1000", "seller"),
+            buyer_fee: None,
+            routing_fee: Some(line("10", "tbc_operator")),
+            expiry: None,
+        }
+    }
+
+    #[test]
+    fn test_envelope_validation() {
+        assert!(sample_envelope().validate().is_ok());
+
+        let mut e = sample_envelope();
+        e.data = "0x123".into();
+        assert!(e.validate().is_err());
+
+        let mut e = sample_envelope();
+        e.gas_limit = MAX_ENVELOPE_GAS_LIMIT + 1;
+        assert!(e.validate().is_err());
+
+        let mut e = sample_envelope();
+        e.fees_bps = 101;
+        assert!(e.validate().is_err());
+
+        let mut e = sample_envelope();
+        e.expiry = Some("2025-99-99T".into());
+        assert!(e.validate().is_err());
+    }
+
+    #[test]
+    fn test_envelope_fee_split() {
+        // 10 + 0.01 on a price of 1000 exceeds 100 bps
+        let mut e = sample_envelope();
+        let mut buyer_fee = e.routing_fee.clone().unwrap();
+        buyer_fee.amount = "0.01".into();
+        buyer_fee.payee = "seller".into();
+        e.buyer_fee = Some(buyer_fee.clone());
+        assert!(e.validate().unwrap_err().contains("max_fees_bps"));
+
+        // Fees must be in the price asset
+        let mut e = sample_envelope();
+        e.routing_fee.as_mut().unwrap().asset = "evm:USDC:369".into();
+        assert!(e.validate().is_err());
+
+        // Protocol fee ratio is a share of the fee
+        let mut e = sample_envelope();
+        e.routing_fee.as_mut().unwrap().amount = "5".into();
+        buyer_fee.amount = "5".into();
+        buyer_fee.protocol_fee = Some(ProtocolFee {
+            ratio: 1.5,
+            dest: "tgp://proverouter".into(),
+            mode: "auto_prove_burn".into(),
+            asset: "prove".into(),
+        });
+        e.buyer_fee = Some(buyer_fee);
+        assert!(e.validate().unwrap_err().contains("ratio"));
+    }
+
     #[test]
     fn test_anomaly_scoring() {
         let mut a = AnomalySummary::new();
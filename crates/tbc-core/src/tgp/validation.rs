@@ -10,7 +10,7 @@
 //   • Comments rewritten for accuracy and v3.2 alignment
 // ============================================================================
 
-use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
 
 // ============================================================================
 // Basic Validation
@@ -60,6 +60,104 @@ pub fn validate_transaction_hash(hash: &str, field_name: &str) -> Result<(), Str
     Ok(())
 }
 
+// ============================================================================
+// QUERY Sanity Checks (used by the state engine before L1–L6)
+// ============================================================================
+
+pub fn validate_payment_profile(profile: &str) -> Result<(), String> {
+    validate_address(profile, "payment_profile")?;
+
+    if profile[2..].chars().all(|c| c == '0') {
+        return Err("payment_profile must not be the zero address".into());
+    }
+    Ok(())
+}
+
+pub fn validate_chain_id(chain_id: u64) -> Result<(), String> {
+    if chain_id == 0 {
+        return Err("chain_id must be a non-zero EIP-155 chain identifier".into());
+    }
+    Ok(())
+}
+
+pub fn validate_amount_nonzero(amount: u64) -> Result<(), String> {
+    validate_positive_amount(amount, "amount")
+}
+
+// ============================================================================
+// Calldata + Monetary Values (TGP-01 Economic Envelope)
+// ============================================================================
+
+/// 2^256 - 1, the largest EVM `value`.
+const UINT256_MAX: &str =
+    "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+/// `0x`-prefixed, even-length hex calldata (`0x` alone = empty calldata).
+pub fn validate_hex_data(data: &str, field_name: &str) -> Result<(), String> {
+    let hex = data
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("{} must start with 0x: {}", field_name, data))?;
+
+    if hex.len() % 2 != 0 {
+        return Err(format!("{} must have an even number of hex digits", field_name));
+    }
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{} contains non-hex characters", field_name));
+    }
+    Ok(())
+}
+
+/// Canonical base-10 integer within uint256 (wei amounts).
+pub fn validate_uint256_decimal(value: &str, field_name: &str) -> Result<(), String> {
+    validate_non_empty(value, field_name)?;
+
+    if !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("{} must be a base-10 integer string: {}", field_name, value));
+    }
+    if value.len() > 1 && value.starts_with('0') {
+        return Err(format!("{} must not have leading zeros: {}", field_name, value));
+    }
+    if value.len() > UINT256_MAX.len() || (value.len() == UINT256_MAX.len() && value > UINT256_MAX) {
+        return Err(format!("{} exceeds uint256: {}", field_name, value));
+    }
+    Ok(())
+}
+
+/// Canonical string decimal (`"10000"`, `"50.25"`): no sign, exponent or
+/// leading zeros, and a non-empty fraction when a point is present.
+pub fn validate_decimal_amount(amount: &str, field_name: &str) -> Result<(), String> {
+    validate_non_empty(amount, field_name)?;
+
+    let (int, frac) = match amount.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (amount, None),
+    };
+
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if !digits(int) || frac.is_some_and(|f| !digits(f)) {
+        return Err(format!("{} must be a canonical decimal string: {}", field_name, amount));
+    }
+    if int.len() > 1 && int.starts_with('0') {
+        return Err(format!("{} must not have leading zeros: {}", field_name, amount));
+    }
+    Ok(())
+}
+
+/// Canonical decimal as an integer count of 10^-`scale` units.
+pub fn parse_decimal_scaled(amount: &str, scale: u32, field_name: &str) -> Result<u128, String> {
+    validate_decimal_amount(amount, field_name)?;
+
+    let (int, frac) = amount.split_once('.').unwrap_or((amount, ""));
+    if frac.len() > scale as usize {
+        return Err(format!("{} has more than {} decimal places: {}", field_name, scale, amount));
+    }
+
+    let padded = format!("{}{:0<width$}", int, frac, width = scale as usize);
+    padded
+        .parse::<u128>()
+        .map_err(|_| format!("{} is out of range: {}", field_name, amount))
+}
+
 // ============================================================================
 // ID + Correlation Validation (TGP-00 v3.2)
 // ============================================================================
@@ -101,6 +199,26 @@ pub fn validate_id_format(id: &str, expected_prefix: Option<&str>) -> Result<(),
     Ok(())
 }
 
+/// Prefixes of the IDs the gateway mints for ACK, SETTLE and ERROR.
+const NON_QUERY_PREFIXES: [&str; 3] = ["ack-", "settle-", "err-"];
+
+/// Validate a QUERY ID.
+///
+/// QUERY IDs are client-chosen, so `q-` IDs, v3.1 IDs and legacy UUIDs are
+/// all accepted. Only the ACK/SETTLE/ERROR prefixes are refused, which keeps
+/// a `correlation_id` recognisable as a QUERY reference.
+pub fn validate_query_id(id: &str, field_name: &str) -> Result<(), String> {
+    validate_non_empty(id, field_name)?;
+
+    if let Some(prefix) = NON_QUERY_PREFIXES.iter().find(|p| id.starts_with(*p)) {
+        return Err(format!(
+            "{} must reference a QUERY, not a '{}' message: {}",
+            field_name, prefix, id
+        ));
+    }
+    Ok(())
+}
+
 /// Validate correlation IDs for QUERY/ACK/SETTLE/ERROR
 pub fn validate_correlation_id(
     correlation_id: &str,
@@ -108,6 +226,10 @@ pub fn validate_correlation_id(
 ) -> Result<(), String> {
     validate_non_empty(correlation_id, "correlation_id")?;
 
+    if expected_phase == Some("QUERY") {
+        return validate_query_id(correlation_id, "correlation_id");
+    }
+
     if let Some(phase) = expected_phase {
         let prefix = match phase {
             "QUERY"  => "q-",
@@ -147,29 +269,17 @@ pub fn validate_url_format(url: &str, field_name: &str) -> Result<(), String> {
     Ok(())
 }
 
-pub fn validate_rfc3339_format(timestamp: &str, field_name: &str) -> Result<(), String> {
+/// Parse an RFC3339 timestamp (offset required) into UTC.
+pub fn parse_rfc3339(timestamp: &str, field_name: &str) -> Result<DateTime<Utc>, String> {
     validate_non_empty(timestamp, field_name)?;
 
-    if !timestamp.contains('T') {
-        return Err(format!(
-            "{} must be RFC3339 (missing 'T'): {}",
-            field_name, timestamp
-        ));
-    }
-
-    let has_tz =
-        timestamp.ends_with('Z') ||
-        timestamp.contains('+') ||
-        timestamp.matches('-').count() > 2;
-
-    if !has_tz {
-        return Err(format!(
-            "{} must include timezone indicator: {}",
-            field_name, timestamp
-        ));
-    }
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("{} must be RFC3339 ({}): {}", field_name, e, timestamp))
+}
 
-    Ok(())
+pub fn validate_rfc3339_format(timestamp: &str, field_name: &str) -> Result<(), String> {
+    parse_rfc3339(timestamp, field_name).map(|_| ())
 }
 
 // ============================================================================
@@ -191,4 +301,55 @@ mod tests {
     fn test_invalid_prefix() {
         assert!(validate_id_format("offer-123", Some("QUERY")).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_query_ids_need_no_prefix() {
+        assert!(validate_query_id("q-123", "id").is_ok());
+        assert!(validate_query_id("3f2b8c1e-9d4a-4c6b-8e2f-1a7d5c9b0e34", "id").is_ok());
+        assert!(validate_correlation_id("3f2b8c1e-9d4a-4c6b-8e2f-1a7d5c9b0e34", Some("QUERY")).is_ok());
+
+        assert!(validate_query_id("ack-1", "id").is_err());
+        assert!(validate_correlation_id("settle-1", Some("QUERY")).is_err());
+    }
+
+    #[test]
+    fn test_payment_profile_rejects_zero_address() {
+        assert!(validate_payment_profile("0x0000000000000000000000000000000000000000").is_err());
+        assert!(validate_payment_profile("0x1111111111111111111111111111111111111111").is_ok());
+    }
+
+    #[test]
+    fn test_rfc3339_parsing() {
+        assert!(validate_rfc3339_format("2025-11-18T15:00:05Z", "ts").is_ok());
+        assert!(validate_rfc3339_format("2025-11-18T15:00:05.123+02:00", "ts").is_ok());
+
+        assert!(validate_rfc3339_format("2025-99-99T", "ts").is_err());
+        assert!(validate_rfc3339_format("2025-11-18T15:00:05", "ts").is_err());
+        assert!(validate_rfc3339_format("2025-02-30T00:00:00Z", "ts").is_err());
+
+        let t = parse_rfc3339("2025-11-18T17:00:05+02:00", "ts").unwrap();
+        assert_eq!(t.to_rfc3339(), "2025-11-18T15:00:05+00:00");
+    }
+
+    #[test]
+    fn test_envelope_value_formats() {
+        assert!(validate_hex_data("0x", "data").is_ok());
+        assert!(validate_hex_data("0xa9059cbb", "data").is_ok());
+        assert!(validate_hex_data("0xabc", "data").is_err());
+        assert!(validate_hex_data("a9059cbb", "data").is_err());
+
+        assert!(validate_uint256_decimal("0", "value").is_ok());
+        assert!(validate_uint256_decimal(UINT256_MAX, "value").is_ok());
+        assert!(validate_uint256_decimal(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936",
+            "value"
+        ).is_err());
+        assert!(validate_uint256_decimal("01", "value").is_err());
+        assert!(validate_uint256_decimal("-1", "value").is_err());
+
+        assert!(validate_decimal_amount("10000.00", "amount").is_ok());
+        assert!(validate_decimal_amount("1e5", "amount").is_err());
+        assert!(validate_decimal_amount("1.", "amount").is_err());
+        assert_eq!(parse_decimal_scaled("50.25", 4, "amount").unwrap(), 502_500);
+    }
+}
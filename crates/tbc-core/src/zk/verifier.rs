@@ -6,7 +6,7 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use super::{ZkProofPayload, ZkProofType, ZkInputs};
+use super::{ZkProofPayload, ZkProofType};
 
 /// Result of ZK proof verification
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,6 +83,9 @@ pub trait NullifierStore: Send + Sync {
     /// Insert nullifier (returns false if already exists)
     async fn insert(&self, nullifier: &str, timestamp: u64) -> bool;
     
+    /// Un-burn a nullifier whose QUERY was refused after all
+    async fn remove(&self, nullifier: &str);
+    
     /// Get count of stored nullifiers
     async fn count(&self) -> usize;
 }
@@ -103,6 +106,10 @@ impl NullifierStore for MemoryNullifierStore {
         self.nullifiers.write().unwrap().insert(nullifier.to_string())
     }
     
+    async fn remove(&self, nullifier: &str) {
+        self.nullifiers.write().unwrap().remove(nullifier);
+    }
+    
     async fn count(&self) -> usize {
         self.nullifiers.read().unwrap().len()
     }
@@ -111,18 +118,11 @@ impl NullifierStore for MemoryNullifierStore {
 /// Mock verifier for testing
 ///
 /// Always returns valid unless proof contains "INVALID"
+#[derive(Default)]
 pub struct MockZkVerifier {
     nullifier_store: MemoryNullifierStore,
 }
 
-impl Default for MockZkVerifier {
-    fn default() -> Self {
-        Self {
-            nullifier_store: MemoryNullifierStore::default(),
-        }
-    }
-}
-
 #[async_trait]
 impl ZkVerifier for MockZkVerifier {
     async fn verify(&self, payload: &ZkProofPayload) -> VerificationResult {
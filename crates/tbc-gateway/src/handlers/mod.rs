@@ -4,60 +4,137 @@
 //!
 //! Equivalent to SIP Transaction User layer (TU).
 //!
-//! QUERY  → run state machine → ACK/ERROR
-//! ACK    → passthrough
-//! SETTLE → passthrough
+//! QUERY  → score → run state machine → ACK/ERROR (allow may be withheld,
+//!          rolling back the layers' commits)
+//! ACK    → score (incl. expiry) → passthrough
+//! SETTLE → score (incl. expiry) → receipt check → passthrough
 //! ERROR  → passthrough
 
+use chrono::Utc;
 use tbc_core::protocol::{
     QueryMessage,
     ErrorMessage,
     AckMessage,
+    AckStatus,
     SettleMessage,
     TGPMessage,
+    TgpErrorCode,
+    make_protocol_error,
 };
 use tbc_core::codec_tx::TGPMetadata;
+use tbc_core::tgp::anomaly::AnomalyEngine;
+use tbc_core::tgp::expiry::IssuedEnvelopes;
+use tbc_core::tgp::settle_verify::{SettleVerifyError, SettlementVerifier};
+use tbc_core::tgp::tx_builder::EnvelopeParams;
+use tbc_core::tgp::layers::LayerPipeline;
+use tbc_core::tgp::state::{handle_query_with, TGPStateResult};
+use tbc_core::tgp::types::{AnomalyKind, AnomalySummary};
 use anyhow::Result;
 
+use crate::logging::{log_anomalies, log_err};
+
 
 // ------------------------------------------------------------
 // QUERY → ACK / ERROR
 // ------------------------------------------------------------
 pub async fn handle_inbound_query(
-    _meta: &TGPMetadata,
+    meta: &TGPMetadata,
+    layers: &LayerPipeline,
+    params: &EnvelopeParams,
+    anomaly: &AnomalyEngine,
     q: QueryMessage,
 ) -> Result<TGPMessage> {
-    // TODO: Implement full state machine when tgp::state is ready
-    // For now, return a simple ACK
-    let ack = AckMessage::offer_for(&q);
-    Ok(TGPMessage::Ack(ack))
+    let anomalies = anomaly.score_query(&q);
+    if !anomalies.is_empty() {
+        log_anomalies(&meta.msg_id, &anomalies);
+    }
+
+    // Core state engine: sanity checks → L1–L6 → envelope → ACK/ERROR
+    let out = match handle_query_with(layers, params, q.clone()).await {
+        TGPStateResult::Ack(ack) => {
+            let allowed = ack.status == AckStatus::Allow;
+            let ack = anomaly.apply(&q, ack, &anomalies);
+            // Withheld allow: hand back what the layers committed
+            if allowed && ack.status != AckStatus::Allow {
+                layers.rollback(&q).await;
+            }
+            TGPMessage::Ack(ack)
+        }
+        TGPStateResult::Error(err) => TGPMessage::Error(err),
+        TGPStateResult::Settle(settle) => TGPMessage::Settle(settle),
+    };
+    Ok(out)
 }
 
 
 // ------------------------------------------------------------
-// ACK passthrough
+// ACK passthrough (unless expired)
 // ------------------------------------------------------------
 pub async fn handle_inbound_ack(
-    _meta: &TGPMetadata,
+    meta: &TGPMetadata,
+    anomaly: &AnomalyEngine,
+    envelopes: &IssuedEnvelopes,
     a: AckMessage,
 ) -> Result<TGPMessage> {
+    let anomalies = anomaly.score_ack(&a, envelopes, Utc::now());
+    if let Some(err) = reject_expired(meta, &anomalies) {
+        return Ok(err);
+    }
+
     // Gateway passes through ACKs
     Ok(TGPMessage::Ack(a))
 }
 
 
 // ------------------------------------------------------------
-// SETTLE passthrough
+// SETTLE passthrough (unless past the issued envelope's expiry,
+// or its claim does not match the receipt). Watcher SETTLEs are
+// published on the internal feed and never arrive here.
 // ------------------------------------------------------------
 pub async fn handle_inbound_settle(
-    _meta: &TGPMetadata,
+    meta: &TGPMetadata,
+    anomaly: &AnomalyEngine,
+    envelopes: &IssuedEnvelopes,
+    verifier: Option<&SettlementVerifier>,
     s: SettleMessage,
 ) -> Result<TGPMessage> {
+    let anomalies = anomaly.score_settle(&s, envelopes, Utc::now());
+    if let Some(err) = reject_expired(meta, &anomalies) {
+        return Ok(err);
+    }
+
+    // Fail closed: whatever its claimed source, no receipt means no SETTLE
+    let verified = match verifier {
+        Some(v) => v.verify(&s, envelopes).await,
+        None => Err(SettleVerifyError::Forged("no receipt verifier configured".into())),
+    };
+    if let Err(e) = verified {
+        let mut err = make_protocol_error(e.code(), format!("SETTLE {} rejected: {}", s.id, e));
+        err.correlation_id = meta.query_reference().map(str::to_string);
+        log_err(&err);
+        return Ok(TGPMessage::Error(err));
+    }
+
     // Gateway does NOT modify terminal settlement states
     Ok(TGPMessage::Settle(s))
 }
 
 
+/// Log any anomalies; fail closed on an `ExpiredEnvelope` one.
+fn reject_expired(meta: &TGPMetadata, anomalies: &AnomalySummary) -> Option<TGPMessage> {
+    if anomalies.is_empty() {
+        return None;
+    }
+    log_anomalies(&meta.msg_id, anomalies);
+
+    let expired = anomalies.events.iter().find(|e| e.kind == AnomalyKind::ExpiredEnvelope)?;
+    let mut err = make_protocol_error(TgpErrorCode::EnvelopeExpired, expired.message.clone());
+    err.correlation_id = meta.query_reference().map(str::to_string);
+    log_err(&err);
+    Some(TGPMessage::Error(err))
+}
+
+
 // ------------------------------------------------------------
 // ERROR passthrough
 // ------------------------------------------------------------
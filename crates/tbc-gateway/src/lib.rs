@@ -30,6 +30,6 @@ pub mod ws;            // WebSocket handler (same security as HTTP)
 
 // Re-exports for convenience
 pub use router::{InboundRouter, TGPInboundRouter};
-pub use ws::{WsState, ws_upgrade};
+pub use ws::{WsState, ws_upgrade, SETTLE_FEED_CAPACITY};
 // pub use store::InMemorySessionStore;  // TODO: depends on store module
 // pub use workers::{run_cleanup_worker, CleanupConfig};  // TODO: depends on workers module
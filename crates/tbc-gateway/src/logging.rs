@@ -18,6 +18,7 @@ use serde_json::json;
 use std::env;
 
 use tbc_core::protocol::ErrorMessage;
+use tbc_core::tgp::types::AnomalySummary;
 
 // ============================================================================
 // Logging Macros
@@ -67,7 +68,7 @@ pub fn log_event(level: &str, msg: &str, fields: serde_json::Value) {
             "msg": msg,
             "fields": fields
         });
-        println!("{}", payload.to_string());
+        println!("{}", payload);
         return;
     }
 
@@ -119,11 +120,39 @@ pub fn log_tx(json_raw: &str) {
     trace("Outbound JSON", json!({ "payload": json_raw }));
 }
 
+/// Canonical message identity (keccak256 of RFC 8785 JSON) for audit trails.
+/// `query_id` is the QUERY the message belongs to, so a whole transaction
+/// can be stitched together from the log.
+pub fn log_digest(direction: &str, msg_type: &str, msg_id: &str, query_id: Option<&str>, digest: &str) {
+    debug(
+        "TGP message",
+        json!({
+            "dir": direction,
+            "type": msg_type,
+            "id": msg_id,
+            "query_id": query_id,
+            "digest": digest
+        })
+    );
+}
+
+pub fn log_anomalies(msg_id: &str, anomalies: &AnomalySummary) {
+    warn(
+        "Anomaly",
+        json!({
+            "id": msg_id,
+            "score": anomalies.total_score,
+            "events": anomalies.events
+        })
+    );
+}
+
 pub fn log_err(err: &ErrorMessage) {
     error(
         "Protocol Error",
         json!({
             "id": err.id,
+            "correlation_id": err.correlation_id,
             "code": err.code,
             "message": err.message,
             "layer_failed": err.layer_failed
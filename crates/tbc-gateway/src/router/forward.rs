@@ -0,0 +1,112 @@
+//! # QUERY Forwarding -- TGP-00 v3.2 multi-hop
+//!
+//! Carries a QUERY that `RoutingTable::route` decided to forward to a peer
+//! gateway, and brings back the peer's reply.
+//!
+//! Transports:
+//!   • `http(s)://` → POST the JSON frame, reply is the response body
+//!   • `ws(s)://`   → one JSON text frame out, first text frame back
+//!
+//! The reply is classified but not trusted: `InboundRouter` only relays an
+//! ACK/ERROR correlated to the forwarded QUERY.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use tbc_core::{
+    codec_tx::{classify_message, encode_message},
+    protocol::{QueryMessage, TGPMessage},
+    tgp::routing::PeerGateway,
+};
+
+/// Per-peer deadline for a forwarded QUERY.
+pub const DEFAULT_FORWARD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// ---------------------------------------------------------------------------
+/// Trait Definition
+/// ---------------------------------------------------------------------------
+#[async_trait]
+pub trait QueryForwarder: Send + Sync {
+    /// Send `query` to `peer` and return its reply.
+    async fn forward(&self, peer: &PeerGateway, query: &QueryMessage) -> Result<TGPMessage>;
+}
+
+/// ---------------------------------------------------------------------------
+/// HTTP / WebSocket Forwarder
+/// ---------------------------------------------------------------------------
+pub struct NetworkForwarder {
+    http: reqwest::Client,
+    timeout: Duration,
+}
+
+impl NetworkForwarder {
+    pub fn new() -> Self {
+        Self::with_timeout(DEFAULT_FORWARD_TIMEOUT)
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .unwrap_or_default(),
+            timeout,
+        }
+    }
+
+    async fn forward_http(&self, url: &str, frame: String) -> Result<String> {
+        let resp = self
+            .http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(frame)
+            .send()
+            .await?;
+        Ok(resp.text().await?)
+    }
+
+    async fn forward_ws(&self, url: &str, frame: String) -> Result<String> {
+        let exchange = async {
+            let (mut ws, _) = connect_async(url).await?;
+            ws.send(Message::Text(frame)).await?;
+
+            while let Some(msg) = ws.next().await {
+                if let Message::Text(reply) = msg? {
+                    let _ = ws.close(None).await;
+                    return Ok(reply);
+                }
+            }
+            Err(anyhow!("peer closed the connection without replying"))
+        };
+
+        tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| anyhow!("peer did not reply within {:?}", self.timeout))?
+    }
+}
+
+impl Default for NetworkForwarder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl QueryForwarder for NetworkForwarder {
+    async fn forward(&self, peer: &PeerGateway, query: &QueryMessage) -> Result<TGPMessage> {
+        let frame = encode_message(&TGPMessage::Query(query.clone()))?;
+
+        let reply = match peer.url.split_once("://").map(|(scheme, _)| scheme) {
+            Some("http" | "https") => self.forward_http(&peer.url, frame).await?,
+            Some("ws" | "wss") => self.forward_ws(&peer.url, frame).await?,
+            _ => return Err(anyhow!("unsupported peer URL {}", peer.url)),
+        };
+
+        let (_, msg) = classify_message(&reply)?;
+        Ok(msg)
+    }
+}
@@ -4,7 +4,7 @@
 //! SESSIONSTORE IS NOW OPTIONAL -- Gateway does not mutate client sessions.
 //!
 //! Message flow:
-//!   classify → validate → dispatch → encode
+//!   decode (JSON | CBOR) → classify → replay → validate → dispatch → encode
 //!
 //! Supported:
 //!   • QUERY
@@ -14,21 +14,37 @@
 //!
 //! Forbidden (legacy):
 //!   • OFFER (removed)
+//!
+//! Multi-hop: with a `RoutingTable` or TAI registry, a QUERY for a remote
+//! transaction area is forwarded (see `forward.rs`) instead of being
+//! handled locally, and the peer's ACK/ERROR is relayed back.
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use serde_json::{json, Value};
 use std::sync::Arc;
 
 use tbc_core::{
     codec_tx::{
-        classify_message,
+        classify_value_with,
+        decode_cbor,
+        encode_cbor,
         encode_message,
         validate_and_classify_message,
         ReplayProtector,
         InMemoryReplayCache,
         TGPValidationResult,
+        UnsupportedVersion,
+        VersionRegistry,
     },
-    protocol::{TGPMessage, make_protocol_error, ErrorMessage},
+    protocol::{QueryMessage, TGPMessage, TgpErrorCode, make_protocol_error},
+    tgp::anomaly::AnomalyEngine,
+    tgp::expiry::{EnvelopeLifetimes, IssuedEnvelopes},
+    tgp::routing::{RouteDecision, RouteResolver, RoutingTable},
+    tgp::settle_verify::SettlementVerifier,
+    tgp::tai::TaiRegistry,
+    tgp::tx_builder::EnvelopeParams,
+    tgp::layers::LayerPipeline,
 };
 
 use crate::handlers::{
@@ -40,6 +56,8 @@ use crate::handlers::{
 
 use crate::logging::*;
 
+use super::forward::{NetworkForwarder, QueryForwarder};
+
 /// ---------------------------------------------------------------------------
 /// Trait Definition
 /// ---------------------------------------------------------------------------
@@ -53,14 +71,102 @@ pub trait TGPInboundRouter {
 /// ---------------------------------------------------------------------------
 pub struct InboundRouter {
     pub replay: Arc<dyn ReplayProtector + Send + Sync>,
+    pub layers: Arc<LayerPipeline>,
+    pub versions: Arc<VersionRegistry>,
+    pub envelope_params: Arc<EnvelopeParams>,
+    pub envelopes: Arc<IssuedEnvelopes>,
+    pub anomaly: Arc<AnomalyEngine>,
+    pub routing: Option<Arc<dyn RouteResolver>>,
+    pub forwarder: Arc<dyn QueryForwarder>,
+    pub settle_verifier: Option<Arc<SettlementVerifier>>,
 }
 
 impl InboundRouter {
     pub fn new() -> Self {
         Self {
             replay: Arc::new(InMemoryReplayCache::default()),
+            layers: Arc::new(LayerPipeline::default()),
+            versions: Arc::new(VersionRegistry::default()),
+            envelope_params: Arc::new(EnvelopeParams::default()),
+            envelopes: Arc::new(IssuedEnvelopes::default()),
+            anomaly: Arc::new(AnomalyEngine::new()),
+            routing: None,
+            forwarder: Arc::new(NetworkForwarder::new()),
+            settle_verifier: None,
         }
     }
+
+    /// Use a replay protector shared with other routers or transports.
+    pub fn with_replay(mut self, replay: Arc<dyn ReplayProtector + Send + Sync>) -> Self {
+        self.replay = replay;
+        self
+    }
+
+    /// Replace the set of accepted TGP wire versions.
+    pub fn with_versions(mut self, versions: VersionRegistry) -> Self {
+        self.versions = Arc::new(versions);
+        self
+    }
+
+    /// Wire versions this router accepts, oldest first.
+    pub fn supported_versions(&self) -> Vec<&'static str> {
+        self.versions.supported_versions()
+    }
+
+    /// Envelope lifetimes (default and per payment profile) for ACK(allow).
+    pub fn with_envelope_lifetimes(mut self, lifetimes: EnvelopeLifetimes) -> Self {
+        Arc::make_mut(&mut self.envelope_params).lifetimes = lifetimes;
+        self
+    }
+
+    /// Gas, fee and endpoint parameters stamped into every Economic Envelope.
+    pub fn with_envelope_params(mut self, params: EnvelopeParams) -> Self {
+        self.envelope_params = Arc::new(params);
+        self
+    }
+
+    /// Replace the L1–L6 verification pipeline used for QUERY handling.
+    pub fn with_layers(mut self, layers: LayerPipeline) -> Self {
+        self.layers = Arc::new(layers);
+        self
+    }
+
+    /// Anomaly scoring and thresholds, shared with whoever reports its stats.
+    pub fn with_anomaly_engine(mut self, engine: Arc<AnomalyEngine>) -> Self {
+        self.anomaly = engine;
+        self
+    }
+
+    /// Forward QUERYs for remote transaction areas to peer gateways.
+    pub fn with_routing(mut self, table: RoutingTable) -> Self {
+        self.routing = Some(Arc::new(table));
+        self
+    }
+
+    /// Route by a live TAI registry, shared with whoever edits it.
+    pub fn with_tai_registry(mut self, registry: Arc<TaiRegistry>) -> Self {
+        self.routing = Some(registry);
+        self
+    }
+
+    /// Replace the HTTP/WS transport used to reach peer gateways.
+    pub fn with_forwarder(mut self, forwarder: Arc<dyn QueryForwarder>) -> Self {
+        self.forwarder = forwarder;
+        self
+    }
+
+    /// Check every SETTLE against on-chain receipts.
+    /// Without one, all SETTLEs are rejected.
+    pub fn with_settlement_verifier(mut self, verifier: Arc<SettlementVerifier>) -> Self {
+        self.settle_verifier = Some(verifier);
+        self
+    }
+}
+
+impl Default for InboundRouter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// ---------------------------------------------------------------------------
@@ -69,31 +175,111 @@ impl InboundRouter {
 #[async_trait]
 impl TGPInboundRouter for InboundRouter {
     async fn route_inbound(&self, raw_json: &str) -> Result<String> {
+        self.route_json(raw_json).await.map(|(outbound, _)| outbound)
+    }
+}
+
+/// Outcome of routing one inbound message.
+struct Routed {
+    message: TGPMessage,
+
+    /// Code of an ERROR this gateway raised itself. `None` for replies and
+    /// for ERRORs echoed from the client or relayed from a peer.
+    refused: Option<TgpErrorCode>,
+}
+
+impl Routed {
+    /// A message this gateway passes along rather than originates.
+    fn relayed(message: TGPMessage) -> Self {
+        Self { message, refused: None }
+    }
+}
+
+impl From<TGPMessage> for Routed {
+    fn from(message: TGPMessage) -> Self {
+        let refused = match &message {
+            TGPMessage::Error(err) => err.error_code(),
+            _ => None,
+        };
+        Self { message, refused }
+    }
+}
+
+impl InboundRouter {
+    /// JSON entry point. Alongside the outbound message, returns the code
+    /// of the ERROR when this gateway refused the message, so transports
+    /// can map it to a status (e.g. HTTP) without re-parsing the reply.
+    pub async fn route_json(&self, raw_json: &str) -> Result<(String, Option<TgpErrorCode>)> {
         log_rx(raw_json);
 
+        let routed = match serde_json::from_str::<Value>(raw_json) {
+            Ok(v) => self.route_value(v).await?,
+            Err(e) => reject(TgpErrorCode::InvalidJson, format!("JSON parse error: {}", e), None).into(),
+        };
+
+        // ====================================================================
+        // 5. ENCODE OUTBOUND (SIP-style echo semantics)
+        // ====================================================================
+        let outbound = encode_message(&routed.message)
+            .map_err(|e| anyhow!("encode error: {}", e))?;
+
+        log_outbound(&routed.message);
+        log_tx(&outbound);
+        Ok((outbound, routed.refused))
+    }
+
+    /// CBOR transport entry point. Decodes into the same message model and
+    /// runs the identical classify → replay → validate → dispatch pipeline.
+    pub async fn route_cbor(&self, frame: &[u8]) -> Result<Vec<u8>> {
+        log_rx(&format!("<cbor {} bytes>", frame.len()));
+
+        let out_msg = match decode_cbor(frame) {
+            Ok(v) => self.route_value(v).await?.message,
+            Err(e) => reject(TgpErrorCode::InvalidCbor, e.to_string(), None),
+        };
+
+        let outbound = encode_cbor(&out_msg)?;
+        log_outbound(&out_msg);
+        Ok(outbound)
+    }
+
+    /// Route one decoded payload, returning the response or an ERROR.
+    async fn route_value(&self, payload: Value) -> Result<Routed> {
         // ====================================================================
-        // 1. CLASSIFY JSON → (metadata, TGPMessage)
+        // 1. CLASSIFY → (metadata, TGPMessage)
         // ====================================================================
-        let (metadata, message) = match classify_message(raw_json) {
+        let (metadata, message) = match classify_value_with(&self.versions, payload) {
             Ok(pair) => pair,
             Err(e) => {
-                let err = make_protocol_error(0, "INVALID_JSON", e.to_string());
-                log_err(&err);
-                return Ok(encode_message(&TGPMessage::Error(err))?);
+                let code = if e.downcast_ref::<UnsupportedVersion>().is_some() {
+                    TgpErrorCode::UnsupportedVersion
+                } else {
+                    TgpErrorCode::InvalidJson
+                };
+                return Ok(reject(code, e.to_string(), None).into());
             }
         };
 
+        log_digest(
+            "rx",
+            &metadata.msg_type,
+            &metadata.msg_id,
+            metadata.correlation_id.as_deref(),
+            &metadata.digest,
+        );
+
         // ====================================================================
         // 2. REPLAY PROTECTION
+        //    Keyed on (type, msg id): a re-send is a replay however it is
+        //    formatted or altered. The digest is only logged.
         // ====================================================================
-        if !self.replay.check_or_insert(&metadata.msg_id) {
-            let err = make_protocol_error(
-                0,
-                "REPLAY_DETECTED",
-                format!("Duplicate message ID: {}", metadata.msg_id),
-            );
-            log_err(&err);
-            return Ok(encode_message(&TGPMessage::Error(err))?);
+        if !self.replay.check_or_insert(&metadata.replay_key()) {
+            return Ok(reject(
+                TgpErrorCode::ReplayDetected,
+                format!("Duplicate message {} (digest {})", metadata.msg_id, metadata.digest),
+                metadata.query_reference(),
+            )
+            .into());
         }
 
         // ====================================================================
@@ -102,7 +288,7 @@ impl TGPInboundRouter for InboundRouter {
         match validate_and_classify_message(&metadata, &message) {
             TGPValidationResult::Reject(err) => {
                 log_err(&err);
-                return Ok(encode_message(&TGPMessage::Error(err))?);
+                return Ok(TGPMessage::Error(err).into());
             }
             TGPValidationResult::Accept => {}
         }
@@ -110,44 +296,111 @@ impl TGPInboundRouter for InboundRouter {
         // ====================================================================
         // 4. DISPATCH TO HANDLERS (stateless, RFC-style)
         // ====================================================================
-        let out_msg: TGPMessage = match &message {
+        let routed: Routed = match &message {
 
             //----------------------------------------------------------
             // QUERY Handler
             //----------------------------------------------------------
             TGPMessage::Query(q) => {
-                handle_inbound_query(&metadata, q.clone()).await?
+                let out = match self.forward_query(q).await {
+                    Some(forwarded) => forwarded,
+                    None => {
+                        handle_inbound_query(&metadata, &self.layers, &self.envelope_params, &self.anomaly, q.clone())
+                            .await?
+                            .into()
+                    }
+                };
+                // Remember the expiry so a late SETTLE can be caught
+                if let TGPMessage::Ack(ref ack) = out.message {
+                    self.envelopes.record(ack);
+                }
+                out
             }
 
             //----------------------------------------------------------
             // ACK Handler  (replaces OFFER)
             //----------------------------------------------------------
             TGPMessage::Ack(a) => {
-                handle_inbound_ack(&metadata, a.clone()).await?
+                handle_inbound_ack(&metadata, &self.anomaly, &self.envelopes, a.clone()).await?.into()
             }
 
             //----------------------------------------------------------
             // SETTLE Handler
             //----------------------------------------------------------
             TGPMessage::Settle(s) => {
-                handle_inbound_settle(&metadata, s.clone()).await?
+                handle_inbound_settle(
+                    &metadata,
+                    &self.anomaly,
+                    &self.envelopes,
+                    self.settle_verifier.as_deref(),
+                    s.clone(),
+                )
+                .await?
+                .into()
             }
 
             //----------------------------------------------------------
             // ERROR Handler
             //----------------------------------------------------------
             TGPMessage::Error(e) => {
-                handle_inbound_error(&metadata, e.clone()).await?
+                Routed::relayed(handle_inbound_error(&metadata, e.clone()).await?)
             }
         };
 
-        // ====================================================================
-        // 5. ENCODE OUTBOUND (SIP-style echo semantics)
-        // ====================================================================
-        let outbound = encode_message(&out_msg)
-            .map_err(|e| anyhow!("encode error: {}", e))?;
+        Ok(routed)
+    }
 
-        log_tx(&outbound);
-        Ok(outbound)
+    /// Forward a QUERY bound for a remote area, trying peers in rank order.
+    /// `None` means the QUERY is handled on this gateway.
+    async fn forward_query(&self, q: &QueryMessage) -> Option<Routed> {
+        let resolver = self.routing.as_ref()?;
+
+        let (forwarded, peers) = match resolver.route(q) {
+            Ok(RouteDecision::Local) => return None,
+            Ok(RouteDecision::Forward { query, peers }) => (query, peers),
+            Err(e) => return Some(reject(e.code(), e.to_string(), Some(&q.id)).into()),
+        };
+
+        for peer in &peers {
+            match self.forwarder.forward(peer, &forwarded).await {
+                // Relay only answers to this QUERY: a peer cannot inject
+                // SETTLEs, QUERYs or replies for other transactions
+                Ok(reply @ (TGPMessage::Ack(_) | TGPMessage::Error(_)))
+                    if reply.query_id() == Some(q.id.as_str()) =>
+                {
+                    info("forward.relayed", json!({ "query_id": q.id, "peer": peer.url }));
+                    return Some(Routed::relayed(reply));
+                }
+                Ok(other) => warn(
+                    "forward.unexpected_reply",
+                    json!({ "query_id": q.id, "peer": peer.url, "type": other.msg_type(), "id": other.id() }),
+                ),
+                Err(e) => warn(
+                    "forward.peer_failed",
+                    json!({ "query_id": q.id, "peer": peer.url, "error": e.to_string() }),
+                ),
+            }
+        }
+
+        Some(reject(
+            TgpErrorCode::ForwardFailed,
+            format!("No peer answered QUERY {} ({} tried)", q.id, peers.len()),
+            Some(&q.id),
+        ).into())
     }
-}
\ No newline at end of file
+}
+
+/// Build (and log) a gateway-originated ERROR, correlated to the QUERY
+/// when the inbound message identified one.
+fn reject(code: TgpErrorCode, message: String, query_id: Option<&str>) -> TGPMessage {
+    let mut err = make_protocol_error(code, message);
+    err.correlation_id = query_id.map(str::to_string);
+    log_err(&err);
+    TGPMessage::Error(err)
+}
+
+fn log_outbound(msg: &TGPMessage) {
+    if let Ok(digest) = msg.digest_hex() {
+        log_digest("tx", msg.msg_type(), msg.id(), msg.query_id(), &digest);
+    }
+}
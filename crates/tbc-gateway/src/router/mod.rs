@@ -2,6 +2,7 @@
 //!
 //! This directory follows SIP RFC3261 structure:
 //!   • `inbound.rs`  – Transaction-layer inbound dispatcher
+//!   • `forward.rs`  – QUERY forwarding to peer gateways (multi-hop)
 //!   • `mod.rs`      – Re-exports + public interface
 //!
 //! The router is intentionally separated from:
@@ -27,8 +28,14 @@
 //! ```
 
 mod inbound;
+mod forward;
 
 pub use inbound::{
     TGPInboundRouter,
     InboundRouter,
+};
+pub use forward::{
+    QueryForwarder,
+    NetworkForwarder,
+    DEFAULT_FORWARD_TIMEOUT,
 };
\ No newline at end of file
@@ -28,11 +28,6 @@ impl WsEnvelope {
         }
         
         // Validate inner message based on type
-        match &self.tgp {
-            TGPMessage::Query(q) => q.validate(),
-            TGPMessage::Ack(a) => a.validate(),
-            TGPMessage::Settle(s) => s.validate(),
-            TGPMessage::Error(e) => e.validate(),
-        }
+        self.tgp.validate()
     }
 }
\ No newline at end of file
@@ -5,6 +5,15 @@
 //! - Fail-closed: all errors result in ERROR response
 //! - Stateless: no session persistence in handler
 //! - All messages routed through full L1-L6 verification
+//!
+//! ENCODING:
+//! - JSON text frames are always accepted
+//! - CBOR binary frames only when the client negotiated the `tgp.cbor`
+//!   subprotocol at upgrade; replies use the format of the inbound frame
+//!
+//! PUSH:
+//! - Watcher SETTLEs for orders this connection opened (QUERY answered
+//!   with ACK(allow)) are pushed in the negotiated format
 
 use std::sync::Arc;
 use axum::{
@@ -14,11 +23,18 @@ use axum::{
     },
     response::IntoResponse,
 };
-use futures::{StreamExt, SinkExt};
+use futures::StreamExt;
+use tokio::sync::broadcast::error::RecvError;
+use tbc_core::codec_tx::{decode_cbor, encode_cbor, encode_message, WireFormat};
+use tbc_core::protocol::{make_protocol_error, SettleMessage, TGPMessage, TgpErrorCode};
 use crate::ws::state::WsState;
+use crate::ws::subscriptions::SettleSubscriptions;
 use crate::ws::router::route_ws_message;
 use crate::logging::log_rx;
 
+/// Subprotocols offered at upgrade, in order of preference.
+pub const WS_SUBPROTOCOLS: [&str; 2] = ["tgp.cbor", "tgp.json"];
+
 /// WebSocket upgrade endpoint
 /// 
 /// SECURITY: Upgrade is stateless. No session created.
@@ -26,7 +42,17 @@ pub async fn ws_upgrade(
     State(state): State<Arc<WsState>>,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_ws_public(socket, state))
+    ws.protocols(WS_SUBPROTOCOLS)
+        .on_upgrade(move |socket| handle_ws_public(socket, state))
+}
+
+/// Wire format negotiated for this connection (JSON unless `tgp.cbor`).
+fn negotiated_format(socket: &WebSocket) -> WireFormat {
+    socket
+        .protocol()
+        .and_then(|p| p.to_str().ok())
+        .and_then(WireFormat::from_subprotocol)
+        .unwrap_or_default()
 }
 
 /// Main WebSocket loop
@@ -37,9 +63,39 @@ pub async fn ws_upgrade(
 /// - All errors are logged and returned as TGP ERROR messages
 /// - Binary frames rejected (attack surface reduction)
 pub async fn handle_ws_public(mut socket: WebSocket, state: Arc<WsState>) {
-    tracing::info!("WebSocket connection established for TBC: {}", state.tbc_id);
+    let format = negotiated_format(&socket);
+    tracing::info!(
+        "WebSocket connection established for TBC: {} ({})",
+        state.tbc_id,
+        format.subprotocol()
+    );
     
-    while let Some(Ok(msg)) = socket.next().await {
+    let mut settles = state.settles.subscribe();
+    let mut subscriptions = SettleSubscriptions::new();
+
+    loop {
+        let msg = tokio::select! {
+            inbound = socket.next() => match inbound {
+                Some(Ok(msg)) => msg,
+                _ => break,
+            },
+            settle = settles.recv() => {
+                match settle {
+                    Ok(settle) => {
+                        if let Some(out) = subscriptions.deliver(&settle) {
+                            push_settle(&mut socket, format, out).await;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("WebSocket client lagged, {} SETTLEs dropped", skipped);
+                    }
+                    // `state` holds a sender, so the feed outlives the connection
+                    Err(RecvError::Closed) => break,
+                }
+                continue;
+            }
+        };
+
         match msg {
             Message::Text(body) => {
                 // Log inbound for audit trail
@@ -47,19 +103,24 @@ pub async fn handle_ws_public(mut socket: WebSocket, state: Arc<WsState>) {
                 
                 // Route through SAME verification pipeline as HTTP
                 // Per TGP-TBC-SEC-00 §10.2: No bypass paths allowed
-                match route_ws_message(&body).await {
+                match route_ws_message(&state.router, &body).await {
                     Ok(resp) => {
+                        if let (Ok(inbound), Ok(reply)) = (
+                            serde_json::from_str::<TGPMessage>(&body),
+                            serde_json::from_str::<TGPMessage>(&resp),
+                        ) {
+                            subscriptions.track(&inbound, &reply);
+                        }
                         let _ = socket.send(Message::Text(resp)).await;
                     }
                     Err(e) => {
                         // Fail-closed: return structured ERROR
                         // Per TGP-TBC-SEC-00 §9.1: All errors must be deterministic
-                        let err_msg = format!(
-                            r#"{{"type":"ERROR","code":"TBC_WS_DISPATCH_ERROR","layer_failed":0,"message":"{}"}}"#,
-                            e.to_string().replace('"', "'")
-                        );
                         tracing::error!("WebSocket dispatch error: {}", e);
-                        let _ = socket.send(Message::Text(err_msg)).await;
+                        let err = dispatch_error(TgpErrorCode::WsDispatchError, &e.to_string());
+                        if let Ok(text) = encode_message(&err) {
+                            let _ = socket.send(Message::Text(text)).await;
+                        }
                     }
                 }
             }
@@ -75,13 +136,58 @@ pub async fn handle_ws_public(mut socket: WebSocket, state: Arc<WsState>) {
             
             Message::Pong(_) => {}
             
+            Message::Binary(frame) if format == WireFormat::Cbor => {
+                // Same pipeline as text frames, CBOR in and out
+                match state.router.route_cbor(&frame).await {
+                    Ok(resp) => {
+                        if let (Some(inbound), Some(reply)) = (cbor_message(&frame), cbor_message(&resp)) {
+                            subscriptions.track(&inbound, &reply);
+                        }
+                        let _ = socket.send(Message::Binary(resp)).await;
+                    }
+                    Err(e) => {
+                        tracing::error!("WebSocket dispatch error: {}", e);
+                        let err = dispatch_error(TgpErrorCode::WsDispatchError, &e.to_string());
+                        if let Ok(bytes) = encode_cbor(&err) {
+                            let _ = socket.send(Message::Binary(bytes)).await;
+                        }
+                    }
+                }
+            }
+
             Message::Binary(_) => {
-                // SECURITY: Reject binary frames (attack surface reduction)
-                // Only JSON text frames are valid TGP messages
-                let err = r#"{"type":"ERROR","code":"TBC_WS_BINARY_REJECTED","layer_failed":0,"message":"Binary frames not supported. Use JSON text."}"#;
+                // SECURITY: Reject binary frames unless CBOR was negotiated
+                // (attack surface reduction)
+                let code = TgpErrorCode::WsBinaryRejected;
+                let err = dispatch_error(code, &format!("{}. Use JSON text.", code.message()));
                 tracing::warn!("Rejected binary WebSocket frame");
-                let _ = socket.send(Message::Text(err.into())).await;
+                if let Ok(text) = encode_message(&err) {
+                    let _ = socket.send(Message::Text(text)).await;
+                }
             }
         }
     }
-}
\ No newline at end of file
+}
+/// Push a watcher SETTLE in the connection's negotiated format.
+async fn push_settle(socket: &mut WebSocket, format: WireFormat, settle: SettleMessage) {
+    let msg = TGPMessage::Settle(settle);
+    let frame = match format {
+        WireFormat::Cbor => encode_cbor(&msg).map(Message::Binary),
+        WireFormat::Json => encode_message(&msg).map(Message::Text),
+    };
+    match frame {
+        Ok(frame) => {
+            let _ = socket.send(frame).await;
+        }
+        Err(e) => tracing::error!("SETTLE push encode error: {}", e),
+    }
+}
+
+fn cbor_message(frame: &[u8]) -> Option<TGPMessage> {
+    serde_json::from_value(decode_cbor(frame).ok()?).ok()
+}
+
+/// Fail-closed ERROR for transport-level dispatch failures.
+fn dispatch_error(code: TgpErrorCode, message: &str) -> TGPMessage {
+    TGPMessage::Error(make_protocol_error(code, message))
+}
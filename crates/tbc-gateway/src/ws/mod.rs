@@ -2,6 +2,8 @@ pub mod state;
 pub mod envelope;
 pub mod handler;
 pub mod router;
+pub mod subscriptions;
 
-pub use state::WsState;
-pub use handler::{ws_upgrade, handle_ws_public};
\ No newline at end of file
+pub use state::{WsState, SETTLE_FEED_CAPACITY};
+pub use subscriptions::SettleSubscriptions;
+pub use handler::{ws_upgrade, handle_ws_public, WS_SUBPROTOCOLS};
\ No newline at end of file
@@ -6,12 +6,11 @@ use crate::router::{InboundRouter, TGPInboundRouter};
 /// SECURITY: Uses the same InboundRouter as HTTP path.
 /// Per TGP-TBC-SEC-00, all verification layers (L1-L6) are evaluated.
 /// Fail-closed: any error results in rejection.
-pub async fn route_ws_message(json: &str) -> Result<String> {
-    // Use the same stateless router as HTTP endpoint
-    // This ensures identical security verification for both transports
-    let router = InboundRouter::new();
-    
+pub async fn route_ws_message(router: &InboundRouter, json: &str) -> Result<String> {
+    // The router (and its replay cache) is shared with the HTTP endpoint,
+    // so a message ID seen on either transport is a replay on both.
+    //
     // Route through full verification pipeline
     // InboundRouter handles: classify → validate → replay check → dispatch → encode
     router.route_inbound(json).await
-}
\ No newline at end of file
+}
@@ -1,8 +1,34 @@
-use serde::{Serialize, Deserialize};
+use std::sync::Arc;
 
-/// Stateless WebSocket-layer state.
-/// Contains only the TBC identifier -- no session tracking.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use tokio::sync::broadcast;
+
+use tbc_core::protocol::SettleMessage;
+
+use crate::router::InboundRouter;
+
+/// Watcher SETTLEs buffered per connection before a slow client lags.
+pub const SETTLE_FEED_CAPACITY: usize = 1024;
+
+/// WebSocket-layer state.
+/// Holds the TBC identifier and the router shared with the HTTP transport
+/// (one replay cache for both) -- no session tracking.
+#[derive(Clone)]
 pub struct WsState {
     pub tbc_id: String,
-}
\ No newline at end of file
+    pub router: Arc<InboundRouter>,
+    /// Watcher SETTLEs; each connection picks out its own orders
+    pub settles: broadcast::Sender<SettleMessage>,
+}
+
+impl WsState {
+    pub fn new(tbc_id: impl Into<String>, router: Arc<InboundRouter>) -> Self {
+        let (settles, _) = broadcast::channel(SETTLE_FEED_CAPACITY);
+        Self { tbc_id: tbc_id.into(), router, settles }
+    }
+
+    /// Push SETTLEs sent on `feed` (by the controller watcher) to clients.
+    pub fn with_settle_feed(mut self, feed: broadcast::Sender<SettleMessage>) -> Self {
+        self.settles = feed;
+        self
+    }
+}
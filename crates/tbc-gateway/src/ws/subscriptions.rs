@@ -0,0 +1,95 @@
+//! Per-connection SETTLE subscriptions
+//!
+//! A WebSocket client is subscribed to the order of every QUERY it sent
+//! that was answered with ACK(allow). Watcher SETTLEs for that order are
+//! pushed to it, correlated to the QUERY, until the order is final.
+//!
+//! Nothing is shared between connections: a client only ever receives
+//! SETTLEs for orders it opened itself. A connection follows at most
+//! `capacity` open orders; past that, the oldest is dropped.
+
+use std::collections::{HashMap, VecDeque};
+
+use tbc_core::contracts::bytes32_to_hex;
+use tbc_core::protocol::{AckStatus, SettleMessage, TGPMessage};
+use tbc_core::tgp::settle_watch::is_final;
+use tbc_core::tgp::tx_builder::settlement_call_for;
+
+/// Open orders one WebSocket connection follows by default.
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 256;
+
+#[derive(Debug)]
+pub struct SettleSubscriptions {
+    capacity: usize,
+    /// Lower-cased order ID → QUERY id
+    by_order: HashMap<String, String>,
+    /// Order IDs, oldest first
+    order: VecDeque<String>,
+}
+
+impl SettleSubscriptions {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_SUBSCRIPTION_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            by_order: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Subscribe to the order of `inbound` when `reply` is its ACK(allow).
+    pub fn track(&mut self, inbound: &TGPMessage, reply: &TGPMessage) {
+        let (TGPMessage::Query(q), TGPMessage::Ack(ack)) = (inbound, reply) else {
+            return;
+        };
+        if ack.status != AckStatus::Allow || ack.correlation_id.as_deref() != Some(q.id.as_str()) {
+            return;
+        }
+        let Ok(call) = settlement_call_for(q) else {
+            return;
+        };
+        let order = bytes32_to_hex(&call.order_id());
+        if self.by_order.insert(order.clone(), q.id.clone()).is_none() {
+            self.order.push_back(order);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.by_order.remove(&oldest);
+            }
+        }
+    }
+
+    /// The SETTLE to push for a watcher SETTLE, correlated to the QUERY
+    /// that opened the order; `None` if this connection did not open it.
+    pub fn deliver(&mut self, settle: &SettleMessage) -> Option<SettleMessage> {
+        let order = settle.result.escrow_id.to_lowercase();
+        let query_id = if is_final(settle) {
+            let query_id = self.by_order.remove(&order)?;
+            self.order.retain(|o| *o != order);
+            query_id
+        } else {
+            self.by_order.get(&order)?.clone()
+        };
+
+        let mut out = settle.clone();
+        out.correlation_id = Some(query_id);
+        Some(out)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_order.is_empty()
+    }
+}
+
+impl Default for SettleSubscriptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,57 +1,93 @@
-//! Integration tests for complete TGP session lifecycle
+//! Integration tests for the TGP-00 v3.2 message lifecycle
 //!
 //! Tests cover:
-//!   • Happy path (Query → Offer → Settle)
-//!   • Error paths (timeouts, invalid states)
-//!   • State machine validation
-//!   • Persistence across messages
+//!   • Happy path (QUERY → ACK(allow) → SETTLE)
+//!   • Error paths (layer failures, invalid JSON, unknown types)
+//!   • Soft policy failures answered with ACK(revise) and a reason
+//!   • QUOTE previews (ACK(offer) with fees, never a tx)
+//!   • Legacy `phase`-tagged payloads upgraded to the canonical model
 //!   • Replay protection integration
+//!   • ACK/ERROR correlation back to the QUERY
+//!   • Envelope expiry on inbound ACK and SETTLE
+//!   • Multi-hop forwarding (loop detection, hop limit, relayed replies)
+//!   • TAI registry edits applied to routing at runtime
+//!   • Anomaly scores withholding ACK(allow) and counted in stats
+//!   • Layer commits rolled back when ACK(allow) is withheld
+//!   • Every routed SETTLE checked against on-chain receipts
+//!   • Watcher SETTLEs pushed to the WS client that opened the order
+//!
+//! The gateway is stateless: every assertion is made on the wire response.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use tbc_gateway::{InboundRouter, TGPInboundRouter, InMemorySessionStore};
-use tbc_core::tgp::state::{TGPState, SessionStore};
+use async_trait::async_trait;
+use serde_json::Value;
+use tbc_core::codec_tx::{decode_cbor, encode_cbor, InMemoryReplayCache, VersionRegistry};
+use tbc_core::contracts::{bytes32_to_hex, event_topic, SETTLEMENT_COMPLETED_EVENT_SIG};
+use tbc_core::protocol::{AckMessage, AckReason, QueryMessage, TGPMessage, TgpErrorCode};
+use tbc_core::tgp::anomaly::AnomalyEngine;
+use tbc_core::tgp::canonical::keccak256;
+use tbc_core::tgp::expiry::EnvelopeLifetimes;
+use tbc_core::tgp::layers::{LayerOutputs, LayerPipeline, PolicyLayer, VerificationLayer};
+use tbc_core::tgp::merchants::merchant_deployments_from_toml;
+use tbc_core::tgp::policy::{Policy, PolicyEngine};
+use tbc_core::tgp::routing::{PeerGateway, RoutingTable};
+use tbc_core::tgp::settle_verify::{ReceiptProvider, SettlementVerifier};
+use tbc_core::tgp::settle_watch::STATUS_COMMITTED;
+use tbc_core::tgp::state::make_settle_message;
+use tbc_core::tgp::tx_builder::settlement_call_for;
+use tbc_core::tgp::tai::{TaiRegistry, TransactionArea};
+use tbc_core::tgp::tx_builder::EnvelopeParams;
+use tbc_core::tgp::types::DomainTrust;
+use tbc_gateway::router::QueryForwarder;
+use tbc_gateway::ws::SettleSubscriptions;
+use tbc_gateway::{ws::router::route_ws_message, InboundRouter, TGPInboundRouter};
 
 // ============================================================================
 // Test Fixtures
 // ============================================================================
 
-fn sample_query() -> &'static str {
-    r#"{
-        "phase": "QUERY",
-        "id": "q-test-1",
-        "from": "buyer-addr",
-        "to": "seller-addr",
-        "asset": "USDC",
-        "amount": 1000,
-        "escrow_from_402": false,
-        "zk_profile": "OPTIONAL"
-    }"#
+const PROFILE: &str = "0x1111111111111111111111111111111111111111";
+const TX_HASH: &str = "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef";
+
+fn sample_query(id: &str, amount: u64) -> String {
+    versioned_query(id, amount, "3.2")
 }
 
-fn sample_settle_success(query_id: &str) -> String {
+fn versioned_query(id: &str, amount: u64, version: &str) -> String {
     format!(
         r#"{{
-            "phase": "SETTLE",
-            "id": "settle-test-1",
-            "query_or_offer_id": "{}",
-            "success": true,
-            "source": "ControllerWatcher",
-            "layer8_tx": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef"
-        }}"#,
-        query_id
+            "type": "QUERY",
+            "tgp_version": "{version}",
+            "id": "{id}",
+            "intent": {{ "verb": "COMMIT", "party": "BUYER", "mode": "direct" }},
+            "payment_profile": "{PROFILE}",
+            "amount": {amount},
+            "chain_id": 369
+        }}"#
     )
 }
 
-fn sample_settle_failure(query_id: &str) -> String {
+/// Order a buyer commit without `metadata.order_id` opens.
+fn order_of(query_id: &str) -> String {
+    bytes32_to_hex(&keccak256(query_id.as_bytes()))
+}
+
+fn sample_settle(id: &str, query_id: &str) -> String {
+    let order = order_of(query_id);
     format!(
         r#"{{
-            "phase": "SETTLE",
-            "id": "settle-test-2",
-            "query_or_offer_id": "{}",
-            "success": false,
-            "source": "ControllerWatcher",
-            "layer8_tx": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef"
-        }}"#,
-        query_id
+            "type": "SETTLE",
+            "id": "{id}",
+            "result": {{ "final_status": "complete", "escrow_id": "{order}" }},
+            "timestamp": "2025-11-18T15:00:05Z",
+            "correlation_id": "{query_id}",
+            "source": "buyer-notify",
+            "tx_hash": "{TX_HASH}"
+        }}"#
     )
 }
 
@@ -59,251 +95,806 @@ fn sample_error(correlation_id: Option<&str>) -> String {
     let corr = correlation_id
         .map(|c| format!(r#""correlation_id": "{}","#, c))
         .unwrap_or_default();
-    
+
     format!(
         r#"{{
-            "phase": "ERROR",
+            "type": "ERROR",
             "id": "err-test-1",
             {}
             "code": "INTERNAL_ERROR",
+            "layer_failed": 0,
             "message": "Something failed"
         }}"#,
         corr
     )
 }
 
+fn legacy_query() -> String {
+    format!(
+        r#"{{
+            "phase": "QUERY",
+            "id": "q-legacy-1",
+            "from": "buyer-addr",
+            "to": "{PROFILE}",
+            "asset": "USDC",
+            "amount": 1000,
+            "escrow_from_402": false,
+            "zk_profile": "OPTIONAL"
+        }}"#
+    )
+}
+
+fn legacy_settle(query_id: &str) -> String {
+    let order = order_of(query_id);
+    format!(
+        r#"{{
+            "phase": "SETTLE",
+            "id": "settle-legacy-1",
+            "query_or_offer_id": "{query_id}",
+            "success": true,
+            "source": "CoreproverIndexer",
+            "layer8_tx": "{TX_HASH}",
+            "session_id": "{order}"
+        }}"#
+    )
+}
+
+fn routed_query(id: &str, area: &str, path: &[&str]) -> String {
+    let mut v: Value = serde_json::from_str(&sample_query(id, 1000)).unwrap();
+    v["routing"] = serde_json::json!({ "transaction_area_id": area, "path": path });
+    v.to_string()
+}
+
+/// Records what it was asked to forward; answers with a deny for the QUERY
+/// from `answering_peer`, fails for every other peer.
+struct ScriptedForwarder {
+    answering_peer: &'static str,
+    correlate: bool,
+    seen: Mutex<Vec<(String, QueryMessage)>>,
+}
+
+impl ScriptedForwarder {
+    fn new(answering_peer: &'static str) -> Self {
+        Self { answering_peer, correlate: true, seen: Mutex::new(Vec::new()) }
+    }
+}
+
+#[async_trait]
+impl QueryForwarder for ScriptedForwarder {
+    async fn forward(&self, peer: &PeerGateway, query: &QueryMessage) -> anyhow::Result<TGPMessage> {
+        self.seen.lock().unwrap().push((peer.url.clone(), query.clone()));
+        if peer.url != self.answering_peer {
+            anyhow::bail!("connection refused");
+        }
+        let mut ack = AckMessage::deny_for(query, AckReason::new("REMOTE_DENY", "denied downstream"));
+        if !self.correlate {
+            ack.correlation_id = Some("q-someone-else".into());
+        }
+        Ok(TGPMessage::Ack(ack))
+    }
+}
+
+fn eu_table() -> RoutingTable {
+    RoutingTable::new("TAID-EU")
+        .with_peer(PeerGateway::new("TAID-US", "https://us-a.example/tgp", DomainTrust::High))
+        .with_peer(PeerGateway::new("TAID-US", "wss://us-b.example/tgp/ws", DomainTrust::Medium))
+}
+
+/// Receipts by tx hash, as an RPC node on `chain_id` would return them.
+struct ChainReceipts {
+    chain_id: u64,
+    receipts: HashMap<String, Value>,
+}
+
+#[async_trait]
+impl ReceiptProvider for ChainReceipts {
+    async fn chain_id(&self) -> Result<u64, String> {
+        Ok(self.chain_id)
+    }
+    async fn tx_receipt(&self, tx_hash: &str) -> Result<Option<Value>, String> {
+        Ok(self.receipts.get(tx_hash).cloned())
+    }
+}
+
+/// Successful tx to `contract` emitting `SettlementCompleted` for `order` on `chain_id`.
+fn settlement_receipt(tx_hash: &str, contract: &str, order: &str, chain_id: u64) -> Value {
+    let data: String = [chain_id, 0, 0, 1000, 0, 1_700_000_000, 1]
+        .iter()
+        .map(|n| format!("{:064x}", n))
+        .collect();
+    serde_json::json!({
+        "transactionHash": tx_hash,
+        "status": "0x1",
+        "to": contract,
+        "logs": [{
+            "address": contract,
+            "topics": [
+                format!("0x{}", event_topic(SETTLEMENT_COMPLETED_EVENT_SIG).iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+                order,
+            ],
+            "data": format!("0x{}", data),
+        }],
+    })
+}
+
+/// Router whose chain holds the settlement of `query_id`'s order in `TX_HASH`.
+fn settling_router(query_id: &str) -> InboundRouter {
+    let chain = ChainReceipts {
+        chain_id: 369,
+        receipts: HashMap::from([(
+            TX_HASH.to_string(),
+            settlement_receipt(TX_HASH, PROFILE, &order_of(query_id), 369),
+        )]),
+    };
+    InboundRouter::new()
+        .with_settlement_verifier(Arc::new(SettlementVerifier::new(Arc::new(chain), 369).with_contract(PROFILE)))
+}
+
+async fn route(router: &InboundRouter, raw: &str) -> Value {
+    let out = router.route_inbound(raw).await.unwrap();
+    serde_json::from_str(&out).expect("router must emit valid JSON")
+}
+
 // ============================================================================
 // Happy Path Tests
 // ============================================================================
 
 #[tokio::test]
 async fn test_happy_path_query_to_settled() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());
-    
-    // Step 1: Send QUERY
-    let response = router.route_inbound(sample_query()).await.unwrap();
-    assert!(response.contains("OFFER"), "Expected OFFER response");
-    assert!(response.contains("q-test-1"), "Response should reference query ID");
-    
-    // Verify session created and transitioned to QuerySent
-    let session = store.get_session("q-test-1").await.unwrap();
-    assert!(session.is_some(), "Session should exist");
-    let session = session.unwrap();
-    assert_eq!(session.state, TGPState::QuerySent, "State should be QuerySent");
-    assert_eq!(session.query_id, Some("q-test-1".to_string()));
-    
-    // Step 2: Send SETTLE (success)
-    let settle = sample_settle_success("q-test-1");
-    let response = router.route_inbound(&settle).await.unwrap();
-    assert!(response.contains("SETTLE"), "Expected SETTLE response");
-    
-    // Verify final state is Settled
-    let session = store.get_session("q-test-1").await.unwrap().unwrap();
-    assert_eq!(session.state, TGPState::Settled, "State should be Settled");
-    assert!(session.is_terminal(), "Settled is a terminal state");
-}
-
-#[tokio::test]
-async fn test_settle_failure_transitions_to_errored() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());
-    
-    // Send QUERY
-    router.route_inbound(sample_query()).await.unwrap();
-    
-    // Send SETTLE with success=false
-    let settle = sample_settle_failure("q-test-1");
-    router.route_inbound(&settle).await.unwrap();
-    
-    // Verify state is Errored
-    let session = store.get_session("q-test-1").await.unwrap().unwrap();
-    assert_eq!(session.state, TGPState::Errored, "Failed settle should transition to Errored");
-    assert!(session.is_terminal(), "Errored is a terminal state");
+    let router = settling_router("q-test-1");
+
+    // Step 1: QUERY → ACK(allow) with Economic Envelope
+    let ack = route(&router, &sample_query("q-test-1", 1000)).await;
+    assert_eq!(ack["type"], "ACK");
+    assert_eq!(ack["status"], "allow");
+    assert_eq!(ack["correlation_id"], "q-test-1");
+    assert!(ack["id"].as_str().unwrap().starts_with("ack-"));
+    assert!(ack["tx"].is_object(), "allow must carry an envelope");
+    assert_eq!(ack["tx"]["ee_version"], "1.0");
+    assert_eq!(ack["tx"]["value"], "1000");
+    assert_eq!(ack["tx"]["chain_id"], 369);
+    assert_eq!(ack["tx"]["expiry"], ack["expires_at"]);
+
+    // Step 2: SETTLE backed by its receipt passes through unchanged
+    let settle = route(&router, &sample_settle("settle-test-1", "q-test-1")).await;
+    assert_eq!(settle["type"], "SETTLE");
+    assert_eq!(settle["result"]["final_status"], "complete");
+    assert_eq!(settle["correlation_id"], "q-test-1");
+}
+
+#[tokio::test]
+async fn test_error_passthrough_preserves_correlation() {
+    let router = InboundRouter::new();
+
+    let err = route(&router, &sample_error(Some("q-test-1"))).await;
+    assert_eq!(err["type"], "ERROR");
+    assert_eq!(err["code"], "INTERNAL_ERROR");
+    assert_eq!(err["correlation_id"], "q-test-1");
+}
+
+#[tokio::test]
+async fn test_only_gateway_errors_report_a_code() {
+    let router = InboundRouter::new();
+
+    // An echoed client ERROR is not this gateway refusing anything
+    let (_, refused) = router.route_json(&sample_error(Some("q-test-1"))).await.unwrap();
+    assert_eq!(refused, None);
+
+    let (_, refused) = router.route_json(&sample_query("q-zero", 0)).await.unwrap();
+    assert_eq!(refused, Some(TgpErrorCode::InvalidMessage));
 }
 
 // ============================================================================
-// Error Path Tests
+// Layer Failure Tests
 // ============================================================================
 
 #[tokio::test]
-async fn test_error_response_forces_errored_state() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());
-    
-    // Send QUERY
-    router.route_inbound(sample_query()).await.unwrap();
-    
-    // Send ERROR with correlation
-    let error = sample_error(Some("q-test-1"));
-    let response = router.route_inbound(&error).await.unwrap();
-    assert!(response.contains("ERROR"), "Should echo ERROR");
-    
-    // Verify state transitioned to Errored
-    let session = store.get_session("q-test-1").await.unwrap().unwrap();
-    assert_eq!(session.state, TGPState::Errored, "ERROR should force Errored state");
+async fn test_zero_amount_rejected() {
+    let router = InboundRouter::new();
+
+    let err = route(&router, &sample_query("q-zero", 0)).await;
+    assert_eq!(err["type"], "ERROR");
+    assert_eq!(err["code"], "INVALID_MESSAGE");
+}
+
+#[tokio::test]
+async fn test_policy_limit_yields_revise() {
+    let router = InboundRouter::new()
+        .with_layers(LayerPipeline::default().with_layer(PolicyLayer::new().with_max_amount(500)));
+
+    let ack = route(&router, &sample_query("q-over", 1000)).await;
+    assert_eq!(ack["type"], "ACK");
+    assert_eq!(ack["status"], "revise");
+    assert_eq!(ack["correlation_id"], "q-over");
+    assert!(ack.get("tx").is_none());
+    assert_eq!(ack["reason"]["code"], "POLICY_AMOUNT_LIMIT");
+    assert_eq!(
+        ack["reason"]["constraints"][0],
+        serde_json::json!({ "field": "amount", "op": "max", "value": 500 })
+    );
 }
 
 #[tokio::test]
-async fn test_error_without_correlation_creates_ephemeral() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());  // ✅ Clone so we can use store later
+async fn test_reloaded_policy_yields_deny() {
+    let engine = Arc::new(PolicyEngine::new());
+    let router = InboundRouter::new()
+        .with_layers(LayerPipeline::default().with_layer(PolicyLayer::new().with_engine(engine.clone())));
 
-    
-    // Send ERROR with no correlation_id
-    let error = sample_error(None);
-    let response = router.route_inbound(&error).await.unwrap();
-    assert!(response.contains("ERROR"), "Should echo ERROR");
-    
-    // No session should be persisted (ephemeral)
-    let session = store.get_session("err-test-1").await.unwrap();
-    assert!(session.is_none(), "Ephemeral session should not be persisted");
+    let ack = route(&router, &sample_query("q-before-policy", 1000)).await;
+    assert_eq!(ack["status"], "allow");
+
+    // Policies swapped at runtime apply to the next QUERY
+    let policy = Policy { blocked_jurisdictions: vec!["KP".into()], ..Policy::default() };
+    engine.upsert_merchant(PROFILE, policy).unwrap();
+    let query = sample_query("q-blocked", 1000)
+        .replace(r#""type": "QUERY","#, r#""type": "QUERY", "metadata": { "jurisdiction": "KP" },"#);
+    let ack = route(&router, &query).await;
+    assert_eq!(ack["status"], "deny");
+    assert_eq!(ack["reason"]["code"], "POLICY_JURISDICTION_NOT_ALLOWED");
+    assert!(ack.get("tx").is_none());
+}
+
+#[tokio::test]
+async fn test_anomaly_threshold_withholds_allow() {
+    let engine = Arc::new(AnomalyEngine::new().with_chain(1).with_deny_threshold(40));
+    let router = InboundRouter::new().with_anomaly_engine(engine.clone());
+
+    // sample_query targets chain 369: a domain mismatch worth 40
+    let ack = route(&router, &sample_query("q-foreign-chain", 1000)).await;
+    assert_eq!(ack["status"], "deny");
+    assert_eq!(ack["reason"]["code"], "ANOMALY_SCORE");
+    assert!(ack.get("tx").is_none());
+
+    // SETTLE without tx_hash from a low-trust source is scored (and, with
+    // no receipt to check, rejected)
+    let settle = sample_settle("settle-anomalous", "q-foreign-chain")
+        .replace(&format!(r#""tx_hash": "{TX_HASH}""#), r#""tx_hash": null"#);
+    let out = route(&router, &settle).await;
+    assert_eq!(out["code"], "TGP_SETTLE_UNVERIFIED");
+
+    let stats = engine.stats();
+    assert_eq!((stats.scored, stats.flagged, stats.denied), (2, 2, 1));
+    assert_eq!(stats.by_kind["DomainMismatch"], 1);
+    assert_eq!(stats.by_kind["MissingTxHash"], 1);
+    assert_eq!(stats.by_kind["SuspiciousTxSource"], 1);
+}
+
+/// L6 stand-in counting what committed QUERYs hold, like a velocity slot
+/// or a burned nullifier.
+#[derive(Default)]
+struct CountingLayer(AtomicUsize);
+
+#[async_trait]
+impl VerificationLayer for CountingLayer {
+    fn layer(&self) -> u8 { 6 }
+    fn name(&self) -> &'static str { "Counting" }
+    fn error_code(&self) -> TgpErrorCode { TgpErrorCode::L6WithdrawFailure }
+    async fn verify(&self, _query: &QueryMessage) -> Result<(), String> {
+        Ok(())
+    }
+    async fn commit(&self, _query: &QueryMessage, _outputs: &mut LayerOutputs) -> Result<(), String> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+    async fn rollback(&self, _query: &QueryMessage) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn test_withheld_allow_rolls_back_commits() {
+    let held = Arc::new(CountingLayer::default());
+    let layers = LayerPipeline::default().with_shared_layer(held.clone());
+
+    let flagged = InboundRouter::new()
+        .with_layers(layers.clone())
+        .with_anomaly_engine(Arc::new(AnomalyEngine::new().with_chain(1).with_deny_threshold(40)));
+    let plain = InboundRouter::new().with_layers(layers);
+
+    let ack = route(&flagged, &sample_query("q-withheld", 1000)).await;
+    assert_eq!(ack["status"], "deny");
+    assert_eq!(held.0.load(Ordering::SeqCst), 0);
+
+    let ack = route(&plain, &sample_query("q-allowed", 1000)).await;
+    assert_eq!(ack["status"], "allow");
+    assert_eq!(held.0.load(Ordering::SeqCst), 1);
 }
 
 // ============================================================================
-// Replay Protection Tests
+// SETTLE Verification Tests
 // ============================================================================
 
 #[tokio::test]
-async fn test_replay_protection() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store);
-    
-    // First send - success
-    let response1 = router.route_inbound(sample_query()).await.unwrap();
-    assert!(response1.contains("OFFER"), "First send should succeed");
-    
-    // Second send (replay) - rejected
-    let response2 = router.route_inbound(sample_query()).await.unwrap();
-    assert!(response2.contains("ERROR"), "Replay should be rejected");
-    assert!(response2.contains("REPLAY_DETECTED"), "Should indicate replay");
-}
-
-#[tokio::test]
-async fn test_replay_detection_preserves_session_state() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());
-    
-    // Send QUERY
-    router.route_inbound(sample_query()).await.unwrap();
-    
-    // Get initial state
-    let session_before = store.get_session("q-test-1").await.unwrap().unwrap();
-    let state_before = session_before.state;
-    
-    // Attempt replay
-    router.route_inbound(sample_query()).await.unwrap();
-    
-    // Verify state unchanged
-    let session_after = store.get_session("q-test-1").await.unwrap().unwrap();
-    assert_eq!(session_after.state, state_before, "Replay should not change state");
+async fn test_settle_checked_against_receipt() {
+    // The envelope issued for the QUERY names the settlement contract
+    let probe = route(&InboundRouter::new(), &sample_query("q-probe", 1000)).await;
+    let contract = probe["tx"]["to"].as_str().unwrap().to_string();
+
+    let forged_hash = format!("0x{}", "99".repeat(32));
+    let other_hash = format!("0x{}", "55".repeat(32));
+    let order = order_of("q-verified");
+    let chain = ChainReceipts {
+        chain_id: 369,
+        receipts: HashMap::from([
+            (TX_HASH.to_string(), settlement_receipt(TX_HASH, &contract, &order, 369)),
+            // Real tx, but to a contract the gateway never issued an envelope for
+            (forged_hash.clone(), settlement_receipt(&forged_hash, "0x00000000000000000000000000000000000000dd", &order, 369)),
+            // Real settlement, but of someone else's order
+            (other_hash.clone(), settlement_receipt(&other_hash, &contract, &order_of("q-other"), 369)),
+        ]),
+    };
+    let router = InboundRouter::new()
+        .with_settlement_verifier(Arc::new(SettlementVerifier::new(Arc::new(chain), 369)));
+
+    let ack = route(&router, &sample_query("q-verified", 1000)).await;
+    assert_eq!(ack["status"], "allow");
+
+    let indexer = sample_settle("settle-indexer", "q-verified").replace("buyer-notify", "coreprover-indexer");
+    let out = route(&router, &indexer).await;
+    assert_eq!(out["type"], "SETTLE");
+
+    let forged = indexer.replace(TX_HASH, &forged_hash).replace("settle-indexer", "settle-forged");
+    let err = route(&router, &forged).await;
+    assert_eq!(err["type"], "ERROR");
+    assert_eq!(err["code"], "TGP_SETTLE_UNVERIFIED");
+    assert_eq!(err["correlation_id"], "q-verified");
+
+    // Claiming a revert against a successful tx
+    let reverted = indexer.replace("\"complete\"", "\"reverted\"").replace("settle-indexer", "settle-reverted");
+    assert_eq!(route(&router, &reverted).await["code"], "TGP_SETTLE_UNVERIFIED");
+
+    // No receipt yet: retryable
+    let pending = indexer
+        .replace(TX_HASH, &format!("0x{}", "77".repeat(32)))
+        .replace("settle-indexer", "settle-pending");
+    let err = route(&router, &pending).await;
+    assert_eq!(err["code"], "TBC_RECEIPT_UNAVAILABLE");
+
+    // Another order's settlement, claimed for this QUERY, with or without
+    // naming that order
+    let borrowed = indexer.replace(TX_HASH, &other_hash).replace("settle-indexer", "settle-borrowed");
+    assert_eq!(route(&router, &borrowed).await["code"], "TGP_SETTLE_UNVERIFIED");
+    let renamed = borrowed.replace(&order, &order_of("q-other")).replace("settle-borrowed", "settle-renamed");
+    assert_eq!(route(&router, &renamed).await["code"], "TGP_SETTLE_UNVERIFIED");
+
+    // Claiming to be the gateway's own watcher earns no trust
+    let watcher = sample_settle("settle-watcher", "q-verified")
+        .replace("buyer-notify", "controller-watcher")
+        .replace(TX_HASH, &forged_hash);
+    assert_eq!(route(&router, &watcher).await["code"], "TGP_SETTLE_UNVERIFIED");
+
+    // Without a verifier, no SETTLE gets through
+    let unverified = sample_settle("settle-noverifier", "q-verified");
+    assert_eq!(route(&InboundRouter::new(), &unverified).await["code"], "TGP_SETTLE_UNVERIFIED");
 }
 
 // ============================================================================
-// State Machine Validation Tests
+// QUOTE Tests
 // ============================================================================
 
 #[tokio::test]
-async fn test_terminal_state_no_further_transitions() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());
-    
-    // Complete a full flow to Settled
-    router.route_inbound(sample_query()).await.unwrap();
-    let settle = sample_settle_success("q-test-1");
-    router.route_inbound(&settle).await.unwrap();
-    
-    // Verify terminal
-    let session = store.get_session("q-test-1").await.unwrap().unwrap();
-    assert!(session.is_terminal(), "Should be in terminal state");
-    
-    // Attempt to send another message (would be rejected by replay protection anyway)
-    // This test documents that terminal states don't transition
-}
-
-#[tokio::test]
-async fn test_session_persistence_across_messages() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());
-    
-    // Send QUERY
-    router.route_inbound(sample_query()).await.unwrap();
-    
-    // Verify session exists
-    let session1 = store.get_session("q-test-1").await.unwrap();
-    assert!(session1.is_some(), "Session should be created");
-    
-    let created_at = session1.as_ref().unwrap().created_at;
-    
-    // Send SETTLE
-    let settle = sample_settle_success("q-test-1");
-    router.route_inbound(&settle).await.unwrap();
-    
-    // Verify same session, different state
-    let session2 = store.get_session("q-test-1").await.unwrap().unwrap();
-    assert_eq!(session2.created_at, created_at, "Should be same session");
-    assert_ne!(session2.state, session1.unwrap().state, "State should have changed");
-    assert!(session2.updated_at > created_at, "Should be updated");
+async fn test_quote_previews_fees_without_tx() {
+    // Fee schedule as a node reads it from TBC_MERCHANTS_FILE
+    let merchants = merchant_deployments_from_toml(&format!(
+        r#"
+        [[merchants]]
+        contract = "{PROFILE}"
+        version = 1
+        merchant_admin = "0x{admin}"
+        tbc_relay_address = "0x{relay}"
+        zk_verifier = "0x{verifier}"
+        tbc_fee_recipient = "0x{tbc}"
+        zk_fee_recipient = "0x{zk}"
+        merchant_fee_recipient = "0x{merchant}"
+        tbc_fee_bps = 100
+        zk_fee_bps = 50
+        ttl_seconds = 3600
+        salt = "0x{salt}"
+        "#,
+        admin = "aa".repeat(20), relay = "bb".repeat(20), verifier = "cc".repeat(20),
+        tbc = "01".repeat(20), zk = "02".repeat(20), merchant = "03".repeat(20),
+        salt = "00".repeat(32),
+    ))
+    .unwrap();
+    let params = merchants
+        .into_iter()
+        .fold(EnvelopeParams::new(), |p, m| p.with_merchant_fees(&m.contract, m.params));
+    let router = InboundRouter::new().with_envelope_params(params);
+
+    let quote = sample_query("q-quote", 1000).replace("\"COMMIT\"", "\"QUOTE\"");
+    let ack = route(&router, &quote).await;
+    assert_eq!(ack["type"], "ACK");
+    assert_eq!(ack["status"], "offer");
+    assert!(ack.get("tx").is_none(), "QUOTE must never carry an executable tx");
+    assert!(ack["expires_at"].is_string());
+    assert_eq!(ack["quote"]["tbc_fee"]["amount"], "10");
+    assert_eq!(ack["quote"]["zk_relay_fee"]["amount"], "5");
+    assert_eq!(ack["quote"]["merchant_fee"]["amount"], "985");
+    assert_eq!(ack["quote"]["total"], "1000");
+    assert!(ack["quote"]["estimated_gas"].as_u64().unwrap() > 0);
 }
 
 // ============================================================================
-// Invalid Message Tests
+// Multi-hop Forwarding Tests
 // ============================================================================
 
 #[tokio::test]
-async fn test_invalid_json_rejected() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store);
-    
-    let invalid = r#"{ this is not valid json }"#;
-    let response = router.route_inbound(invalid).await.unwrap();
-    
-    assert!(response.contains("ERROR"), "Invalid JSON should return ERROR");
-    assert!(response.contains("INVALID_JSON"), "Should indicate JSON error");
+async fn test_remote_query_forwarded_and_reply_relayed() {
+    let forwarder = Arc::new(ScriptedForwarder::new("wss://us-b.example/tgp/ws"));
+    let router = InboundRouter::new()
+        .with_routing(eu_table())
+        .with_forwarder(forwarder.clone());
+
+    let ack = route(&router, &routed_query("q-remote", "TAID-US", &["TAID-APAC"])).await;
+    assert_eq!(ack["type"], "ACK");
+    assert_eq!(ack["status"], "deny");
+    assert_eq!(ack["correlation_id"], "q-remote");
+    assert_eq!(ack["reason"]["code"], "REMOTE_DENY");
+
+    // Highest trust tried first; this hop appended to the forwarded path
+    let seen = forwarder.seen.lock().unwrap().clone();
+    let peers: Vec<&str> = seen.iter().map(|(url, _)| url.as_str()).collect();
+    assert_eq!(peers, vec!["https://us-a.example/tgp", "wss://us-b.example/tgp/ws"]);
+    assert_eq!(seen[1].1.routing.path, vec!["TAID-APAC", "TAID-EU"]);
+
+    // Local areas are still handled here
+    let local = route(&router, &routed_query("q-local", "TAID-EU", &[])).await;
+    assert_eq!(local["status"], "allow");
+}
+
+#[tokio::test]
+async fn test_routing_loop_and_hop_limit_rejected() {
+    let router = InboundRouter::new()
+        .with_routing(eu_table().with_max_hops(2))
+        .with_forwarder(Arc::new(ScriptedForwarder::new("https://us-a.example/tgp")));
+
+    let looped = route(&router, &routed_query("q-loop", "TAID-US", &["TAID-EU"])).await;
+    assert_eq!(looped["code"], "TGP_ROUTING_LOOP");
+    assert_eq!(looped["correlation_id"], "q-loop");
+
+    let far = route(&router, &routed_query("q-far", "TAID-US", &["A", "B"])).await;
+    assert_eq!(far["code"], "TGP_HOP_LIMIT_EXCEEDED");
+
+    let unknown = route(&router, &routed_query("q-nowhere", "TAID-MARS", &[])).await;
+    assert_eq!(unknown["code"], "TGP_NO_ROUTE");
 }
 
 #[tokio::test]
-async fn test_unknown_session_rejected() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store);
-    
-    // Try to SETTLE without creating session first
-    let settle = sample_settle_success("nonexistent-query");
-    let result = router.route_inbound(&settle).await;
-    
-    // Should return error (unknown session)
-    assert!(result.is_err(), "Unknown session should error");
+async fn test_uncorrelated_peer_reply_not_relayed() {
+    let forwarder = ScriptedForwarder {
+        correlate: false,
+        ..ScriptedForwarder::new("https://us-a.example/tgp")
+    };
+    let router = InboundRouter::new()
+        .with_routing(eu_table())
+        .with_forwarder(Arc::new(forwarder));
+
+    let err = route(&router, &routed_query("q-hijack", "TAID-US", &[])).await;
+    assert_eq!(err["type"], "ERROR");
+    assert_eq!(err["code"], "TBC_FORWARD_FAILED");
+    assert_eq!(err["correlation_id"], "q-hijack");
+}
+
+#[tokio::test]
+async fn test_tai_registry_edits_apply_to_routing() {
+    let registry = Arc::new(TaiRegistry::new("TAID-EU").unwrap());
+    let router = InboundRouter::new()
+        .with_tai_registry(registry.clone())
+        .with_forwarder(Arc::new(ScriptedForwarder::new("https://us-a.example/tgp")));
+
+    let err = route(&router, &routed_query("q-tai-1", "TAID-US", &[])).await;
+    assert_eq!(err["code"], "TGP_NO_ROUTE");
+
+    registry
+        .upsert(TransactionArea::new("TAID-US").with_chain(369).with_peer("https://us-a.example/tgp", DomainTrust::High))
+        .unwrap();
+    let ack = route(&router, &routed_query("q-tai-2", "TAID-US", &[])).await;
+    assert_eq!(ack["reason"]["code"], "REMOTE_DENY");
+
+    // Registered chains are enforced before forwarding
+    registry.upsert(TransactionArea::new("TAID-US").with_chain(1)).unwrap();
+    let err = route(&router, &routed_query("q-tai-3", "TAID-US", &[])).await;
+    assert_eq!(err["code"], "INVALID_MESSAGE");
+    assert_eq!(err["correlation_id"], "q-tai-3");
 }
 
 // ============================================================================
-// Session Counting Tests
+// Correlation Tests
 // ============================================================================
 
 #[tokio::test]
-async fn test_session_count_increases() {
-    let store = InMemorySessionStore::new();
-    let router = InboundRouter::new(store.clone());
-    
-    let initial_count = store.session_count();
-    
-    // Create 3 sessions
-    for i in 1..=3 {
-        let query = format!(
-            r#"{{
-                "phase": "QUERY",
-                "id": "q-test-{}",
-                "from": "buyer",
-                "to": "seller",
-                "asset": "USDC",
-                "amount": 100,
-                "escrow_from_402": false,
-                "zk_profile": "OPTIONAL"
-            }}"#,
-            i
-        );
-        router.route_inbound(&query).await.unwrap();
+async fn test_errors_correlate_to_query() {
+    let router = InboundRouter::new();
+
+    let err = route(&router, &sample_query("q-corr", 0)).await;
+    assert_eq!(err["type"], "ERROR");
+    assert!(err["id"].as_str().unwrap().starts_with("err-"));
+    assert_eq!(err["correlation_id"], "q-corr");
+}
+
+#[tokio::test]
+async fn test_unprefixed_query_id_accepted() {
+    let router = InboundRouter::new();
+
+    // v3.1 and legacy clients use bare UUIDs as QUERY IDs
+    let id = "3f2b8c1e-9d4a-4c6b-8e2f-1a7d5c9b0e34";
+    let ack = route(&router, &sample_query(id, 1000)).await;
+    assert_eq!(ack["status"], "allow");
+    assert_eq!(ack["correlation_id"], id);
+}
+
+#[tokio::test]
+async fn test_settle_must_reference_query() {
+    let router = InboundRouter::new();
+
+    let err = route(&router, &sample_settle("settle-bad", "settle-other")).await;
+    assert_eq!(err["code"], "INVALID_MESSAGE");
+    assert!(err.get("correlation_id").is_none());
+}
+
+#[tokio::test]
+async fn test_watcher_settle_pushed_to_order_owner() {
+    let router = InboundRouter::new();
+    let raw = sample_query("q-watched", 1000);
+    let query: QueryMessage = serde_json::from_str(&raw).unwrap();
+    let reply: TGPMessage = serde_json::from_str(&router.route_inbound(&raw).await.unwrap()).unwrap();
+
+    let mut owner = SettleSubscriptions::new();
+    owner.track(&TGPMessage::Query(query.clone()), &reply);
+    assert_eq!(owner.len(), 1);
+    let mut bystander = SettleSubscriptions::new();
+
+    // What the controller watcher emits for this order's logs
+    let order = bytes32_to_hex(&settlement_call_for(&query).unwrap().order_id());
+    let committed = make_settle_message("settle-w1", STATUS_COMMITTED, order.clone());
+    let complete = make_settle_message("settle-w2", "complete", order.to_uppercase().replace("0X", "0x"));
+
+    let pushed = owner.deliver(&committed).expect("owner sees its commit");
+    assert_eq!(pushed.correlation_id.as_deref(), Some("q-watched"));
+    assert!(bystander.deliver(&committed).is_none());
+
+    // A final status ends the subscription
+    assert_eq!(owner.deliver(&complete).unwrap().result.final_status, "complete");
+    assert!(owner.is_empty());
+    assert!(owner.deliver(&complete).is_none());
+
+    // Rejected QUERYs open no order
+    let raw = sample_query("q-unwatched", 0);
+    let rejected: TGPMessage = serde_json::from_str(&router.route_inbound(&raw).await.unwrap()).unwrap();
+    owner.track(&serde_json::from_str(&raw).unwrap(), &rejected);
+    assert!(owner.is_empty());
+}
+
+#[tokio::test]
+async fn test_settle_subscriptions_capped_per_connection() {
+    let router = InboundRouter::new();
+    let mut subscriptions = SettleSubscriptions::with_capacity(2);
+
+    for id in ["q-cap-1", "q-cap-2", "q-cap-3"] {
+        let raw = sample_query(id, 1000);
+        let reply: TGPMessage = serde_json::from_str(&router.route_inbound(&raw).await.unwrap()).unwrap();
+        subscriptions.track(&serde_json::from_str(&raw).unwrap(), &reply);
     }
-    
-    let final_count = store.session_count();
-    assert_eq!(final_count, initial_count + 3, "Should have 3 more sessions");
+    assert_eq!(subscriptions.len(), 2);
+
+    // The oldest order was dropped to make room
+    let settle = |query_id: &str| make_settle_message("settle-cap", "complete", order_of(query_id));
+    assert!(subscriptions.deliver(&settle("q-cap-1")).is_none());
+    assert!(subscriptions.deliver(&settle("q-cap-3")).is_some());
+    assert_eq!(subscriptions.len(), 1);
+}
+
+// ============================================================================
+// Envelope Expiry Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_expired_ack_rejected() {
+    let router = InboundRouter::new();
+    let ack = r#"{
+        "type": "ACK", "status": "allow", "id": "ack-late", "correlation_id": "q-late",
+        "intent": { "verb": "COMMIT", "party": "BUYER", "mode": "direct" },
+        "tx": {
+            "ee_version": "1.0",
+            "to": "0x1111111111111111111111111111111111111111",
+            "value": "1000", "data": "0x", "chain_id": 369, "gas_limit": 250000,
+            "fees_bps": 0, "max_fees_bps": 100,
+            "price": { "amount": "1000", "asset": "evm:native:369", "payer": "buyer", "payee": "seller" },
+            "expiry": "2025-11-18T15:05:00Z"
+        },
+        "expires_at": "2025-11-18T15:05:00Z"
+    }"#;
+
+    let err = route(&router, ack).await;
+    assert_eq!(err["code"], "TGP_ENVELOPE_EXPIRED");
+    assert_eq!(err["correlation_id"], "q-late");
+}
+
+#[tokio::test]
+async fn test_settle_after_envelope_expiry_rejected() {
+    // Envelopes lapse as soon as they are issued
+    let router = InboundRouter::new()
+        .with_envelope_lifetimes(EnvelopeLifetimes::with_default(Duration::ZERO));
+
+    let ack = route(&router, &sample_query("q-exp", 1000)).await;
+    assert_eq!(ack["status"], "allow");
+
+    // Judged by when the gateway receives it, not the SETTLE's own timestamp
+    let err = route(&router, &sample_settle("settle-exp", "q-exp")).await;
+    assert_eq!(err["code"], "TGP_ENVELOPE_EXPIRED");
+    assert_eq!(err["correlation_id"], "q-exp");
+}
+
+#[tokio::test]
+async fn test_malformed_timestamp_rejected() {
+    let router = InboundRouter::new();
+
+    let bad = sample_settle("settle-ts", "q-ts").replace("2025-11-18T15:00:05Z", "2025-99-99T");
+    assert_eq!(route(&router, &bad).await["code"], "INVALID_MESSAGE");
+}
+
+// ============================================================================
+// Legacy Compatibility Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_legacy_query_upgraded() {
+    // Legacy QUERYs name no chain; without a configured one they are refused
+    let err = route(&InboundRouter::new(), &legacy_query()).await;
+    assert_eq!(err["type"], "ERROR");
+    assert!(err["message"].as_str().unwrap().contains("no chain_id"));
+
+    let router = InboundRouter::new()
+        .with_versions(VersionRegistry::default().with_legacy_chain(369));
+
+    let ack = route(&router, &legacy_query()).await;
+    assert_eq!(ack["type"], "ACK");
+    assert_eq!(ack["status"], "allow");
+    assert_eq!(ack["correlation_id"], "q-legacy-1");
+    assert_eq!(ack["intent"]["verb"], "PAY");
+}
+
+#[tokio::test]
+async fn test_legacy_settle_upgraded() {
+    let router = settling_router("q-legacy-1");
+
+    let settle = route(&router, &legacy_settle("q-legacy-1")).await;
+    assert_eq!(settle["type"], "SETTLE");
+    assert_eq!(settle["correlation_id"], "q-legacy-1");
+    assert_eq!(settle["source"], "coreprover-indexer");
+    assert_eq!(settle["tx_hash"], TX_HASH);
+}
+
+// ============================================================================
+// CBOR Transport Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_cbor_query_runs_same_pipeline() {
+    let router = InboundRouter::new();
+    let query: TGPMessage = serde_json::from_str(&sample_query("q-cbor", 1000)).unwrap();
+    let frame = encode_cbor(&query).unwrap();
+
+    let ack = decode_cbor(&router.route_cbor(&frame).await.unwrap()).unwrap();
+    assert_eq!(ack["type"], "ACK");
+    assert_eq!(ack["status"], "allow");
+
+    // Same message over JSON is a replay
+    let json = route(&router, &serde_json::to_string(&query).unwrap()).await;
+    assert_eq!(json["code"], "REPLAY_DETECTED");
+
+    let err = decode_cbor(&router.route_cbor(&[0xff]).await.unwrap()).unwrap();
+    assert_eq!(err["code"], "INVALID_CBOR");
+}
+
+// ============================================================================
+// Version Negotiation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_v3_1_query_accepted() {
+    let router = InboundRouter::new();
+    assert_eq!(router.supported_versions(), vec!["3.1", "3.2"]);
+
+    let ack = route(&router, &versioned_query("q-v31", 1000, "3.1")).await;
+    assert_eq!(ack["type"], "ACK");
+    assert_eq!(ack["status"], "allow");
+}
+
+#[tokio::test]
+async fn test_unsupported_version_names_accepted_versions() {
+    let router = InboundRouter::new();
+
+    let err = route(&router, &versioned_query("q-v9", 1000, "9.0")).await;
+    assert_eq!(err["type"], "ERROR");
+    assert_eq!(err["code"], "UNSUPPORTED_VERSION");
+    assert!(err["message"].as_str().unwrap().contains("3.1, 3.2"));
+}
+
+// ============================================================================
+// Replay Protection Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_replay_protection() {
+    let router = InboundRouter::new();
+
+    // First send - success
+    let first = route(&router, &sample_query("q-replay", 1000)).await;
+    assert_eq!(first["type"], "ACK", "First send should succeed");
+
+    // Second send (replay) - rejected
+    let second = route(&router, &sample_query("q-replay", 1000)).await;
+    assert_eq!(second["type"], "ERROR", "Replay should be rejected");
+    assert_eq!(second["code"], "REPLAY_DETECTED");
+    assert_eq!(second["correlation_id"], "q-replay");
+}
+
+#[tokio::test]
+async fn test_replay_detected_across_formatting() {
+    let router = InboundRouter::new();
+
+    let pretty = sample_query("q-fmt", 1000);
+    let compact: String = pretty.split_whitespace().collect();
+
+    assert_eq!(route(&router, &pretty).await["type"], "ACK");
+    assert_eq!(route(&router, &compact).await["code"], "REPLAY_DETECTED");
+}
+
+#[tokio::test]
+async fn test_replay_keyed_on_message_id() {
+    let router = InboundRouter::new();
+
+    assert_eq!(route(&router, &sample_query("q-alter", 1000)).await["type"], "ACK");
+
+    // Same ID with an altered body is still a replay
+    let altered = route(&router, &sample_query("q-alter", 2000)).await;
+    assert_eq!(altered["code"], "REPLAY_DETECTED");
+}
+
+#[tokio::test]
+async fn test_replay_shared_across_transports() {
+    let router = InboundRouter::new();
+
+    // HTTP first
+    let first = route(&router, &sample_query("q-xport", 1000)).await;
+    assert_eq!(first["type"], "ACK");
+
+    // Same ID over WebSocket - rejected
+    let out = route_ws_message(&router, &sample_query("q-xport", 1000)).await.unwrap();
+    let second: Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(second["code"], "REPLAY_DETECTED");
+}
+
+#[tokio::test]
+async fn test_replay_cache_injected() {
+    let replay = Arc::new(InMemoryReplayCache::new(1024));
+    let a = InboundRouter::new().with_replay(replay.clone());
+    let b = InboundRouter::new().with_replay(replay.clone());
+
+    assert_eq!(route(&a, &sample_query("q-shared", 1000)).await["type"], "ACK");
+    assert_eq!(route(&b, &sample_query("q-shared", 1000)).await["code"], "REPLAY_DETECTED");
+    assert_eq!(replay.stats().hits, 1);
+}
+
+// ============================================================================
+// Invalid Message Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_invalid_json_rejected() {
+    let router = InboundRouter::new();
+
+    let err = route(&router, r#"{ this is not valid json }"#).await;
+    assert_eq!(err["type"], "ERROR");
+    assert_eq!(err["code"], "INVALID_JSON");
+}
+
+#[tokio::test]
+async fn test_offer_type_rejected() {
+    let router = InboundRouter::new();
+
+    let err = route(&router, r#"{ "type": "OFFER", "id": "offer-1" }"#).await;
+    assert_eq!(err["type"], "ERROR");
+    assert_eq!(err["code"], "INVALID_JSON");
 }
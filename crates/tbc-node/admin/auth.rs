@@ -85,7 +85,7 @@ impl AdminKeyStore {
                 if parts.len() >= 2 {
                     let name = parts[0].to_string();
                     let pubkey = parts[1].to_string();
-                    let role = match parts.get(2).map(|s| *s) {
+                    let role = match parts.get(2).copied() {
                         Some("super") => AdminRole::SuperAdmin,
                         Some("operator") => AdminRole::Operator,
                         _ => AdminRole::Monitor,
@@ -185,6 +185,7 @@ impl AdminAuth {
         Self { key_store }
     }
 
+    #[allow(dead_code)]
     pub fn with_key_store(key_store: AdminKeyStore) -> Self {
         Self { key_store }
     }
@@ -5,6 +5,8 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tbc_core::tgp::policy::Policy;
+use tbc_core::tgp::tai::TransactionArea;
 
 /// Admin command enumeration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +53,12 @@ pub enum AdminCommand {
     /// Get verification layer status
     GetLayerStatus,
 
+    /// List registered transaction areas
+    ListTransactionAreas,
+
+    /// List the L5 global and merchant policies
+    ListPolicies,
+
     // ===========================================
     // SuperAdmin Commands
     // ===========================================
@@ -88,6 +96,33 @@ pub enum AdminCommand {
     
     /// Remove a merchant from whitelist
     RemoveMerchantWhitelist { address: String },
+
+    /// Revoke DKW-00 delegations by DKI nonce, session key or signer
+    RevokeDelegation { key: String },
+
+    /// Lift a delegation revocation
+    RestoreDelegation { key: String },
+    
+    /// Register or replace a transaction area
+    UpsertTransactionArea { area: TransactionArea },
+
+    /// Remove a transaction area
+    RemoveTransactionArea { tai: String },
+
+    /// Reload the TAI registry from its TOML file
+    ReloadTransactionAreas,
+
+    /// Replace the L5 global policy
+    SetGlobalPolicy { policy: Policy },
+
+    /// Set or replace a merchant's L5 policy
+    UpsertMerchantPolicy { profile: String, policy: Policy },
+
+    /// Remove a merchant's L5 policy
+    RemoveMerchantPolicy { profile: String },
+
+    /// Reload L5 policies from their file
+    ReloadPolicies,
     
     /// Clear the nullifier cache (dangerous!)
     ClearNullifierCache { confirm: bool },
@@ -110,6 +145,8 @@ impl AdminCommand {
             Self::GetRpcHealth => "get_rpc_health",
             Self::QuerySession { .. } => "query_session",
             Self::GetLayerStatus => "get_layer_status",
+            Self::ListTransactionAreas => "list_transaction_areas",
+            Self::ListPolicies => "list_policies",
             Self::ReloadConfig => "reload_config",
             Self::SetConfig { .. } => "set_config",
             Self::AddAdmin { .. } => "add_admin",
@@ -118,6 +155,15 @@ impl AdminCommand {
             Self::SetLayerEnabled { .. } => "set_layer_enabled",
             Self::AddMerchantWhitelist { .. } => "add_merchant_whitelist",
             Self::RemoveMerchantWhitelist { .. } => "remove_merchant_whitelist",
+            Self::RevokeDelegation { .. } => "revoke_delegation",
+            Self::RestoreDelegation { .. } => "restore_delegation",
+            Self::UpsertTransactionArea { .. } => "upsert_transaction_area",
+            Self::RemoveTransactionArea { .. } => "remove_transaction_area",
+            Self::ReloadTransactionAreas => "reload_transaction_areas",
+            Self::SetGlobalPolicy { .. } => "set_global_policy",
+            Self::UpsertMerchantPolicy { .. } => "upsert_merchant_policy",
+            Self::RemoveMerchantPolicy { .. } => "remove_merchant_policy",
+            Self::ReloadPolicies => "reload_policies",
             Self::ClearNullifierCache { .. } => "clear_nullifier_cache",
             Self::Shutdown { .. } => "shutdown",
         }
@@ -140,7 +186,9 @@ impl AdminCommand {
             | Self::GetNullifierStatus
             | Self::GetRpcHealth
             | Self::QuerySession { .. }
-            | Self::GetLayerStatus => AdminRole::Operator,
+            | Self::GetLayerStatus
+            | Self::ListTransactionAreas
+            | Self::ListPolicies => AdminRole::Operator,
 
             // SuperAdmin commands
             Self::ReloadConfig
@@ -151,6 +199,15 @@ impl AdminCommand {
             | Self::SetLayerEnabled { .. }
             | Self::AddMerchantWhitelist { .. }
             | Self::RemoveMerchantWhitelist { .. }
+            | Self::RevokeDelegation { .. }
+            | Self::RestoreDelegation { .. }
+            | Self::UpsertTransactionArea { .. }
+            | Self::RemoveTransactionArea { .. }
+            | Self::ReloadTransactionAreas
+            | Self::SetGlobalPolicy { .. }
+            | Self::UpsertMerchantPolicy { .. }
+            | Self::RemoveMerchantPolicy { .. }
+            | Self::ReloadPolicies
             | Self::ClearNullifierCache { .. }
             | Self::Shutdown { .. } => AdminRole::SuperAdmin,
         }
@@ -11,7 +11,5 @@ pub mod auth;
 pub mod commands;
 pub mod routes;
 
-pub use auth::{AdminAuth, AdminKeyStore};
-pub use commands::AdminCommand;
-pub use routes::{build_admin_routes, run_admin_command, AdminState};
+pub use routes::run_admin_command;
 
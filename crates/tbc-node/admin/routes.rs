@@ -19,25 +19,106 @@ use super::{
     commands::{AdminCommand, CommandResult},
 };
 use crate::config::GatewayConfig;
+use tbc_core::codec_tx::ReplayProtector;
+use tbc_core::tgp::anomaly::AnomalyEngine;
+use tbc_core::tgp::delegation::DelegationRevocations;
+use tbc_core::tgp::layers::CodeIntegrityAlerts;
+use tbc_core::tgp::merchants::MerchantRegistry;
+use tbc_core::tgp::policy::PolicyEngine;
+use tbc_core::tgp::tai::TaiRegistry;
+use tbc_core::zk::NullifierStore;
 
 /// Admin API state
 pub struct AdminState {
     pub auth: AdminAuth,
     pub config: GatewayConfig,
     pub start_time: std::time::Instant,
+
+    /// Replay cache shared by the HTTP and WebSocket transports
+    pub replay: Arc<dyn ReplayProtector + Send + Sync>,
+
+    /// Burned ZK nullifiers
+    pub nullifiers: Arc<dyn NullifierStore>,
+
+    /// Transaction areas, shared with the TGP router
+    pub tai: Option<Arc<TaiRegistry>>,
+
+    /// Anomaly scoring, shared with the TGP router
+    pub anomaly: Arc<AnomalyEngine>,
+
+    /// L1 merchant registry, shared with the TGP router
+    pub merchants: Option<Arc<MerchantRegistry>>,
+
+    /// L3 bytecode mismatch alerts
+    pub code_alerts: Option<Arc<CodeIntegrityAlerts>>,
+
+    /// Revoked DKW-00 delegations, shared with L2
+    pub delegations: Arc<DelegationRevocations>,
+
+    /// L5 policies, shared with the TGP router
+    pub policies: Arc<PolicyEngine>,
 }
 
 impl AdminState {
-    pub fn new(config: GatewayConfig) -> Self {
+    pub fn new(
+        config: GatewayConfig,
+        replay: Arc<dyn ReplayProtector + Send + Sync>,
+        nullifiers: Arc<dyn NullifierStore>,
+    ) -> Self {
         Self {
             auth: AdminAuth::new(),
             config,
             start_time: std::time::Instant::now(),
+            replay,
+            nullifiers,
+            tai: None,
+            anomaly: Arc::new(AnomalyEngine::new()),
+            merchants: None,
+            code_alerts: None,
+            delegations: Arc::new(DelegationRevocations::new()),
+            policies: Arc::new(PolicyEngine::new()),
         }
     }
+
+    /// Report the stats of the engine the router scores with.
+    pub fn with_anomaly_engine(mut self, engine: Arc<AnomalyEngine>) -> Self {
+        self.anomaly = engine;
+        self
+    }
+
+    /// Let admin commands edit the registry the router consults.
+    pub fn with_tai_registry(mut self, registry: Arc<TaiRegistry>) -> Self {
+        self.tai = Some(registry);
+        self
+    }
+
+    /// Let the whitelist commands edit the registry L1 consults.
+    pub fn with_merchant_registry(mut self, registry: Arc<MerchantRegistry>) -> Self {
+        self.merchants = Some(registry);
+        self
+    }
+
+    /// Report the alerts L3 records.
+    pub fn with_code_alerts(mut self, alerts: Arc<CodeIntegrityAlerts>) -> Self {
+        self.code_alerts = Some(alerts);
+        self
+    }
+
+    /// Let the revocation commands edit the list L2 consults.
+    pub fn with_delegation_revocations(mut self, revocations: Arc<DelegationRevocations>) -> Self {
+        self.delegations = revocations;
+        self
+    }
+
+    /// Let the policy commands edit the policies L5 enforces.
+    pub fn with_policy_engine(mut self, policies: Arc<PolicyEngine>) -> Self {
+        self.policies = policies;
+        self
+    }
 }
 
-/// Build admin routes
+/// Build admin routes (standalone; the node mounts these in `routers.rs`)
+#[allow(dead_code)]
 pub fn build_admin_routes(state: Arc<AdminState>) -> Router {
     Router::new()
         .route("/admin/exec", post(execute_command))
@@ -46,6 +127,7 @@ pub fn build_admin_routes(state: Arc<AdminState>) -> Router {
 }
 
 /// Public health endpoint (no auth required)
+#[allow(dead_code)]
 async fn public_health(
     State(state): State<Arc<AdminState>>,
 ) -> impl IntoResponse {
@@ -60,6 +142,7 @@ async fn public_health(
 }
 
 /// Execute authenticated admin command
+#[allow(dead_code)]
 async fn execute_command(
     State(state): State<Arc<AdminState>>,
     Json(request): Json<SignedRequest>,
@@ -169,6 +252,7 @@ pub async fn run_admin_command(
 
         AdminCommand::GetStats => {
             // TODO: Implement real stats
+            let replay = state.replay.stats();
             CommandResult::ok(cmd_name, json!({
                 "connections": {
                     "websocket": 0,
@@ -183,6 +267,14 @@ pub async fn run_admin_command(
                     "verified": 0,
                     "rejected": 0,
                 },
+                "replay": {
+                    "backend": state.config.store_backend,
+                    "entries": replay.len,
+                    "hits": replay.hits,
+                    "misses": replay.misses,
+                    "evictions": replay.evictions,
+                },
+                "anomalies": state.anomaly.stats(),
             }))
         }
 
@@ -208,9 +300,10 @@ pub async fn run_admin_command(
         }
 
         AdminCommand::GetNullifierStatus => {
-            // TODO: Implement nullifier status
+            // TODO: Track verified/rejected counts
             CommandResult::ok(cmd_name, json!({
-                "cached": 0,
+                "backend": state.config.store_backend,
+                "cached": state.nullifiers.count().await,
                 "verified": 0,
                 "rejected": 0,
             }))
@@ -236,15 +329,48 @@ pub async fn run_admin_command(
         AdminCommand::GetLayerStatus => {
             CommandResult::ok(cmd_name, json!({
                 "layers": {
-                    "L1": { "name": "Registry", "enabled": true },
-                    "L2": { "name": "Signature", "enabled": true },
-                    "L3": { "name": "Bytecode", "enabled": true },
+                    "L1": {
+                        "name": "Registry",
+                        "enabled": true,
+                        "merchants": state.merchants.as_ref().map(|m| m.stats()),
+                    },
+                    "L2": {
+                        "name": "Signature",
+                        "enabled": true,
+                        "revoked_delegations": state.delegations.len(),
+                    },
+                    "L3": {
+                        "name": "Bytecode",
+                        "enabled": true,
+                        "alerts": state.code_alerts.as_ref().map(|a| json!({
+                            "total": a.total(),
+                            "recent": a.recent(),
+                        })),
+                    },
                     "L4": { "name": "ZK", "enabled": true },
-                    "L5": { "name": "Policy", "enabled": true },
+                    "L5": {
+                        "name": "Policy",
+                        "enabled": true,
+                        "merchant_policies": state.policies.policies().merchants.len(),
+                    },
                 }
             }))
         }
 
+        AdminCommand::ListTransactionAreas => {
+            let Some(ref tai) = state.tai else {
+                return CommandResult::err(cmd_name, TAI_NOT_CONFIGURED);
+            };
+            CommandResult::ok(cmd_name, json!({
+                "local_area": tai.local_area(),
+                "areas": tai.areas(),
+            }))
+        }
+
+        AdminCommand::ListPolicies => {
+            CommandResult::ok(cmd_name, json!(state.policies.policies()))
+        }
+
         // ===========================================
         // SuperAdmin Commands
         // ===========================================
@@ -304,7 +430,7 @@ pub async fn run_admin_command(
             CommandResult::ok(cmd_name, json!({ "reloaded": true }))
         }
 
-        AdminCommand::SetConfig { key, value } => {
+        AdminCommand::SetConfig { key, value: _ } => {
             // TODO: Implement runtime config changes
             tracing::info!(
                 by = %admin.name,
@@ -314,7 +440,7 @@ pub async fn run_admin_command(
             CommandResult::err(cmd_name, "Runtime config changes not yet implemented")
         }
 
-        AdminCommand::RemoveAdmin { public_key } => {
+        AdminCommand::RemoveAdmin { public_key: _ } => {
             // TODO: Implement admin removal
             CommandResult::err(cmd_name, "Admin removal not yet implemented")
         }
@@ -330,16 +456,141 @@ pub async fn run_admin_command(
         }
 
         AdminCommand::AddMerchantWhitelist { address } => {
-            tracing::info!(
-                by = %admin.name,
-                address = %address,
-                "Merchant whitelist add attempted"
-            );
-            CommandResult::err(cmd_name, "Merchant whitelist not yet implemented")
+            let Some(ref merchants) = state.merchants else {
+                return CommandResult::err(cmd_name, MERCHANTS_NOT_CONFIGURED);
+            };
+            match merchants.whitelist(&address) {
+                Ok(added) => {
+                    tracing::info!(by = %admin.name, address = %address, added, "Merchant whitelisted");
+                    CommandResult::ok(cmd_name, json!({ "address": address, "added": added }))
+                }
+                Err(e) => CommandResult::err(cmd_name, e),
+            }
         }
 
         AdminCommand::RemoveMerchantWhitelist { address } => {
-            CommandResult::err(cmd_name, "Merchant whitelist not yet implemented")
+            let Some(ref merchants) = state.merchants else {
+                return CommandResult::err(cmd_name, MERCHANTS_NOT_CONFIGURED);
+            };
+            if merchants.unwhitelist(&address) {
+                tracing::info!(by = %admin.name, address = %address, "Merchant removed from whitelist");
+                CommandResult::ok(cmd_name, json!({ "removed": address }))
+            } else {
+                CommandResult::err(cmd_name, format!("Merchant {} is not whitelisted", address))
+            }
+        }
+
+        AdminCommand::RevokeDelegation { key } => {
+            match state.delegations.revoke(&key) {
+                Ok(added) => {
+                    tracing::info!(by = %admin.name, key = %key, added, "Delegation revoked");
+                    CommandResult::ok(cmd_name, json!({ "key": key, "added": added }))
+                }
+                Err(e) => CommandResult::err(cmd_name, e),
+            }
+        }
+
+        AdminCommand::RestoreDelegation { key } => {
+            if state.delegations.restore(&key) {
+                tracing::info!(by = %admin.name, key = %key, "Delegation restored");
+                CommandResult::ok(cmd_name, json!({ "restored": key }))
+            } else {
+                CommandResult::err(cmd_name, format!("{} is not revoked", key))
+            }
+        }
+
+        AdminCommand::UpsertTransactionArea { area } => {
+            let Some(ref tai) = state.tai else {
+                return CommandResult::err(cmd_name, TAI_NOT_CONFIGURED);
+            };
+            let area_id = area.tai.clone();
+            match tai.upsert(area) {
+                Ok(previous) => {
+                    tracing::info!(
+                        by = %admin.name,
+                        tai = %area_id,
+                        replaced = previous.is_some(),
+                        "Transaction area registered"
+                    );
+                    CommandResult::ok(cmd_name, json!({
+                        "tai": area_id,
+                        "replaced": previous.is_some(),
+                    }))
+                }
+                Err(e) => CommandResult::err(cmd_name, e),
+            }
+        }
+
+        AdminCommand::RemoveTransactionArea { tai: area_id } => {
+            let Some(ref tai) = state.tai else {
+                return CommandResult::err(cmd_name, TAI_NOT_CONFIGURED);
+            };
+            match tai.remove(&area_id) {
+                Some(_) => {
+                    tracing::info!(by = %admin.name, tai = %area_id, "Transaction area removed");
+                    CommandResult::ok(cmd_name, json!({ "removed": area_id }))
+                }
+                None => CommandResult::err(cmd_name, format!("Unknown transaction area {}", area_id)),
+            }
+        }
+
+        AdminCommand::ReloadTransactionAreas => {
+            let (Some(ref tai), Some(ref path)) = (&state.tai, &state.config.tai_file) else {
+                return CommandResult::err(cmd_name, "No TAI registry file configured (TBC_TAI_FILE)");
+            };
+            match tai.reload(path) {
+                Ok(count) => {
+                    tracing::info!(by = %admin.name, areas = count, "TAI registry reloaded");
+                    CommandResult::ok(cmd_name, json!({ "areas": count }))
+                }
+                Err(e) => CommandResult::err(cmd_name, e),
+            }
+        }
+
+        AdminCommand::SetGlobalPolicy { policy } => {
+            match state.policies.set_global(policy) {
+                Ok(()) => {
+                    tracing::info!(by = %admin.name, "Global policy replaced");
+                    CommandResult::ok(cmd_name, json!({ "global": state.policies.policies().global }))
+                }
+                Err(e) => CommandResult::err(cmd_name, e),
+            }
+        }
+
+        AdminCommand::UpsertMerchantPolicy { profile, policy } => {
+            match state.policies.upsert_merchant(&profile, policy) {
+                Ok(previous) => {
+                    tracing::info!(by = %admin.name, merchant = %profile, "Merchant policy updated");
+                    CommandResult::ok(cmd_name, json!({
+                        "merchant": profile,
+                        "replaced": previous.is_some(),
+                    }))
+                }
+                Err(e) => CommandResult::err(cmd_name, e),
+            }
+        }
+
+        AdminCommand::RemoveMerchantPolicy { profile } => {
+            match state.policies.remove_merchant(&profile) {
+                Some(_) => {
+                    tracing::info!(by = %admin.name, merchant = %profile, "Merchant policy removed");
+                    CommandResult::ok(cmd_name, json!({ "removed": profile }))
+                }
+                None => CommandResult::err(cmd_name, format!("No policy for merchant {}", profile)),
+            }
+        }
+
+        AdminCommand::ReloadPolicies => {
+            let Some(ref path) = state.config.policy_file else {
+                return CommandResult::err(cmd_name, "No policy file configured (TBC_POLICY_FILE)");
+            };
+            match state.policies.reload(path) {
+                Ok(count) => {
+                    tracing::info!(by = %admin.name, merchants = count, "Policies reloaded");
+                    CommandResult::ok(cmd_name, json!({ "merchants": count }))
+                }
+                Err(e) => CommandResult::err(cmd_name, e),
+            }
         }
 
         AdminCommand::ClearNullifierCache { confirm } => {
@@ -369,6 +620,10 @@ pub async fn run_admin_command(
     }
 }
 
+const TAI_NOT_CONFIGURED: &str = "TAI registry not configured (set TBC_AREA_ID or TBC_TAI_FILE)";
+
+const MERCHANTS_NOT_CONFIGURED: &str = "Merchant registry not configured (set TBC_MERCHANT_FACTORIES)";
+
 /// Mask a URL for safe display (hide credentials)
 fn mask_url(url: &str) -> String {
     if let Ok(parsed) = url::Url::parse(url) {
@@ -1,8 +1,26 @@
-use serde::{Serialize, Deserialize};
 use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::broadcast;
+
+use tbc_core::codec_tx::{InMemoryReplayCache, ReplayProtector, VersionRegistry, DEFAULT_REPLAY_CAPACITY};
+use tbc_core::contracts::types::address_to_hex;
+use tbc_core::protocol::SettleMessage;
+use tbc_core::store::PersistentStore;
+use tbc_core::tgp::delegation::DelegationRevocations;
+use tbc_core::tgp::layers::{
+    CodeIntegrityAlerts, CodeSource, ContractRpcLayer, CryptographicLayer, LayerPipeline, PolicyLayer,
+    RegistryLayer, ZkAttestationLayer,
+};
+use tbc_core::tgp::merchants::MerchantRegistry;
+use tbc_core::tgp::settle_verify::SettlementVerifier;
+use tbc_core::zk::{MemoryNullifierStore, NullifierStore};
+use tbc_gateway::{InboundRouter, SETTLE_FEED_CAPACITY};
 
 use crate::config::GatewayConfig;
 use crate::rpc_adapters::RpcAdapter;
+use crate::zk_verifier::RemoteZkVerifier;
 use crate::admin::routes::AdminState;
 
 #[derive(Clone)]
@@ -10,15 +28,168 @@ pub struct AppState {
     pub cfg: Arc<GatewayConfig>,
     pub rpc: Arc<RpcAdapter>,
     pub admin: Arc<AdminState>,
+
+    /// The one TGP router behind both `/tgp` and `/tgp/ws`.
+    pub router: Arc<InboundRouter>,
+
+    /// Durable store, when `store_backend = "persistent"`.
+    pub store: Option<PersistentStore>,
+
+    /// Controller watcher SETTLEs, pushed to WebSocket clients.
+    pub settles: broadcast::Sender<SettleMessage>,
+
+    /// L1 merchant registry, when factories are configured.
+    pub merchants: Option<Arc<MerchantRegistry>>,
 }
 
 impl AppState {
-    pub fn new(cfg: GatewayConfig, rpc: RpcAdapter) -> Self {
-        let admin = AdminState::new(cfg.clone());
-        Self {
+    pub fn new(cfg: GatewayConfig, rpc: RpcAdapter) -> Result<Self> {
+        let replay_ttl = Duration::from_secs(cfg.replay_ttl_secs);
+
+        // One replay cache for every transport (TGP-TBC-SEC-00 §10.2)
+        let (replay, nullifiers, store): (
+            Arc<dyn ReplayProtector + Send + Sync>,
+            Arc<dyn NullifierStore>,
+            Option<PersistentStore>,
+        ) = if cfg.persistent_store() {
+            let store = PersistentStore::open(&cfg.data_dir)?.with_replay_ttl(replay_ttl);
+            (Arc::new(store.clone()), Arc::new(store.clone()), Some(store))
+        } else {
+            (
+                Arc::new(InMemoryReplayCache::with_ttl(DEFAULT_REPLAY_CAPACITY, replay_ttl)),
+                Arc::new(MemoryNullifierStore::default()),
+                None,
+            )
+        };
+
+        // One registry for the router and the admin commands editing it
+        let tai = cfg.tai_registry().map_err(anyhow::Error::msg)?.map(Arc::new);
+
+        let anomaly = Arc::new(cfg.anomaly_engine(tai.clone()));
+
+        // Every routed SETTLE is checked against receipts from the node's RPC
+        let rpc = Arc::new(rpc);
+        let verifier = cfg
+            .settlement_contracts
+            .iter()
+            .fold(SettlementVerifier::new(rpc.clone(), cfg.chain_id), |v, c| v.with_contract(c));
+
+        // QUOTE pricing and the L5 fee ceiling share the merchant fee schedules
+        let merchant_deployments = cfg.merchant_deployments().map_err(anyhow::Error::msg)?;
+        let envelope_params = cfg.envelope_params(&merchant_deployments).map_err(anyhow::Error::msg)?;
+
+        let mut router = InboundRouter::new()
+            .with_replay(replay.clone())
+            .with_versions(VersionRegistry::default().with_legacy_chain(cfg.chain_id))
+            .with_envelope_params(envelope_params.clone())
+            .with_anomaly_engine(anomaly.clone())
+            .with_settlement_verifier(Arc::new(verifier));
+        let mut admin = AdminState::new(cfg.clone(), replay, nullifiers.clone()).with_anomaly_engine(anomaly);
+        if let Some(registry) = tai {
+            router = router.with_tai_registry(registry.clone());
+            admin = admin.with_tai_registry(registry);
+        }
+
+        // The factory mirror, pinned to the merchant file's CREATE2 parameters
+        let merchants = cfg.merchant_registry(&merchant_deployments).map_err(anyhow::Error::msg)?.map(Arc::new);
+
+        // L2 and the revocation commands share one revocation list. A
+        // merchant-scoped DKI must name the merchant's admin.
+        let revocations = Arc::new(DelegationRevocations::new());
+        let mut l2 = CryptographicLayer::new().with_revocations(revocations.clone());
+        for merchant in &merchant_deployments {
+            l2 = l2.with_merchant_admin(&merchant.contract, address_to_hex(&merchant.params.merchant_admin));
+        }
+        if let Some(ref registry) = merchants {
+            l2 = l2.with_registry(registry.clone());
+        }
+        let mut layers = LayerPipeline::default().with_layer(l2);
+        admin = admin.with_delegation_revocations(revocations);
+
+        // L4 burns ZK nullifiers into the store the admin API reports on.
+        // Without a verifier service shielded QUERYs fail closed.
+        let mut l4 = ZkAttestationLayer::new().with_nullifier_store(nullifiers);
+        if let Some(ref url) = cfg.zk_verifier_url {
+            let types = cfg.zk_proof_types().map_err(anyhow::Error::msg)?;
+            l4 = l4.with_verifier(Arc::new(RemoteZkVerifier::new(url, types)));
+        }
+        layers = layers.with_layer(l4);
+
+        // L5 and the policy commands share one policy set
+        let policies = Arc::new(cfg.policy_engine().map_err(anyhow::Error::msg)?);
+        layers = layers.with_layer(
+            PolicyLayer::new()
+                .with_engine(policies.clone())
+                .with_fee_schedule(&envelope_params),
+        );
+        admin = admin.with_policy_engine(policies);
+
+        // L1 and the whitelist commands share the factory mirror
+        if let Some(ref registry) = merchants {
+            layers = layers.with_layer(RegistryLayer::new().with_registry(registry.clone()));
+            admin = admin.with_merchant_registry(registry.clone());
+        }
+
+        // L3 reads merchant bytecode from every configured RPC
+        if cfg.bytecode_checks() {
+            let alerts = Arc::new(CodeIntegrityAlerts::new());
+            let sources = cfg
+                .l3_endpoints()
+                .into_iter()
+                .map(|url| {
+                    let source: Arc<dyn CodeSource> = if url == rpc.rpc_url {
+                        rpc.clone()
+                    } else {
+                        Arc::new(RpcAdapter::new(url))
+                    };
+                    source
+                })
+                .collect();
+            let mut l3 = ContractRpcLayer::new()
+                .with_supported_chains([cfg.chain_id])
+                .with_code_sources(sources)
+                .with_alerts(alerts.clone());
+            if let Some(quorum) = cfg.l3_quorum {
+                l3 = l3.with_quorum(quorum);
+            }
+            for (version, template) in cfg.templates().map_err(anyhow::Error::msg)? {
+                l3 = l3.with_template(version, &template);
+            }
+            // Merchants the registry has not seen are held to their file version
+            for merchant in &merchant_deployments {
+                l3 = l3.with_merchant_version(&merchant.contract, merchant.params.version);
+            }
+            if let Some(ref registry) = merchants {
+                l3 = l3.with_registry(registry.clone());
+            }
+            layers = layers.with_layer(l3);
+            admin = admin.with_code_alerts(alerts);
+        }
+        router = router.with_layers(layers);
+
+        Ok(Self {
             cfg: Arc::new(cfg),
-            rpc: Arc::new(rpc),
+            rpc,
             admin: Arc::new(admin),
-        }
+            router: Arc::new(router),
+            store,
+            settles: broadcast::channel(SETTLE_FEED_CAPACITY).0,
+            merchants,
+        })
     }
-}
\ No newline at end of file
+}
+
+/// Periodically drop expired replay IDs and nullifiers from the durable store.
+pub fn spawn_store_compaction(store: PersistentStore, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match store.compact() {
+                Ok(0) => {}
+                Ok(n) => tracing::debug!(removed = n, "Store compaction"),
+                Err(e) => tracing::error!(error = %e, "Store compaction failed"),
+            }
+        }
+    });
+}
@@ -1,5 +1,20 @@
 use serde::{Serialize, Deserialize};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tbc_core::contracts::factory::TemplateInfo;
+use tbc_core::contracts::types::{hex_to_bytes32, Bytes32};
+use tbc_core::tgp::anomaly::AnomalyEngine;
+use tbc_core::tgp::expiry::EnvelopeLifetimes;
+use tbc_core::tgp::merchants::{load_merchant_deployments, MerchantDeployment, MerchantRegistry};
+use tbc_core::tgp::policy::PolicyEngine;
+use tbc_core::tgp::routing::{PeerGateway, DEFAULT_MAX_HOPS};
+use tbc_core::tgp::tai::{TaiRegistry, TransactionArea};
+use tbc_core::tgp::types::DomainTrust;
+use tbc_core::tgp::tx_builder::{EnvelopeParams, DEFAULT_GAS_LIMIT, DEFAULT_MAX_FEES_BPS};
+use tbc_core::tgp::validation::validate_address;
+use tbc_core::zk::ZkProofType;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayConfig {
@@ -10,6 +25,9 @@ pub struct GatewayConfig {
     /// SETTLE monitoring interval (in ms)
     pub settle_poll_interval_ms: u64,
 
+    /// Blocks the watcher waits before acting on a log (reorg depth)
+    pub settle_confirmations: u64,
+
     /// Allow CORS for extension
     pub allow_origin: String,
     
@@ -21,6 +39,92 @@ pub struct GatewayConfig {
     
     /// Log level (default: info)
     pub log_level: String,
+
+    /// Replay / nullifier store backend: "memory" or "persistent"
+    pub store_backend: String,
+
+    /// Directory for the persistent store
+    pub data_dir: String,
+
+    /// Replay window for message IDs (in seconds)
+    pub replay_ttl_secs: u64,
+
+    /// Persistent store compaction interval (in seconds)
+    pub store_compact_interval_secs: u64,
+
+    /// ACK(allow) envelope lifetime (in seconds)
+    pub envelope_ttl_secs: u64,
+
+    /// Per payment profile envelope lifetimes, `0xprofile=secs,...` as
+    /// given; checked by `envelope_lifetimes()`
+    pub envelope_ttl_profiles: Option<String>,
+
+    /// Routing fee charged per envelope (basis points)
+    pub fees_bps: u32,
+
+    /// Fee ceiling advertised per envelope (basis points)
+    pub max_fees_bps: u32,
+
+    /// Gas limit for escrow contract calls
+    pub gas_limit: u64,
+
+    /// Public URL of this gateway, advertised as `tbc_endpoint`
+    pub public_url: Option<String>,
+
+    /// Transaction area this gateway serves; enables multi-hop forwarding
+    pub area_id: Option<String>,
+
+    /// Peer gateways for QUERYs bound to other areas, `AREA=url@trust,...`
+    /// as given; checked by `peers()`
+    pub peers: Option<String>,
+
+    /// Maximum `routing.path` length for forwarded QUERYs
+    pub max_hops: usize,
+
+    /// TOML file describing transaction areas and their peers
+    pub tai_file: Option<String>,
+
+    /// L5 policy file (TOML, or JSON by extension)
+    pub policy_file: Option<String>,
+
+    /// QUERY anomaly score that turns ACK(allow) into ACK(revise)
+    pub anomaly_revise_score: Option<u16>,
+
+    /// QUERY anomaly score that turns ACK(allow) into ACK(deny)
+    pub anomaly_deny_score: Option<u16>,
+
+    /// Settlement contracts the watcher polls; their receipts also back
+    /// SETTLEs for QUERYs this gateway did not issue an envelope for
+    pub settlement_contracts: Vec<String>,
+
+    /// MerchantContractFactory addresses; enables the L1 merchant registry
+    pub merchant_factories: Vec<String>,
+
+    /// Block the merchant registry replays factory history from (the
+    /// factories' deploy block); required with `merchant_factories`
+    pub merchant_from_block: Option<u64>,
+
+    /// TOML file with the deployment parameters (and fee schedules) of
+    /// the merchants this gateway serves
+    pub merchants_file: Option<String>,
+
+    /// Extra RPC endpoints L3 reads merchant bytecode from, besides `rpc_url`
+    pub l3_rpc_urls: Vec<String>,
+
+    /// Endpoints that must agree on a merchant's bytecode (default: majority)
+    pub l3_quorum: Option<usize>,
+
+    /// Merchant template code hashes, `version=0xhash,...` as given
+    /// (keccak256 of runtime code); checked by `templates()`
+    pub template_code_hashes: Option<String>,
+
+    /// Service L4 sends attached ZK proofs to; without one shielded
+    /// QUERYs fail closed
+    pub zk_verifier_url: Option<String>,
+
+    /// Proof types the verifier service is trusted for, `ZKB01,...` as
+    /// given; checked by `zk_proof_types()`
+    pub zk_proof_types: Option<String>,
 }
 
 impl GatewayConfig {
@@ -31,10 +135,37 @@ impl GatewayConfig {
     /// - TBC_RPC_URL: Blockchain RPC endpoint (default: https://rpc.pulsechain.com)
     /// - TBC_CHAIN_ID: Chain ID (default: 369 for PulseChain)
     /// - TBC_SETTLE_POLL_MS: Settlement poll interval in ms (default: 1000)
+    /// - TBC_SETTLE_CONFIRMATIONS: Blocks before watched logs are acted on (default: 12)
     /// - TBC_ALLOW_ORIGIN: CORS allowed origin (default: *)
     /// - TBC_ID: Instance identifier for logging (default: tbc-primary)
     /// - TBC_WS_PATH: WebSocket path (default: /tgp/ws)
     /// - TBC_LOG_LEVEL: Log level (default: info)
+    /// - TBC_STORE_BACKEND: Replay/nullifier store, memory|persistent (default: memory)
+    /// - TBC_DATA_DIR: Persistent store directory (default: ./data)
+    /// - TBC_REPLAY_TTL_SECS: Replay window in seconds (default: 600)
+    /// - TBC_STORE_COMPACT_SECS: Persistent store compaction interval (default: 60)
+    /// - TBC_ENVELOPE_TTL_SECS: ACK(allow) envelope lifetime in seconds (default: 300)
+    /// - TBC_ENVELOPE_TTL_PROFILES: Per-profile lifetimes, `0xprofile=secs,...` (default: none)
+    /// - TBC_FEES_BPS: Routing fee in basis points (default: 0)
+    /// - TBC_MAX_FEES_BPS: Advertised fee ceiling in basis points (default: 100)
+    /// - TBC_GAS_LIMIT: Envelope gas limit (default: 250000)
+    /// - TBC_PUBLIC_URL: Public gateway URL for envelopes (default: none)
+    /// - TBC_AREA_ID: Local transaction area; enables forwarding (default: none)
+    /// - TBC_PEERS: Peer gateways, `AREA=url@trust,...` (default: none)
+    /// - TBC_MAX_HOPS: Hop limit for forwarded QUERYs (default: 8)
+    /// - TBC_TAI_FILE: TAI registry TOML; replaces the three above (default: none)
+    /// - TBC_POLICY_FILE: L5 merchant/global policies, TOML or JSON (default: none)
+    /// - TBC_ANOMALY_REVISE_SCORE: Anomaly score answered with ACK(revise) (default: off)
+    /// - TBC_ANOMALY_DENY_SCORE: Anomaly score answered with ACK(deny) (default: off)
+    /// - TBC_SETTLEMENT_CONTRACTS: Watched settlement contracts, `0xaddr,...` (default: none)
+    /// - TBC_MERCHANT_FACTORIES: Merchant factories backing L1, `0xaddr,...` (default: none)
+    /// - TBC_MERCHANT_FROM_BLOCK: Factory deploy block to mirror history from (required with factories)
+    /// - TBC_MERCHANTS_FILE: Merchant deployment parameters and fee schedules, TOML (default: none)
+    /// - TBC_L3_RPC_URLS: Extra RPCs for the L3 bytecode quorum, `url,...` (default: none)
+    /// - TBC_L3_QUORUM: RPCs that must agree on merchant bytecode (default: majority)
+    /// - TBC_TEMPLATE_CODE_HASHES: Template code hashes, `version=0xhash,...` (default: none)
+    /// - TBC_ZK_VERIFIER_URL: ZK proof verifier service for L4 (default: none)
+    /// - TBC_ZK_PROOF_TYPES: Proof types it verifies, `ZKB01,...` (default: ZKB01,ZKS01)
     /// - PORT: Alternative port binding (for Railway/Heroku compatibility)
     pub fn load() -> Self {
         // Support PORT env var for Railway/Heroku/Fly.io
@@ -58,6 +189,11 @@ impl GatewayConfig {
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(1000),
             
+            settle_confirmations: env::var("TBC_SETTLE_CONFIRMATIONS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(12),
+            
             allow_origin: env::var("TBC_ALLOW_ORIGIN")
                 .unwrap_or_else(|_| "*".into()),
             
@@ -69,9 +205,267 @@ impl GatewayConfig {
             
             log_level: env::var("TBC_LOG_LEVEL")
                 .unwrap_or_else(|_| "info".into()),
+            
+            store_backend: env::var("TBC_STORE_BACKEND")
+                .unwrap_or_else(|_| "memory".into()),
+            
+            data_dir: env::var("TBC_DATA_DIR")
+                .unwrap_or_else(|_| "./data".into()),
+            
+            replay_ttl_secs: env::var("TBC_REPLAY_TTL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(600),
+            
+            store_compact_interval_secs: env::var("TBC_STORE_COMPACT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(60),
+            
+            envelope_ttl_secs: env::var("TBC_ENVELOPE_TTL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(300),
+            
+            envelope_ttl_profiles: env::var("TBC_ENVELOPE_TTL_PROFILES").ok(),
+            
+            fees_bps: env::var("TBC_FEES_BPS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+            
+            max_fees_bps: env::var("TBC_MAX_FEES_BPS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_MAX_FEES_BPS),
+            
+            gas_limit: env::var("TBC_GAS_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_GAS_LIMIT),
+            
+            public_url: env::var("TBC_PUBLIC_URL").ok(),
+            
+            area_id: env::var("TBC_AREA_ID").ok(),
+            
+            peers: env::var("TBC_PEERS").ok(),
+            
+            max_hops: env::var("TBC_MAX_HOPS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_MAX_HOPS),
+            
+            tai_file: env::var("TBC_TAI_FILE").ok(),
+
+            policy_file: env::var("TBC_POLICY_FILE").ok(),
+            
+            anomaly_revise_score: env::var("TBC_ANOMALY_REVISE_SCORE")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            
+            anomaly_deny_score: env::var("TBC_ANOMALY_DENY_SCORE")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            
+            settlement_contracts: env::var("TBC_SETTLEMENT_CONTRACTS")
+                .map(|s| parse_addresses(&s))
+                .unwrap_or_default(),
+            
+            merchant_factories: env::var("TBC_MERCHANT_FACTORIES")
+                .map(|s| parse_addresses(&s))
+                .unwrap_or_default(),
+            
+            merchant_from_block: env::var("TBC_MERCHANT_FROM_BLOCK")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            
+            merchants_file: env::var("TBC_MERCHANTS_FILE").ok(),
+            
+            l3_rpc_urls: env::var("TBC_L3_RPC_URLS")
+                .map(|s| s.split(',').map(|u| u.trim().to_string()).filter(|u| !u.is_empty()).collect())
+                .unwrap_or_default(),
+            
+            l3_quorum: env::var("TBC_L3_QUORUM")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            
+            template_code_hashes: env::var("TBC_TEMPLATE_CODE_HASHES").ok(),
+            
+            zk_verifier_url: env::var("TBC_ZK_VERIFIER_URL").ok(),
+            
+            zk_proof_types: env::var("TBC_ZK_PROOF_TYPES").ok(),
+        }
+    }
+
+    /// Envelope lifetimes for the TGP router. Any malformed or repeated
+    /// per-profile entry is an error.
+    pub fn envelope_lifetimes(&self) -> Result<EnvelopeLifetimes, String> {
+        let profiles = match self.envelope_ttl_profiles {
+            Some(ref raw) => parse_profile_ttls(raw).map_err(|e| format!("TBC_ENVELOPE_TTL_PROFILES: {}", e))?,
+            None => Vec::new(),
+        };
+        Ok(profiles.into_iter().fold(
+            EnvelopeLifetimes::with_default(Duration::from_secs(self.envelope_ttl_secs)),
+            |lifetimes, (profile, secs)| lifetimes.with_profile(&profile, Duration::from_secs(secs)),
+        ))
+    }
+
+    /// Economic Envelope parameters for the TGP router, with the fee
+    /// schedule of every merchant in `merchants_file`.
+    pub fn envelope_params(&self, merchants: &[MerchantDeployment]) -> Result<EnvelopeParams, String> {
+        let mut params = EnvelopeParams::new()
+            .with_gas_limit(self.gas_limit)
+            .with_fees_bps(self.fees_bps)
+            .with_max_fees_bps(self.max_fees_bps)
+            .with_rpc_url(&self.rpc_url)
+            .with_lifetimes(self.envelope_lifetimes()?);
+
+        if let Some(ref url) = self.public_url {
+            params = params.with_tbc_endpoint(url);
+        }
+        Ok(merchants
+            .iter()
+            .fold(params, |p, m| p.with_merchant_fees(&m.contract, m.params.clone())))
+    }
+
+    /// Merchants listed in `merchants_file`; none without one.
+    pub fn merchant_deployments(&self) -> Result<Vec<MerchantDeployment>, String> {
+        match self.merchants_file {
+            Some(ref path) => load_merchant_deployments(path),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// L5 policies, loaded from `policy_file`; empty (allow all) without one.
+    pub fn policy_engine(&self) -> Result<PolicyEngine, String> {
+        match self.policy_file {
+            Some(ref path) => PolicyEngine::load(path),
+            None => Ok(PolicyEngine::new()),
+        }
+    }
+
+    /// TAI registry for multi-hop routing: loaded from `tai_file`, or
+    /// built from `area_id` and `peers`. `None` when neither is set.
+    pub fn tai_registry(&self) -> Result<Option<TaiRegistry>, String> {
+        if let Some(ref path) = self.tai_file {
+            return TaiRegistry::load(path).map(Some);
+        }
+        let peers = self.peers()?;
+        let Some(ref area) = self.area_id else {
+            return Ok(None);
+        };
+
+        let registry = TaiRegistry::new(area)?.with_max_hops(self.max_hops);
+        for peer in &peers {
+            let mut entry = registry.get(&peer.area).unwrap_or_else(|| TransactionArea::new(&peer.area));
+            entry = entry.with_peer(&peer.url, peer.trust);
+            registry.upsert(entry)?;
+        }
+        Ok(Some(registry))
+    }
+    
+    /// Peer gateways from `TBC_PEERS`. Any malformed entry, or unknown
+    /// trust level, is an error.
+    pub fn peers(&self) -> Result<Vec<PeerGateway>, String> {
+        match self.peers {
+            Some(ref raw) => parse_peers(raw).map_err(|e| format!("TBC_PEERS: {}", e)),
+            None => Ok(Vec::new()),
         }
     }
     
+    /// Anomaly engine for this gateway's chain and areas: every local area
+    /// of the TAI registry when there is one, else `area_id`.
+    pub fn anomaly_engine(&self, tai: Option<Arc<TaiRegistry>>) -> AnomalyEngine {
+        let mut engine = AnomalyEngine::new().with_chain(self.chain_id);
+        match (tai, &self.area_id) {
+            (Some(registry), _) => engine = engine.with_tai_registry(registry),
+            (None, Some(area)) => engine = engine.with_area(area),
+            (None, None) => {}
+        }
+        if let Some(score) = self.anomaly_revise_score {
+            engine = engine.with_revise_threshold(score);
+        }
+        if let Some(score) = self.anomaly_deny_score {
+            engine = engine.with_deny_threshold(score);
+        }
+        engine
+    }
+    
+    /// Factory-backed merchant registry for L1, with the merchant file
+    /// entries that name their factory pinned; `None` without factories.
+    pub fn merchant_registry(&self, merchants: &[MerchantDeployment]) -> Result<Option<MerchantRegistry>, String> {
+        if self.merchant_factories.is_empty() {
+            if let Some(m) = merchants.iter().find(|m| m.factory.is_some()) {
+                return Err(format!("merchant {} names a factory, but TBC_MERCHANT_FACTORIES is unset", m.contract));
+            }
+            return Ok(None);
+        }
+        let from_block = self
+            .merchant_from_block
+            .ok_or("TBC_MERCHANT_FROM_BLOCK (the factories' deploy block) is required with TBC_MERCHANT_FACTORIES")?;
+
+        let registry = self
+            .merchant_factories
+            .iter()
+            .try_fold(MerchantRegistry::new(), |r, f| r.with_factory(f))?;
+        for merchant in merchants {
+            registry.pin(merchant)?;
+        }
+        registry.set_next_block(from_block);
+        Ok(Some(registry))
+    }
+    
+    /// True when L3 should check merchant bytecode over RPC.
+    pub fn bytecode_checks(&self) -> bool {
+        !self.l3_rpc_urls.is_empty() || self.template_code_hashes.is_some()
+    }
+    
+    /// RPC endpoints for the L3 bytecode quorum: `rpc_url` first, then the
+    /// extra endpoints (duplicates dropped).
+    pub fn l3_endpoints(&self) -> Vec<String> {
+        let mut urls = vec![self.rpc_url.clone()];
+        for url in &self.l3_rpc_urls {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+        urls
+    }
+    
+    /// Configured merchant templates, by version. Any malformed or
+    /// repeated entry is an error.
+    pub fn templates(&self) -> Result<Vec<(u64, TemplateInfo)>, String> {
+        let Some(ref raw) = self.template_code_hashes else {
+            return Ok(Vec::new());
+        };
+        Ok(parse_template_hashes(raw)
+            .map_err(|e| format!("TBC_TEMPLATE_CODE_HASHES: {}", e))?
+            .into_iter()
+            .map(|(version, code_hash)| (version, TemplateInfo { code_hash, exists: true, ..Default::default() }))
+            .collect())
+    }
+    
+    /// Proof types the ZK verifier service is trusted for. An unknown or
+    /// repeated type is an error.
+    pub fn zk_proof_types(&self) -> Result<Vec<ZkProofType>, String> {
+        let raw = self.zk_proof_types.as_deref().unwrap_or("ZKB01,ZKS01");
+        let mut out = Vec::new();
+        for name in raw.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let zk_type: ZkProofType = serde_json::from_value(serde_json::Value::String(name.to_uppercase()))
+                .map_err(|_| format!("TBC_ZK_PROOF_TYPES: unknown proof type {}", name))?;
+            if out.contains(&zk_type) {
+                return Err(format!("TBC_ZK_PROOF_TYPES: {} listed twice", zk_type));
+            }
+            out.push(zk_type);
+        }
+        Ok(out)
+    }
+    
+    /// True when replay IDs and nullifiers should survive restarts.
+    pub fn persistent_store(&self) -> bool {
+        self.store_backend.eq_ignore_ascii_case("persistent")
+    }
+    
     /// Print configuration summary (safe - no secrets)
     pub fn print_summary(&self) {
         println!("┌────────────────────────────────────────┐");
@@ -83,6 +477,82 @@ impl GatewayConfig {
         println!("│ WS Path:   {:<27}│", self.ws_path);
         println!("│ Log Level: {:<27}│", self.log_level);
         println!("│ CORS:      {:<27}│", self.allow_origin);
+        println!("│ Store:     {:<27}│", self.store_backend);
         println!("└────────────────────────────────────────┘");
     }
-}
\ No newline at end of file
+}
+
+/// Parse `0xaddr,0xother`, lower-cased. Empty entries are skipped.
+fn parse_addresses(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|a| a.trim().to_lowercase())
+        .filter(|a| !a.is_empty())
+        .collect()
+}
+
+/// Parse `1=0xhash,2=0xhash`. Empty entries are skipped; a malformed or
+/// repeated one fails the whole list.
+fn parse_template_hashes(raw: &str) -> Result<Vec<(u64, Bytes32)>, String> {
+    let mut out: Vec<(u64, Bytes32)> = Vec::new();
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (version, hash) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("{} is not version=0xhash", entry))?;
+        let version: u64 = version
+            .trim()
+            .parse()
+            .map_err(|_| format!("{}: version must be an integer", entry))?;
+        let hash = hex_to_bytes32(hash.trim()).map_err(|e| format!("template v{} code hash: {}", version, e))?;
+        if out.iter().any(|(v, _)| *v == version) {
+            return Err(format!("template v{} listed twice", version));
+        }
+        out.push((version, hash));
+    }
+    Ok(out)
+}
+
+/// Parse `0xprofile=secs,0xother=secs`, profiles lower-cased. Empty
+/// entries are skipped; a malformed or repeated one fails the whole list.
+fn parse_profile_ttls(raw: &str) -> Result<Vec<(String, u64)>, String> {
+    let mut out: Vec<(String, u64)> = Vec::new();
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (profile, secs) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("{} is not 0xprofile=secs", entry))?;
+        let profile = profile.trim().to_lowercase();
+        validate_address(&profile, "profile").map_err(|e| format!("{}: {}", entry, e))?;
+        let secs: u64 = secs
+            .trim()
+            .parse()
+            .map_err(|_| format!("{}: lifetime must be whole seconds", entry))?;
+        if out.iter().any(|(p, _)| *p == profile) {
+            return Err(format!("profile {} listed twice", profile));
+        }
+        out.push((profile, secs));
+    }
+    Ok(out)
+}
+
+/// Parse `AREA=url@trust,...`; trust is unknown|low|medium|high and
+/// defaults to low. Empty entries are skipped; a malformed one fails the
+/// whole list.
+fn parse_peers(raw: &str) -> Result<Vec<PeerGateway>, String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (area, rest) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("{} is not AREA=url@trust", entry))?;
+            let (url, trust) = match rest.rsplit_once('@') {
+                Some((url, trust)) => (url, trust.parse().map_err(|e| format!("{}: {}", entry, e))?),
+                None => (rest, DomainTrust::Low),
+            };
+            let (area, url) = (area.trim(), url.trim());
+            if area.is_empty() || url.is_empty() {
+                return Err(format!("{} is not AREA=url@trust", entry));
+            }
+            Ok(PeerGateway::new(area, url, trust))
+        })
+        .collect()
+}
@@ -2,9 +2,13 @@ mod admin;
 mod app_state;
 mod config;
 mod rpc_adapters;
+mod watcher;
 mod routers;
 mod health;
 mod errors;
+mod zk_verifier;
+
+use std::time::Duration;
 
 use tokio::net::TcpListener;
 use tower_http::cors::{CorsLayer, Any};
@@ -14,7 +18,11 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use crate::{
     config::GatewayConfig,
     rpc_adapters::RpcAdapter,
-    app_state::AppState,
+    app_state::{spawn_store_compaction, AppState},
+    watcher::{
+        spawn_merchant_registry_sync, spawn_settlement_watcher, MerchantRegistrySync,
+        SettlementWatcher,
+    },
 };
 
 #[tokio::main]
@@ -60,7 +68,32 @@ async fn main() {
     // Initialize adapters
     // ------------------------------------------------------
     let rpc = RpcAdapter::new(cfg.rpc_url.clone());
-    let state = AppState::new(cfg.clone(), rpc);
+    let state = AppState::new(cfg.clone(), rpc).expect("Failed to initialize gateway state");
+
+    if let Some(store) = state.store.clone() {
+        spawn_store_compaction(store, Duration::from_secs(cfg.store_compact_interval_secs));
+    }
+
+    if cfg.settlement_contracts.is_empty() && state.merchants.is_none() {
+        tracing::info!("Settlement watcher disabled (set TBC_SETTLEMENT_CONTRACTS or TBC_MERCHANT_FACTORIES)");
+    } else {
+        let mut watcher = SettlementWatcher::new(
+            state.rpc.clone(),
+            cfg.settlement_contracts.clone(),
+            state.settles.clone(),
+        )
+        .with_confirmations(cfg.settle_confirmations);
+        if let Some(registry) = state.merchants.clone() {
+            watcher = watcher.with_merchant_registry(registry);
+        }
+        spawn_settlement_watcher(watcher, Duration::from_millis(cfg.settle_poll_interval_ms.max(1)));
+    }
+
+    if let Some(registry) = state.merchants.clone() {
+        let sync = MerchantRegistrySync::new(state.rpc.clone(), registry)
+            .with_confirmations(cfg.settle_confirmations);
+        spawn_merchant_registry_sync(sync, Duration::from_millis(cfg.settle_poll_interval_ms.max(1)));
+    }
 
     // ------------------------------------------------------
     // Build Axum router with CORS
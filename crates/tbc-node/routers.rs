@@ -4,7 +4,7 @@ use axum::{
     Extension,
     routing::{post, get},
     extract::State,
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse,
     Json,
 };
@@ -16,13 +16,19 @@ use crate::{
     admin::auth::SignedRequest,
     admin::commands::{AdminCommand, CommandResult},
 };
-use tbc_gateway::{InboundRouter, TGPInboundRouter, WsState};
+use tbc_core::codec_tx::encode_message;
+use tbc_core::protocol::{make_protocol_error, TGPMessage, TgpErrorCode};
+use tbc_gateway::WsState;
 
 pub fn build_routes(state: AppState) -> Router {
-    // Create WebSocket state (stateless per TGP-TBC-SEC-00)
-    let ws_state = Arc::new(WsState {
-        tbc_id: state.cfg.tbc_id.clone().unwrap_or_else(|| "tbc-default".to_string()),
-    });
+    // WebSocket state shares the HTTP router (one replay cache, no sessions)
+    let ws_state = Arc::new(
+        WsState::new(
+            state.cfg.tbc_id.clone().unwrap_or_else(|| "tbc-default".to_string()),
+            state.router.clone(),
+        )
+        .with_settle_feed(state.settles.clone()),
+    );
     
     // Log admin key status
     let admin_count = state.admin.auth.key_store().list_admins().len();
@@ -61,26 +67,40 @@ pub fn build_routes(state: AppState) -> Router {
 /// HTTP POST handler for TGP messages
 /// 
 /// SECURITY: Routes through full L1-L6 verification pipeline
+/// Gateway-originated ERRORs carry the HTTP status of their code.
 async fn tgp_inbound(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     body: String,
-) -> String {
-    let router = InboundRouter::new();
-    router.route_inbound(&body).await.unwrap_or_else(|e| {
-        // Fail-closed: return structured ERROR
-        format!(r#"{{"type":"ERROR","code":"TBC_HTTP_DISPATCH_ERROR","layer_failed":0,"message":"{}"}}"#, e)
-    })
+) -> impl IntoResponse {
+    let (outbound, refused) = match state.router.route_json(&body).await {
+        Ok(routed) => routed,
+        Err(e) => {
+            // Fail-closed: return structured ERROR
+            let code = TgpErrorCode::HttpDispatchError;
+            let err = TGPMessage::Error(make_protocol_error(code, e.to_string()));
+            (encode_message(&err).unwrap_or_default(), Some(code))
+        }
+    };
+
+    // Only ERRORs this gateway raised map to a status; echoed and
+    // relayed ERRORs travel as 200
+    let status = refused
+        .and_then(|c| StatusCode::from_u16(c.http_status()).ok())
+        .unwrap_or(StatusCode::OK);
+
+    (status, [(header::CONTENT_TYPE, "application/json")], outbound)
 }
 
 /// WebSocket upgrade handler for TGP messages
 /// 
-/// SECURITY: Uses same InboundRouter as HTTP endpoint
+/// SECURITY: Uses the same InboundRouter (and replay cache) as HTTP
 /// Per TGP-TBC-SEC-00: identical security guarantees
 async fn ws_handler(
     Extension(ws_state): Extension<Arc<WsState>>,
     ws: axum::extract::ws::WebSocketUpgrade,
 ) -> impl axum::response::IntoResponse {
-    ws.on_upgrade(move |socket| tbc_gateway::ws::handler::handle_ws_public(socket, ws_state))
+    ws.protocols(tbc_gateway::ws::WS_SUBPROTOCOLS)
+        .on_upgrade(move |socket| tbc_gateway::ws::handler::handle_ws_public(socket, ws_state))
 }
 
 /// Public status endpoint (no auth - for evaluators/demos)
@@ -102,6 +122,7 @@ async fn public_status(
         "service": "CoreProve TBC",
         "version": "0.1.0",
         "protocol": "TGP-00 v3.2",
+        "supported_versions": state.router.supported_versions(),
         "status": "operational",
         "uptime_seconds": uptime,
         "region": std::env::var("FLY_REGION").unwrap_or_else(|_| "unknown".into()),
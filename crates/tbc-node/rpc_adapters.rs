@@ -1,23 +1,138 @@
-use anyhow::Result;
-use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tbc_core::tgp::layers::CodeSource;
+use tbc_core::tgp::settle_verify::ReceiptProvider;
+
+/// Longest a JSON-RPC call may take, connection included.
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest to wait for the connection to an RPC endpoint.
+const RPC_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
 pub struct RpcAdapter {
     pub rpc_url: String,
+    http: reqwest::Client,
+    next_id: AtomicU64,
 }
 
+#[allow(dead_code)]
 impl RpcAdapter {
     pub fn new(rpc_url: impl Into<String>) -> Self {
-        Self { rpc_url: rpc_url.into() }
+        Self {
+            rpc_url: rpc_url.into(),
+            http: reqwest::Client::builder()
+                .timeout(RPC_TIMEOUT)
+                .connect_timeout(RPC_CONNECT_TIMEOUT)
+                .build()
+                .expect("Failed to build RPC HTTP client"),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// One JSON-RPC 2.0 call; returns `result`, or the node's `error` as an Err.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        let mut resp: Value = self
+            .http
+            .post(&self.rpc_url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(err) = resp.get("error") {
+            return Err(anyhow!("{} failed: {}", method, err));
+        }
+        Ok(resp.get_mut("result").map(Value::take).unwrap_or(Value::Null))
     }
 
     pub async fn eth_call(&self, to: &str, data: &str) -> Result<String> {
-        // Future: use reqwest or ethers-rs
-        // Stub for now
-        Ok("0xdeadbeef".into())
+        let result = self
+            .request("eth_call", json!([{ "to": to, "data": data }, "latest"]))
+            .await?;
+        result
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("eth_call returned {}", result))
+    }
+
+    /// `None` while the transaction is unknown or pending.
+    pub async fn get_tx_receipt(&self, tx_hash: &str) -> Result<Option<Value>> {
+        let receipt = self.request("eth_getTransactionReceipt", json!([tx_hash])).await?;
+        Ok(Some(receipt).filter(|r| !r.is_null()))
+    }
+
+    /// Runtime bytecode at `address` (empty when nothing is deployed).
+    pub async fn get_code(&self, address: &str) -> Result<Vec<u8>> {
+        let result = self.request("eth_getCode", json!([address, "latest"])).await?;
+        let code = result.as_str().ok_or_else(|| anyhow!("eth_getCode returned {}", result))?;
+        Ok(hex::decode(code.strip_prefix("0x").unwrap_or(code))?)
+    }
+
+    pub async fn get_chain_id(&self) -> Result<u64> {
+        let result = self.request("eth_chainId", json!([])).await?;
+        parse_quantity(&result).ok_or_else(|| anyhow!("eth_chainId returned {}", result))
+    }
+
+    pub async fn block_number(&self) -> Result<u64> {
+        let result = self.request("eth_blockNumber", json!([])).await?;
+        parse_quantity(&result).ok_or_else(|| anyhow!("eth_blockNumber returned {}", result))
     }
 
-    pub async fn get_tx_receipt(&self, tx_hash: &str) -> Result<Option<serde_json::Value>> {
-        Ok(None) // stub
+    /// Logs emitted by `addresses` in `[from, to]` whose `topics[0]` is one of `topics`.
+    pub async fn get_logs(&self, addresses: &[String], topics: &[String], from: u64, to: u64) -> Result<Vec<Value>> {
+        let filter = json!({
+            "address": addresses,
+            "topics": [topics],
+            "fromBlock": format!("0x{:x}", from),
+            "toBlock": format!("0x{:x}", to),
+        });
+        match self.request("eth_getLogs", json!([filter])).await? {
+            Value::Array(logs) => Ok(logs),
+            other => Err(anyhow!("eth_getLogs returned {}", other)),
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Hex JSON-RPC quantity (`"0x1a"`).
+fn parse_quantity(v: &Value) -> Option<u64> {
+    u64::from_str_radix(v.as_str()?.strip_prefix("0x")?, 16).ok()
+}
+
+#[async_trait]
+impl ReceiptProvider for RpcAdapter {
+    async fn chain_id(&self) -> Result<u64, String> {
+        self.get_chain_id().await.map_err(|e| e.to_string())
+    }
+
+    async fn tx_receipt(&self, tx_hash: &str) -> Result<Option<Value>, String> {
+        self.get_tx_receipt(tx_hash).await.map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl CodeSource for RpcAdapter {
+    /// Host only: paths and credentials often carry API keys.
+    fn label(&self) -> String {
+        url::Url::parse(&self.rpc_url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_else(|| "rpc".into())
+    }
+
+    async fn code_at(&self, address: &str) -> Result<Vec<u8>, String> {
+        self.get_code(address).await.map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,201 @@
+//! Controller Watcher
+//!
+//! Polls the settlement contracts -- the configured ones and every merchant
+//! in the L1 registry -- for `SettlementCompleted`, `BuyerRefunded` and
+//! `BuyerCommitted` logs every `settle_poll_interval_ms`, turns each into
+//! a watcher SETTLE (`tgp::settle_watch`) and publishes it on the SETTLE
+//! feed, from which WebSocket connections push it to the client that
+//! opened the order.
+//!
+//! Also keeps the L1 merchant registry in step with the merchant factories:
+//! their `MerchantDeployed` / `MerchantRevoked` history is replayed from
+//! `merchant_from_block`, then followed on the same interval.
+//!
+//! Both only read blocks `settle_confirmations` deep, so a shallow reorg
+//! cannot retract a log already acted on.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::broadcast;
+
+use tbc_core::contracts::{
+    bytes32_to_hex, event_topic, MERCHANT_DEPLOYED_EVENT_SIG, MERCHANT_REVOKED_EVENT_SIG,
+};
+use tbc_core::protocol::SettleMessage;
+use tbc_core::tgp::merchants::MerchantRegistry;
+use tbc_core::tgp::settle_watch::{settle_from_log, watched_topics};
+
+use crate::rpc_adapters::RpcAdapter;
+
+/// Most blocks requested per `eth_getLogs` call.
+const MAX_BLOCK_RANGE: u64 = 1_000;
+
+/// Same, while replaying factory history (sparse logs).
+const HISTORY_BLOCK_RANGE: u64 = 10_000;
+
+pub struct SettlementWatcher {
+    rpc: Arc<RpcAdapter>,
+    contracts: Vec<String>,
+    /// Merchant contracts admitted by L1, watched alongside `contracts`
+    merchants: Option<Arc<MerchantRegistry>>,
+    confirmations: u64,
+    topics: Vec<String>,
+    feed: broadcast::Sender<SettleMessage>,
+    /// First block not yet scanned; `None` until the chain head is known
+    next_block: Option<u64>,
+}
+
+impl SettlementWatcher {
+    pub fn new(rpc: Arc<RpcAdapter>, contracts: Vec<String>, feed: broadcast::Sender<SettleMessage>) -> Self {
+        Self {
+            rpc,
+            contracts,
+            merchants: None,
+            confirmations: 0,
+            topics: watched_topics(),
+            feed,
+            next_block: None,
+        }
+    }
+
+    /// Also watch every merchant the registry admits, as it changes.
+    pub fn with_merchant_registry(mut self, registry: Arc<MerchantRegistry>) -> Self {
+        self.merchants = Some(registry);
+        self
+    }
+
+    /// Blocks a log must be buried under before it is reported.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Configured contracts plus the registry's merchants.
+    fn watched(&self) -> Vec<String> {
+        let mut contracts = self.contracts.clone();
+        for merchant in self.merchants.iter().flat_map(|r| r.merchants()) {
+            if !contracts.contains(&merchant) {
+                contracts.push(merchant);
+            }
+        }
+        contracts
+    }
+
+    /// Scan the blocks confirmed since the last poll; returns SETTLEs published.
+    pub async fn poll(&mut self) -> Result<usize> {
+        let Some(head) = self.rpc.block_number().await?.checked_sub(self.confirmations) else {
+            return Ok(0);
+        };
+        // Start at the head: only settlements from now on are reported
+        let from = *self.next_block.get_or_insert(head + 1);
+        if from > head {
+            return Ok(0);
+        }
+        let to = head.min(from + MAX_BLOCK_RANGE - 1);
+
+        let contracts = self.watched();
+        if contracts.is_empty() {
+            self.next_block = Some(to + 1);
+            return Ok(0);
+        }
+        let logs = self.rpc.get_logs(&contracts, &self.topics, from, to).await?;
+        let mut published = 0;
+        for log in logs.iter().filter(|l| l.get("removed") != Some(&serde_json::Value::Bool(true))) {
+            if let Some(settle) = settle_from_log(log) {
+                tracing::info!(
+                    settle_id = %settle.id,
+                    order_id = %settle.result.escrow_id,
+                    final_status = %settle.result.final_status,
+                    "Settlement observed"
+                );
+                // No subscribers is not an error
+                let _ = self.feed.send(settle);
+                published += 1;
+            }
+        }
+
+        self.next_block = Some(to + 1);
+        Ok(published)
+    }
+}
+
+/// Run the watcher every `interval`; RPC failures are logged and retried.
+pub fn spawn_settlement_watcher(mut watcher: SettlementWatcher, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match watcher.poll().await {
+                Ok(0) => {}
+                Ok(n) => tracing::debug!(published = n, "Settlement watcher poll"),
+                Err(e) => tracing::warn!(error = %e, "Settlement watcher poll failed"),
+            }
+        }
+    });
+}
+
+// ----------------------------------------------------------------------------
+// Merchant registry mirror
+// ----------------------------------------------------------------------------
+
+pub struct MerchantRegistrySync {
+    rpc: Arc<RpcAdapter>,
+    registry: Arc<MerchantRegistry>,
+    confirmations: u64,
+    topics: Vec<String>,
+}
+
+impl MerchantRegistrySync {
+    pub fn new(rpc: Arc<RpcAdapter>, registry: Arc<MerchantRegistry>) -> Self {
+        let topics = [MERCHANT_DEPLOYED_EVENT_SIG, MERCHANT_REVOKED_EVENT_SIG]
+            .iter()
+            .map(|sig| bytes32_to_hex(&event_topic(sig)))
+            .collect();
+        Self { rpc, registry, confirmations: 0, topics }
+    }
+
+    /// Blocks a factory log must be buried under before it is applied.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Apply the next range of factory logs; returns true once at the
+    /// confirmed head.
+    pub async fn poll(&self) -> Result<bool> {
+        let Some(head) = self.rpc.block_number().await?.checked_sub(self.confirmations) else {
+            return Ok(true);
+        };
+        let from = self.registry.next_block().unwrap_or(head + 1);
+        if from > head {
+            return Ok(true);
+        }
+        let to = head.min(from + HISTORY_BLOCK_RANGE - 1);
+
+        let logs = self.rpc.get_logs(self.registry.factories(), &self.topics, from, to).await?;
+        let applied = logs.iter().filter(|log| self.registry.apply_log(log)).count();
+        if applied > 0 {
+            tracing::info!(applied, from, to, "Merchant registry updated");
+        }
+
+        self.registry.set_next_block(to + 1);
+        Ok(to == head)
+    }
+}
+
+/// Replay factory history back to back, then poll every `interval`.
+pub fn spawn_merchant_registry_sync(sync: MerchantRegistrySync, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            match sync.poll().await {
+                Ok(false) => continue,
+                Ok(true) => {}
+                Err(e) => tracing::warn!(error = %e, "Merchant registry sync failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
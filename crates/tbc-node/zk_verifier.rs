@@ -0,0 +1,76 @@
+//! ZK proof verification delegated to an external verifier service.
+//!
+//! L4 POSTs each attached TGP_ZK_PROOF payload to `TBC_ZK_VERIFIER_URL`
+//! and expects a `VerificationResult` back. Any transport or decoding
+//! failure counts as an invalid proof. Replays are tracked by L4's own
+//! nullifier store, not by the service.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tbc_core::zk::{VerificationResult, ZkProofPayload, ZkProofType, ZkVerifier};
+
+/// Longest a proof verification may take, connection included.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest to wait for the connection to the verifier service.
+const VERIFY_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct RemoteZkVerifier {
+    url: String,
+    types: Vec<ZkProofType>,
+    http: reqwest::Client,
+}
+
+impl RemoteZkVerifier {
+    /// Verifier at `url`, trusted for the proof `types`.
+    pub fn new(url: impl Into<String>, types: Vec<ZkProofType>) -> Self {
+        Self {
+            url: url.into(),
+            types,
+            http: reqwest::Client::builder()
+                .timeout(VERIFY_TIMEOUT)
+                .connect_timeout(VERIFY_CONNECT_TIMEOUT)
+                .build()
+                .expect("Failed to build ZK verifier HTTP client"),
+        }
+    }
+
+    async fn request(&self, payload: &ZkProofPayload) -> reqwest::Result<VerificationResult> {
+        self.http
+            .post(&self.url)
+            .json(payload)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+}
+
+#[async_trait]
+impl ZkVerifier for RemoteZkVerifier {
+    async fn verify(&self, payload: &ZkProofPayload) -> VerificationResult {
+        match self.request(payload).await {
+            Ok(result) if result.proof_type == payload.zk_type => result,
+            Ok(result) => VerificationResult::invalid(
+                payload.zk_type,
+                format!("verifier checked a {} proof", result.proof_type),
+            ),
+            Err(e) => VerificationResult::invalid(payload.zk_type, format!("verifier unavailable: {}", e)),
+        }
+    }
+
+    fn supported_types(&self) -> Vec<ZkProofType> {
+        self.types.clone()
+    }
+
+    async fn is_nullifier_used(&self, _nullifier: &str) -> bool {
+        false
+    }
+
+    async fn mark_nullifier_used(&self, _nullifier: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
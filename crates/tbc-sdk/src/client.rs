@@ -8,7 +8,7 @@ pub struct CoreProverClient {
 }
 
 impl CoreProverClient {
-    pub fn new(rpc_url: &str) -> Result<Self> {
+    pub fn new(_rpc_url: &str) -> Result<Self> {
         Ok(Self {})
     }
     